@@ -0,0 +1,717 @@
+//! Generates the config printed by `lazybar --dump-default-config` and the
+//! schema printed by `lazybar --schema` from the "Configuration options"
+//! lists in the doc comments of the parsers. The parsers themselves aren't
+//! read, so the output is only as accurate as those lists: an option that
+//! isn't documented is missing, and a documented default that differs from
+//! the one in the code is wrong here too. `tests/defaults.rs` fails when a
+//! panel's documented defaults, in either output, and its parser disagree.
+
+use std::{
+    collections::HashSet,
+    env,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Map, Value};
+
+/// The panels that are enabled in the generated bar. Every other panel is
+/// included but commented out.
+const LEFT: &[&str] = &["xwindow"];
+const CENTER: &[&str] = &["clock"];
+const RIGHT: &[&str] = &["cpu", "separator", "memory"];
+
+const HEADER: &[&str] = &[
+    "lazybar configuration",
+    "",
+    "Generated by `lazybar --dump-default-config`. Every option is listed with",
+    "its default value, so uncommenting an option without changing it has no",
+    "effect. Panels that aren't used by the bar below are commented out.",
+    "See https://docs.rs/lazybar for more details.",
+];
+
+/// The mouse buttons that actions can be bound to.
+const ACTIONS: &[&str] = &[
+    "action_left",
+    "action_middle",
+    "action_right",
+    "action_scroll_up",
+    "action_scroll_down",
+];
+
+/// An option documented in a "Configuration options" list.
+struct ConfigOption {
+    /// The keys that the option applies to. Related options are sometimes
+    /// documented together.
+    keys: Vec<String>,
+    /// The lines of the list item, including the type and default.
+    lines: Vec<String>,
+    kind: Option<String>,
+    default: Option<String>,
+}
+
+/// A panel type and its documentation.
+struct PanelDoc {
+    kind: String,
+    summary: Vec<String>,
+    options: Vec<ConfigOption>,
+    highlight: bool,
+    style: bool,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/parser.rs");
+    println!("cargo:rerun-if-changed=src/utils.rs");
+    println!("cargo:rerun-if-changed=src/animation.rs");
+    println!("cargo:rerun-if-changed=src/expr.rs");
+    println!("cargo:rerun-if-changed=src/state.rs");
+    println!("cargo:rerun-if-changed=src/attrs.rs");
+    println!("cargo:rerun-if-changed=src/highlight.rs");
+    println!("cargo:rerun-if-changed=src/style.rs");
+    println!("cargo:rerun-if-changed=src/panels");
+
+    let parser = read("src/parser.rs");
+    let bar = options(&doc_before(&parser, "fn parse_bar_options("));
+    let highlight =
+        options(&doc_before(&read("src/highlight.rs"), "pub fn parse("));
+    let style = options(&doc_before(&read("src/style.rs"), "pub fn parse<"));
+    let utils = read("src/utils.rs");
+    let animation = read("src/animation.rs");
+    let expr = read("src/expr.rs");
+    let state = read("src/state.rs");
+    let shared = [
+        (&utils, "impl Decoration"),
+        (&utils, "impl Ellipsize"),
+        (&animation, "impl Transition"),
+        (&expr, "impl Expr"),
+        (&state, "impl States"),
+        (&utils, "impl EscapeMarkup"),
+        (&utils, "impl Watchdog"),
+    ]
+    .iter()
+    .flat_map(|(source, block)| {
+        options(&doc_before(
+            &source[source.find(block).unwrap_or_default()..],
+            "fn parse<",
+        ))
+    })
+    .collect::<Vec<_>>();
+    let panels = panel_types(&parser)
+        .into_iter()
+        .filter_map(|(kind, ident)| {
+            let source = panel_source(ident.as_str())?;
+            let impl_start = source
+                .find(format!("PanelConfig for {ident}").as_str())
+                .unwrap_or_default();
+            let doc = doc_before(&source[impl_start..], "fn parse(");
+            Some(PanelDoc {
+                kind,
+                summary: doc_before(&source, &format!("pub struct {ident}"))
+                    .into_iter()
+                    .take_while(|line| !line.is_empty())
+                    .collect(),
+                highlight: doc.iter().any(|line| line.contains("Highlight::")),
+                style: doc.iter().any(|line| line.contains("Style::")),
+                options: options(&doc),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(
+        out_dir.join("default_config.toml"),
+        default_config(&bar, &shared, &panels),
+    )
+    .unwrap();
+    fs::write(
+        out_dir.join("schema.json"),
+        serde_json::to_string_pretty(&schema(
+            &bar, &shared, &highlight, &style, &panels,
+        ))
+        .unwrap(),
+    )
+    .unwrap();
+}
+
+fn read(path: impl AsRef<Path>) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+/// Returns the lines of the doc comment directly above the first line that
+/// contains `item`, skipping attributes, without the leading `///`.
+fn doc_before(source: &str, item: &str) -> Vec<String> {
+    let lines = source.lines().collect::<Vec<_>>();
+    let Some(index) = lines.iter().position(|line| line.contains(item)) else {
+        return Vec::new();
+    };
+    let mut doc = lines[..index]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with("///") || line.starts_with("#["))
+        .filter_map(|line| line.strip_prefix("///"))
+        .map(|line| line.strip_prefix(' ').unwrap_or(line).to_owned())
+        .collect::<Vec<_>>();
+    doc.reverse();
+    doc
+}
+
+/// Returns each panel type and the name of the struct that it's parsed into,
+/// in the order that they're matched in `build_panel`.
+fn panel_types(parser: &str) -> Vec<(String, String)> {
+    let start = parser.find("fn build_panel(").unwrap_or_default();
+    let mut types = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pending = None;
+    for line in parser[start..].lines() {
+        let line = line.trim();
+        if line.starts_with('"') && line.contains("=>") {
+            pending = pending
+                .or_else(|| line[1..].split('"').next().map(ToOwned::to_owned));
+        }
+        if let Some((before, _)) = line.split_once("::parse(") {
+            let before = before.split("::<").next().unwrap_or(before);
+            let ident = before
+                .rsplit(|c: char| !c.is_ascii_alphanumeric())
+                .next()
+                .unwrap_or_default()
+                .to_owned();
+            if let Some(kind) = pending.take() {
+                if seen.insert(ident.clone()) {
+                    types.push((kind, ident));
+                }
+            }
+        }
+        if line.starts_with("s => Err") {
+            break;
+        }
+    }
+    types
+}
+
+/// Returns the source of the file in `src/panels` that implements
+/// `PanelConfig` for the struct named `ident`.
+fn panel_source(ident: &str) -> Option<String> {
+    let mut paths = fs::read_dir("src/panels")
+        .unwrap()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.into_iter().map(read).find(|source| {
+        source.lines().any(|line| {
+            line.split_once("PanelConfig for ")
+                .is_some_and(|(_, rest)| {
+                    rest.split(|c: char| !c.is_ascii_alphanumeric()).next()
+                        == Some(ident)
+                })
+        })
+    })
+}
+
+/// Collects the items of the "Configuration options" list in `doc`.
+fn options(doc: &[String]) -> Vec<ConfigOption> {
+    let Some(start) = doc
+        .iter()
+        .position(|line| line.starts_with("Configuration options"))
+    else {
+        return Vec::new();
+    };
+
+    let mut items: Vec<Vec<String>> = Vec::new();
+    for line in &doc[start + 1..] {
+        if line.starts_with("- ") {
+            items.push(vec![line.clone()]);
+        } else if let Some(item) = items.last_mut() {
+            if !line.is_empty() {
+                item.push(line.clone());
+            }
+        }
+    }
+
+    items
+        .into_iter()
+        .map(|lines| {
+            let field = |name: &str| {
+                lines.iter().find_map(|line| {
+                    Some(
+                        line.trim_start()
+                            .strip_prefix("- ")?
+                            .strip_prefix(name)?
+                            .strip_prefix(": ")?
+                            .to_owned(),
+                    )
+                })
+            };
+            ConfigOption {
+                keys: option_keys(lines[0].as_str()),
+                kind: field("type"),
+                default: field("default"),
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Returns the backticked names before the description of an option, e.g.
+/// `margin_left` and `margin_right` in
+/// ``- `margin_left`, `margin_right`: ...``.
+fn option_keys(line: &str) -> Vec<String> {
+    let Some((names, _)) = line.split_once("`:") else {
+        return Vec::new();
+    };
+    names
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .filter(|name| {
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Returns the contents of a default that consists of a single code span.
+fn code_literal(default: &str) -> Option<&str> {
+    let literal = default.strip_prefix('`')?.strip_suffix('`')?;
+    (!literal.contains('`')).then_some(literal)
+}
+
+/// Replaces rustdoc links like ``[`Attrs`][crate::Attrs]`` with their text.
+/// Brackets inside code spans are left alone.
+fn strip_links(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_code = false;
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        if c == '`' {
+            in_code = !in_code;
+        } else if c == '[' && !in_code {
+            if let Some(end) = rest.find(']') {
+                out.push_str(&rest[..end]);
+                rest = &rest[end + 1..];
+                let close = match rest.chars().next() {
+                    Some('[') => Some(']'),
+                    Some('(') => Some(')'),
+                    _ => None,
+                };
+                if let Some(end) = close.and_then(|close| rest.find(close)) {
+                    rest = &rest[end + 1..];
+                }
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Writes the default config: the bar options, then each panel with its
+/// documented options. The values are the defaults from the docs.
+fn default_config(
+    bar: &[ConfigOption],
+    shared: &[ConfigOption],
+    panels: &[PanelDoc],
+) -> String {
+    let mut out = String::new();
+    push_comment(&mut out, HEADER);
+
+    out.push_str("\n[bars.main]\n");
+    push_options(&mut out, bar);
+    for (key, panels) in [
+        ("panels_left", LEFT),
+        ("panels_center", CENTER),
+        ("panels_right", RIGHT),
+    ] {
+        let names = panels
+            .iter()
+            .map(|p| format!("\"{p}\""))
+            .collect::<Vec<_>>();
+        let _ = writeln!(out, "{key} = [{}]", names.join(", "));
+    }
+
+    out.push_str("\n# Options shared by most panels:\n");
+    for source in ["src/utils.rs", "src/attrs.rs"] {
+        // the first paragraph only describes the function
+        let doc = doc_before(&read(source), "pub fn parse<")
+            .into_iter()
+            .skip_while(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        push_comment(&mut out, &doc);
+    }
+    push_options(&mut out, shared);
+
+    for panel in panels {
+        let enabled = [LEFT, CENTER, RIGHT]
+            .iter()
+            .any(|panels| panels.contains(&panel.kind.as_str()));
+        let prefix = if enabled { "" } else { "# " };
+
+        out.push('\n');
+        push_comment(&mut out, &panel.summary);
+        let _ = writeln!(out, "{prefix}[panels.{}]", panel.kind);
+        let _ = writeln!(out, "{prefix}type = \"{}\"", panel.kind);
+        push_options(&mut out, &panel.options);
+    }
+
+    out
+}
+
+/// Writes each line of `doc` as a comment, with intra-doc links reduced to
+/// their text.
+fn push_comment(out: &mut String, doc: &[impl AsRef<str>]) {
+    for line in doc {
+        let line = strip_links(line.as_ref());
+        if line.is_empty() {
+            out.push_str("#\n");
+        } else {
+            let _ = writeln!(out, "# {line}");
+        }
+    }
+}
+
+/// Writes each option as a comment, followed by a commented assignment of its
+/// default value if it has one that can be written in TOML.
+fn push_options(out: &mut String, options: &[ConfigOption]) {
+    for option in options {
+        out.push('\n');
+        push_comment(out, &option.lines);
+        let Some(value) = toml_value(option) else {
+            continue;
+        };
+        for key in &option.keys {
+            let _ = writeln!(out, "# {key} = {value}");
+        }
+    }
+}
+
+/// Converts a documented default into a TOML value, if it's a literal of the
+/// documented type.
+fn toml_value(option: &ConfigOption) -> Option<String> {
+    let (Some(kind), Some(default)) = (&option.kind, &option.default) else {
+        return None;
+    };
+    if kind.starts_with("String") {
+        if default.len() > 1
+            && default.starts_with('"')
+            && default.ends_with('"')
+        {
+            return Some(default.clone());
+        }
+        let literal = code_literal(default)?;
+        Some(format!(
+            "\"{}\"",
+            literal.replace('\\', "\\\\").replace('"', "\\\"")
+        ))
+    } else {
+        let literal = code_literal(default).unwrap_or(default);
+        let valid = match kind.as_str() {
+            "bool" => literal.parse::<bool>().is_ok(),
+            kind if kind.starts_with(['u', 'i', 'f']) => {
+                literal.parse::<f64>().is_ok()
+            }
+            kind if kind.starts_with("Array") => literal.starts_with('['),
+            _ => false,
+        };
+        valid.then(|| literal.to_owned())
+    }
+}
+
+/// Builds the JSON schema from the same documented options as the default
+/// config, so an option is only validated as well as it's documented.
+fn schema(
+    bar: &[ConfigOption],
+    shared: &[ConfigOption],
+    highlight: &[ConfigOption],
+    style: &[ConfigOption],
+    panels: &[PanelDoc],
+) -> Value {
+    let string = json!({ "type": "string" });
+
+    let mut bar_properties = properties(bar);
+    bar_properties.insert(
+        String::from("monitors"),
+        json!({
+            "description": "`all`, the name of an output, or an array of them",
+            "type": ["string", "array"],
+            "items": string,
+        }),
+    );
+
+    let mut common = Map::new();
+    common.insert(
+        String::from("dependence"),
+        json!({
+            "description": "whether the panel is only shown when its \
+                            neighbors are",
+            "enum": ["none", "left", "right", "both"],
+        }),
+    );
+    for action in ACTIONS {
+        common.insert(
+            (*action).to_owned(),
+            json!({
+                "description": "a shell command to run when the panel is \
+                                clicked or scrolled",
+                "type": "string",
+            }),
+        );
+    }
+    for attr in ["fg", "bg", "font", "font_features"] {
+        common.insert(attr.to_owned(), string.clone());
+    }
+    common.extend(properties(shared));
+
+    let mut defs = Map::new();
+    let mut conditions = Vec::new();
+    for panel in panels {
+        let mut properties = common.clone();
+        if panel.highlight {
+            properties.extend(self::properties(highlight));
+        }
+        if panel.style {
+            properties.extend(self::properties(style));
+        }
+        properties.extend(self::properties(&panel.options));
+        let name = format!("panel_{}", panel.kind);
+        defs.insert(
+            name.clone(),
+            json!({
+                "description": strip_links(&panel.summary.join(" ")),
+                "properties": properties,
+            }),
+        );
+        conditions.push(json!({
+            "if": { "properties": { "type": { "const": panel.kind } } },
+            "then": { "$ref": format!("#/$defs/{name}") },
+        }));
+    }
+    defs.insert(
+        String::from("bar"),
+        json!({
+            "type": "object",
+            "properties": bar_properties,
+            "additionalProperties": {
+                "description": "options that only apply on the output with \
+                                this name",
+                "$ref": "#/$defs/bar",
+            },
+        }),
+    );
+    defs.insert(
+        String::from("panel"),
+        json!({
+            "type": "object",
+            "required": ["type"],
+            "properties": {
+                "type": {
+                    "enum": panels.iter().map(|p| &p.kind).collect::<Vec<_>>(),
+                },
+            },
+            "patternProperties": {
+                "^(.+_)?(fg|bg|font_features)$": string,
+                "^(.+_)?font$": {
+                    "type": ["string", "array"],
+                    "items": string,
+                },
+                "^(.+_)?format(_.+)?$": string,
+            },
+            "allOf": conditions,
+        }),
+    );
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "lazybar configuration",
+        "type": "object",
+        "properties": {
+            "include": {
+                "description": "other config files to read first, relative \
+                                to this one",
+                "type": ["string", "array"],
+                "items": string,
+            },
+            "bars": {
+                "type": "object",
+                "additionalProperties": { "$ref": "#/$defs/bar" },
+            },
+            "panels": {
+                "type": "object",
+                "additionalProperties": { "$ref": "#/$defs/panel" },
+            },
+            "ramps": {
+                "type": "object",
+                "additionalProperties": {
+                    "description": "pango markup strings keyed by 0, 1, ..., \
+                                    or tables with the markup as `icon` and \
+                                    attributes like `fg`",
+                    "type": "object",
+                    "properties": {
+                        "interpolate": {
+                            "description": "blend the foreground colors of \
+                                            the steps",
+                            "type": "boolean",
+                        },
+                    },
+                    "patternProperties": {
+                        "^[0-9]+$": { "type": ["string", "object"] },
+                    },
+                },
+            },
+            "palette": {
+                "description": "named colors that can be used anywhere a \
+                                color is expected as `$name`",
+                "type": "object",
+                "properties": {
+                    "xresources": {
+                        "description": "import color0 through color15, \
+                                        background, and foreground from the \
+                                        X resource database",
+                        "type": "boolean",
+                        "default": false,
+                    },
+                    "wal": {
+                        "description": "import colors from the colors.json \
+                                        file of pywal or wallust and recolor \
+                                        the bar when it changes",
+                        "type": "boolean",
+                        "default": false,
+                    },
+                    "wal_path": {
+                        "description": "the path of the colors.json file",
+                        "type": "string",
+                    },
+                    "colors": {
+                        "type": "object",
+                        "additionalProperties": string,
+                    },
+                    "dark": {
+                        "description": "colors used on top of `colors` with \
+                                        the dark theme",
+                        "type": "object",
+                        "additionalProperties": string,
+                    },
+                    "light": {
+                        "description": "colors used on top of `colors` with \
+                                        the light theme",
+                        "type": "object",
+                        "additionalProperties": string,
+                    },
+                    "theme": {
+                        "description": "the theme to use, or how to choose \
+                                        it",
+                        "enum": ["dark", "light", "portal", "time"],
+                        "default": "dark",
+                    },
+                    "light_at": {
+                        "description": "when to switch to the light theme \
+                                        if `theme` is `time`, as HH:MM",
+                        "type": "string",
+                        "default": "07:00",
+                    },
+                    "dark_at": {
+                        "description": "when to switch to the dark theme \
+                                        if `theme` is `time`, as HH:MM",
+                        "type": "string",
+                        "default": "19:00",
+                    },
+                },
+            },
+            "profile": {
+                "description": "values that override the rest of the config \
+                                on the host with this name",
+                "type": "object",
+                "additionalProperties": { "type": "object" },
+            },
+        },
+        "$defs": defs,
+    })
+}
+
+/// Returns a JSON schema property for each key of each option.
+fn properties(options: &[ConfigOption]) -> Map<String, Value> {
+    let mut properties = Map::new();
+    for option in options {
+        let mut property = Map::new();
+        let description = option
+            .lines
+            .iter()
+            .take_while(|line| !line.starts_with("  - "))
+            .map(|line| line.trim_start().trim_start_matches("- "))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let description = strip_links(
+            description
+                .split_once("`: ")
+                .map_or(description.as_str(), |(_, d)| d),
+        );
+        property.insert(String::from("description"), description.into());
+        if let Some(kind) = &option.kind {
+            property.extend(json_type(kind.as_str()));
+        }
+        if let Some(default) =
+            toml_value(option).and_then(|value| toml_to_json(value.as_str()))
+        {
+            property.insert(String::from("default"), default);
+        }
+        for key in &option.keys {
+            properties.insert(key.clone(), Value::Object(property.clone()));
+        }
+    }
+    properties
+}
+
+/// Converts a documented type like `u64` or `array of String` into JSON
+/// schema keywords.
+fn json_type(kind: &str) -> Map<String, Value> {
+    let lower = kind.to_lowercase();
+    let scalar = |kind: &str| match kind {
+        "string" => Some("string"),
+        "bool" => Some("boolean"),
+        "f32" | "f64" => Some("number"),
+        kind if kind.starts_with(['u', 'i']) => Some("integer"),
+        _ => None,
+    };
+    let mut schema = Map::new();
+    if lower.starts_with("string - one of ") {
+        let choices = kind.split('`').skip(1).step_by(2).collect::<Vec<_>>();
+        schema.insert(String::from("enum"), json!(choices));
+    } else if let Some(kinds) = lower
+        .split_once(" or ")
+        .map(|(a, b)| [a, b].map(|k| scalar(k).or((k == "array").then_some(k))))
+        .and_then(|[a, b]| Some([a?, b?]))
+    {
+        schema.insert(String::from("type"), json!(kinds));
+    } else if let Some(items) = lower.strip_prefix("array of ") {
+        schema.insert(String::from("type"), json!("array"));
+        if let Some(items) = scalar(items) {
+            schema.insert(String::from("items"), json!({ "type": items }));
+        }
+    } else if let Some(values) = lower.strip_prefix("table of ") {
+        schema.insert(String::from("type"), json!("object"));
+        if let Some(values) = scalar(values) {
+            schema.insert(
+                String::from("additionalProperties"),
+                json!({ "type": values }),
+            );
+        }
+    } else if let Some(kind) = scalar(lower.as_str()) {
+        schema.insert(String::from("type"), json!(kind));
+        if lower.starts_with('u') {
+            schema.insert(String::from("minimum"), json!(0));
+        }
+    }
+    schema
+}
+
+/// Converts a TOML value produced by [`toml_value`] into JSON.
+fn toml_to_json(value: &str) -> Option<Value> {
+    if let Some(s) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        return Some(Value::String(
+            s.replace("\\\"", "\"").replace("\\\\", "\\"),
+        ));
+    }
+    serde_json::from_str(value).ok()
+}
@@ -0,0 +1,321 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    rc::Rc,
+    str::FromStr,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::Value;
+use tokio::time::{self, Instant, Interval, MissedTickBehavior};
+use tokio_stream::Stream;
+
+use crate::{
+    bar::PanelDrawInfo, remove_string_from_config, remove_uint_from_config,
+    PanelStream,
+};
+
+/// How often an animated panel is redrawn
+pub(crate) const FRAME: Duration = Duration::from_millis(16);
+
+/// How the old content of a panel turns into the new content
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// The old content fades out as the new content fades in
+    Fade,
+    /// The new content pushes the old content up
+    SlideUp,
+    /// The new content pushes the old content down
+    SlideDown,
+    /// The new content pushes the old content to the left
+    SlideLeft,
+    /// The new content pushes the old content to the right
+    SlideRight,
+}
+
+/// How the progress of a transition changes over time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    /// At a constant speed
+    Linear,
+    /// Starting slowly
+    EaseIn,
+    /// Ending slowly
+    EaseOut,
+    /// Starting and ending slowly
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress from 0 to 1 onto this curve.
+    #[must_use]
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Self::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (2.0f64.mul_add(-t, 2.0)).powi(2) / 2.0
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = anyhow::Error;
+
+    /// Parses `linear`, `ease_in`, `ease_out`, or `ease_in_out`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "linear" => Ok(Self::Linear),
+            "ease_in" => Ok(Self::EaseIn),
+            "ease_out" => Ok(Self::EaseOut),
+            "ease_in_out" => Ok(Self::EaseInOut),
+            other => Err(anyhow!("Unknown easing `{other}`")),
+        }
+    }
+}
+
+/// An animation shown when the content of a panel changes
+#[derive(Clone, Copy, Debug)]
+pub struct Transition {
+    /// What the animation looks like
+    pub kind: TransitionKind,
+    /// How long the animation takes
+    pub duration: Duration,
+    /// How the animation speeds up and slows down
+    pub easing: Easing,
+}
+
+impl Transition {
+    /// Configuration options:
+    ///
+    /// - `transition`: how the panel animates when its content changes: `fade`,
+    ///   `slide_up`, `slide_down`, `slide_left`, or `slide_right`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `transition_duration`: how long the animation takes in milliseconds
+    ///   - type: u64
+    ///   - default: `200`
+    ///
+    /// - `transition_easing`: how the animation speeds up and slows down:
+    ///   `linear`, `ease_in`, `ease_out`, or `ease_in_out`
+    ///   - type: String
+    ///   - default: `ease_out`
+    pub(crate) fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Option<Self> {
+        let kind = remove_string_from_config("transition", table);
+        let duration = remove_uint_from_config("transition_duration", table);
+        let easing = remove_string_from_config("transition_easing", table);
+
+        let kind = match kind?.as_str() {
+            "fade" => TransitionKind::Fade,
+            "slide_up" => TransitionKind::SlideUp,
+            "slide_down" => TransitionKind::SlideDown,
+            "slide_left" => TransitionKind::SlideLeft,
+            "slide_right" => TransitionKind::SlideRight,
+            other => {
+                log::warn!("Unknown transition `{other}`");
+                return None;
+            }
+        };
+        let easing = easing.map_or(Easing::EaseOut, |easing| {
+            easing.parse().unwrap_or_else(|e| {
+                log::warn!("{e}, using `ease_out`");
+                Easing::EaseOut
+            })
+        });
+
+        Some(Self {
+            kind,
+            duration: Duration::from_millis(duration.unwrap_or(200)),
+            easing,
+        })
+    }
+
+    /// Wraps the stream of a panel so that each change to its content is
+    /// animated. Panels without a transition shouldn't be wrapped, so that
+    /// they aren't redrawn more than necessary.
+    #[must_use]
+    pub fn apply(self, stream: PanelStream) -> PanelStream {
+        let mut interval = time::interval(FRAME);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        Box::pin(Animated {
+            transition: self,
+            inner: stream,
+            current: None,
+            animation: None,
+            interval,
+        })
+    }
+}
+
+/// The draw function of a panel, shared between animation frames
+type SharedDrawFn = Rc<dyn Fn(&cairo::Context) -> Result<()>>;
+
+/// Content that a panel has drawn, kept so that transitions can draw it again
+#[derive(Clone)]
+struct Content {
+    draw_fn: SharedDrawFn,
+    width: f64,
+    height: f64,
+}
+
+/// A transition in progress
+struct Animation {
+    old: Content,
+    new: Content,
+    template: PanelDrawInfo,
+    start: Instant,
+}
+
+/// A [`PanelStream`] that produces animation frames between changes
+struct Animated {
+    transition: Transition,
+    inner: PanelStream,
+    current: Option<Content>,
+    animation: Option<Animation>,
+    interval: Interval,
+}
+
+impl Animated {
+    /// Produces a frame `t` of the way through the animation, or the new
+    /// content on its own if the animation is done.
+    fn frame(&mut self) -> PanelDrawInfo {
+        let Some(animation) = &self.animation else {
+            unreachable!("frames are only produced during an animation");
+        };
+        let elapsed = animation.start.elapsed().as_secs_f64();
+        let t =
+            elapsed / self.transition.duration.as_secs_f64().max(f64::EPSILON);
+        if t >= 1.0 {
+            let animation = self.animation.take().unwrap();
+            return with_draw_fn(&animation.template, &animation.new.draw_fn);
+        }
+
+        let t = self.transition.easing.apply(t);
+        let kind = self.transition.kind;
+        let (old, new) = (animation.old.clone(), animation.new.clone());
+        let mut frame = with_draw_fn(&animation.template, &new.draw_fn);
+        frame.draw_fn = Box::new(move |cr| draw_frame(cr, kind, t, &old, &new));
+        frame
+    }
+}
+
+impl Stream for Animated {
+    type Item = Result<PanelDrawInfo>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match self.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(draw_info))) => {
+                let template = PanelDrawInfo {
+                    draw_fn: Box::new(|_| Ok(())),
+                    ..draw_info
+                };
+                let new = Content {
+                    draw_fn: Rc::from(draw_info.draw_fn),
+                    width: f64::from(template.width),
+                    height: f64::from(template.height),
+                };
+                let old = self.current.replace(new.clone());
+                match old {
+                    // nothing to animate from the first time the panel is
+                    // drawn, or if it's empty
+                    Some(old) if old.width > 0.0 && new.width > 0.0 => {
+                        self.animation = Some(Animation {
+                            old,
+                            new,
+                            template,
+                            start: Instant::now(),
+                        });
+                        self.interval.reset();
+                        Poll::Ready(Some(Ok(self.frame())))
+                    }
+                    _ => {
+                        self.animation = None;
+                        Poll::Ready(Some(Ok(with_draw_fn(
+                            &template,
+                            &new.draw_fn,
+                        ))))
+                    }
+                }
+            }
+            Poll::Pending if self.animation.is_some() => {
+                match self.interval.poll_tick(cx) {
+                    Poll::Ready(_) => Poll::Ready(Some(Ok(self.frame()))),
+                    Poll::Pending => Poll::Pending,
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+/// Copies a [`PanelDrawInfo`] with a shared draw function.
+fn with_draw_fn(
+    template: &PanelDrawInfo,
+    draw_fn: &SharedDrawFn,
+) -> PanelDrawInfo {
+    let draw_fn = draw_fn.clone();
+    let mut draw_info = PanelDrawInfo::new(
+        (template.width, template.height),
+        template.dependence,
+        Box::new(move |cr| draw_fn(cr)),
+    );
+    draw_info.text.clone_from(&template.text);
+    draw_info.expand = template.expand;
+    draw_info.values.clone_from(&template.values);
+    draw_info
+}
+
+/// Draws the old and new content of a panel, `t` of the way through a
+/// transition.
+fn draw_frame(
+    cr: &cairo::Context,
+    kind: TransitionKind,
+    t: f64,
+    old: &Content,
+    new: &Content,
+) -> Result<()> {
+    cr.save()?;
+    cr.rectangle(0.0, 0.0, new.width, new.height);
+    cr.clip();
+    match kind {
+        TransitionKind::Fade => {
+            for (content, alpha) in [(old, 1.0 - t), (new, t)] {
+                cr.push_group();
+                (content.draw_fn)(cr)?;
+                cr.pop_group_to_source()?;
+                cr.paint_with_alpha(alpha)?;
+            }
+        }
+        _ => {
+            let (dx, dy) = match kind {
+                TransitionKind::SlideUp => (0.0, -new.height),
+                TransitionKind::SlideDown => (0.0, new.height),
+                TransitionKind::SlideLeft => (-new.width, 0.0),
+                _ => (new.width, 0.0),
+            };
+            for (content, offset) in [(old, t), (new, t - 1.0)] {
+                cr.save()?;
+                cr.translate(dx * offset, dy * offset);
+                (content.draw_fn)(cr)?;
+                cr.restore()?;
+            }
+        }
+    }
+    cr.restore()?;
+    Ok(())
+}
@@ -4,7 +4,10 @@ use csscolorparser::Color;
 use derive_builder::Builder;
 use pango::FontDescription;
 
-use crate::{remove_color_from_config, remove_string_from_config};
+use crate::{
+    remove_font_from_config, remove_paint_from_config,
+    remove_string_from_config, Paint,
+};
 
 /// Attributes of a panel, or the defaults for the bar.
 #[derive(Builder, Clone, Default, Debug)]
@@ -12,17 +15,20 @@ pub struct Attrs {
     #[builder(default = "None", setter(strip_option))]
     font: Option<FontDescription>,
     #[builder(default = "None", setter(strip_option))]
-    fg: Option<Color>,
+    fg: Option<Paint>,
     #[builder(default = "None", setter(strip_option))]
-    bg: Option<Color>,
+    bg: Option<Paint>,
+    #[builder(default = "None", setter(strip_option))]
+    font_features: Option<String>,
 }
 
 impl AttrsBuilder {
     const fn global() -> Self {
         Self {
             font: None,
-            fg: Some(Some(Color::new(1.0, 1.0, 1.0, 1.0))),
-            bg: Some(Some(Color::new(0.0, 0.0, 0.0, 1.0))),
+            fg: Some(Some(Paint::Color(Color::new(1.0, 1.0, 1.0, 1.0)))),
+            bg: Some(Some(Paint::Color(Color::new(0.0, 0.0, 0.0, 1.0)))),
+            font_features: None,
         }
     }
 }
@@ -34,33 +40,49 @@ impl Attrs {
     /// Configuration options:
     ///
     /// `fg: String`: Specify the foreground (usually text) color. All parsing
-    /// methods from [csscolorparser] are available.
+    /// methods from [csscolorparser] are available. Linear gradients can be
+    /// given as `gradient(90deg, #f00, #00f 80%)`; see [`Paint`] for the full
+    /// syntax.
     ///
-    /// `bg: String`: Specify the background color. All parsing methods from
-    /// [csscolorparser] are available.
+    /// `bg: String`: Specify the background color. This can be a color or a
+    /// gradient, like `fg`.
     ///
     /// `font: String`: Specify the font to be used. This will be turned into a
     /// [`pango::FontDescription`], so it's very configurable. Font family,
-    /// weight, size, and more can be specified.
+    /// weight, size, and more can be specified. An array of fonts can be
+    /// given instead, e.g. `["JetBrainsMono Nerd Font 10", "Noto Color
+    /// Emoji"]`, so that characters missing from the first font, like icons or
+    /// emoji, are taken from the others. See [`remove_font_from_config`].
+    ///
+    /// `font_features: String`: Specify OpenType features of the font, in
+    /// the format of the CSS `font-feature-settings` property. For example,
+    /// `tnum` gives every digit the same width, so numbers that change don't
+    /// shift the bar, and `tnum, zero` also adds a slash to zeros.
     pub fn parse<S: std::hash::BuildHasher>(
         table: &mut HashMap<String, config::Value, S>,
         prefix: &str,
     ) -> Self {
         let mut builder = AttrsBuilder::default();
         if let Some(fg) =
-            remove_color_from_config(format!("{prefix}fg").as_str(), table)
+            remove_paint_from_config(format!("{prefix}fg").as_str(), table)
         {
             builder.fg(fg);
         }
         if let Some(bg) =
-            remove_color_from_config(format!("{prefix}bg").as_str(), table)
+            remove_paint_from_config(format!("{prefix}bg").as_str(), table)
         {
             builder.bg(bg);
         }
         if let Some(font) =
-            remove_string_from_config(format!("{prefix}font").as_str(), table)
+            remove_font_from_config(format!("{prefix}font").as_str(), table)
         {
-            builder.font(FontDescription::from_string(font.as_str()));
+            builder.font(font);
+        }
+        if let Some(features) = remove_string_from_config(
+            format!("{prefix}font_features").as_str(),
+            table,
+        ) {
+            builder.font_features(features);
         }
 
         // this can never panic: no validator functions, and all fields are
@@ -80,42 +102,79 @@ impl Attrs {
     ) -> Self {
         let mut builder = AttrsBuilder::global();
         if let Some(fg) =
-            remove_color_from_config(format!("{prefix}fg").as_str(), table)
+            remove_paint_from_config(format!("{prefix}fg").as_str(), table)
         {
             builder.fg(fg);
         }
         if let Some(bg) =
-            remove_color_from_config(format!("{prefix}bg").as_str(), table)
+            remove_paint_from_config(format!("{prefix}bg").as_str(), table)
         {
             builder.bg(bg);
         }
         if let Some(font) =
-            remove_string_from_config(format!("{prefix}font").as_str(), table)
+            remove_font_from_config(format!("{prefix}font").as_str(), table)
         {
-            builder.font(FontDescription::from_string(font.as_str()));
+            builder.font(font);
+        }
+        if let Some(features) = remove_string_from_config(
+            format!("{prefix}font_features").as_str(),
+            table,
+        ) {
+            builder.font_features(features);
         }
 
         builder.build().unwrap()
     }
 
-    /// Sets the font of a [`pango::Layout`].
+    /// Sets the font of a [`pango::Layout`]. Setting markup on the layout
+    /// afterward resets the font features, so this should be called after
+    /// [`pango::Layout::set_markup`].
     pub fn apply_font(&self, layout: &pango::Layout) {
         if let Some(font) = &self.font {
             layout.set_font_description(Some(font));
         }
+        if let Some(features) = &self.font_features {
+            let attributes = layout.attributes().unwrap_or_default();
+            // attributes from markup take precedence
+            attributes.insert_before(pango::AttrFontFeatures::new(features));
+            layout.set_attributes(Some(&attributes));
+        }
     }
 
     /// Sets the foreground (usually text) color of a [`cairo::Context`].
-    pub fn apply_fg(&self, cr: &cairo::Context) {
+    /// Gradients are stretched across a `width` by `height` area at the
+    /// current origin.
+    pub fn apply_fg(&self, cr: &cairo::Context, width: f64, height: f64) {
         if let Some(fg) = &self.fg {
-            cr.set_source_rgba(fg.r, fg.g, fg.b, fg.a);
+            fg.apply(cr, width, height);
         }
     }
 
-    /// Sets the background color of a [`cairo::Context`].
-    pub fn apply_bg(&self, cr: &cairo::Context) {
+    /// Sets the background color of a [`cairo::Context`]. Gradients are
+    /// stretched across a `width` by `height` area at the current origin.
+    pub fn apply_bg(&self, cr: &cairo::Context, width: f64, height: f64) {
         if let Some(bg) = &self.bg {
-            cr.set_source_rgba(bg.r, bg.g, bg.b, bg.a);
+            bg.apply(cr, width, height);
+        }
+    }
+
+    /// Returns the foreground color if it's a solid color.
+    pub(crate) fn fg_color(&self) -> Option<Color> {
+        match &self.fg {
+            Some(Paint::Color(color)) => Some(color.clone()),
+            _ => None,
+        }
+    }
+
+    /// Replaces the foreground color.
+    pub(crate) fn set_fg(&mut self, fg: Paint) {
+        self.fg = Some(fg);
+    }
+
+    /// Sets the font, unless one is already set.
+    pub(crate) fn set_font_if_unset(&mut self, font: &FontDescription) {
+        if self.font.is_none() {
+            self.font = Some(font.clone());
         }
     }
 
@@ -131,5 +190,8 @@ impl Attrs {
         if self.bg.is_none() {
             self.bg.clone_from(&new.bg);
         }
+        if self.font_features.is_none() {
+            self.font_features.clone_from(&new.font_features);
+        }
     }
 }
@@ -1,13 +1,32 @@
-use std::{fmt::Debug, ops::BitAnd, rc::Rc};
+use std::{
+    any::Any,
+    cell::Cell,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    ops::BitAnd,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use cairo::{Extend, ImageSurface, Matrix, SurfacePattern};
 use csscolorparser::Color;
-use tokio_stream::StreamMap;
+use tokio::{sync::mpsc::UnboundedSender, time::Instant};
+use tokio_stream::{StreamExt, StreamMap};
 use xcb::{x, Event};
 
 use crate::{
-    create_surface, create_window, map_window, set_wm_properties, Alignment,
-    Margins, PanelDrawFn, PanelStream, Position,
+    bar_geometry, clear_strut, create_surface, create_trigger, create_window,
+    draw_common, icon,
+    ipc::Command,
+    map_window, panel_log,
+    popup::{Anchor, Popup},
+    rounded_rectangle, run_command_with_env, set_wm_properties, shape_window,
+    trigger_y, wallpaper_surface, Actions, Alignment, Attrs, Autohide,
+    BackgroundImage, Border, Ellipsize, ErrorBadge, Expr, ImageScale, Margins,
+    Monitor, PanelConfig, PanelDrawFn, PanelRebuildFn, PanelStream, Placement,
+    Position, SeparatorStyle, Watchdog,
 };
 
 #[derive(PartialEq, Eq, Debug)]
@@ -32,6 +51,9 @@ struct Extents {
     left: f64,
     center: (f64, f64),
     right: f64,
+    /// The space given to each expanding panel in the left, center, and right
+    /// parts of the bar
+    expand: (f64, f64, f64),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -39,6 +61,9 @@ struct Extents {
 ///
 /// If a panel is dependent on another panel with non-None dependence, it will
 /// not be shown.
+///
+/// To depend on a panel elsewhere on the bar, or on the values it shows, use
+/// [`depends_on`][crate::PanelCommon::depends_on].
 pub enum Dependence {
     /// The panel will always be shown
     None,
@@ -50,6 +75,49 @@ pub enum Dependence {
     Both,
 }
 
+/// A mouse button that can be pressed over a [`Panel`]. Scrolling is reported
+/// as a press of a separate button for each direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    /// The left mouse button (X button 1)
+    Left,
+    /// The middle mouse button (X button 2)
+    Middle,
+    /// The right mouse button (X button 3)
+    Right,
+    /// The scroll wheel moving up (X button 4)
+    ScrollUp,
+    /// The scroll wheel moving down (X button 5)
+    ScrollDown,
+}
+
+impl MouseButton {
+    pub(crate) const fn try_from_x(button: x::Button) -> Option<Self> {
+        match button {
+            1 => Some(Self::Left),
+            2 => Some(Self::Middle),
+            3 => Some(Self::Right),
+            4 => Some(Self::ScrollUp),
+            5 => Some(Self::ScrollDown),
+            _ => None,
+        }
+    }
+}
+
+/// A mouse event that occurred over a [`Panel`]. The coordinates are relative
+/// to the top left corner of the panel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseEvent {
+    /// Which button was pressed
+    pub button: MouseButton,
+    /// The x-coordinate of the pointer, relative to the panel
+    pub x: f64,
+    /// The y-coordinate of the pointer, relative to the panel
+    pub y: f64,
+    /// Where to open a [`Popup`][crate::popup::Popup] for the panel
+    pub anchor: Anchor,
+}
+
 /// Information describing how to draw/redraw a [`Panel`].
 pub struct PanelDrawInfo {
     /// The width in pixels of the panel.
@@ -62,12 +130,21 @@ pub struct PanelDrawInfo {
     /// (0, 0). Translating the Context is the responsibility of functions in
     /// this module.
     pub draw_fn: PanelDrawFn,
+    /// The text shown by the panel, without markup. This is empty for panels
+    /// that don't draw a single piece of text.
+    pub text: String,
+    /// Whether the panel takes up the space left over in its part of the bar,
+    /// in addition to its width. Separators aren't drawn next to it.
+    pub expand: bool,
+    /// The values shown by the panel, keyed by name, for use in
+    /// [`Expr`][crate::Expr]s. Panels document the values they provide.
+    pub values: HashMap<String, String>,
 }
 
 impl PanelDrawInfo {
     /// Creates a new [`PanelDrawInfo`] from its components.
     #[must_use]
-    pub const fn new(
+    pub fn new(
         dims: (i32, i32),
         dependence: Dependence,
         draw_fn: PanelDrawFn,
@@ -77,12 +154,20 @@ impl PanelDrawInfo {
             height: dims.1,
             dependence,
             draw_fn,
+            text: String::new(),
+            expand: false,
+            values: HashMap::new(),
         }
     }
+
+    /// Records a value shown by the panel. See [`PanelDrawInfo::values`].
+    pub fn set_value(&mut self, name: &str, value: impl ToString) {
+        self.values.insert(name.to_owned(), value.to_string());
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum PanelStatus {
+pub(crate) enum PanelStatus {
     Shown,
     ZeroWidth,
     Dependent(Dependence),
@@ -102,9 +187,12 @@ impl BitAnd for PanelStatus {
 
 impl From<&Panel> for PanelStatus {
     fn from(value: &Panel) -> Self {
+        if value.hidden || value.disabled {
+            return Self::ZeroWidth;
+        }
         value.draw_info.as_ref().map_or(Self::ZeroWidth, |d| {
             match (d.dependence, d.width) {
-                (Dependence::None, 0) => Self::ZeroWidth,
+                (Dependence::None, 0) if !d.expand => Self::ZeroWidth,
                 (Dependence::None, _) => Self::Shown,
                 (dep, _) => Self::Dependent(dep),
             }
@@ -112,25 +200,364 @@ impl From<&Panel> for PanelStatus {
     }
 }
 
+/// How long to wait before restarting a panel that has failed once. The
+/// delay doubles each time it fails again, up to [`MAX_RESTART_DELAY`].
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(300);
+/// The opacity of a panel that has stalled
+const STALE_ALPHA: f64 = 0.4;
+
+/// The error produced in place of a panic in a panel. The panel's stream
+/// can't continue, so it's restarted.
+#[derive(Debug)]
+struct Panicked(String);
+
+impl Panicked {
+    fn new(payload: &(dyn Any + Send)) -> Self {
+        Self(
+            payload
+                .downcast_ref::<&str>()
+                .map(|s| (*s).to_owned())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| String::from("unknown cause")),
+        )
+    }
+}
+
+impl Display for Panicked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "panicked: {}", self.0)
+    }
+}
+
+impl std::error::Error for Panicked {}
+
+/// The error produced when a panel's stream ends. Panels are expected to
+/// keep running for as long as the bar does, so it's restarted.
+#[derive(Debug)]
+struct Ended;
+
+impl Display for Ended {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("stopped producing content")
+    }
+}
+
+impl std::error::Error for Ended {}
+
+/// Failed panels waiting to be built again and restarted, along with how to
+/// build them. Bars without a window restart their panels the same way.
+#[derive(Default)]
+pub(crate) struct Restarts {
+    rebuild: Option<PanelRebuildFn>,
+    /// The panels waiting to be restarted, and when to restart them
+    pending: Vec<(Alignment, usize, Instant)>,
+}
+
+impl Restarts {
+    pub(crate) fn new(rebuild: Option<PanelRebuildFn>) -> Self {
+        Self {
+            rebuild,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Sets how failed panels are built again. If this is [`None`], failed
+    /// panels aren't restarted.
+    pub(crate) fn set_rebuild(&mut self, rebuild: Option<PanelRebuildFn>) {
+        self.rebuild = rebuild;
+    }
+
+    /// Whether failed panels can be built again.
+    pub(crate) const fn can_restart(&self) -> bool {
+        self.rebuild.is_some()
+    }
+
+    /// Logs a panel's error and schedules it to be restarted, waiting twice
+    /// as long each time it fails in a row. Returns whether its stream should
+    /// be dropped. If panels can't be built again, a panel whose stream is
+    /// still running is left alone instead.
+    pub(crate) fn fail(
+        &mut self,
+        panel: &mut Panel,
+        alignment: Alignment,
+        idx: usize,
+        e: &anyhow::Error,
+    ) -> bool {
+        let stopped = e.is::<Panicked>() || e.is::<Ended>();
+        log::warn!("Error produced by {alignment} panel `{}`: {e}", panel.name);
+        panel.failures = panel.failures.saturating_add(1);
+        if !self.can_restart() && !stopped {
+            return false;
+        }
+        // the old stream is gone, so nothing receives these
+        panel.events = None;
+        panel.refresh = None;
+        panel.stall_deadline = None;
+        if !self.can_restart() {
+            return false;
+        }
+        let delay = RESTART_DELAY
+            .saturating_mul(2u32.saturating_pow(panel.failures - 1))
+            .min(MAX_RESTART_DELAY);
+        log::info!("Restarting `{}` in {}s", panel.name, delay.as_secs());
+        self.pending.push((alignment, idx, Instant::now() + delay));
+        true
+    }
+
+    /// Schedules a panel to be restarted at `at`, if panels can be built
+    /// again.
+    pub(crate) fn schedule(
+        &mut self,
+        alignment: Alignment,
+        idx: usize,
+        at: Instant,
+    ) {
+        if self.can_restart() {
+            self.pending.push((alignment, idx, at));
+        }
+    }
+
+    /// Stops waiting to restart the panels for which `f` returns `true`.
+    pub(crate) fn cancel(&mut self, f: impl Fn(Alignment, usize) -> bool) {
+        self.pending.retain(|(a, idx, _)| !f(*a, *idx));
+    }
+
+    /// Returns when the next failed panel should be restarted, if any.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.pending.iter().map(|(_, _, at)| *at).min()
+    }
+
+    /// Returns the panels whose delay has passed, which are no longer
+    /// waiting.
+    pub(crate) fn take_due(&mut self) -> Vec<(Alignment, usize)> {
+        let now = Instant::now();
+        let (due, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(_, _, at)| *at <= now);
+        self.pending = pending;
+        due.into_iter().map(|(a, idx, _)| (a, idx)).collect()
+    }
+
+    /// Builds the panel called `name` again and starts its stream. See
+    /// [`Bar::start_panel`].
+    ///
+    /// # Errors
+    ///
+    /// If panels can't be built again, or if this one can't be built.
+    pub(crate) fn rebuild(
+        &self,
+        cr: &Rc<cairo::Context>,
+        attrs: &Attrs,
+        height: i32,
+        name: String,
+    ) -> Result<(Panel, Result<PanelStream>)> {
+        let rebuild = self
+            .rebuild
+            .as_ref()
+            .ok_or_else(|| anyhow!("{name} can't be built again"))?;
+        let config = rebuild(name.as_str())?;
+        Ok(Bar::start_panel(cr, attrs, height, name, config))
+    }
+}
+
 /// A panel on the bar
 pub struct Panel {
+    /// The name of the panel in the config file
+    pub name: String,
     /// How to draw the panel.
     pub draw_info: Option<PanelDrawInfo>,
     /// The current x-coordinate of the panel
     pub x: f64,
     /// The current y-coordinate of the panel
     pub y: f64,
+    /// Where to send mouse events that occur over the panel, if it accepts
+    /// them
+    pub events: Option<UnboundedSender<MouseEvent>>,
+    /// Where to send refresh requests, if the panel accepts them
+    pub refresh: Option<UnboundedSender<()>>,
+    /// Whether the panel has been hidden at runtime. Hidden panels still
+    /// receive updates, but they take up no space on the bar.
+    pub hidden: bool,
+    /// Whether the panel has been disabled at runtime. Disabled panels have
+    /// no stream, so they don't update, and they take up no space on the
+    /// bar.
+    pub disabled: bool,
+    /// The commands to run when the panel is clicked or scrolled
+    pub actions: Actions,
+    /// The distance in pixels between the left edge of the panel and its
+    /// content, taken up by its [`Decoration`][crate::Decoration]
+    pub inset: f64,
+    /// The condition on other panels under which the panel is shown
+    pub depends_on: Option<Expr>,
+    /// How many times in a row the panel has panicked or failed to start.
+    /// This is reset when it draws.
+    pub failures: u32,
+    /// How long the panel can go without drawing before it's restarted
+    pub watchdog: Watchdog,
+    /// When the panel is considered stalled unless it draws first
+    pub stall_deadline: Option<Instant>,
+    /// Whether the panel has stalled and hasn't drawn since. Stale panels
+    /// are drawn faded.
+    pub stale: bool,
+    /// What the debug overlay shows about the panel
+    pub stats: PanelStats,
+}
+
+/// Counters kept for each panel and shown in the debug overlay.
+#[derive(Debug, Default)]
+pub struct PanelStats {
+    /// How many times the panel has changed its content
+    pub updates: u64,
+    /// When the panel last changed its content
+    pub last_update: Option<Instant>,
+    /// How long the panel took to draw the last time it was drawn
+    pub draw_time: Cell<Duration>,
+    /// How long the panel has taken to draw since it started
+    pub draws: Cell<Timing>,
+}
+
+impl PanelStats {
+    const fn new() -> Self {
+        Self {
+            updates: 0,
+            last_update: None,
+            draw_time: Cell::new(Duration::ZERO),
+            draws: Cell::new(Timing::new()),
+        }
+    }
+}
+
+/// How many times something has happened and how long it took.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timing {
+    /// How many times it happened
+    pub count: u64,
+    /// How long it took altogether
+    pub total: Duration,
+    /// How long it took the longest time
+    pub max: Duration,
+}
+
+impl Timing {
+    const fn new() -> Self {
+        Self {
+            count: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+
+    /// Adds one occurrence that took `time`.
+    pub fn record(&mut self, time: Duration) {
+        self.count += 1;
+        self.total += time;
+        self.max = self.max.max(time);
+    }
+
+    /// How long it took on average.
+    #[must_use]
+    pub fn mean(&self) -> Duration {
+        u32::try_from(self.count)
+            .ok()
+            .and_then(|count| self.total.checked_div(count))
+            .unwrap_or_default()
+    }
 }
 
 impl Panel {
     /// Create a new panel.
     #[must_use]
-    pub const fn new(draw_info: Option<PanelDrawInfo>) -> Self {
+    pub const fn new(
+        name: String,
+        draw_info: Option<PanelDrawInfo>,
+        events: Option<UnboundedSender<MouseEvent>>,
+        refresh: Option<UnboundedSender<()>>,
+        actions: Actions,
+    ) -> Self {
         Self {
+            name,
             draw_info,
             x: 0.0,
             y: 0.0,
+            events,
+            refresh,
+            hidden: false,
+            disabled: false,
+            actions,
+            inset: 0.0,
+            depends_on: None,
+            failures: 0,
+            watchdog: Watchdog(None),
+            stall_deadline: None,
+            stale: false,
+            stats: PanelStats::new(),
+        }
+    }
+
+    /// Runs the command configured for `button`, or passes the click on to
+    /// the panel if there isn't one. `x` and `y` are relative to the content
+    /// of the panel.
+    pub(crate) fn click(
+        &self,
+        button: MouseButton,
+        x: f64,
+        y: f64,
+        anchor: Anchor,
+    ) {
+        if let Some(command) = self.actions.get(button) {
+            let text = self.draw_info.as_ref().map_or("", |d| d.text.as_str());
+            run_command_with_env(command, &[("LAZYBAR_TEXT", text)]);
+        } else if let Some(events) = &self.events {
+            if events
+                .send(MouseEvent {
+                    button,
+                    x,
+                    y,
+                    anchor,
+                })
+                .is_err()
+            {
+                log::warn!("Panel event receiver has been dropped");
+            }
+        }
+    }
+
+    /// Replaces the panel with `panel`, which has been built again from the
+    /// same config. The old content stays until the new panel draws, along
+    /// with whether it's hidden or stale, its failures, and its stats.
+    pub(crate) fn replace(&mut self, panel: Self) {
+        *self = Self {
+            hidden: self.hidden,
+            failures: self.failures,
+            stale: self.stale,
+            stats: std::mem::take(&mut self.stats),
+            draw_info: self.draw_info.take(),
+            ..panel
+        };
+    }
+
+    /// Draws the panel at the origin of `cr`, faded if it's stale.
+    fn draw(&self, cr: &cairo::Context) -> Result<()> {
+        let Some(draw_info) = &self.draw_info else {
+            return Ok(());
+        };
+        let start = Instant::now();
+        if self.stale {
+            cr.push_group();
+            let result = (draw_info.draw_fn)(cr);
+            cr.pop_group_to_source()?;
+            result?;
+            cr.paint_with_alpha(STALE_ALPHA)?;
+        } else {
+            (draw_info.draw_fn)(cr)?;
         }
+        let time = start.elapsed();
+        self.stats.draw_time.set(time);
+        let mut draws = self.stats.draws.get();
+        draws.record(time);
+        self.stats.draws.set(draws);
+        Ok(())
     }
 }
 
@@ -139,6 +566,7 @@ impl Panel {
 pub struct Bar {
     name: String,
     position: Position,
+    placement: Placement,
     pub(crate) conn: xcb::Connection,
     screen: i32,
     window: x::Window,
@@ -146,6 +574,7 @@ pub struct Bar {
     pub(crate) cr: Rc<cairo::Context>,
     width: i32,
     height: u16,
+    scale: f64,
     bg: Color,
     margins: Margins,
     extents: Extents,
@@ -154,38 +583,83 @@ pub struct Bar {
     pub(crate) right: Vec<Panel>,
     pub(crate) streams: StreamMap<Alignment, StreamMap<usize, PanelStream>>,
     center_state: CenterState,
+    pub(crate) monitor: Option<Monitor>,
+    autohide: Option<AutohideState>,
+    visible: bool,
+    separator: Option<PanelDrawInfo>,
+    corner_radius: f64,
+    border: Option<Border>,
+    bg_image: Option<BackgroundImage>,
+    bg_pattern: Option<SurfacePattern>,
+    root_pixmap: x::Atom,
+    attrs: Attrs,
+    error_badge: Option<ErrorBadge>,
+    restarts: Restarts,
+    debug_overlay: Option<Popup>,
+    /// The time spent on panel updates, other than drawing the panels and
+    /// presenting the result
+    layout_time: Timing,
+    /// The time spent sending what was drawn to the X server
+    present_time: Cell<Timing>,
+}
+
+/// The state of a bar that hides itself when not in use.
+struct AutohideState {
+    config: Autohide,
+    trigger: x::Window,
+    shown: bool,
+    /// When to next show (`true`) or hide (`false`) the bar
+    pending: Option<(Instant, bool)>,
 }
 
 impl Bar {
     /// Create a new bar, typically from information held by a
-    /// [`BarConfig`][crate::BarConfig].
+    /// [`BarConfig`][crate::BarConfig]. `height` is in pixels on the screen,
+    /// and everything drawn on the bar is multiplied by `scale`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         position: Position,
+        placement: Placement,
         height: u16,
+        scale: f64,
         transparent: bool,
         bg: Color,
         margins: Margins,
+        monitor: Option<Monitor>,
     ) -> Result<Self> {
-        let (conn, screen, window, width, visual) =
-            create_window(position, height, transparent, &bg, name.as_str())?;
+        let (conn, screen, window, geometry, visual) = create_window(
+            position,
+            placement,
+            height,
+            transparent,
+            &bg,
+            name.as_str(),
+            monitor.as_ref(),
+        )?;
         set_wm_properties(
             &conn,
+            screen,
             window,
             position,
-            width.into(),
+            geometry,
             height.into(),
         )?;
+        let width = geometry.width;
         map_window(&conn, window)?;
         let surface =
             create_surface(&conn, window, visual, width.into(), height.into())?;
         let cr = cairo::Context::new(&surface)?;
+        // everything is drawn in logical pixels
+        cr.scale(scale, scale);
         surface.flush();
         conn.flush()?;
+        let logical_width = f64::from(width) / scale;
 
         Ok(Self {
             name,
             position,
+            placement,
             conn,
             screen,
             window,
@@ -193,22 +667,1201 @@ impl Bar {
             cr: Rc::new(cr),
             width: width.into(),
             height,
+            scale,
             bg,
             margins,
             extents: Extents {
                 left: 0.0,
-                center: ((width / 2).into(), (width / 2).into()),
-                right: width.into(),
+                center: (logical_width / 2.0, logical_width / 2.0),
+                right: logical_width,
+                expand: (0.0, 0.0, 0.0),
             },
             left: Vec::new(),
             center: Vec::new(),
             right: Vec::new(),
             streams: StreamMap::new(),
             center_state: CenterState::Center,
+            monitor,
+            autohide: None,
+            visible: true,
+            separator: None,
+            corner_radius: 0.0,
+            border: None,
+            bg_image: None,
+            bg_pattern: None,
+            root_pixmap: x::ATOM_NONE,
+            attrs: Attrs::default(),
+            error_badge: None,
+            restarts: Restarts::default(),
+            debug_overlay: None,
+            layout_time: Timing::new(),
+            present_time: Cell::new(Timing::new()),
         })
     }
 
-    fn apply_dependence(panels: &[Panel]) -> Vec<PanelStatus> {
+    /// Hides the bar when the pointer leaves it, and shows it again when the
+    /// pointer touches the edge of the monitor next to it. The bar no longer
+    /// reserves space on the screen.
+    pub fn set_autohide(&mut self, config: Autohide) -> Result<()> {
+        let geometry = bar_geometry(
+            &self.conn,
+            self.screen,
+            self.position,
+            self.placement,
+            self.height,
+            self.monitor.as_ref(),
+        )?;
+        let trigger = create_trigger(
+            &self.conn,
+            self.screen,
+            self.position,
+            self.placement,
+            self.height,
+            geometry,
+        )?;
+        clear_strut(&self.conn, self.window)?;
+        self.conn.check_request(self.conn.send_request_checked(
+            &x::ChangeWindowAttributes {
+                window: self.window,
+                value_list: &[x::Cw::EventMask(
+                    x::EventMask::EXPOSURE
+                        | x::EventMask::BUTTON_PRESS
+                        | x::EventMask::ENTER_WINDOW
+                        | x::EventMask::LEAVE_WINDOW,
+                )],
+            },
+        ))?;
+        // stay visible for a moment so that it's clear the bar started
+        self.autohide = Some(AutohideState {
+            config,
+            trigger,
+            shown: true,
+            pending: Some((Instant::now() + config.hide_timeout, false)),
+        });
+        Ok(())
+    }
+
+    /// Returns when the bar should next be shown or hidden, if it should.
+    pub(crate) fn autohide_deadline(&self) -> Option<Instant> {
+        self.autohide
+            .as_ref()?
+            .pending
+            .map(|(deadline, _)| deadline)
+    }
+
+    /// Shows or hides the bar once its deadline has passed.
+    pub(crate) fn autohide_fire(&mut self) -> Result<()> {
+        let Some(state) = &mut self.autohide else {
+            return Ok(());
+        };
+        let Some((_, show)) = state.pending.take() else {
+            return Ok(());
+        };
+        self.autohide_show(show)
+    }
+
+    /// Maps the bar and unmaps the trigger window, or vice versa.
+    fn autohide_show(&mut self, show: bool) -> Result<()> {
+        let Some(state) = &mut self.autohide else {
+            return Ok(());
+        };
+        state.shown = show;
+        self.visible = show;
+        let (unmap, map) = if show {
+            (state.trigger, self.window)
+        } else {
+            (self.window, state.trigger)
+        };
+        self.conn.send_request(&x::UnmapWindow { window: unmap });
+        self.conn.send_request(&x::MapWindow { window: map });
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    /// Schedules showing or hiding the bar when the pointer enters or leaves
+    /// the bar or the trigger window.
+    fn pointer_crossed(&mut self, window: x::Window, entered: bool) {
+        let Some(state) = &mut self.autohide else {
+            return;
+        };
+        if window == state.trigger && !state.shown {
+            state.pending = entered
+                .then(|| (Instant::now() + state.config.reveal_delay, true));
+        } else if window == self.window && state.shown {
+            state.pending = (!entered)
+                .then(|| (Instant::now() + state.config.hide_timeout, false));
+        }
+    }
+
+    /// Moves and resizes the bar to fit `monitor`, or the whole screen if
+    /// `monitor` is [`None`]. This is used when the monitor configuration
+    /// changes at runtime.
+    pub fn move_to_monitor(&mut self, monitor: Option<Monitor>) -> Result<()> {
+        let geometry = bar_geometry(
+            &self.conn,
+            self.screen,
+            self.position,
+            self.placement,
+            self.height,
+            monitor.as_ref(),
+        )?;
+        self.conn.check_request(self.conn.send_request_checked(
+            &x::ConfigureWindow {
+                window: self.window,
+                value_list: &[
+                    x::ConfigWindow::X(i32::from(geometry.x)),
+                    x::ConfigWindow::Y(i32::from(geometry.y)),
+                    x::ConfigWindow::Width(u32::from(geometry.width)),
+                ],
+            },
+        ))?;
+        if let Some(state) = &self.autohide {
+            self.conn.check_request(self.conn.send_request_checked(
+                &x::ConfigureWindow {
+                    window: state.trigger,
+                    value_list: &[
+                        x::ConfigWindow::X(i32::from(geometry.x)),
+                        x::ConfigWindow::Y(i32::from(trigger_y(
+                            self.position,
+                            self.placement,
+                            self.height,
+                            geometry,
+                        ))),
+                        x::ConfigWindow::Width(u32::from(geometry.width)),
+                    ],
+                },
+            ))?;
+        } else if self.visible {
+            set_wm_properties(
+                &self.conn,
+                self.screen,
+                self.window,
+                self.position,
+                geometry,
+                self.height.into(),
+            )?;
+        }
+        self.surface
+            .set_size(geometry.width.into(), self.height.into())?;
+        self.width = geometry.width.into();
+        self.monitor = monitor;
+        if self.corner_radius > 0.0 {
+            self.shape()?;
+        }
+        if self.bg_image.is_some() {
+            self.load_background()?;
+        }
+        if self.debug_overlay.is_some() {
+            self.set_debug_overlay(true)?;
+        }
+        self.redraw_bar()
+    }
+
+    /// Sets the image drawn behind the panels. The bar isn't redrawn.
+    pub fn set_background_image(
+        &mut self,
+        image: Option<BackgroundImage>,
+    ) -> Result<()> {
+        if matches!(image, Some(BackgroundImage::Wallpaper)) {
+            self.root_pixmap = self
+                .conn
+                .wait_for_reply(self.conn.send_request(&x::InternAtom {
+                    only_if_exists: false,
+                    name: b"_XROOTPMAP_ID",
+                }))?
+                .atom();
+            // redraw when the wallpaper changes
+            let root = self
+                .conn
+                .get_setup()
+                .roots()
+                .nth(usize::try_from(self.screen)?)
+                .context("Failed to find screen")?
+                .root();
+            self.conn.check_request(self.conn.send_request_checked(
+                &x::ChangeWindowAttributes {
+                    window: root,
+                    value_list: &[x::Cw::EventMask(
+                        x::EventMask::PROPERTY_CHANGE,
+                    )],
+                },
+            ))?;
+        }
+        self.bg_image = image;
+        self.load_background()
+    }
+
+    /// Loads the background image and fits it to the current size and
+    /// position of the bar.
+    fn load_background(&mut self) -> Result<()> {
+        self.bg_pattern = match &self.bg_image {
+            None => None,
+            Some(BackgroundImage::File { path, scale }) => {
+                let image = icon::load_image(path)
+                    .with_context(|| format!("Failed to load {path:?}"))?;
+                Some(fit_image(
+                    &image,
+                    *scale,
+                    f64::from(self.logical_width()),
+                    f64::from(self.logical_height()),
+                )?)
+            }
+            Some(BackgroundImage::Wallpaper) => {
+                let geometry = bar_geometry(
+                    &self.conn,
+                    self.screen,
+                    self.position,
+                    self.placement,
+                    self.height,
+                    self.monitor.as_ref(),
+                )?;
+                wallpaper_surface(&self.conn, self.screen, self.root_pixmap)?
+                    .map(|surface| {
+                        let pattern = SurfacePattern::create(&surface);
+                        // the wallpaper covers the whole screen
+                        pattern.set_matrix(Matrix::new(
+                            self.scale,
+                            0.0,
+                            0.0,
+                            self.scale,
+                            f64::from(geometry.x),
+                            f64::from(geometry.y),
+                        ));
+                        pattern
+                    })
+            }
+        };
+        Ok(())
+    }
+
+    /// Rounds the corners of the bar and sets the line drawn around its edge.
+    /// The bar isn't redrawn.
+    pub fn set_outline(
+        &mut self,
+        corner_radius: f64,
+        border: Option<Border>,
+    ) -> Result<()> {
+        self.corner_radius = corner_radius.max(0.0);
+        self.border = border.filter(|border| border.width > 0.0);
+        self.clip();
+        self.shape()
+    }
+
+    fn shape(&self) -> Result<()> {
+        shape_window(
+            &self.conn,
+            self.window,
+            u16::try_from(self.width).unwrap_or(u16::MAX),
+            self.height,
+            self.corner_radius * self.scale,
+        )
+    }
+
+    /// The width of the bar in logical pixels, which is what panels and
+    /// everything drawn on the bar work with. On the screen, each logical
+    /// pixel is `scale` pixels wide.
+    #[allow(clippy::cast_possible_truncation)]
+    fn logical_width(&self) -> i32 {
+        (f64::from(self.width) / self.scale).round() as i32
+    }
+
+    /// The height of the bar in logical pixels. See
+    /// [`logical_width`][Self::logical_width].
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn logical_height(&self) -> u16 {
+        (f64::from(self.height) / self.scale).round() as u16
+    }
+
+    /// Replaces the current path with the outline of the bar, `inset` pixels
+    /// inside its edges.
+    fn outline(&self, inset: f64) {
+        rounded_rectangle(
+            &self.cr,
+            inset,
+            inset,
+            2.0f64.mul_add(-inset, f64::from(self.logical_width())),
+            2.0f64.mul_add(-inset, f64::from(self.logical_height())),
+            self.corner_radius - inset,
+        );
+    }
+
+    /// Keeps everything that's drawn on the bar inside its rounded corners
+    /// and its border.
+    fn clip(&self) {
+        self.cr.reset_clip();
+        if self.corner_radius > 0.0 || self.border.is_some() {
+            self.outline(self.border.as_ref().map_or(0.0, |b| b.width));
+            self.cr.clip();
+        }
+    }
+
+    fn draw_border(&self) -> Result<()> {
+        let Some(border) = &self.border else {
+            return Ok(());
+        };
+        self.cr.save()?;
+        self.cr.reset_clip();
+        self.outline(border.width / 2.0);
+        self.cr.set_source_rgba(
+            border.color.r,
+            border.color.g,
+            border.color.b,
+            border.color.a,
+        );
+        self.cr.set_line_width(border.width);
+        self.cr.stroke()?;
+        self.cr.restore()?;
+
+        Ok(())
+    }
+
+    /// Replaces the panels with the given alignment and starts their streams.
+    /// Panels that fail to start show the error badge and are restarted
+    /// later. The bar isn't redrawn.
+    pub fn set_panels(
+        &mut self,
+        alignment: Alignment,
+        panels: Vec<(String, Box<dyn PanelConfig>)>,
+        attrs: &Attrs,
+    ) -> Result<()> {
+        self.attrs = attrs.clone();
+        self.restarts.cancel(|a, _| a == alignment);
+        let old = match alignment {
+            Alignment::Left => &self.left,
+            Alignment::Center => &self.center,
+            Alignment::Right => &self.right,
+        };
+        let mut list = Vec::with_capacity(panels.len());
+        let mut streams = StreamMap::with_capacity(panels.len());
+        let mut failed = Vec::new();
+        for (idx, (name, panel)) in panels.into_iter().enumerate() {
+            // panels hidden or disabled at runtime stay that way when the
+            // config is reloaded
+            let hidden = old.iter().any(|p| p.name == name && p.hidden);
+            if old.iter().any(|p| p.name == name && p.disabled) {
+                let actions = panel.actions();
+                list.push(Panel {
+                    hidden,
+                    disabled: true,
+                    ..Panel::new(name, None, None, None, actions)
+                });
+                continue;
+            }
+            let (panel, stream) = Self::start_panel(
+                &self.cr,
+                &self.attrs,
+                i32::from(self.logical_height()),
+                name,
+                panel,
+            );
+            match stream {
+                Ok(stream) => {
+                    streams.insert(idx, stream);
+                }
+                Err(e) => failed.push((idx, e)),
+            }
+            list.push(Panel { hidden, ..panel });
+        }
+        match alignment {
+            Alignment::Left => self.left = list,
+            Alignment::Center => self.center = list,
+            Alignment::Right => self.right = list,
+        }
+        self.streams.insert(alignment, streams);
+        for (idx, e) in failed {
+            self.fail_panel(alignment, idx, &e);
+            self.panel_mut(alignment, idx).draw_info = self.error_badge();
+        }
+        Ok(())
+    }
+
+    /// Gets everything the bar needs to know about a panel and starts its
+    /// stream. Panics while the stream starts or runs are caught and turned
+    /// into [`Panicked`] errors, which end the stream, and the end of the
+    /// stream is reported as an [`Ended`] error. The panel logs through its
+    /// own target; see [`PanelLogger`][crate::PanelLogger].
+    pub(crate) fn start_panel(
+        cr: &Rc<cairo::Context>,
+        attrs: &Attrs,
+        height: i32,
+        name: String,
+        mut panel: Box<dyn PanelConfig>,
+    ) -> (Panel, Result<PanelStream>) {
+        let mut decoration = panel.decoration();
+        decoration.attrs.apply_to(attrs);
+        let watchdog = panel.watchdog();
+        let target = Rc::<str>::from(name.as_str());
+        let entry = Panel {
+            inset: decoration.inset(),
+            depends_on: panel.depends_on(),
+            watchdog,
+            stall_deadline: watchdog.0.map(|timeout| Instant::now() + timeout),
+            ..Panel::new(
+                name,
+                None,
+                panel.event_sender(),
+                panel.refresh_sender(),
+                panel.actions(),
+            )
+        };
+        let transition = panel.transition();
+        let visible_when = panel.visible_when();
+        let stream = panic::catch_unwind(AssertUnwindSafe(|| {
+            panel_log::scoped(&target, || {
+                panel.into_stream(cr.clone(), attrs.clone(), height)
+            })
+        }))
+        .unwrap_or_else(|payload| Err(Panicked::new(payload.as_ref()).into()))
+        .map(|mut stream| {
+            if let Some(visible_when) = visible_when {
+                stream = Box::pin(stream.map(move |draw_info| {
+                    draw_info.map(|d| visible_when.apply(d))
+                }));
+            }
+            // the content moves within the decoration, which stays still
+            if let Some(transition) = transition {
+                stream = transition.apply(stream);
+            }
+            if !decoration.is_empty() {
+                stream = Box::pin(stream.map(move |draw_info| {
+                    draw_info.map(|d| decoration.apply(d))
+                }));
+            }
+            let stream = panel_log::Scoped::new(target, stream);
+            Box::pin(
+                futures::StreamExt::catch_unwind(AssertUnwindSafe(stream))
+                    .map(|result| {
+                        result.unwrap_or_else(|payload| {
+                            Err(Panicked::new(payload.as_ref()).into())
+                        })
+                    })
+                    .chain(tokio_stream::once(Err(Ended.into()))),
+            ) as PanelStream
+        });
+        (entry, stream)
+    }
+
+    /// Handles an item produced by a panel's stream. New content is drawn,
+    /// and errors are logged and shown with the error badge until the panel
+    /// draws again. A panel that produces an error, panics, or stops is
+    /// restarted with a new stream.
+    pub(crate) fn handle_panel_result(
+        &mut self,
+        alignment: Alignment,
+        idx: usize,
+        result: Result<PanelDrawInfo>,
+    ) -> Result<()> {
+        let start = Instant::now();
+        let before = self.draw_and_present_time();
+        let result = self.apply_panel_result(alignment, idx, result);
+        let spent = self.draw_and_present_time().saturating_sub(before);
+        self.layout_time
+            .record(start.elapsed().saturating_sub(spent));
+        result
+    }
+
+    fn apply_panel_result(
+        &mut self,
+        alignment: Alignment,
+        idx: usize,
+        result: Result<PanelDrawInfo>,
+    ) -> Result<()> {
+        // errors show that the panel is still running, too
+        let panel = self.panel_mut(alignment, idx);
+        panel.stall_deadline =
+            panel.watchdog.0.map(|timeout| Instant::now() + timeout);
+        match result {
+            Ok(draw_info) => {
+                let panel = self.panel_mut(alignment, idx);
+                panel.stats.updates += 1;
+                panel.stats.last_update = Some(Instant::now());
+                panel.failures = 0;
+                panel.stale = false;
+                self.update_panel(alignment, idx, draw_info)
+            }
+            Err(e) => {
+                self.fail_panel(alignment, idx, &e);
+                match self.error_badge() {
+                    Some(badge) => self.update_panel(alignment, idx, badge),
+                    None => {
+                        self.panel_mut(alignment, idx).draw_info = None;
+                        self.redraw_bar()
+                    }
+                }
+            }
+        }
+    }
+
+    /// Logs a panel's error, stops its stream, and schedules it to be
+    /// restarted. See [`Restarts::fail`].
+    fn fail_panel(
+        &mut self,
+        alignment: Alignment,
+        idx: usize,
+        e: &anyhow::Error,
+    ) {
+        let panel = match alignment {
+            Alignment::Left => &mut self.left[idx],
+            Alignment::Center => &mut self.center[idx],
+            Alignment::Right => &mut self.right[idx],
+        };
+        if self.restarts.fail(panel, alignment, idx, e) {
+            self.remove_stream(alignment, idx);
+        }
+    }
+
+    /// Drops the stream of a panel, if it's running.
+    fn remove_stream(&mut self, alignment: Alignment, idx: usize) {
+        if let Some((_, streams)) =
+            self.streams.iter_mut().find(|(a, _)| *a == alignment)
+        {
+            streams.remove(&idx);
+        }
+    }
+
+    /// Draws the error badge, if there is one.
+    fn error_badge(&self) -> Option<PanelDrawInfo> {
+        let badge = self.error_badge.as_ref()?;
+        draw_common(
+            &self.cr,
+            badge.markup.as_str(),
+            &badge.attrs,
+            Dependence::None,
+            &Ellipsize::default(),
+        )
+        .map_err(|e| log::warn!("Failed to draw error badge: {e}"))
+        .ok()
+    }
+
+    /// Returns when the next panel with a watchdog will be considered stalled,
+    /// if any.
+    pub(crate) fn stall_deadline(&self) -> Option<Instant> {
+        [&self.left, &self.center, &self.right]
+            .into_iter()
+            .flatten()
+            .filter_map(|panel| panel.stall_deadline)
+            .min()
+    }
+
+    /// Marks the panels that haven't drawn before their watchdog fired as
+    /// stale and schedules them to be restarted right away.
+    pub(crate) fn check_stalled(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let mut stalled = Vec::new();
+        for alignment in [Alignment::Left, Alignment::Center, Alignment::Right]
+        {
+            for idx in 0..self.panels(alignment).len() {
+                let panel = self.panel_mut(alignment, idx);
+                if !panel.stall_deadline.is_some_and(|at| at <= now) {
+                    continue;
+                }
+                log::warn!(
+                    "{alignment} panel `{}` hasn't drawn in {}s",
+                    panel.name,
+                    panel.watchdog.0.unwrap_or_default().as_secs()
+                );
+                panel.stall_deadline = None;
+                panel.stale = true;
+                stalled.push((alignment, idx));
+            }
+        }
+        if stalled.is_empty() {
+            return Ok(());
+        }
+        for (alignment, idx) in stalled {
+            self.restarts.schedule(alignment, idx, now);
+        }
+        self.redraw_bar()
+    }
+
+    /// Returns when the next failed panel should be restarted, if any.
+    pub(crate) fn restart_deadline(&self) -> Option<Instant> {
+        self.restarts.deadline()
+    }
+
+    /// Builds the failed or stalled panels whose delay has passed again and
+    /// starts their streams. Their old content stays until they draw.
+    pub(crate) fn restart_panels(&mut self) -> Result<()> {
+        for (alignment, idx) in self.restarts.take_due() {
+            self.restart_panel(alignment, idx);
+        }
+        self.redraw_bar()
+    }
+
+    /// Builds a panel again and starts its stream. Its old content stays
+    /// until it draws, and if it can't be built or started, it fails as
+    /// usual.
+    fn restart_panel(&mut self, alignment: Alignment, idx: usize) {
+        let name = self.panel_mut(alignment, idx).name.clone();
+        let (panel, stream) = match self.restarts.rebuild(
+            &self.cr,
+            &self.attrs,
+            i32::from(self.logical_height()),
+            name,
+        ) {
+            Ok(started) => started,
+            Err(e) => {
+                self.fail_panel(alignment, idx, &e);
+                return;
+            }
+        };
+        self.panel_mut(alignment, idx).replace(panel);
+        match stream {
+            Ok(stream) => {
+                if let Some((_, streams)) =
+                    self.streams.iter_mut().find(|(a, _)| *a == alignment)
+                {
+                    streams.insert(idx, stream);
+                } else {
+                    // the streams of a section are dropped once they have
+                    // all ended
+                    let mut streams = StreamMap::new();
+                    streams.insert(idx, stream);
+                    self.streams.insert(alignment, streams);
+                }
+            }
+            Err(e) => self.fail_panel(alignment, idx, &e),
+        }
+    }
+
+    /// Sets the background color of the bar, e.g. after the palette changes.
+    /// The bar isn't redrawn.
+    pub fn set_bg(&mut self, bg: Color) {
+        self.bg = bg;
+    }
+
+    /// Sets what to draw in place of a panel that has failed. Unset
+    /// attributes of the badge fall back to `attrs`. Panels that are already
+    /// showing the badge aren't changed.
+    pub fn set_error_badge(
+        &mut self,
+        badge: Option<&ErrorBadge>,
+        attrs: &Attrs,
+    ) {
+        self.error_badge = badge.map(|badge| {
+            let mut badge = badge.clone();
+            badge.attrs.apply_to(attrs);
+            badge
+        });
+    }
+
+    /// Sets how failed panels are built again so that they can be restarted.
+    /// If this is [`None`], failed panels aren't restarted.
+    pub fn set_rebuild_panel(&mut self, rebuild: Option<PanelRebuildFn>) {
+        self.restarts.set_rebuild(rebuild);
+    }
+
+    /// Sets what to draw between adjacent panels. Unset attributes of the
+    /// separator fall back to `attrs`. The bar isn't redrawn.
+    pub fn set_separator(
+        &mut self,
+        separator: Option<&SeparatorStyle>,
+        attrs: &Attrs,
+    ) -> Result<()> {
+        self.separator = Self::draw_separator_style(
+            &self.cr,
+            separator,
+            attrs,
+            self.logical_height(),
+        )?;
+        Ok(())
+    }
+
+    /// Prepares a separator to be drawn on a bar of the given height.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn draw_separator_style(
+        cr: &Rc<cairo::Context>,
+        separator: Option<&SeparatorStyle>,
+        attrs: &Attrs,
+        bar_height: u16,
+    ) -> Result<Option<PanelDrawInfo>> {
+        Ok(match separator {
+            None => None,
+            Some(SeparatorStyle::Text { markup, attrs: own }) => {
+                let mut own = own.clone();
+                own.apply_to(attrs);
+                Some(draw_common(
+                    cr,
+                    markup.as_str(),
+                    &own,
+                    Dependence::None,
+                    &Ellipsize::default(),
+                )?)
+            }
+            Some(SeparatorStyle::Line {
+                width,
+                padding,
+                inset,
+                attrs: own,
+            }) => {
+                let mut own = own.clone();
+                own.apply_to(attrs);
+                let (width, padding, inset) = (*width, *padding, *inset);
+                let total = 2.0f64.mul_add(padding, width);
+                let height = f64::from(bar_height);
+                Some(PanelDrawInfo::new(
+                    (total.ceil() as i32, i32::from(bar_height)),
+                    Dependence::None,
+                    Box::new(move |cr| {
+                        own.apply_bg(cr, total, height);
+                        cr.rectangle(0.0, 0.0, total, height);
+                        cr.fill()?;
+                        own.apply_fg(cr, total, height);
+                        cr.rectangle(
+                            padding,
+                            inset,
+                            width,
+                            2.0f64.mul_add(-inset, height),
+                        );
+                        cr.fill()?;
+                        Ok(())
+                    }),
+                ))
+            }
+        })
+    }
+
+    /// Returns the width of the shown panels in `panels`, including the
+    /// separators between them but not any leftover space, and the number of
+    /// expanding panels among them.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn measure(
+        panels: &[Panel],
+        statuses: &[PanelStatus],
+        separator: Option<&PanelDrawInfo>,
+    ) -> (f64, usize) {
+        let separator = separator.map_or(0, |separator| separator.width);
+        let mut width = 0;
+        let mut expand = 0;
+        let mut first = true;
+
+        for draw_info in panels
+            .iter()
+            .zip(statuses)
+            .filter(|(_, status)| **status == PanelStatus::Shown)
+            .filter_map(|(panel, _)| panel.draw_info.as_ref())
+        {
+            if draw_info.expand {
+                expand += 1;
+                first = true;
+            } else if draw_info.width > 0 {
+                if !first {
+                    width += separator;
+                }
+                first = false;
+            }
+            width += draw_info.width;
+        }
+
+        (f64::from(width), expand)
+    }
+
+    /// Whether any shown panel takes up leftover space.
+    fn expanding(&self) -> bool {
+        [&self.left, &self.center, &self.right]
+            .into_iter()
+            .flatten()
+            .any(|panel| {
+                !panel.hidden
+                    && panel.draw_info.as_ref().is_some_and(|d| d.expand)
+            })
+    }
+
+    /// Whether a panel changing from `cur_width` to `new_width` adds or
+    /// removes a separator.
+    fn separators_change(&self, cur_width: f64, new_width: f64) -> bool {
+        self.separator.is_some() && (cur_width > 0.0) != (new_width > 0.0)
+    }
+
+    /// Draws the separator, if there is one, with its left edge at `x`.
+    /// Returns its width.
+    fn draw_separator(
+        cr: &cairo::Context,
+        separator: Option<&PanelDrawInfo>,
+        x: f64,
+        height: u16,
+    ) -> Result<f64> {
+        let Some(separator) = separator else {
+            return Ok(0.0);
+        };
+        cr.save()?;
+        cr.translate(x, f64::from(i32::from(height) - separator.height) / 2.0);
+        (separator.draw_fn)(cr)?;
+        cr.restore()?;
+        Ok(f64::from(separator.width))
+    }
+
+    /// Draws `panels` from left to right, starting at `x`, with separators
+    /// between them. Each expanding panel is followed by `expand` pixels of
+    /// empty space. Returns the x-coordinate of the right edge of the last
+    /// panel.
+    pub(crate) fn draw_panels<'a>(
+        cr: &cairo::Context,
+        panels: impl IntoIterator<Item = &'a mut Panel>,
+        separator: Option<&PanelDrawInfo>,
+        mut x: f64,
+        height: u16,
+        expand: f64,
+    ) -> Result<f64> {
+        let mut first = true;
+
+        for panel in panels {
+            let Some(draw_info) = &panel.draw_info else {
+                continue;
+            };
+            if draw_info.expand {
+                first = true;
+            } else if draw_info.width > 0 {
+                if !first {
+                    x += Self::draw_separator(cr, separator, x, height)?;
+                }
+                first = false;
+            }
+            cr.save()?;
+            let y = f64::from(i32::from(height) - draw_info.height) / 2.0;
+            panel.x = x;
+            panel.y = y;
+            cr.translate(x, y);
+            panel.draw(cr)?;
+            x += f64::from(draw_info.width);
+            if draw_info.expand {
+                x += expand;
+            }
+            cr.restore()?;
+        }
+
+        Ok(x)
+    }
+
+    /// Shows or hides the bar. A hidden bar doesn't reserve space on the
+    /// screen. If the bar hides itself automatically, showing it has the same
+    /// effect as revealing it with the pointer.
+    pub fn set_visible(&mut self, visible: bool) -> Result<()> {
+        if let Some(state) = &mut self.autohide {
+            // hide again later unless the pointer enters the bar
+            state.pending = visible
+                .then(|| (Instant::now() + state.config.hide_timeout, false));
+            return self.autohide_show(visible);
+        }
+        if visible == self.visible {
+            return Ok(());
+        }
+        self.visible = visible;
+        if visible {
+            let geometry = bar_geometry(
+                &self.conn,
+                self.screen,
+                self.position,
+                self.placement,
+                self.height,
+                self.monitor.as_ref(),
+            )?;
+            set_wm_properties(
+                &self.conn,
+                self.screen,
+                self.window,
+                self.position,
+                geometry,
+                self.height.into(),
+            )?;
+            map_window(&self.conn, self.window)
+        } else {
+            clear_strut(&self.conn, self.window)?;
+            self.conn.check_request(self.conn.send_request_checked(
+                &x::UnmapWindow {
+                    window: self.window,
+                },
+            ))?;
+            Ok(())
+        }
+    }
+
+    /// Opens or closes a window directly below the bar (or above it, for bars
+    /// at the bottom of the screen) that shows how many times each panel has
+    /// updated, when it last updated, and how long it took to draw. It's
+    /// refreshed every second while it's open, and it closes when it's
+    /// clicked.
+    pub fn set_debug_overlay(&mut self, enabled: bool) -> Result<()> {
+        self.debug_overlay = None;
+        if !enabled {
+            return Ok(());
+        }
+        let geometry = bar_geometry(
+            &self.conn,
+            self.screen,
+            self.position,
+            self.placement,
+            self.height,
+            self.monitor.as_ref(),
+        )?;
+        let anchor = Anchor {
+            x: geometry.x,
+            y: match self.position {
+                Position::Top => geometry
+                    .y
+                    .saturating_add(i16::try_from(self.height).unwrap_or(0)),
+                Position::Bottom => geometry.y,
+            },
+            position: self.position,
+        };
+        self.debug_overlay =
+            Some(Popup::open(anchor, self.debug_overlay_content()?)?);
+        Ok(())
+    }
+
+    /// Returns whether the debug overlay is open.
+    pub(crate) const fn debug_overlay_open(&self) -> bool {
+        self.debug_overlay.is_some()
+    }
+
+    /// Redraws the debug overlay with the latest numbers, unless it has been
+    /// closed.
+    pub(crate) fn update_debug_overlay(&mut self) -> Result<()> {
+        let Some(overlay) = &self.debug_overlay else {
+            return Ok(());
+        };
+        if !overlay.is_open() {
+            self.debug_overlay = None;
+            return Ok(());
+        }
+        overlay.update(self.debug_overlay_content()?)
+    }
+
+    fn debug_overlay_content(&self) -> Result<PanelDrawInfo> {
+        let now = Instant::now();
+        let lines = [Alignment::Left, Alignment::Center, Alignment::Right]
+            .into_iter()
+            .flat_map(|alignment| {
+                self.panels(alignment)
+                    .iter()
+                    .map(move |panel| (alignment, panel))
+            })
+            .map(|(alignment, panel)| {
+                let last = panel.stats.last_update.map_or_else(
+                    || String::from("never"),
+                    |at| format!("{:.1}s ago", (now - at).as_secs_f64()),
+                );
+                let mut line = format!(
+                    "{:<6} {:<16} {:>6} updates  last {:>10}  draw {:>10.2?}",
+                    alignment.to_string(),
+                    panel.name,
+                    panel.stats.updates,
+                    last,
+                    panel.stats.draw_time.get(),
+                );
+                if panel.stale {
+                    line.push_str("  stale");
+                }
+                if panel.failures > 0 {
+                    line.push_str(
+                        format!("  failed {}x", panel.failures).as_str(),
+                    );
+                }
+                line
+            })
+            .collect::<Vec<_>>();
+        draw_common(
+            &self.cr,
+            format!(
+                "<tt>{}</tt>",
+                glib::markup_escape_text(lines.join("\n").as_str())
+            )
+            .as_str(),
+            &self.attrs,
+            Dependence::None,
+            &Ellipsize::default(),
+        )
+    }
+
+    /// How long the panels have taken to draw and the bar has taken to
+    /// present them altogether.
+    fn draw_and_present_time(&self) -> Duration {
+        [&self.left, &self.center, &self.right]
+            .into_iter()
+            .flatten()
+            .map(|panel| panel.stats.draws.get().total)
+            .sum::<Duration>()
+            + self.present_time.get().total
+    }
+
+    /// Summarizes how long panel updates have taken since the bar started,
+    /// which is printed at the end of a benchmark run. Panels are listed
+    /// from the one that took the longest to draw altogether.
+    #[must_use]
+    pub fn profile_summary(&self, elapsed: Duration) -> String {
+        let mut panels = [&self.left, &self.center, &self.right]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        panels.sort_by_key(|panel| {
+            std::cmp::Reverse(panel.stats.draws.get().total)
+        });
+        let row = |name: &str, timing: Timing| {
+            format!(
+                "{name:<20} {:>8} {:>12.2?} {:>12.2?} {:>12.2?}\n",
+                timing.count,
+                timing.mean(),
+                timing.max,
+                timing.total,
+            )
+        };
+
+        let mut summary = format!(
+            "{}{} over {:.1?}, {} updates\n{:<20} {:>8} {:>12} {:>12} \
+             {:>12}\n",
+            self.name,
+            self.monitor
+                .as_ref()
+                .map(|m| format!(" on {}", m.name))
+                .unwrap_or_default(),
+            elapsed,
+            self.layout_time.count,
+            "",
+            "count",
+            "mean",
+            "max",
+            "total",
+        );
+        for panel in panels {
+            summary.push_str(
+                row(
+                    format!("draw {}", panel.name).as_str(),
+                    panel.stats.draws.get(),
+                )
+                .as_str(),
+            );
+        }
+        summary.push_str(row("layout", self.layout_time).as_str());
+        summary.push_str(row("present", self.present_time.get()).as_str());
+        summary
+    }
+
+    /// Returns the name and current text of each panel, from left to right.
+    #[must_use]
+    pub fn panel_texts(&self) -> Vec<(String, String)> {
+        self.left
+            .iter()
+            .chain(&self.center)
+            .chain(&self.right)
+            .map(|p| {
+                (
+                    p.name.clone(),
+                    p.draw_info
+                        .as_ref()
+                        .map(|d| d.text.clone())
+                        .unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    /// Carries out a command received over IPC. Commands that affect the
+    /// whole process are handled before they reach the bar.
+    pub fn handle_command(&mut self, command: &Command) -> Result<()> {
+        match command {
+            Command::Show => self.set_visible(true),
+            Command::Hide => self.set_visible(false),
+            Command::Toggle => self.set_visible(!self.visible),
+            Command::Refresh(name) => {
+                for panel in self.panels_named(name.as_str())? {
+                    match &panel.refresh {
+                        Some(send) => {
+                            let _ = send.send(());
+                        }
+                        None => {
+                            return Err(anyhow!("{name} can't be refreshed"))
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Command::ShowPanel(name) => {
+                self.set_panel_hidden(name, Some(false))
+            }
+            Command::HidePanel(name) => self.set_panel_hidden(name, Some(true)),
+            Command::TogglePanel(name) => self.set_panel_hidden(name, None),
+            Command::EnablePanel(name) => self.set_panel_enabled(name, true),
+            Command::DisablePanel(name) => self.set_panel_enabled(name, false),
+            Command::ToggleDebug => {
+                self.set_debug_overlay(self.debug_overlay.is_none())
+            }
+            Command::Reload
+            | Command::Quit
+            | Command::Theme(_)
+            | Command::ToggleTheme => Ok(()),
+        }
+    }
+
+    /// Returns every panel with the given name, or an error if there are
+    /// none.
+    fn panels_named(&mut self, name: &str) -> Result<Vec<&mut Panel>> {
+        let panels = self
+            .left
+            .iter_mut()
+            .chain(&mut self.center)
+            .chain(&mut self.right)
+            .filter(|p| p.name == name)
+            .collect::<Vec<_>>();
+        if panels.is_empty() {
+            return Err(anyhow!("No panel named {name}"));
+        }
+        Ok(panels)
+    }
+
+    /// Hides or shows every panel with the given name, or toggles them if
+    /// `hidden` is [`None`]. Hidden panels keep running, so they're up to
+    /// date when they're shown again.
+    fn set_panel_hidden(
+        &mut self,
+        name: &str,
+        hidden: Option<bool>,
+    ) -> Result<()> {
+        for panel in self.panels_named(name)? {
+            panel.hidden = hidden.unwrap_or(!panel.hidden);
+        }
+        self.redraw_bar()
+    }
+
+    /// Disables every panel with the given name, dropping its stream, or
+    /// builds disabled panels with the given name again and starts them.
+    /// Panels that are already in the requested state are left alone.
+    fn set_panel_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        self.panels_named(name)?;
+        if enabled && !self.restarts.can_restart() {
+            return Err(anyhow!("{name} can't be built again"));
+        }
+        let found = [Alignment::Left, Alignment::Center, Alignment::Right]
+            .into_iter()
+            .flat_map(|alignment| {
+                self.panels(alignment)
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| p.name == name && p.disabled == enabled)
+                    .map(move |(idx, _)| (alignment, idx))
+            })
+            .collect::<Vec<_>>();
+        for (alignment, idx) in found {
+            if enabled {
+                let panel = self.panel_mut(alignment, idx);
+                panel.disabled = false;
+                panel.failures = 0;
+                self.restart_panel(alignment, idx);
+            } else {
+                self.restarts.cancel(|a, i| (a, i) == (alignment, idx));
+                self.remove_stream(alignment, idx);
+                let panel = self.panel_mut(alignment, idx);
+                panel.disabled = true;
+                panel.draw_info = None;
+                panel.events = None;
+                panel.refresh = None;
+                panel.stall_deadline = None;
+                panel.stale = false;
+            }
+        }
+        self.redraw_bar()
+    }
+
+    pub(crate) fn apply_dependence(panels: &[Panel]) -> Vec<PanelStatus> {
         (0..panels.len())
             .map(|idx| match PanelStatus::from(&panels[idx]) {
                 PanelStatus::Shown => PanelStatus::Shown,
@@ -232,14 +1885,142 @@ impl Bar {
             .collect()
     }
 
+    fn panel_mut(&mut self, alignment: Alignment, idx: usize) -> &mut Panel {
+        match alignment {
+            Alignment::Left => self.left.get_mut(idx),
+            Alignment::Center => self.center.get_mut(idx),
+            Alignment::Right => self.right.get_mut(idx),
+        }
+        .expect("one or more panels have vanished")
+    }
+
+    fn panels(&self, alignment: Alignment) -> &[Panel] {
+        match alignment {
+            Alignment::Left => &self.left,
+            Alignment::Center => &self.center,
+            Alignment::Right => &self.right,
+        }
+    }
+
+    /// The left, center, and right panels.
+    fn sections(&self) -> [&[Panel]; 3] {
+        [&self.left, &self.center, &self.right]
+    }
+
+    /// Resolves which panels with the given alignment are shown. See
+    /// [`statuses`].
+    fn statuses(&self, alignment: Alignment) -> Vec<PanelStatus> {
+        statuses(self.sections(), alignment)
+    }
+
+    /// Returns which panels are shown in each part of the bar that has a
+    /// panel with a `depends_on` expression, or [`None`] if there are no such
+    /// panels.
+    fn dependent_statuses(&self) -> Option<Vec<PanelStatus>> {
+        let mut statuses = Vec::new();
+        for alignment in [Alignment::Left, Alignment::Center, Alignment::Right]
+        {
+            if self
+                .panels(alignment)
+                .iter()
+                .any(|panel| panel.depends_on.is_some())
+            {
+                statuses.extend(self.statuses(alignment));
+            }
+        }
+        (!statuses.is_empty()).then_some(statuses)
+    }
+
     /// Handle an event from the X server.
     pub fn process_event(&mut self, event: &Event) -> Result<()> {
         match event {
             Event::X(x::Event::Expose(_)) => self.redraw_bar(),
+            Event::X(x::Event::PropertyNotify(event))
+                if event.atom() == self.root_pixmap
+                    && matches!(
+                        self.bg_image,
+                        Some(BackgroundImage::Wallpaper)
+                    ) =>
+            {
+                self.load_background()?;
+                self.redraw_bar()
+            }
+            Event::X(x::Event::EnterNotify(event))
+                if event.mode() == x::NotifyMode::Normal =>
+            {
+                self.pointer_crossed(event.event(), true);
+                Ok(())
+            }
+            Event::X(x::Event::LeaveNotify(event))
+                if event.mode() == x::NotifyMode::Normal =>
+            {
+                self.pointer_crossed(event.event(), false);
+                Ok(())
+            }
+            Event::X(x::Event::ButtonPress(event)) => {
+                if let Some(button) = MouseButton::try_from_x(event.detail()) {
+                    // the bar's position on the screen, for anchoring popups
+                    let origin = (
+                        event.root_x() - event.event_x(),
+                        event.root_y() - event.event_y(),
+                    );
+                    self.dispatch_click(
+                        button,
+                        f64::from(event.event_x()) / self.scale,
+                        f64::from(event.event_y()) / self.scale,
+                        origin,
+                    );
+                }
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
 
+    /// Sends a click or scroll at (x, y) in bar coordinates to the visible
+    /// panel under the pointer. If the panel has an action bound to the
+    /// button, the action is run instead.
+    #[allow(clippy::cast_possible_truncation)]
+    fn dispatch_click(
+        &self,
+        button: MouseButton,
+        x: f64,
+        y: f64,
+        origin: (i16, i16),
+    ) {
+        let target = [Alignment::Left, Alignment::Center, Alignment::Right]
+            .into_iter()
+            .flat_map(|alignment| {
+                self.statuses(alignment)
+                    .into_iter()
+                    .zip(self.panels(alignment))
+            })
+            .filter(|(status, _)| *status == PanelStatus::Shown)
+            .map(|(_, panel)| panel)
+            .find(|panel| {
+                panel.draw_info.as_ref().is_some_and(|d| {
+                    x >= panel.x && x < panel.x + f64::from(d.width)
+                })
+            });
+
+        let Some(panel) = target else {
+            return;
+        };
+        panel.click(
+            button,
+            x - panel.x - panel.inset,
+            y - panel.y,
+            Anchor {
+                x: origin.0 + ((panel.x + panel.inset) * self.scale) as i16,
+                y: match self.position {
+                    Position::Top => origin.1 + self.height as i16,
+                    Position::Bottom => origin.1,
+                },
+                position: self.position,
+            },
+        );
+    }
+
     fn redraw_background(&self, scope: &Region) -> Result<()> {
         self.cr.save()?;
         self.cr.set_operator(cairo::Operator::Source);
@@ -250,26 +2031,26 @@ impl Bar {
                 0.0,
                 0.0,
                 self.extents.left,
-                f64::from(self.height),
+                f64::from(self.logical_height()),
             ),
             Region::CenterRight => self.cr.rectangle(
                 self.extents.center.0,
                 0.0,
-                f64::from(self.width) - self.extents.center.0,
-                f64::from(self.height),
+                f64::from(self.logical_width()) - self.extents.center.0,
+                f64::from(self.logical_height()),
             ),
             Region::Right => self.cr.rectangle(
                 self.extents.right,
                 0.0,
-                f64::from(self.width) - self.extents.right,
-                f64::from(self.height),
+                f64::from(self.logical_width()) - self.extents.right,
+                f64::from(self.logical_height()),
             ),
             Region::All => {
                 self.cr.rectangle(
                     0.0,
                     0.0,
-                    f64::from(self.width),
-                    f64::from(self.height),
+                    f64::from(self.logical_width()),
+                    f64::from(self.logical_height()),
                 );
             }
             Region::Custom { start_x, end_x } => {
@@ -277,10 +2058,15 @@ impl Bar {
                     *start_x,
                     0.0,
                     end_x - start_x,
-                    f64::from(self.height),
+                    f64::from(self.logical_height()),
                 );
             }
         }
+        if let Some(pattern) = &self.bg_pattern {
+            self.cr.fill_preserve()?;
+            self.cr.set_operator(cairo::Operator::Over);
+            self.cr.set_source(pattern)?;
+        }
         self.cr.fill()?;
         self.cr.restore()?;
 
@@ -292,8 +2078,39 @@ impl Bar {
         &mut self,
         alignment: Alignment,
         idx: usize,
-        draw_info: PanelDrawInfo,
+        mut draw_info: PanelDrawInfo,
     ) -> Result<()> {
+        // panels elsewhere on the bar may depend on this one
+        if let Some(before) = self.dependent_statuses() {
+            let old =
+                self.panel_mut(alignment, idx).draw_info.replace(draw_info);
+            if self.dependent_statuses().as_ref() != Some(&before) {
+                return self.redraw_bar();
+            }
+            // put the old content back so that the widths can be compared
+            draw_info = std::mem::replace(
+                &mut self.panel_mut(alignment, idx).draw_info,
+                old,
+            )
+            .expect("the new content was just set");
+        }
+
+        let panel = match alignment {
+            Alignment::Left => self.left.get_mut(idx),
+            Alignment::Center => self.center.get_mut(idx),
+            Alignment::Right => self.right.get_mut(idx),
+        }
+        .expect("one or more panels have vanished");
+        let was_expanding = panel.draw_info.as_ref().is_some_and(|d| d.expand);
+        if panel.hidden {
+            panel.draw_info = Some(draw_info);
+            return Ok(());
+        }
+        if was_expanding != draw_info.expand {
+            panel.draw_info = Some(draw_info);
+            return self.redraw_bar();
+        }
+
         let new_width = f64::from(draw_info.width);
         match alignment {
             Alignment::Left => {
@@ -313,6 +2130,10 @@ impl Bar {
 
                 if (new_width - cur_width).abs() < f64::EPSILON {
                     self.redraw_one(alignment, idx)?;
+                } else if self.separators_change(cur_width, new_width)
+                    || self.expanding()
+                {
+                    self.redraw_bar()?;
                 } else if new_width - cur_width
                     + self.extents.left
                     + self.margins.internal
@@ -367,6 +2188,10 @@ impl Bar {
 
                 if (new_width - cur_width).abs() < f64::EPSILON {
                     self.redraw_one(alignment, idx)?;
+                } else if self.separators_change(cur_width, new_width)
+                    || self.expanding()
+                {
+                    self.redraw_bar()?;
                 } else if self.extents.right
                     - new_width
                     - cur_width
@@ -383,13 +2208,12 @@ impl Bar {
                     > new_width - cur_width
                 {
                     self.extents.right += new_width - cur_width;
-                    self.redraw_center_right(true)?;
+                    self.redraw_center_right()?;
                 } else {
                     self.redraw_bar()?;
                 }
 
-                self.surface.flush();
-                self.conn.flush()?;
+                self.present()?;
 
                 Ok(())
             }
@@ -411,11 +2235,10 @@ impl Bar {
                         end_x: panel.x + f64::from(draw_info.width),
                     })?;
                     self.cr.translate(panel.x, panel.y);
-                    (draw_info.draw_fn)(&self.cr)?;
+                    panel.draw(&self.cr)?;
                 }
 
-                self.surface.flush();
-                self.conn.flush()?;
+                self.present()?;
                 self.cr.restore()?;
 
                 Ok(())
@@ -438,11 +2261,10 @@ impl Bar {
                         end_x: panel.x + f64::from(draw_info.width),
                     })?;
                     self.cr.translate(panel.x, panel.y);
-                    (draw_info.draw_fn)(&self.cr)?;
+                    panel.draw(&self.cr)?;
                 }
 
-                self.surface.flush();
-                self.conn.flush()?;
+                self.present()?;
                 self.cr.restore()?;
 
                 Ok(())
@@ -465,11 +2287,10 @@ impl Bar {
                         end_x: panel.x + f64::from(draw_info.width),
                     })?;
                     self.cr.translate(panel.x, panel.y);
-                    (draw_info.draw_fn)(&self.cr)?;
+                    panel.draw(&self.cr)?;
                 }
 
-                self.surface.flush();
-                self.conn.flush()?;
+                self.present()?;
                 self.cr.restore()?;
 
                 Ok(())
@@ -485,150 +2306,144 @@ impl Bar {
     /// other cases in which we can redraw only the left or right side. See
     /// [`Bar::update_panel`] for specifics.
     pub fn redraw_bar(&mut self) -> Result<()> {
+        self.clip();
         self.redraw_background(&Region::All)?;
 
-        self.redraw_left()?;
-        self.redraw_center_right(false)?;
+        let statuses = [Alignment::Left, Alignment::Center, Alignment::Right]
+            .map(|alignment| self.statuses(alignment));
+        let layout = plan_layout(
+            self.logical_width(),
+            &self.margins,
+            self.separator.as_ref(),
+            self.sections(),
+            &statuses,
+        );
+        let height = self.logical_height();
+        let (left_end, center_end) = draw_layout(
+            &self.cr,
+            [
+                self.left.as_mut_slice(),
+                self.center.as_mut_slice(),
+                self.right.as_mut_slice(),
+            ],
+            &statuses,
+            self.separator.as_ref(),
+            &layout,
+            height,
+        )?;
+        self.extents = Extents {
+            left: left_end,
+            center: (layout.center, center_end),
+            right: layout.right,
+            expand: layout.expand,
+        };
+        self.center_state = layout.center_state;
+
+        self.draw_border()?;
+        self.present()?;
 
         Ok(())
     }
 
-    fn redraw_left(&mut self) -> Result<()> {
-        self.redraw_background(&Region::Left)?;
-
-        self.extents.left = self.margins.left;
-
-        let statuses = Self::apply_dependence(self.left.as_slice());
-
-        for panel in self
-            .left
-            .iter_mut()
-            .enumerate()
-            .filter(|(idx, _)| {
-                statuses.get(*idx).unwrap() == &PanelStatus::Shown
-            })
-            .map(|(_, panel)| panel)
-        {
-            if let Some(draw_info) = &panel.draw_info {
-                self.cr.save()?;
-                let x = self.extents.left;
-                let y =
-                    f64::from(i32::from(self.height) - draw_info.height) / 2.0;
-                panel.x = x;
-                panel.y = y;
-                self.cr.translate(x, y);
-                (draw_info.draw_fn)(&self.cr)?;
-                self.extents.left += f64::from(draw_info.width);
-                self.cr.restore()?;
-            }
-        }
-
+    /// Sends what has been drawn to the X server.
+    fn present(&self) -> Result<()> {
+        let start = Instant::now();
         self.surface.flush();
         self.conn.flush()?;
-
+        let mut present_time = self.present_time.get();
+        present_time.record(start.elapsed());
+        self.present_time.set(present_time);
         Ok(())
     }
 
-    fn redraw_center_right(&mut self, standalone: bool) -> Result<()> {
-        if standalone {
-            self.redraw_background(&Region::CenterRight)?;
-        }
+    fn redraw_left(&mut self) -> Result<()> {
+        self.redraw_background(&Region::Left)?;
 
-        let center_statuses = Self::apply_dependence(self.center.as_slice());
+        let statuses = self.statuses(Alignment::Left);
 
-        let center_panels = self
-            .center
-            .iter_mut()
-            .enumerate()
-            .filter(|(idx, _)| {
-                center_statuses.get(*idx).unwrap() == &PanelStatus::Shown
-            })
-            .map(|(_, panel)| panel)
-            .collect::<Vec<_>>();
+        self.extents.left = Self::draw_panels(
+            &self.cr,
+            self.left
+                .iter_mut()
+                .enumerate()
+                .filter(|(idx, _)| {
+                    statuses.get(*idx).unwrap() == &PanelStatus::Shown
+                })
+                .map(|(_, panel)| panel),
+            self.separator.as_ref(),
+            self.margins.left,
+            self.logical_height(),
+            self.extents.expand.0,
+        )?;
 
-        let right_statuses = Self::apply_dependence(self.right.as_slice());
+        self.present()?;
 
-        let right_panels = self
-            .right
-            .iter()
-            .enumerate()
-            .filter(|(idx, _)| {
-                right_statuses.get(*idx).unwrap() == &PanelStatus::Shown
-            })
-            .map(|(_, panel)| panel);
+        Ok(())
+    }
 
-        let center_width = f64::from(
-            center_panels
-                .iter()
-                .filter_map(|p| p.draw_info.as_ref().map(|i| i.width))
-                .sum::<i32>(),
-        );
+    fn redraw_center_right(&mut self) -> Result<()> {
+        self.redraw_background(&Region::CenterRight)?;
 
-        self.extents.right = f64::from(
-            self.width
-                - right_panels
-                    .filter_map(|p| p.draw_info.as_ref().map(|i| i.width))
-                    .sum::<i32>(),
-        ) - self.margins.internal;
-
-        if center_width
-            > 2.0f64.mul_add(
-                -self.margins.internal,
-                self.extents.right - self.extents.left,
-            )
-        {
-            self.extents.center.0 = self.margins.internal + self.extents.left;
-            self.extents.center.1 = self.margins.internal + self.extents.left;
+        let center_statuses = self.statuses(Alignment::Center);
+        let right_statuses = self.statuses(Alignment::Right);
+
+        if self.expanding() {
+            // the center was placed by `plan_layout`
             self.center_state = CenterState::Unknown;
-        } else if center_width / 2.0
-            > self.extents.right
-                - f64::from(self.width / 2)
-                - self.margins.internal
-        {
-            self.extents.center.0 =
-                self.extents.right - center_width - self.margins.internal;
-            self.extents.center.1 =
-                self.extents.right - center_width - self.margins.internal;
-            self.center_state = CenterState::Left;
-        } else if center_width / 2.0
-            > f64::from(self.width / 2)
-                - self.extents.left
-                - self.margins.internal
-        {
-            self.extents.center.0 = self.extents.left + self.margins.internal;
-            self.extents.center.1 = self.extents.left + self.margins.internal;
-            self.center_state = CenterState::Right;
         } else {
-            self.extents.center.0 =
-                f64::from(self.width / 2) - center_width / 2.0;
-            self.extents.center.1 =
-                f64::from(self.width / 2) - center_width / 2.0;
-            self.center_state = CenterState::Center;
+            let (center_width, _) = Self::measure(
+                &self.center,
+                &center_statuses,
+                self.separator.as_ref(),
+            );
+            let (right_width, _) = Self::measure(
+                &self.right,
+                &right_statuses,
+                self.separator.as_ref(),
+            );
+            self.extents.right = f64::from(self.logical_width())
+                - right_width
+                - self.margins.internal;
+            self.place_center(center_width);
         }
 
-        for panel in center_panels {
-            if let Some(draw_info) = &panel.draw_info {
-                self.cr.save()?;
-                let x = self.extents.center.1;
-                let y =
-                    f64::from(i32::from(self.height) - draw_info.height) / 2.0;
-                panel.x = x;
-                panel.y = y;
-                self.cr.translate(x, y);
-                (draw_info.draw_fn)(&self.cr)?;
-                self.extents.center.1 += f64::from(draw_info.width);
-                self.cr.restore()?;
-            }
-        }
+        self.extents.center.1 = Self::draw_panels(
+            &self.cr,
+            self.center
+                .iter_mut()
+                .enumerate()
+                .filter(|(idx, _)| {
+                    center_statuses.get(*idx).unwrap() == &PanelStatus::Shown
+                })
+                .map(|(_, panel)| panel),
+            self.separator.as_ref(),
+            self.extents.center.0,
+            self.logical_height(),
+            self.extents.expand.1,
+        )?;
 
-        self.redraw_right(standalone, Some(right_statuses))?;
+        self.redraw_right(true, Some(right_statuses))?;
 
-        self.surface.flush();
-        self.conn.flush()?;
+        self.present()?;
 
         Ok(())
     }
 
+    /// Centers a group of panels of the given width between the left and
+    /// right panels. See [`place_center`].
+    fn place_center(&mut self, center_width: f64) {
+        let (center_start, center_state) = place_center(
+            self.logical_width(),
+            self.extents.left,
+            self.extents.right,
+            self.margins.internal,
+            center_width,
+        );
+        self.extents.center = (center_start, center_start);
+        self.center_state = center_state;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
     fn redraw_right(
         &mut self,
         standalone: bool,
@@ -638,55 +2453,348 @@ impl Bar {
             self.redraw_background(&Region::Right)?;
         }
 
-        let statuses = statuses
-            .unwrap_or_else(|| Self::apply_dependence(self.right.as_slice()));
+        let statuses =
+            statuses.unwrap_or_else(|| self.statuses(Alignment::Right));
+
+        let (width, count) =
+            Self::measure(&self.right, &statuses, self.separator.as_ref());
+        self.extents.right = right_start(
+            self.logical_width(),
+            self.extents.center.1,
+            &self.margins,
+            self.extents.expand.2.mul_add(count as f64, width),
+        );
 
-        let total_width = f64::from(
+        Self::draw_panels(
+            &self.cr,
             self.right
-                .iter()
+                .iter_mut()
                 .enumerate()
                 .filter(|(idx, _)| {
                     statuses.get(*idx).unwrap() == &PanelStatus::Shown
                 })
-                .map(|(_, panel)| panel)
-                .filter_map(|p| p.draw_info.as_ref().map(|i| i.width))
-                .sum::<i32>(),
-        ) + self.margins.right;
+                .map(|(_, panel)| panel),
+            self.separator.as_ref(),
+            self.extents.right,
+            self.logical_height(),
+            self.extents.expand.2,
+        )?;
+
+        self.present()?;
+
+        Ok(())
+    }
+}
+
+/// Where the parts of a bar are drawn, as worked out by [`plan_layout`].
+#[derive(Debug)]
+pub(crate) struct Layout {
+    /// The x-coordinate of the left edge of the left part
+    pub(crate) left: f64,
+    /// The x-coordinate of the left edge of the center part
+    pub(crate) center: f64,
+    /// The x-coordinate of the left edge of the right part
+    pub(crate) right: f64,
+    /// The space given to each expanding panel in the left, center, and right
+    /// parts of the bar
+    pub(crate) expand: (f64, f64, f64),
+    center_state: CenterState,
+}
+
+/// Lays out the shown panels of `sections`, the left, center, and right
+/// parts of a bar `width` logical pixels wide. `statuses` are the statuses
+/// of the panels in each part, as returned by [`statuses`].
+///
+/// If any shown panel expands, the space between the parts is divided among
+/// the expanding panels. If the center part has expanding panels, it takes
+/// all of the space between the left and right parts. Otherwise, the left
+/// and right parts each fill the space between themselves and the center.
+///
+/// If no panel expands, the center part is centered on the bar, or pushed
+/// away from the left or right part if there isn't room.
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn plan_layout(
+    width: i32,
+    margins: &Margins,
+    separator: Option<&PanelDrawInfo>,
+    sections: [&[Panel]; 3],
+    statuses: &[Vec<PanelStatus>; 3],
+) -> Layout {
+    let measure =
+        |idx: usize| Bar::measure(sections[idx], &statuses[idx], separator);
+    let (left_width, left_count) = measure(0);
+    let (center_width, center_count) = measure(1);
+    let (right_width, right_count) = measure(2);
+    let internal = margins.internal;
+    let left_end = margins.left + left_width;
+
+    if left_count + center_count + right_count == 0 {
+        let (center, center_state) = place_center(
+            width,
+            left_end,
+            f64::from(width) - right_width - internal,
+            internal,
+            center_width,
+        );
+        return Layout {
+            left: margins.left,
+            center,
+            right: right_start(
+                width,
+                center + center_width,
+                margins,
+                right_width,
+            ),
+            expand: (0.0, 0.0, 0.0),
+            center_state,
+        };
+    }
 
-        if total_width > f64::from(self.width) - self.extents.center.1 {
-            self.extents.right = self.extents.center.1 + self.margins.internal;
+    let right_edge = f64::from(width) - margins.right - right_width;
+    let share = |space: f64, count: usize| {
+        if count == 0 {
+            0.0
         } else {
-            self.extents.right = f64::from(self.width) - total_width;
+            space.max(0.0) / count as f64
         }
+    };
+
+    let center_expand = share(
+        2.0f64.mul_add(-internal, right_edge - left_end) - center_width,
+        center_count,
+    );
+    let center = if center_count > 0 {
+        left_end + internal
+    } else if center_width > 0.0 || (left_count > 0 && right_count > 0) {
+        (f64::from(width / 2) - center_width / 2.0)
+            .min(right_edge - internal - center_width)
+            .max(left_end + internal)
+    } else if left_count > 0 {
+        right_edge
+    } else {
+        left_end
+    };
+    let center_end =
+        center_expand.mul_add(center_count as f64, center + center_width);
+    let right_expand = share(right_edge - internal - center_end, right_count);
+
+    Layout {
+        left: margins.left,
+        center,
+        right: right_start(
+            width,
+            center_end,
+            margins,
+            right_expand.mul_add(right_count as f64, right_width),
+        ),
+        expand: (
+            share(center - internal - left_end, left_count),
+            center_expand,
+            right_expand,
+        ),
+        center_state: CenterState::Unknown,
+    }
+}
 
-        let mut temp = self.extents.right;
+/// Centers a group of panels `center_width` pixels wide on a bar `width`
+/// pixels wide, or pushes it away from the left or right panels, which end
+/// at `left_end` and start at `right_start`, if there isn't room. Returns the
+/// x-coordinate of the left edge of the group.
+fn place_center(
+    width: i32,
+    left_end: f64,
+    right_start: f64,
+    internal: f64,
+    center_width: f64,
+) -> (f64, CenterState) {
+    if center_width > 2.0f64.mul_add(-internal, right_start - left_end) {
+        (internal + left_end, CenterState::Unknown)
+    } else if center_width / 2.0 > right_start - f64::from(width / 2) - internal
+    {
+        (right_start - center_width - internal, CenterState::Left)
+    } else if center_width / 2.0 > f64::from(width / 2) - left_end - internal {
+        (left_end + internal, CenterState::Right)
+    } else {
+        (
+            f64::from(width / 2) - center_width / 2.0,
+            CenterState::Center,
+        )
+    }
+}
 
-        for panel in self
-            .right
-            .iter_mut()
-            .enumerate()
-            .filter(|(idx, _)| {
-                statuses.get(*idx).unwrap() == &PanelStatus::Shown
-            })
-            .map(|(_, panel)| panel)
+/// Returns the x-coordinate of the left edge of the right panels, which take
+/// up `right_width` pixels including the space given to expanding panels, on
+/// a bar `width` pixels wide whose center panels end at `center_end`.
+fn right_start(
+    width: i32,
+    center_end: f64,
+    margins: &Margins,
+    right_width: f64,
+) -> f64 {
+    let total_width = right_width + margins.right;
+    if total_width > f64::from(width) - center_end {
+        center_end + margins.internal
+    } else {
+        f64::from(width) - total_width
+    }
+}
+
+/// Draws the shown panels of `sections`, the left, center, and right parts
+/// of a bar, where `layout` places them. Returns the x-coordinates of the
+/// right edges of the left and center parts.
+pub(crate) fn draw_layout(
+    cr: &cairo::Context,
+    sections: [&mut [Panel]; 3],
+    statuses: &[Vec<PanelStatus>; 3],
+    separator: Option<&PanelDrawInfo>,
+    layout: &Layout,
+    height: u16,
+) -> Result<(f64, f64)> {
+    let [left, center, right] = sections;
+
+    let left_end = Bar::draw_panels(
+        cr,
+        shown(left, &statuses[0]),
+        separator,
+        layout.left,
+        height,
+        layout.expand.0,
+    )?;
+    let center_end = Bar::draw_panels(
+        cr,
+        shown(center, &statuses[1]),
+        separator,
+        layout.center,
+        height,
+        layout.expand.1,
+    )?;
+    Bar::draw_panels(
+        cr,
+        shown(right, &statuses[2]),
+        separator,
+        layout.right,
+        height,
+        layout.expand.2,
+    )?;
+
+    Ok((left_end, center_end))
+}
+
+/// Returns the panels among `panels` whose status is
+/// [`Shown`][PanelStatus::Shown].
+fn shown<'a>(
+    panels: &'a mut [Panel],
+    statuses: &'a [PanelStatus],
+) -> impl Iterator<Item = &'a mut Panel> {
+    panels
+        .iter_mut()
+        .zip(statuses)
+        .filter(|(_, status)| **status == PanelStatus::Shown)
+        .map(|(panel, _)| panel)
+}
+
+/// Resolves which panels with the given alignment are shown, taking both
+/// their [`Dependence`] and their
+/// [`depends_on`][crate::PanelCommon::depends_on] into account. `sections`
+/// are the left, center, and right panels of the bar.
+pub(crate) fn statuses(
+    sections: [&[Panel]; 3],
+    alignment: Alignment,
+) -> Vec<PanelStatus> {
+    let panels = match alignment {
+        Alignment::Left => sections[0],
+        Alignment::Center => sections[1],
+        Alignment::Right => sections[2],
+    };
+    let statuses = Bar::apply_dependence(panels);
+    if panels.iter().all(|panel| panel.depends_on.is_none()) {
+        return statuses;
+    }
+    let values = panel_values(sections);
+    statuses
+        .into_iter()
+        .zip(panels)
+        .map(|(status, panel)| match &panel.depends_on {
+            Some(expr) if !expr.eval(&values) => PanelStatus::ZeroWidth,
+            _ => status,
+        })
+        .collect()
+}
+
+/// Returns the values that `depends_on` expressions refer to: whether each
+/// panel is shown, as its name, and the values it provides, as its name
+/// followed by a dot and the name of the value. Whether a panel is shown only
+/// takes its [`Dependence`] into account, so that panels can't depend on
+/// each other in a loop.
+fn panel_values(sections: [&[Panel]; 3]) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    for panels in sections {
+        for (panel, status) in panels.iter().zip(Bar::apply_dependence(panels))
         {
-            if let Some(draw_info) = &panel.draw_info {
-                self.cr.save()?;
-                let x = temp;
-                let y =
-                    f64::from(i32::from(self.height) - draw_info.height) / 2.0;
-                panel.x = x;
-                panel.y = y;
-                self.cr.translate(x, y);
-                (draw_info.draw_fn)(&self.cr)?;
-                temp += f64::from(draw_info.width);
-                self.cr.restore()?;
+            let shown = status == PanelStatus::Shown;
+            // a panel that appears more than once is shown if any copy is
+            values
+                .entry(panel.name.clone())
+                .and_modify(|v: &mut String| {
+                    if shown {
+                        *v = shown.to_string();
+                    }
+                })
+                .or_insert_with(|| shown.to_string());
+            let Some(draw_info) = &panel.draw_info else {
+                continue;
+            };
+            for (name, value) in &draw_info.values {
+                values.insert(format!("{}.{name}", panel.name), value.clone());
             }
         }
+    }
+    values
+}
 
-        self.surface.flush();
-        self.conn.flush()?;
-
-        Ok(())
+/// Creates a pattern that draws `image` on a bar of the given size.
+fn fit_image(
+    image: &ImageSurface,
+    scale: ImageScale,
+    width: f64,
+    height: f64,
+) -> Result<SurfacePattern> {
+    let image_width = f64::from(image.width());
+    let image_height = f64::from(image.height());
+    if image_width == 0.0 || image_height == 0.0 {
+        return Err(anyhow!("The image is empty"));
     }
+
+    let (sx, sy, extend) = match scale {
+        ImageScale::Tile => (1.0, 1.0, Extend::Repeat),
+        ImageScale::Center => (1.0, 1.0, Extend::None),
+        ImageScale::Stretch => {
+            (width / image_width, height / image_height, Extend::Pad)
+        }
+        ImageScale::Fill => {
+            let s = (width / image_width).max(height / image_height);
+            (s, s, Extend::Pad)
+        }
+    };
+    let (x, y) = if scale == ImageScale::Tile {
+        (0.0, 0.0)
+    } else {
+        (
+            sx.mul_add(-image_width, width) / 2.0,
+            sy.mul_add(-image_height, height) / 2.0,
+        )
+    };
+
+    let pattern = SurfacePattern::create(image);
+    pattern.set_extend(extend);
+    // maps bar coordinates to image coordinates
+    pattern.set_matrix(Matrix::new(
+        1.0 / sx,
+        0.0,
+        0.0,
+        1.0 / sy,
+        -x / sx,
+        -y / sy,
+    ));
+    Ok(pattern)
 }
@@ -0,0 +1,88 @@
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::{anyhow, Context, Result};
+use lazybar::ipc::{self, Command};
+
+const USAGE: &str = "usage: lazybar-msg [-b <bar>] <command>
+
+commands:
+    show                  show the bar if it's hidden
+    hide                  hide the bar
+    toggle                show the bar if it's hidden, otherwise hide it
+    reload                read the config file again and rebuild the panels
+    refresh <panel>       make a panel update immediately
+    show-panel <panel>    show a panel if it was hidden
+    hide-panel <panel>    hide a panel, keeping it up to date
+    toggle-panel <panel>  show a panel if it's hidden, otherwise hide it
+    disable-panel <panel> stop a panel until it's enabled again
+    enable-panel <panel>  start a disabled panel again
+    theme <theme>         switch to the dark or light theme, or toggle
+    debug                 show or hide the debug overlay
+    quit                  close the bar
+
+If -b isn't given and exactly one bar is running, that bar is used.";
+
+/// Finds the socket of the only running bar.
+fn only_socket() -> Result<PathBuf> {
+    let dir = ipc::socket_dir();
+    let mut sockets = fs::read_dir(dir.as_path())
+        .with_context(|| format!("No bars are running in {}", dir.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sock"));
+    match (sockets.next(), sockets.next()) {
+        (Some(socket), None) => Ok(socket),
+        (None, _) => Err(anyhow!("No bars are running")),
+        (Some(_), Some(_)) => {
+            Err(anyhow!("More than one bar is running, choose one with -b"))
+        }
+    }
+}
+
+fn send(args: Vec<String>) -> Result<()> {
+    let (socket, args) = match args.first().map(String::as_str) {
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Some("-b") => {
+            let bar =
+                args.get(1).ok_or_else(|| anyhow!("-b requires a bar"))?;
+            (ipc::socket_path(bar.as_str())?, &args[2..])
+        }
+        _ => (only_socket()?, args.as_slice()),
+    };
+    let command = args.join(" ").parse::<Command>()?;
+
+    let mut stream =
+        UnixStream::connect(socket.as_path()).with_context(|| {
+            format!("Failed to connect to {}", socket.display())
+        })?;
+    writeln!(stream, "{command}")?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+
+    match reply.trim_end() {
+        "ok" => Ok(()),
+        "" => Err(anyhow!("The bar closed the connection without replying")),
+        reply => Err(anyhow!(
+            "{}",
+            reply.strip_prefix("error: ").unwrap_or(reply)
+        )),
+    }
+}
+
+fn main() -> ExitCode {
+    match send(env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("lazybar-msg: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use anyhow::Result;
+use tokio::sync::{broadcast, watch};
+use zbus::{connection, fdo};
+
+use crate::ipc::Command;
+
+/// The object path of the service.
+const PATH: &str = "/org/lazybar/Bar";
+
+/// The name and current text of each panel on a bar, from left to right.
+pub(crate) type PanelTexts = Vec<(String, String)>;
+
+/// The monitor of each running bar with the same name, along with its panel
+/// texts. Bars are added and removed as monitors come and go.
+pub(crate) type BarTexts =
+    Arc<Mutex<Vec<(String, watch::Receiver<PanelTexts>)>>>;
+
+/// The `org.lazybar.Bar` interface. Commands are passed on to the bars the
+/// same way as commands received over [IPC][crate::ipc].
+struct Service {
+    commands: broadcast::Sender<Command>,
+    texts: BarTexts,
+}
+
+impl Service {
+    /// Returns the texts of the running bars.
+    fn texts(
+        &self,
+    ) -> MutexGuard<'_, Vec<(String, watch::Receiver<PanelTexts>)>> {
+        self.texts.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Sends a command to the bars if any of them has a panel with the given
+    /// name.
+    fn send_to_panel(
+        &self,
+        panel: String,
+        command: fn(String) -> Command,
+    ) -> fdo::Result<()> {
+        if !self.texts().iter().any(|(_, texts)| {
+            texts.borrow().iter().any(|(name, _)| *name == panel)
+        }) {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "No panel named {panel}"
+            )));
+        }
+        let _ = self.commands.send(command(panel));
+        Ok(())
+    }
+}
+
+#[zbus::interface(name = "org.lazybar.Bar")]
+impl Service {
+    /// Shows the bar if it's hidden.
+    fn show(&self) {
+        let _ = self.commands.send(Command::Show);
+    }
+
+    /// Hides the bar.
+    fn hide(&self) {
+        let _ = self.commands.send(Command::Hide);
+    }
+
+    /// Shows the bar if it's hidden, otherwise hides it.
+    fn toggle(&self) {
+        let _ = self.commands.send(Command::Toggle);
+    }
+
+    /// Makes the named panel update immediately, if it supports that.
+    fn refresh(&self, panel: String) -> fdo::Result<()> {
+        self.send_to_panel(panel, Command::Refresh)
+    }
+
+    /// Shows the named panel if it was hidden.
+    fn show_panel(&self, panel: String) -> fdo::Result<()> {
+        self.send_to_panel(panel, Command::ShowPanel)
+    }
+
+    /// Hides the named panel. It keeps updating while it's hidden.
+    fn hide_panel(&self, panel: String) -> fdo::Result<()> {
+        self.send_to_panel(panel, Command::HidePanel)
+    }
+
+    /// Shows the named panel if it's hidden, otherwise hides it.
+    fn toggle_panel(&self, panel: String) -> fdo::Result<()> {
+        self.send_to_panel(panel, Command::TogglePanel)
+    }
+
+    /// Builds the named panel again and starts it if it was disabled.
+    fn enable_panel(&self, panel: String) -> fdo::Result<()> {
+        self.send_to_panel(panel, Command::EnablePanel)
+    }
+
+    /// Stops the named panel. It doesn't update until it's enabled again.
+    fn disable_panel(&self, panel: String) -> fdo::Result<()> {
+        self.send_to_panel(panel, Command::DisablePanel)
+    }
+
+    /// Returns the monitor, name, and current text of each panel. The monitor
+    /// is empty if the bar spans the whole screen, and the text is empty if
+    /// the panel hasn't been drawn yet.
+    fn panel_texts(&self) -> Vec<(String, String, String)> {
+        self.texts()
+            .iter()
+            .flat_map(|(monitor, texts)| {
+                texts
+                    .borrow()
+                    .iter()
+                    .map(|(name, text)| {
+                        (monitor.clone(), name.clone(), text.clone())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Returns the well-known bus name for the bar named `bar_name`:
+/// `org.lazybar.Bar.<bar_name>`, with any characters that aren't allowed in
+/// bus names replaced by underscores.
+fn bus_name(bar_name: &str) -> String {
+    let mut element = bar_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if element.is_empty() || element.starts_with(|c: char| c.is_ascii_digit()) {
+        element.insert(0, '_');
+    }
+    format!("org.lazybar.Bar.{element}")
+}
+
+/// Serves the `org.lazybar.Bar` interface at `/org/lazybar/Bar` on the
+/// session bus until the process exits. See [`bus_name`] for the name that
+/// it's served under.
+pub(crate) async fn serve(
+    bar_name: &str,
+    commands: broadcast::Sender<Command>,
+    texts: BarTexts,
+) -> Result<()> {
+    let conn = connection::Builder::session()?
+        .name(bus_name(bar_name))?
+        .serve_at(PATH, Service { commands, texts })?
+        .build()
+        .await?;
+    std::future::pending::<()>().await;
+    drop(conn);
+    Ok(())
+}
@@ -0,0 +1,305 @@
+use std::{cmp::Ordering, collections::HashMap, iter::Peekable, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use config::Value;
+
+use crate::{bar::PanelDrawInfo, remove_string_from_config};
+
+/// A comparison between two operands of an [`Expr`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+impl Comparison {
+    const fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => matches!(ordering, Ordering::Equal),
+            Self::Ne => !matches!(ordering, Ordering::Equal),
+            Self::Lt => matches!(ordering, Ordering::Less),
+            Self::Le => !matches!(ordering, Ordering::Greater),
+            Self::Gt => matches!(ordering, Ordering::Greater),
+            Self::Ge => !matches!(ordering, Ordering::Less),
+        }
+    }
+}
+
+/// A literal or a named value in an [`Expr`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    /// A number, e.g. `0` or `-1.5`
+    Number(f64),
+    /// A quoted string, e.g. `"muted"`
+    String(String),
+    /// `true` or `false`
+    Bool(bool),
+    /// The name of a value, e.g. `volume`. Missing values are empty strings.
+    Value(String),
+}
+
+impl Operand {
+    fn resolve(&self, values: &HashMap<String, String>) -> String {
+        match self {
+            Self::Number(n) => n.to_string(),
+            Self::String(s) => s.clone(),
+            Self::Bool(b) => b.to_string(),
+            Self::Value(name) => values.get(name).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// A condition over the values of a panel, e.g. `volume == 0`,
+/// `count > 0 && !muted`, or `stdout != ""`.
+///
+/// Operands are numbers, quoted strings, `true`, `false`, or the names of
+/// values. Two operands are compared as numbers if both are numbers, and as
+/// strings otherwise. An operand on its own is true unless it's empty, `0`,
+/// or `false`. Conditions can be combined with `&&`, `||`, `!`, and
+/// parentheses.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// Whether an operand is truthy
+    Truthy(Operand),
+    /// A comparison between two operands
+    Compare(Operand, Comparison, Operand),
+    /// `!`
+    Not(Box<Expr>),
+    /// `&&`
+    And(Box<Expr>, Box<Expr>),
+    /// `||`
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Configuration options:
+    ///
+    /// - `visible_when`: the panel is hidden unless this expression is true,
+    ///   e.g. `volume == 0` or `count > 0`. Each panel documents the values
+    ///   it provides, and the text it shows is always available as `text`.
+    ///   See [`Expr`].
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `depends_on`: the panel is hidden unless this expression over the
+    ///   other panels on the bar is true. The name of a panel is true while
+    ///   it's shown, and its values are available as its name followed by a
+    ///   dot, e.g. `call && !mic.muted` or `mpd.state == "play"`.
+    ///   - type: String
+    ///   - default: none
+    pub(crate) fn parse<S: std::hash::BuildHasher>(
+        key: &str,
+        table: &mut HashMap<String, Value, S>,
+    ) -> Result<Option<Self>> {
+        remove_string_from_config(key, table)
+            .map(|expr| expr.parse())
+            .transpose()
+    }
+
+    /// Evaluates the expression over a set of named values.
+    #[must_use]
+    pub fn eval(&self, values: &HashMap<String, String>) -> bool {
+        match self {
+            Self::Truthy(operand) => truthy(&operand.resolve(values)),
+            Self::Compare(lhs, comparison, rhs) => {
+                let (lhs, rhs) = (lhs.resolve(values), rhs.resolve(values));
+                let ordering = match (lhs.parse::<f64>(), rhs.parse::<f64>()) {
+                    (Ok(lhs), Ok(rhs)) => lhs.partial_cmp(&rhs),
+                    _ => Some(lhs.cmp(&rhs)),
+                };
+                ordering.is_some_and(|o| comparison.matches(o))
+            }
+            Self::Not(expr) => !expr.eval(values),
+            Self::And(lhs, rhs) => lhs.eval(values) && rhs.eval(values),
+            Self::Or(lhs, rhs) => lhs.eval(values) || rhs.eval(values),
+        }
+    }
+
+    /// Hides a panel whose values don't satisfy the expression by giving it
+    /// a width of zero. The text of the panel is available as `text`.
+    #[must_use]
+    pub fn apply(&self, mut draw_info: PanelDrawInfo) -> PanelDrawInfo {
+        draw_info
+            .values
+            .entry(String::from("text"))
+            .or_insert_with(|| draw_info.text.clone());
+        if self.eval(&draw_info.values) {
+            return draw_info;
+        }
+        let mut hidden = PanelDrawInfo::new(
+            (0, draw_info.height),
+            draw_info.dependence,
+            Box::new(|_| Ok(())),
+        );
+        hidden.text = draw_info.text;
+        hidden.values = draw_info.values;
+        hidden
+    }
+}
+
+/// Whether a value is true on its own, i.e. it isn't empty, `0`, or `false`
+pub(crate) fn truthy(value: &str) -> bool {
+    !(value.is_empty()
+        || value == "false"
+        || value.parse::<f64>().is_ok_and(|n| n == 0.0))
+}
+
+impl FromStr for Expr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut tokens = tokenize(s)?.into_iter().peekable();
+        let expr = parse_or(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(expr),
+            Some(token) => {
+                Err(anyhow!("Unexpected {token:?} in expression `{s}`"))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Operand(Operand),
+    Comparison(Comparison),
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+type Tokens = Peekable<std::vec::IntoIter<Token>>;
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '=' if chars.next_if_eq(&'=').is_some() => {
+                Token::Comparison(Comparison::Eq)
+            }
+            '!' if chars.next_if_eq(&'=').is_some() => {
+                Token::Comparison(Comparison::Ne)
+            }
+            '!' => Token::Not,
+            '<' if chars.next_if_eq(&'=').is_some() => {
+                Token::Comparison(Comparison::Le)
+            }
+            '<' => Token::Comparison(Comparison::Lt),
+            '>' if chars.next_if_eq(&'=').is_some() => {
+                Token::Comparison(Comparison::Ge)
+            }
+            '>' => Token::Comparison(Comparison::Gt),
+            '"' | '\'' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(anyhow!(
+                                "Unterminated string in expression `{s}`"
+                            ))
+                        }
+                        Some('\\') => {
+                            string.extend(chars.next());
+                        }
+                        Some(next) if next == c => break,
+                        Some(next) => string.push(next),
+                    }
+                }
+                Token::Operand(Operand::String(string))
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut number = String::from(c);
+                while let Some(next) =
+                    chars.next_if(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    number.push(next);
+                }
+                Token::Operand(Operand::Number(number.parse().map_err(
+                    |_| {
+                        anyhow!("Invalid number `{number}` in expression `{s}`")
+                    },
+                )?))
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::from(c);
+                // dots and dashes allow for `panel-name.value`
+                while let Some(next) = chars.next_if(|c| {
+                    c.is_alphanumeric() || matches!(c, '_' | '.' | '-')
+                }) {
+                    name.push(next);
+                }
+                Token::Operand(match name.as_str() {
+                    "true" => Operand::Bool(true),
+                    "false" => Operand::Bool(false),
+                    _ => Operand::Value(name),
+                })
+            }
+            c => return Err(anyhow!("Unexpected `{c}` in expression `{s}`")),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_or(tokens: &mut Tokens) -> Result<Expr> {
+    let mut expr = parse_and(tokens)?;
+    while tokens.next_if_eq(&Token::Or).is_some() {
+        expr = Expr::Or(Box::new(expr), Box::new(parse_and(tokens)?));
+    }
+    Ok(expr)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Expr> {
+    let mut expr = parse_not(tokens)?;
+    while tokens.next_if_eq(&Token::And).is_some() {
+        expr = Expr::And(Box::new(expr), Box::new(parse_not(tokens)?));
+    }
+    Ok(expr)
+}
+
+fn parse_not(tokens: &mut Tokens) -> Result<Expr> {
+    if tokens.next_if_eq(&Token::Not).is_some() {
+        return Ok(Expr::Not(Box::new(parse_not(tokens)?)));
+    }
+    match tokens.next() {
+        Some(Token::Open) => {
+            let expr = parse_or(tokens)?;
+            match tokens.next() {
+                Some(Token::Close) => Ok(expr),
+                _ => Err(anyhow!("Missing `)` in expression")),
+            }
+        }
+        Some(Token::Operand(lhs)) => match tokens.peek() {
+            Some(&Token::Comparison(comparison)) => {
+                tokens.next();
+                match tokens.next() {
+                    Some(Token::Operand(rhs)) => {
+                        Ok(Expr::Compare(lhs, comparison, rhs))
+                    }
+                    _ => Err(anyhow!("Missing operand after {comparison:?}")),
+                }
+            }
+            _ => Ok(Expr::Truthy(lhs)),
+        },
+        Some(token) => Err(anyhow!("Unexpected {token:?} in expression")),
+        None => Err(anyhow!("Unexpected end of expression")),
+    }
+}
@@ -0,0 +1,339 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::expr::truthy;
+
+/// Fills in a format string with named values.
+///
+/// - `{name}` is replaced with the value called `name`. Missing values are
+///   empty.
+/// - `{name:spec}` also formats the value, much like Rust's [`format!`]:
+///   `spec` is `[[fill]align][0][width][.precision]`, where `align` is `<`,
+///   `^`, or `>`. For example, `{volume:>3}` right-aligns the volume in three
+///   characters, and `{load:.1}` rounds a number to one decimal place.
+///   Precision shortens values that aren't numbers instead. Numbers are
+///   right-aligned by default, and anything else is left-aligned.
+/// - `{name#filter}` converts a number into human-readable units first.
+///   `#bytes` turns a number of bytes into `1.5 GiB`, `#si` turns a number
+///   into `1.5 k`, and `#duration` turns a number of seconds into `1h 5m`.
+///   Precision sets the number of decimal places for `#bytes` and `#si`, and
+///   the number of units for `#duration`, e.g. `{rx#bytes:>9.2}/s` or
+///   `{uptime#duration:.3}`. Values that aren't numbers are left alone.
+/// - `{?name:text}` is replaced with `text` if the value called `name` is
+///   true, and `{!name:text}` if it isn't. A value is true unless it's empty,
+///   `0`, or `false`, as in [`Expr`][crate::Expr]. `text` can contain other
+///   placeholders, e.g. `{?muted:MUTED}{!muted:{volume}%}`.
+/// - `{{` and `}}` are a literal `{` and `}`.
+/// - `%name%` is replaced with the value called `name`, as long as there is
+///   one, so older format strings keep working.
+///
+/// Anything else, including a `{` without a matching `}`, is left as it is.
+#[must_use]
+pub fn render_format(format: &str, values: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(format.len());
+    render(format, values, &mut out, false);
+    out
+}
+
+/// Renders `format` into `out` and returns the number of bytes consumed. If
+/// `nested` is true, this stops at the first unmatched `}`, which ends a
+/// conditional segment.
+fn render(
+    format: &str,
+    values: &HashMap<String, String>,
+    out: &mut String,
+    nested: bool,
+) -> usize {
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '{' if rest.starts_with("{{") => {
+                out.push('{');
+                rest = &rest[2..];
+            }
+            '}' if nested => return format.len() - rest.len(),
+            '}' if rest.starts_with("}}") => {
+                out.push('}');
+                rest = &rest[2..];
+            }
+            '{' => {
+                let consumed = placeholder(rest, values, out);
+                rest = &rest[consumed..];
+            }
+            '%' => {
+                let legacy = rest[1..].find('%').and_then(|end| {
+                    let name = &rest[1..=end];
+                    is_name(name)
+                        .then(|| values.get(name))
+                        .flatten()
+                        .map(|value| (end + 2, value))
+                });
+                if let Some((consumed, value)) = legacy {
+                    out.push_str(value);
+                    rest = &rest[consumed..];
+                } else {
+                    out.push('%');
+                    rest = &rest[1..];
+                }
+            }
+            c => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    format.len()
+}
+
+/// Renders the placeholder at the start of `format`, which begins with `{`,
+/// into `out` and returns the number of bytes consumed.
+fn placeholder(
+    format: &str,
+    values: &HashMap<String, String>,
+    out: &mut String,
+) -> usize {
+    let inner = &format[1..];
+    if let Some(negate) = inner
+        .strip_prefix('?')
+        .map(|_| false)
+        .or_else(|| inner.strip_prefix('!').map(|_| true))
+    {
+        if let Some((name, _)) =
+            inner[1..].split_once(':').filter(|(name, _)| is_name(name))
+        {
+            let body_start = 2 + name.len() + 1;
+            let mut body = String::new();
+            let consumed =
+                render(&format[body_start..], values, &mut body, true);
+            let end = body_start + consumed;
+            if format[end..].starts_with('}') {
+                let value = values.get(name).map_or("", String::as_str);
+                if truthy(value) != negate {
+                    out.push_str(body.as_str());
+                }
+                return end + 1;
+            }
+        }
+    } else if let Some(end) = inner.find('}') {
+        let (name, spec) =
+            inner[..end].split_once(':').unwrap_or((&inner[..end], ""));
+        let (name, filter) = match name.split_once('#') {
+            Some((name, filter)) => (name, Filter::parse(filter)),
+            None => (name, Some(None)),
+        };
+        if let (true, Some(filter), Some(spec)) =
+            (is_name(name), filter, Spec::parse(spec))
+        {
+            let value = values.get(name).map_or("", String::as_str);
+            out.push_str(spec.apply(value, filter).as_str());
+            return end + 2;
+        }
+    }
+    out.push('{');
+    1
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Filter {
+    Bytes,
+    Si,
+    Duration,
+}
+
+impl Filter {
+    /// Returns `Some(None)` for an empty filter, and [`None`] for an unknown
+    /// one.
+    fn parse(filter: &str) -> Option<Option<Self>> {
+        match filter {
+            "" => Some(None),
+            "bytes" => Some(Some(Self::Bytes)),
+            "si" => Some(Some(Self::Si)),
+            "duration" => Some(Some(Self::Duration)),
+            _ => None,
+        }
+    }
+
+    fn apply(self, value: f64, precision: Option<usize>) -> String {
+        match self {
+            Self::Bytes => human(value, 1024.0, &BYTES, precision),
+            Self::Si => human(value, 1000.0, &SI, precision),
+            Self::Duration => duration(
+                Duration::from_secs_f64(value.max(0.0)),
+                precision.unwrap_or(2),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Self> {
+        let align = |c| match c {
+            '<' => Some(Align::Left),
+            '^' => Some(Align::Center),
+            '>' => Some(Align::Right),
+            _ => None,
+        };
+
+        let mut out = Self::default();
+        let mut chars = spec.chars().peekable();
+        let mut lookahead = spec.chars().skip(1);
+        if let (Some(fill), Some(a)) =
+            (chars.peek().copied(), lookahead.next().and_then(align))
+        {
+            out.fill = Some(fill);
+            out.align = Some(a);
+            chars.nth(1);
+        } else if let Some(a) = chars.peek().copied().and_then(align) {
+            out.align = Some(a);
+            chars.next();
+        }
+        out.zero = chars.next_if_eq(&'0').is_some();
+        let mut width = String::new();
+        while let Some(c) = chars.next_if(char::is_ascii_digit) {
+            width.push(c);
+        }
+        out.width = if width.is_empty() {
+            0
+        } else {
+            width.parse().ok()?
+        };
+        if chars.next_if_eq(&'.').is_some() {
+            let precision = chars.collect::<String>();
+            out.precision = Some(precision.parse().ok()?);
+        } else if chars.next().is_some() {
+            return None;
+        }
+        Some(out)
+    }
+
+    fn apply(self, value: &str, filter: Option<Filter>) -> String {
+        let number = value.trim().parse::<f64>().ok();
+        let value = match (filter, self.precision, number) {
+            (Some(filter), precision, Some(number)) => {
+                filter.apply(number, precision)
+            }
+            (None, Some(precision), Some(number)) => {
+                format!("{number:.precision$}")
+            }
+            (None, Some(precision), None) => {
+                value.chars().take(precision).collect()
+            }
+            (_, _, _) => value.to_owned(),
+        };
+
+        let padding = self.width.saturating_sub(value.chars().count());
+        if padding == 0 {
+            return value;
+        }
+        if self.zero
+            && self.align.is_none()
+            && filter.is_none()
+            && number.is_some()
+        {
+            let (sign, digits) = value
+                .strip_prefix('-')
+                .map_or(("", value.as_str()), |digits| ("-", digits));
+            return format!("{sign}{}{digits}", "0".repeat(padding));
+        }
+
+        let fill = self.fill.unwrap_or(' ').to_string();
+        let align = self.align.unwrap_or(if number.is_some() {
+            Align::Right
+        } else {
+            Align::Left
+        });
+        let (left, right) = match align {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+        format!("{}{value}{}", fill.repeat(left), fill.repeat(right))
+    }
+}
+
+const BYTES: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+const SI: [&str; 6] = ["", "k", "M", "G", "T", "P"];
+
+/// Formats a number of bytes with binary prefixes, e.g. `1.5 GiB`.
+#[must_use]
+pub fn human_bytes(bytes: f64) -> String {
+    human(bytes, 1024.0, &BYTES, None)
+}
+
+/// Formats a number with SI prefixes, e.g. `1.5 k`.
+#[must_use]
+pub fn human_si(value: f64) -> String {
+    human(value, 1000.0, &SI, None)
+}
+
+/// Divides `value` by `base` until it's smaller, then adds the matching unit.
+/// Without a precision, values below 10 get one decimal place, except in the
+/// first unit.
+fn human(
+    value: f64,
+    base: f64,
+    units: &[&str],
+    precision: Option<usize>,
+) -> String {
+    let mut value = value;
+    let mut unit = 0;
+    while value.abs() >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    let precision = precision
+        .unwrap_or_else(|| usize::from(unit > 0 && value.abs() < 10.0));
+    format!("{value:.precision$} {}", units[unit])
+        .trim_end()
+        .to_owned()
+}
+
+/// Formats a duration with its two largest units, e.g. `1h 5m` or `42s`.
+#[must_use]
+pub fn human_duration(duration: Duration) -> String {
+    self::duration(duration, 2)
+}
+
+/// Formats a duration with up to `units` of its largest units.
+fn duration(duration: Duration, units: usize) -> String {
+    let secs = duration.as_secs();
+    let parts = [
+        (secs / 86400, "d"),
+        (secs / 3600 % 24, "h"),
+        (secs / 60 % 60, "m"),
+        (secs % 60, "s"),
+    ];
+    let first = parts
+        .iter()
+        .position(|(n, _)| *n > 0)
+        .unwrap_or(parts.len() - 1);
+    parts[first..]
+        .iter()
+        .take(units.max(1))
+        .filter(|(n, _)| *n > 0 || first == parts.len() - 1)
+        .map(|(n, unit)| format!("{n}{unit}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,148 @@
+use std::{collections::HashMap, f64::consts::PI, rc::Rc};
+
+use anyhow::Result;
+use config::Value;
+use pangocairo::functions::{create_layout, show_layout};
+
+use crate::{
+    bar::{Dependence, PanelDrawInfo},
+    remove_bool_from_config, remove_float_from_config,
+    remove_paint_from_config, Attrs, Paint,
+};
+
+/// A ring that fills clockwise as a value grows, e.g. the volume of a sink.
+/// See [`draw_gauge`].
+#[derive(Clone, Debug)]
+pub struct Gauge {
+    /// The diameter of the gauge in pixels. If this is [`None`], the gauge is
+    /// as tall as the bar.
+    pub size: Option<f64>,
+    /// The width of the ring in pixels
+    pub thickness: f64,
+    /// How far around the ring reaches in degrees. Anything less than 360
+    /// leaves a gap at the bottom.
+    pub sweep: f64,
+    /// The color of the filled part. If this is [`None`], the foreground
+    /// color of the panel is used.
+    pub fill: Option<Paint>,
+    /// The color of the empty part. If this is [`None`], the foreground color
+    /// of the panel is used at a quarter of its opacity.
+    pub bg: Option<Paint>,
+    /// Whether the text of the panel is drawn in the middle of the ring
+    pub text: bool,
+}
+
+impl Default for Gauge {
+    fn default() -> Self {
+        Self {
+            size: None,
+            thickness: 3.0,
+            sweep: 270.0,
+            fill: None,
+            bg: None,
+            text: false,
+        }
+    }
+}
+
+impl Gauge {
+    /// Parses the options documented in [`Style::parse`][crate::Style::parse].
+    pub(crate) fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let default = Self::default();
+        Self {
+            size: remove_float_from_config("gauge_size", table),
+            thickness: remove_float_from_config("gauge_thickness", table)
+                .unwrap_or(default.thickness),
+            sweep: remove_float_from_config("gauge_sweep", table)
+                .map_or(default.sweep, |sweep| sweep.clamp(0.0, 360.0)),
+            fill: remove_paint_from_config("gauge_fill", table),
+            bg: remove_paint_from_config("gauge_bg", table),
+            text: remove_bool_from_config("gauge_text", table)
+                .unwrap_or(default.text),
+        }
+    }
+}
+
+/// Draws a [`Gauge`] filled to `fraction`, which is limited to the range from
+/// 0 to 1. `height` is the height of the bar, which is used if the gauge
+/// doesn't have a size. If the gauge shows text, `text` is interpreted as
+/// markup and drawn in the middle of the ring.
+#[allow(clippy::cast_possible_truncation)]
+pub fn draw_gauge(
+    cr: &Rc<cairo::Context>,
+    fraction: f64,
+    gauge: &Gauge,
+    height: i32,
+    text: &str,
+    attrs: &Attrs,
+    dependence: Dependence,
+) -> Result<PanelDrawInfo> {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let size = gauge.size.unwrap_or_else(|| f64::from(height));
+    let layout = gauge.text.then(|| {
+        let layout = create_layout(cr);
+        layout.set_markup(text);
+        attrs.apply_font(&layout);
+        layout
+    });
+    let plain = pango::parse_markup(text, '\0')
+        .map_or_else(|_| text.to_string(), |(_, text, _)| text.to_string());
+    let gauge = gauge.clone();
+    let attrs = attrs.clone();
+
+    let mut draw_info = PanelDrawInfo::new(
+        (size.ceil() as i32, size.ceil() as i32),
+        dependence,
+        Box::new(move |cr| {
+            let center = size / 2.0;
+            let radius = (center - gauge.thickness / 2.0).max(0.0);
+            let sweep = gauge.sweep.to_radians();
+            // the gap, if there is one, is centered at the bottom
+            let start = PI / 2.0 + (2.0 * PI - sweep) / 2.0;
+
+            cr.save()?;
+            cr.set_line_width(gauge.thickness);
+            cr.new_path();
+            cr.arc(center, center, radius, start, start + sweep);
+            match &gauge.bg {
+                Some(bg) => {
+                    bg.apply(cr, size, size);
+                    cr.stroke()?;
+                }
+                None => {
+                    cr.push_group();
+                    attrs.apply_fg(cr, size, size);
+                    cr.stroke()?;
+                    cr.pop_group_to_source()?;
+                    cr.paint_with_alpha(0.25)?;
+                }
+            }
+
+            if fraction > 0.0 {
+                cr.new_path();
+                cr.arc(center, center, radius, start, start + sweep * fraction);
+                match &gauge.fill {
+                    Some(fill) => fill.apply(cr, size, size),
+                    None => attrs.apply_fg(cr, size, size),
+                }
+                cr.stroke()?;
+            }
+
+            if let Some(layout) = &layout {
+                let (width, height) = layout.pixel_size();
+                attrs.apply_fg(cr, size, size);
+                cr.move_to(
+                    (size - f64::from(width)) / 2.0,
+                    (size - f64::from(height)) / 2.0,
+                );
+                show_layout(cr, layout);
+            }
+            cr.restore()?;
+            Ok(())
+        }),
+    );
+    draw_info.text = plain;
+    Ok(draw_info)
+}
@@ -0,0 +1,241 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
+
+use anyhow::Result;
+use config::Value;
+use csscolorparser::Color;
+
+use crate::{
+    bar::{Dependence, PanelDrawInfo},
+    remove_bool_from_config, remove_color_from_config,
+    remove_float_from_config, remove_paint_from_config,
+    remove_uint_from_config, Attrs, Paint,
+};
+
+/// The most recent values of a panel, oldest first. Once the history is full,
+/// pushing a value drops the oldest one.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    values: VecDeque<f64>,
+    length: usize,
+}
+
+impl History {
+    /// Creates an empty history that holds up to `length` values.
+    #[must_use]
+    pub fn new(length: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(length),
+            length,
+        }
+    }
+
+    /// Adds a value to the history, dropping the oldest value if it's full.
+    pub fn push(&mut self, value: f64) {
+        if self.length == 0 {
+            return;
+        }
+        if self.values.len() == self.length {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    /// The values in the history, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = f64> + '_ {
+        self.values.iter().copied()
+    }
+
+    /// The number of values the history can hold
+    #[must_use]
+    pub const fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The largest value in the history, or zero if it's empty
+    #[must_use]
+    pub fn max(&self) -> f64 {
+        self.iter().fold(0.0, f64::max)
+    }
+}
+
+/// A small rolling graph of the recent values of a panel. See
+/// [`draw_graph`].
+#[derive(Clone, Debug)]
+pub struct Graph {
+    /// The width of the graph in pixels
+    pub width: f64,
+    /// The height of the graph in pixels. It's centered vertically on the
+    /// bar.
+    pub height: f64,
+    /// How many values the graph shows. They're spread evenly across its
+    /// width.
+    pub length: usize,
+    /// The value at the top of the graph. If this is [`None`], the panel's
+    /// own maximum is used, or the largest value in the history if the panel
+    /// doesn't have one.
+    pub max: Option<f64>,
+    /// Whether the area under the line is filled
+    pub area: bool,
+    /// The width of the line in pixels
+    pub line_width: f64,
+    /// The color of the line. If this is [`None`], the foreground color of
+    /// the panel is used.
+    pub color: Option<Color>,
+    /// The color of the area under the line. If this is [`None`], the color
+    /// of the line is used at half opacity.
+    pub fill: Option<Paint>,
+    /// The color behind the graph. If this is [`None`], nothing is drawn.
+    pub bg: Option<Paint>,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self {
+            width: 60.0,
+            height: 16.0,
+            length: 30,
+            max: None,
+            area: true,
+            line_width: 1.0,
+            color: None,
+            fill: None,
+            bg: None,
+        }
+    }
+}
+
+impl Graph {
+    /// Parses the options documented in [`Style::parse`][crate::Style::parse].
+    pub(crate) fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let default = Self::default();
+        Self {
+            width: remove_float_from_config("graph_width", table)
+                .unwrap_or(default.width),
+            height: remove_float_from_config("graph_height", table)
+                .unwrap_or(default.height),
+            length: remove_uint_from_config("graph_history", table)
+                .map_or(default.length, |length| length.max(2) as usize),
+            max: remove_float_from_config("graph_max", table)
+                .filter(|max| *max > 0.0),
+            area: remove_bool_from_config("graph_area", table)
+                .unwrap_or(default.area),
+            line_width: remove_float_from_config("graph_line_width", table)
+                .unwrap_or(default.line_width),
+            color: remove_color_from_config("graph_color", table),
+            fill: remove_paint_from_config("graph_fill", table),
+            bg: remove_paint_from_config("graph_bg", table),
+        }
+    }
+
+    /// Creates an empty [`History`] that holds as many values as the graph
+    /// shows.
+    #[must_use]
+    pub fn history(&self) -> History {
+        History::new(self.length)
+    }
+}
+
+/// Draws a [`Graph`] of `history`. The top of the graph is
+/// [`Graph::max`], then `max`, then the largest value in the history. `text`
+/// isn't drawn, but it's interpreted as markup and made available to
+/// `visible_when` and transitions.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+pub fn draw_graph(
+    cr: &Rc<cairo::Context>,
+    history: &History,
+    graph: &Graph,
+    max: Option<f64>,
+    text: &str,
+    attrs: &Attrs,
+    dependence: Dependence,
+) -> Result<PanelDrawInfo> {
+    let max = graph
+        .max
+        .or(max)
+        .unwrap_or_else(|| history.max())
+        .max(f64::EPSILON);
+    let (width, height) = (graph.width, graph.height);
+    let step = width / (history.length().max(2) - 1) as f64;
+    // right-align the values so that a new graph fills in from the right
+    let offset = history.length().saturating_sub(history.iter().count());
+    let inset = graph.line_width / 2.0;
+    let points = history
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            (
+                (offset + i) as f64 * step,
+                inset
+                    + (height - graph.line_width)
+                        * (1.0 - (value / max).clamp(0.0, 1.0)),
+            )
+        })
+        .collect::<Vec<_>>();
+    let plain = pango::parse_markup(text, '\0')
+        .map_or_else(|_| text.to_string(), |(_, text, _)| text.to_string());
+    let graph = graph.clone();
+    let attrs = attrs.clone();
+
+    let mut draw_info = PanelDrawInfo::new(
+        (width.ceil() as i32, height.ceil() as i32),
+        dependence,
+        Box::new(move |cr| {
+            if let Some(bg) = &graph.bg {
+                cr.rectangle(0.0, 0.0, width, height);
+                bg.apply(cr, width, height);
+                cr.fill()?;
+            }
+
+            let Some(&(first, _)) = points.first() else {
+                return Ok(());
+            };
+            let line = || {
+                for &(x, y) in &points {
+                    cr.line_to(x, y);
+                }
+            };
+
+            if graph.area {
+                cr.save()?;
+                cr.move_to(first, height);
+                line();
+                cr.line_to(width, height);
+                cr.close_path();
+                cr.clip();
+                match (&graph.fill, &graph.color) {
+                    (Some(fill), _) => {
+                        fill.apply(cr, width, height);
+                        cr.paint()?;
+                    }
+                    (None, Some(c)) => {
+                        cr.set_source_rgba(c.r, c.g, c.b, c.a);
+                        cr.paint_with_alpha(0.5)?;
+                    }
+                    (None, None) => {
+                        attrs.apply_fg(cr, width, height);
+                        cr.paint_with_alpha(0.5)?;
+                    }
+                }
+                cr.restore()?;
+            }
+
+            cr.new_path();
+            line();
+            match &graph.color {
+                Some(c) => cr.set_source_rgba(c.r, c.g, c.b, c.a),
+                None => attrs.apply_fg(cr, width, height),
+            }
+            cr.set_line_width(graph.line_width);
+            cr.set_line_join(cairo::LineJoin::Round);
+            cr.stroke()?;
+            Ok(())
+        }),
+    );
+    draw_info.text = plain;
+    Ok(draw_info)
+}
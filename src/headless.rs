@@ -0,0 +1,413 @@
+use std::{
+    collections::HashMap, env, fs::File, path::Path, rc::Rc, time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use cairo::{Format, ImageSurface};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{runtime::Runtime, task, time};
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::{self, Bar, Dependence, PanelDrawInfo},
+    draw_common, render_format, Alignment, Attrs, Color, Ellipsize, ErrorBadge,
+    Margins, PanelCommon, PanelConfig, PanelStream, SeparatorStyle,
+};
+
+/// How long a panel has to produce its first frame before it's left empty.
+const FIRST_FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The environment variable that makes [`compare_golden`] replace reference
+/// images instead of comparing against them.
+pub const BLESS_VAR: &str = "LAZYBAR_BLESS";
+
+/// Draws a bar onto an image. Each panel is started, drawn once with the
+/// first content it produces, and stopped.
+///
+/// The panels are laid out as they would be on a bar on the screen,
+/// including expanding panels and `depends_on`. Background images, borders,
+/// and rounded corners aren't drawn.
+pub struct Headless {
+    width: u16,
+    height: u16,
+    scale: f64,
+    bg: Color,
+    margins: Margins,
+    attrs: Attrs,
+    separator: Option<SeparatorStyle>,
+    error_badge: Option<ErrorBadge>,
+    panels: Vec<(Alignment, String, Box<dyn PanelConfig>)>,
+}
+
+impl Headless {
+    /// Create a renderer for a bar of the given size in logical pixels. The
+    /// image is `scale` times as large.
+    #[must_use]
+    pub const fn new(
+        width: u16,
+        height: u16,
+        scale: f64,
+        bg: Color,
+        margins: Margins,
+        attrs: Attrs,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            scale,
+            bg,
+            margins,
+            attrs,
+            separator: None,
+            error_badge: None,
+            panels: Vec::new(),
+        }
+    }
+
+    /// Sets what to draw between adjacent panels.
+    pub fn set_separator(&mut self, separator: Option<SeparatorStyle>) {
+        self.separator = separator;
+    }
+
+    /// Sets what to draw in place of a panel that fails to start.
+    pub fn set_error_badge(&mut self, badge: Option<ErrorBadge>) {
+        self.error_badge = badge;
+    }
+
+    /// Add a panel to the bar with a given [`Alignment`]. It will appear to
+    /// the right of all other panels with the same alignment.
+    pub fn add_panel(
+        &mut self,
+        name: String,
+        panel: Box<dyn PanelConfig>,
+        alignment: Alignment,
+    ) {
+        self.panels.push((alignment, name, panel));
+    }
+
+    /// Draws the bar. The panels run on their own runtime, which is shut
+    /// down once they have been drawn.
+    ///
+    /// # Errors
+    ///
+    /// If the image can't be created or drawn on. Panels that fail are
+    /// logged and drawn as the error badge.
+    pub fn render(self) -> Result<ImageSurface> {
+        let rt = Runtime::new()?;
+        let local = task::LocalSet::new();
+        let surface = local.block_on(&rt, self.render_inner());
+        drop(local);
+        rt.shutdown_background();
+        surface
+    }
+
+    #[allow(
+        clippy::future_not_send,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    async fn render_inner(self) -> Result<ImageSurface> {
+        let surface = ImageSurface::create(
+            Format::ARgb32,
+            (f64::from(self.width) * self.scale).round() as i32,
+            (f64::from(self.height) * self.scale).round() as i32,
+        )?;
+        let cr = Rc::new(cairo::Context::new(&surface)?);
+        cr.scale(self.scale, self.scale);
+
+        cr.save()?;
+        cr.set_operator(cairo::Operator::Source);
+        cr.set_source_rgba(self.bg.r, self.bg.g, self.bg.b, self.bg.a);
+        cr.rectangle(0.0, 0.0, f64::from(self.width), f64::from(self.height));
+        cr.fill()?;
+        cr.restore()?;
+
+        let separator = Bar::draw_separator_style(
+            &cr,
+            self.separator.as_ref(),
+            &self.attrs,
+            self.height,
+        )?;
+        let badge_attrs = self.error_badge.as_ref().map(|badge| {
+            let mut attrs = badge.attrs.clone();
+            attrs.apply_to(&self.attrs);
+            (badge.markup.as_str(), attrs)
+        });
+        // draw info can't be cloned, so the badge is drawn for each failure
+        let badge = || {
+            let (markup, attrs) = badge_attrs.as_ref()?;
+            draw_common(
+                &cr,
+                markup,
+                attrs,
+                Dependence::None,
+                &Ellipsize::default(),
+            )
+            .map_err(|e| log::warn!("Failed to draw error badge: {e}"))
+            .ok()
+        };
+
+        let mut left = Vec::new();
+        let mut center = Vec::new();
+        let mut right = Vec::new();
+        for (alignment, name, config) in self.panels {
+            let (mut panel, stream) = Bar::start_panel(
+                &cr,
+                &self.attrs,
+                i32::from(self.height),
+                name,
+                config,
+            );
+            let draw_info = match stream {
+                Ok(stream) => first_frame(&panel.name, stream).await,
+                Err(e) => Err(e),
+            };
+            panel.draw_info = match draw_info {
+                Ok(draw_info) => draw_info,
+                Err(e) => {
+                    log::warn!("Panel `{}` failed: {e}", panel.name);
+                    badge()
+                }
+            };
+            match alignment {
+                Alignment::Left => left.push(panel),
+                Alignment::Center => center.push(panel),
+                Alignment::Right => right.push(panel),
+            }
+        }
+
+        let separator = separator.as_ref();
+        let statuses = [Alignment::Left, Alignment::Center, Alignment::Right]
+            .map(|alignment| {
+                bar::statuses([&left, &center, &right], alignment)
+            });
+        let layout = bar::plan_layout(
+            i32::from(self.width),
+            &self.margins,
+            separator,
+            [&left, &center, &right],
+            &statuses,
+        );
+        bar::draw_layout(
+            &cr,
+            [&mut left, &mut center, &mut right],
+            &statuses,
+            separator,
+            &layout,
+            self.height,
+        )?;
+
+        drop(cr);
+        surface.flush();
+        Ok(surface)
+    }
+}
+
+/// Waits for the first content that a panel draws. Panels that don't draw
+/// in time are left empty.
+async fn first_frame(
+    name: &str,
+    mut stream: PanelStream,
+) -> Result<Option<PanelDrawInfo>> {
+    match time::timeout(FIRST_FRAME_TIMEOUT, stream.next()).await {
+        Ok(Some(draw_info)) => draw_info.map(Some),
+        Ok(None) => Ok(None),
+        Err(_) => {
+            log::warn!(
+                "Panel `{name}` didn't draw within {}s",
+                FIRST_FRAME_TIMEOUT.as_secs()
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Shows fixed values through a format string, in place of a panel that
+/// would get them from its data source. The format is written like that of
+/// the panel being imitated, e.g. `{percent}%`, and drawn with the same
+/// attributes and decoration.
+///
+/// This is only meant for tests, so it can't be used in the config file.
+#[doc(hidden)]
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_struct_attr(doc(hidden))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct FakePanel {
+    common: PanelCommon,
+    values: HashMap<String, String>,
+}
+
+impl FakePanel {
+    /// Create a panel that fills in `format` with `values`. The other options
+    /// of [`PanelCommon`] can be set with [`parse`][PanelConfig::parse].
+    ///
+    /// # Errors
+    ///
+    /// If the format can't be parsed.
+    pub fn new(format: &str, values: &[(&str, &str)]) -> Result<Self> {
+        let mut table = HashMap::new();
+        table.insert(String::from("format"), Value::from(format));
+        let mut panel = Self::parse(&mut table, &Config::default())?;
+        panel.values = values
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect();
+        Ok(panel)
+    }
+}
+
+impl PanelConfig for FakePanel {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let text = render_format(self.common.formats[0].as_str(), &self.values);
+        let draw_info = draw_common(
+            &cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        );
+        Ok(Box::pin(
+            tokio_stream::once(draw_info).chain(tokio_stream::pending()),
+        ))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `format`: the text to display, with `{name}` replaced by the value
+    ///   called `name`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `values`: the values to fill in
+    ///   - type: table of Strings
+    ///   - default: none
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = FakePanelBuilder::default();
+        builder.values(
+            table
+                .remove("values")
+                .and_then(|values| values.into_table().ok())
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(name, value)| {
+                    Some((name, value.into_string().ok()?))
+                })
+                .collect(),
+        );
+        builder.common(PanelCommon::parse(table, &[""], &[""], &[""])?);
+
+        Ok(builder.build()?)
+    }
+}
+
+/// Compares `surface` with the PNG image at `path`, allowing each channel of
+/// each pixel to differ by up to `tolerance`, e.g. to make up for
+/// differences in font rendering between machines.
+///
+/// If [`BLESS_VAR`] is set, `surface` is written to `path` instead and the
+/// comparison succeeds. A missing reference image is an error otherwise, so
+/// that it can't pass unnoticed. When the images
+/// differ, `surface` is written next to the reference image with the
+/// extension `actual.png` so that the two can be inspected.
+///
+/// # Errors
+///
+/// If the images differ, if there's no image at `path`, or if either one
+/// can't be read or written.
+pub fn compare_golden(
+    surface: &ImageSurface,
+    path: impl AsRef<Path>,
+    tolerance: u8,
+) -> Result<()> {
+    let path = path.as_ref();
+    if env::var_os(BLESS_VAR).is_some() {
+        return write_png(surface, path);
+    }
+    if !path.exists() {
+        return Err(anyhow!(
+            "{} doesn't exist. Set {BLESS_VAR} to create it",
+            path.display()
+        ));
+    }
+
+    let golden = ImageSurface::create_from_png(&mut File::open(path)?)?;
+    let mismatch = if (golden.width(), golden.height(), golden.format())
+        == (surface.width(), surface.height(), surface.format())
+    {
+        let mut differing = None;
+        surface.with_data(|actual| {
+            golden
+                .with_data(|expected| {
+                    differing =
+                        Some(differing_pixels(actual, expected, tolerance));
+                })
+                .ok();
+        })?;
+        let differing = differing
+            .ok_or_else(|| anyhow!("Failed to read {}", path.display()))?;
+        (differing > 0).then(|| format!("{differing} pixels differ"))
+    } else {
+        Some(format!(
+            "expected a {}x{} {:?} image, got {}x{} {:?}",
+            golden.width(),
+            golden.height(),
+            golden.format(),
+            surface.width(),
+            surface.height(),
+            surface.format()
+        ))
+    };
+
+    match mismatch {
+        None => Ok(()),
+        Some(mismatch) => {
+            let actual = path.with_extension("actual.png");
+            write_png(surface, &actual)?;
+            Err(anyhow!(
+                "{} doesn't match {}: {mismatch}",
+                actual.display(),
+                path.display()
+            ))
+        }
+    }
+}
+
+/// Writes `surface` to a PNG file at `path`.
+///
+/// # Errors
+///
+/// If the file can't be created or written.
+pub fn write_png(surface: &ImageSurface, path: impl AsRef<Path>) -> Result<()> {
+    surface.write_to_png(&mut File::create(path)?)?;
+    Ok(())
+}
+
+/// Counts the pixels that differ between two images of the same size by more
+/// than `tolerance` in any channel.
+fn differing_pixels(actual: &[u8], expected: &[u8], tolerance: u8) -> usize {
+    actual
+        .chunks(4)
+        .zip(expected.chunks(4))
+        .filter(|(a, e)| {
+            a.iter().zip(*e).any(|(a, e)| a.abs_diff(*e) > tolerance)
+        })
+        .count()
+}
@@ -0,0 +1,137 @@
+use std::{
+    env,
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    process::Command,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use serde_json::Value as Json;
+use tokio::sync::mpsc::UnboundedSender;
+
+const MAGIC: &[u8] = b"i3-ipc";
+
+pub const RUN_COMMAND: u32 = 0;
+pub const GET_WORKSPACES: u32 = 1;
+const SUBSCRIBE: u32 = 2;
+pub const GET_BINDING_STATE: u32 = 12;
+
+/// Finds the IPC socket of the running i3 or sway instance.
+fn socket_path() -> Result<PathBuf> {
+    if let Some(path) =
+        env::var_os("I3SOCK").or_else(|| env::var_os("SWAYSOCK"))
+    {
+        return Ok(PathBuf::from(path));
+    }
+
+    ["i3", "sway"]
+        .into_iter()
+        .find_map(|wm| {
+            let output =
+                Command::new(wm).arg("--get-socketpath").output().ok()?;
+            let path = String::from_utf8(output.stdout).ok()?;
+            let path = path.trim();
+            (output.status.success() && !path.is_empty())
+                .then(|| PathBuf::from(path))
+        })
+        .ok_or_else(|| anyhow!("Failed to find the i3/sway IPC socket"))
+}
+
+fn send(stream: &mut UnixStream, msg_type: u32, payload: &[u8]) -> Result<()> {
+    let mut msg = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    msg.extend_from_slice(MAGIC);
+    msg.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    msg.extend_from_slice(&msg_type.to_ne_bytes());
+    msg.extend_from_slice(payload);
+    stream.write_all(msg.as_slice())?;
+    Ok(())
+}
+
+/// Reads one message, returning its type (with the event bit set for events)
+/// and its payload.
+fn recv(stream: &mut UnixStream) -> Result<(u32, Json)> {
+    let mut header = [0; 14];
+    stream.read_exact(&mut header)?;
+    if &header[..6] != MAGIC {
+        return Err(anyhow!("Invalid i3 IPC message"));
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into()?);
+    let msg_type = u32::from_ne_bytes(header[10..14].try_into()?);
+
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(payload.as_mut_slice())?;
+    Ok((msg_type, serde_json::from_slice(payload.as_slice())?))
+}
+
+/// Sends a single request on a new connection and returns the reply.
+pub fn request(msg_type: u32, payload: &str) -> Result<Json> {
+    let mut stream = UnixStream::connect(socket_path()?)?;
+    send(&mut stream, msg_type, payload.as_bytes())?;
+    Ok(recv(&mut stream)?.1)
+}
+
+/// Runs one or more commands, as with `i3-msg`.
+pub fn run_command(command: &str) -> Result<()> {
+    let reply = request(RUN_COMMAND, command)?;
+    match reply
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|r| r["success"] == false)
+    {
+        Some(failure) => Err(anyhow!(
+            "i3 command {command} failed: {}",
+            failure["error"].as_str().unwrap_or_default()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Quotes a string for use as an argument in an i3 command.
+pub fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Subscribes to the given event types on their own thread, sending each
+/// event's payload. [`Json::Null`] is sent whenever a subscription starts,
+/// including after the window manager restarts, so that the receiver can
+/// refresh its state.
+pub fn subscribe(
+    events: &'static [&'static str],
+    sender: UnboundedSender<Json>,
+) {
+    thread::spawn(move || loop {
+        let result = (|| -> Result<()> {
+            let mut stream = UnixStream::connect(socket_path()?)?;
+            send(
+                &mut stream,
+                SUBSCRIBE,
+                serde_json::to_string(events)?.as_bytes(),
+            )?;
+            let (_, reply) = recv(&mut stream)?;
+            if reply["success"] != true {
+                return Err(anyhow!("Failed to subscribe to {events:?}"));
+            }
+            sender.send(Json::Null)?;
+
+            loop {
+                let (msg_type, payload) = recv(&mut stream)?;
+                // events have the high bit set
+                if msg_type & (1 << 31) != 0 {
+                    sender.send(payload)?;
+                }
+            }
+        })();
+
+        if sender.is_closed() {
+            break;
+        }
+        if let Err(e) = result {
+            log::warn!("i3 IPC subscription failed: {e}");
+        }
+        thread::sleep(Duration::from_secs(5));
+    });
+}
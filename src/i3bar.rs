@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::{
+    bar::MouseButton,
+    popup::Anchor,
+    text::{self, Click, TextPanels},
+    Position,
+};
+
+/// Writes the text of `panels` to stdout in the i3bar protocol each time it
+/// changes, and passes on the clicks that are read from stdin. Each panel is
+/// a block named after it, so blocks are separated if `separator` is true.
+/// `position` is where the bar is on the screen, which decides where popups
+/// open.
+///
+/// This returns once the panels have all stopped and stdin is closed.
+#[allow(clippy::future_not_send)]
+pub(crate) async fn run(
+    panels: TextPanels,
+    separator: bool,
+    position: Position,
+) -> Result<()> {
+    let mut stdout = io::stdout();
+    writeln!(stdout, "{}", json!({ "version": 1, "click_events": true }))?;
+    // the status lines form an endless array
+    writeln!(stdout, "[")?;
+
+    text::run(
+        panels,
+        |panels| {
+            let blocks = panels
+                .shown()
+                .into_iter()
+                .filter_map(|(_, panel)| {
+                    Some(json!({
+                        "name": panel.name,
+                        "full_text": panel.draw_info.as_ref()?.text,
+                        "markup": "none",
+                        "separator": separator,
+                    }))
+                })
+                .collect();
+            format!("{},", Value::Array(blocks))
+        },
+        |line| parse_click(line, position),
+    )
+    .await
+}
+
+/// Parses a line of the click event stream, which is an endless JSON array
+/// with one event per line. The first line opens the array, and each event
+/// after the first starts with a comma.
+#[allow(clippy::cast_possible_truncation)]
+fn parse_click(line: &str, position: Position) -> Option<Click> {
+    let line = line.trim().trim_start_matches(['[', ',']).trim_start();
+    if line.is_empty() {
+        return None;
+    }
+    let event = serde_json::from_str::<Value>(line)
+        .map_err(|e| log::warn!("Invalid click event `{line}`: {e}"))
+        .ok()?;
+    let number = |key: &str| event[key].as_f64().unwrap_or_default();
+    let button = u8::try_from(event["button"].as_u64()?).ok()?;
+    // the anchor is the left edge of the block and the inner edge of the bar
+    let (x, y) = (number("relative_x"), number("relative_y"));
+    let top = number("y") - y;
+    Some(Click {
+        name: event["name"].as_str()?.to_owned(),
+        button: MouseButton::try_from_x(button)?,
+        x,
+        y,
+        anchor: Anchor {
+            x: (number("x") - x) as i16,
+            y: match position {
+                Position::Top => top + number("height"),
+                Position::Bottom => top,
+            } as i16,
+            position,
+        },
+    })
+}
@@ -0,0 +1,260 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::find_icon;
+
+lazy_static! {
+    static ref REGEX: Regex =
+        Regex::new(r"\{icon:(?<path>[^}]+?)(?::(?<height>\d+(?:\.\d+)?))?\}")
+            .unwrap();
+}
+
+/// Loaded images, by path and (for SVGs) height. Images that failed to load
+/// are stored as [`None`] so the error is only logged once.
+type Cache = HashMap<(PathBuf, Option<u64>), Option<cairo::ImageSurface>>;
+
+thread_local! {
+    static CACHE: RefCell<Cache> = RefCell::new(HashMap::new());
+}
+
+/// The character that stands in for an icon in the text of a
+/// [`pango::Layout`].
+pub(crate) const PLACEHOLDER: char = '\u{fffc}';
+
+/// A raster (PNG, JPEG, etc.) or SVG image, scaled to a given height.
+#[derive(Clone, Debug)]
+pub struct Icon {
+    surface: cairo::ImageSurface,
+    height: f64,
+}
+
+impl Icon {
+    /// Loads an image from a file. SVG images (`.svg` or `.svgz`) are
+    /// rendered at the given height so they stay sharp, and anything else is
+    /// decoded by its contents and scaled when it's drawn. Images are only read
+    /// from disk once per path (and height, for SVGs), so this is cheap to
+    /// call on every redraw.
+    pub fn load(path: impl AsRef<Path>, height: f64) -> Result<Self> {
+        let path = path.as_ref();
+        let svg = path
+            .extension()
+            .is_some_and(|ext| ext == "svg" || ext == "svgz");
+        let key = (path.to_path_buf(), svg.then_some(height.to_bits()));
+        let surface = CACHE.with_borrow_mut(|cache| {
+            cache
+                .entry(key)
+                .or_insert_with(|| {
+                    if svg {
+                        render_svg(path, height)
+                    } else {
+                        load_image(path)
+                    }
+                    .map_err(|e| {
+                        log::warn!(
+                            "Failed to load icon {}: {e}",
+                            path.display()
+                        );
+                    })
+                    .ok()
+                })
+                .clone()
+        });
+
+        surface
+            .map(|surface| Self { surface, height })
+            .ok_or_else(|| anyhow!("Failed to load icon {}", path.display()))
+    }
+
+    /// Decodes a raster image (PNG, JPEG, etc.) from memory, e.g. album art
+    /// fetched from a music player. Unlike [`Icon::load`], this isn't cached.
+    pub fn from_bytes(data: &[u8], height: f64) -> Result<Self> {
+        let surface = to_surface(image::load_from_memory(data)?)?;
+        Ok(Self { surface, height })
+    }
+
+    /// Loads an icon by name from the icon theme. See
+    /// [`find_icon`][crate::find_icon].
+    pub fn from_theme(name: &str, height: f64) -> Result<Self> {
+        let path = find_icon(name, height.ceil() as u32)
+            .ok_or_else(|| anyhow!("No icon named {name} in the icon theme"))?;
+        Self::load(path, height)
+    }
+
+    /// The width in pixels of the icon at its height, keeping its aspect
+    /// ratio.
+    #[must_use]
+    pub fn width(&self) -> f64 {
+        self.height * f64::from(self.surface.width())
+            / f64::from(self.surface.height().max(1))
+    }
+
+    /// The height in pixels of the icon.
+    #[must_use]
+    pub const fn height(&self) -> f64 {
+        self.height
+    }
+
+    /// Draws the icon with its top left corner at (`x`, `y`).
+    pub fn draw(&self, cr: &cairo::Context, x: f64, y: f64) -> Result<()> {
+        let scale = self.height / f64::from(self.surface.height().max(1));
+        cr.save()?;
+        cr.translate(x, y);
+        cr.scale(scale, scale);
+        cr.set_source_surface(&self.surface, 0.0, 0.0)?;
+        cr.paint()?;
+        cr.restore()?;
+        Ok(())
+    }
+}
+
+/// Decodes a raster image (PNG, JPEG, etc.) from a file by its contents.
+pub(crate) fn load_image(path: &Path) -> Result<cairo::ImageSurface> {
+    to_surface(image::open(path)?)
+}
+
+/// Copies a decoded image into a surface that cairo can draw.
+fn to_surface(image: image::DynamicImage) -> Result<cairo::ImageSurface> {
+    let image = image.into_rgba8();
+    let (width, height) = image.dimensions();
+    let mut surface = cairo::ImageSurface::create(
+        cairo::Format::ARgb32,
+        width as i32,
+        height as i32,
+    )?;
+    let stride = surface.stride() as usize;
+    {
+        let mut data = surface.data()?;
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [r, g, b, a] = pixel.0;
+            // cairo expects premultiplied alpha
+            let premultiply = |c: u8| (u32::from(c) * u32::from(a) + 127) / 255;
+            let argb = u32::from(a) << 24
+                | premultiply(r) << 16
+                | premultiply(g) << 8
+                | premultiply(b);
+            let offset = y as usize * stride + x as usize * 4;
+            data[offset..offset + 4].copy_from_slice(&argb.to_ne_bytes());
+        }
+    }
+    Ok(surface)
+}
+
+/// Renders an SVG image to a surface of the given height, keeping its aspect
+/// ratio. Images without an intrinsic size are rendered as squares.
+fn render_svg(path: &Path, height: f64) -> Result<cairo::ImageSurface> {
+    let handle = rsvg::Loader::new().read_path(path)?;
+    let renderer = rsvg::CairoRenderer::new(&handle);
+    let width = renderer
+        .intrinsic_size_in_pixels()
+        .filter(|(_, h)| *h > 0.0)
+        .map_or(height, |(w, h)| height * w / h);
+
+    let surface = cairo::ImageSurface::create(
+        cairo::Format::ARgb32,
+        width.ceil() as i32,
+        height.ceil() as i32,
+    )?;
+    let cr = cairo::Context::new(&surface)?;
+    renderer.render_document(
+        &cr,
+        &cairo::Rectangle::new(0.0, 0.0, width, height),
+    )?;
+    drop(cr);
+    Ok(surface)
+}
+
+/// Replaces each `{icon:/path/to/icon}` or `{icon:/path/to/icon:HEIGHT}` in
+/// `text` with a placeholder character, returning the new text and the path
+/// and height (if given) of each icon in order.
+pub(crate) fn extract_icons(
+    text: &str,
+) -> (String, Vec<(String, Option<f64>)>) {
+    let mut icons = Vec::new();
+    let text = REGEX.replace_all(text, |caps: &regex::Captures| {
+        icons.push((
+            caps["path"].to_owned(),
+            caps.name("height").and_then(|h| h.as_str().parse().ok()),
+        ));
+        PLACEHOLDER.to_string()
+    });
+    (text.into_owned(), icons)
+}
+
+/// Reserves space in `layout` for icons whose placeholders were inserted by
+/// [`extract_icons`], returning each icon with the position to draw it at.
+/// Icons without a height are as tall as the font of the layout, and all
+/// icons are centered on their line of text.
+pub(crate) fn place_icons(
+    layout: &pango::Layout,
+    icons: Vec<(String, Option<f64>)>,
+) -> Vec<(Icon, f64, f64)> {
+    if icons.is_empty() {
+        return Vec::new();
+    }
+
+    let metrics = layout
+        .context()
+        .metrics(layout.font_description().as_ref(), None);
+    let (ascent, descent) = (metrics.ascent(), metrics.descent());
+    let font_height = f64::from(ascent + descent) / f64::from(pango::SCALE);
+
+    let attributes = layout.attributes().unwrap_or_default();
+    let text = layout.text();
+    let mut placed = Vec::new();
+    for ((path, height), (index, _)) in icons
+        .into_iter()
+        .zip(text.char_indices().filter(|(_, c)| *c == PLACEHOLDER))
+    {
+        let height = height.unwrap_or(font_height);
+        let icon = if path.contains('/') {
+            Icon::load(path, height)
+        } else {
+            Icon::from_theme(&path, height)
+        }
+        .ok();
+        // icons that failed to load take up no space
+        let (width, height) = icon.as_ref().map_or((0, 0), |icon| {
+            (
+                (icon.width() * f64::from(pango::SCALE)) as i32,
+                (icon.height() * f64::from(pango::SCALE)) as i32,
+            )
+        });
+        // center the icon on the middle of the text
+        let rect = pango::Rectangle::new(
+            0,
+            (descent - ascent - height) / 2,
+            width,
+            height,
+        );
+        let mut shape = pango::AttrShape::new(&rect, &rect);
+        shape.set_start_index(index as u32);
+        shape.set_end_index((index + PLACEHOLDER.len_utf8()) as u32);
+        attributes.insert(shape);
+        if let Some(icon) = icon {
+            placed.push((icon, index, rect.y()));
+        }
+    }
+    layout.set_attributes(Some(&attributes));
+
+    let mut iter = layout.iter();
+    placed
+        .into_iter()
+        .map(|(icon, index, offset)| {
+            while (iter.index() as usize) < index && iter.next_char() {}
+            let x = iter.char_extents().x();
+            let y = iter.baseline() + offset;
+            (
+                icon,
+                f64::from(x) / f64::from(pango::SCALE),
+                f64::from(y) / f64::from(pango::SCALE),
+            )
+        })
+        .collect()
+}
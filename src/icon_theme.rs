@@ -0,0 +1,266 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref THEME: Mutex<Option<String>> = Mutex::new(None);
+    static ref THEMES: Mutex<HashMap<String, Option<Arc<Theme>>>> =
+        Mutex::new(HashMap::new());
+    static ref LOOKUPS: Mutex<HashMap<(String, u32), Option<PathBuf>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The file types that can be drawn by [`Icon`][crate::Icon], in order of
+/// preference.
+const EXTENSIONS: &[&str] = &["png", "svg"];
+
+/// Sets the icon theme used by [`find_icon`]. It's shared by all bars.
+pub(crate) fn set_icon_theme(name: String) {
+    *THEME.lock().unwrap() = Some(name);
+    LOOKUPS.lock().unwrap().clear();
+}
+
+/// Finds the file for a named icon (e.g. `network-wireless-signal-good`)
+/// following the [freedesktop icon theme
+/// specification](https://specifications.freedesktop.org/icon-theme-spec/latest/).
+/// The configured icon theme and the themes it inherits from are searched
+/// first, then `hicolor`, then the unthemed icons in `/usr/share/pixmaps`.
+/// Within a theme, the icon closest to `size` pixels is chosen.
+///
+/// If no theme is configured with the `icon_theme` bar option, the theme
+/// chosen in the GTK settings is used.
+#[must_use]
+pub fn find_icon(name: &str, size: u32) -> Option<PathBuf> {
+    LOOKUPS
+        .lock()
+        .unwrap()
+        .entry((name.to_owned(), size))
+        .or_insert_with(|| {
+            let theme =
+                THEME.lock().unwrap().clone().unwrap_or_else(default_theme);
+            let mut visited = HashSet::new();
+            find_in_theme(name, size, &theme, &mut visited)
+                .or_else(|| find_in_theme(name, size, "hicolor", &mut visited))
+                .or_else(|| {
+                    base_dirs().into_iter().find_map(|dir| {
+                        EXTENSIONS
+                            .iter()
+                            .map(|ext| dir.join(format!("{name}.{ext}")))
+                            .find(|path| path.is_file())
+                    })
+                })
+        })
+        .clone()
+}
+
+/// Searches a theme and the themes it inherits from.
+fn find_in_theme(
+    name: &str,
+    size: u32,
+    theme: &str,
+    visited: &mut HashSet<String>,
+) -> Option<PathBuf> {
+    if !visited.insert(theme.to_owned()) {
+        return None;
+    }
+    let theme = THEMES
+        .lock()
+        .unwrap()
+        .entry(theme.to_owned())
+        .or_insert_with(|| Theme::load(theme).map(Arc::new))
+        .clone()?;
+
+    theme.find(name, size).or_else(|| {
+        theme
+            .parents
+            .iter()
+            .find_map(|parent| find_in_theme(name, size, parent, visited))
+    })
+}
+
+/// The directories that contain icon themes, in order of precedence.
+fn base_dirs() -> Vec<PathBuf> {
+    let home = env::var("HOME").unwrap_or_default();
+    let data_home = env::var("XDG_DATA_HOME")
+        .unwrap_or_else(|_| format!("{home}/.local/share"));
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| String::from("/usr/local/share:/usr/share"));
+
+    let mut dirs = vec![PathBuf::from(format!("{home}/.icons"))];
+    dirs.extend(
+        std::iter::once(data_home.as_str())
+            .chain(data_dirs.split(':'))
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| PathBuf::from(dir).join("icons")),
+    );
+    dirs.push(PathBuf::from("/usr/share/pixmaps"));
+    dirs
+}
+
+/// Reads the icon theme chosen in the GTK settings, or `hicolor`.
+fn default_theme() -> String {
+    let config = env::var("XDG_CONFIG_HOME").unwrap_or_else(|_| {
+        format!("{}/.config", env::var("HOME").unwrap_or_default())
+    });
+    ["gtk-3.0", "gtk-4.0"]
+        .iter()
+        .filter_map(|dir| {
+            fs::read_to_string(format!("{config}/{dir}/settings.ini")).ok()
+        })
+        .find_map(|settings| {
+            settings.lines().find_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                (key.trim() == "gtk-icon-theme-name")
+                    .then(|| value.trim().trim_matches('"').to_owned())
+            })
+        })
+        .unwrap_or_else(|| String::from("hicolor"))
+}
+
+/// How the icons in a directory of a theme can be scaled.
+#[derive(Debug)]
+enum Kind {
+    Fixed,
+    Scalable { min: u32, max: u32 },
+    Threshold(u32),
+}
+
+/// A directory of icons of one size within a theme.
+#[derive(Debug)]
+struct Subdir {
+    path: String,
+    size: u32,
+    kind: Kind,
+}
+
+impl Subdir {
+    fn matches(&self, size: u32) -> bool {
+        self.distance(size) == 0
+    }
+
+    fn distance(&self, size: u32) -> u32 {
+        match self.kind {
+            Kind::Fixed => self.size.abs_diff(size),
+            Kind::Scalable { min, max } => {
+                min.saturating_sub(size) + size.saturating_sub(max)
+            }
+            Kind::Threshold(threshold) => {
+                self.size.saturating_sub(threshold).saturating_sub(size)
+                    + size.saturating_sub(self.size + threshold)
+            }
+        }
+    }
+}
+
+/// An icon theme, parsed from its `index.theme`.
+#[derive(Debug)]
+struct Theme {
+    /// Every directory with this theme's name in the base directories
+    dirs: Vec<PathBuf>,
+    parents: Vec<String>,
+    subdirs: Vec<Subdir>,
+}
+
+impl Theme {
+    fn load(name: &str) -> Option<Self> {
+        let dirs = base_dirs()
+            .into_iter()
+            .map(|dir| dir.join(name))
+            .filter(|dir| dir.is_dir())
+            .collect::<Vec<_>>();
+        let index = dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join("index.theme")).ok())?;
+
+        let mut sections = HashMap::<&str, HashMap<&str, &str>>::new();
+        let mut section = "";
+        for line in index.lines().map(str::trim) {
+            if let Some(name) =
+                line.strip_prefix('[').and_then(|l| l.strip_suffix(']'))
+            {
+                section = name;
+            } else if let Some((key, value)) = line.split_once('=') {
+                sections
+                    .entry(section)
+                    .or_default()
+                    .insert(key.trim(), value.trim());
+            }
+        }
+
+        let list = |key| {
+            sections
+                .get("Icon Theme")
+                .and_then(|s| s.get(key))
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_owned)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+        let parents = list("Inherits");
+        let subdirs = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter_map(|path| {
+                let keys = sections.get(path.as_str())?;
+                let get = |key, default| {
+                    keys.get(key)
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(default)
+                };
+                let size = get("Size", 0);
+                // only icons meant for unscaled displays are used
+                if get("Scale", 1) != 1 {
+                    return None;
+                }
+                let kind = match keys.get("Type").copied() {
+                    Some("Fixed") => Kind::Fixed,
+                    Some("Scalable") => Kind::Scalable {
+                        min: get("MinSize", size),
+                        max: get("MaxSize", size),
+                    },
+                    _ => Kind::Threshold(get("Threshold", 2)),
+                };
+                Some(Subdir { path, size, kind })
+            })
+            .collect();
+
+        Some(Self {
+            dirs,
+            parents,
+            subdirs,
+        })
+    }
+
+    /// Finds an icon of exactly the right size, or otherwise the closest one.
+    fn find(&self, name: &str, size: u32) -> Option<PathBuf> {
+        let files = |subdir: &Subdir| {
+            self.dirs
+                .iter()
+                .flat_map(|dir| {
+                    EXTENSIONS.iter().map(move |ext| {
+                        dir.join(&subdir.path).join(format!("{name}.{ext}"))
+                    })
+                })
+                .find(|path| path.is_file())
+        };
+
+        self.subdirs
+            .iter()
+            .filter(|subdir| subdir.matches(size))
+            .find_map(files)
+            .or_else(|| {
+                let mut subdirs = self.subdirs.iter().collect::<Vec<_>>();
+                subdirs.sort_by_key(|subdir| subdir.distance(size));
+                subdirs.into_iter().find_map(files)
+            })
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    env,
+    fmt::Display,
+    fs::{self, DirBuilder},
+    io,
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt},
+        net::UnixStream,
+    },
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use nix::unistd::Uid;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{self, UnixListener},
+    time,
+};
+
+use crate::Theme;
+
+/// How long a client has to send its command before it's disconnected.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A request sent to a running bar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Show the bar if it's hidden.
+    Show,
+    /// Hide the bar.
+    Hide,
+    /// Show the bar if it's hidden, otherwise hide it.
+    Toggle,
+    /// Read the config file again and rebuild the panels of the bars in
+    /// place.
+    Reload,
+    /// Update the panel with the given name immediately.
+    Refresh(String),
+    /// Show the panel with the given name if it was hidden.
+    ShowPanel(String),
+    /// Hide the panel with the given name. It keeps updating while it's
+    /// hidden.
+    HidePanel(String),
+    /// Show the panel with the given name if it's hidden, otherwise hide it.
+    TogglePanel(String),
+    /// Build the panel with the given name again and start it if it was
+    /// disabled.
+    EnablePanel(String),
+    /// Stop the panel with the given name and drop its stream. Unlike a
+    /// hidden panel, it doesn't update until it's enabled again.
+    DisablePanel(String),
+    /// Switch to the given theme of the palette.
+    Theme(Theme),
+    /// Switch to the theme of the palette that isn't in use.
+    ToggleTheme,
+    /// Show the debug overlay if it's hidden, otherwise hide it.
+    ToggleDebug,
+    /// Close the bar.
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut words = s.split_whitespace();
+        let command = words.next();
+        let mut panel = || {
+            words.next().map(ToOwned::to_owned).ok_or_else(|| {
+                anyhow!(
+                    "`{}` requires a panel name",
+                    command.unwrap_or_default()
+                )
+            })
+        };
+        let command = match command {
+            Some("show") => Self::Show,
+            Some("hide") => Self::Hide,
+            Some("toggle") => Self::Toggle,
+            Some("reload") => Self::Reload,
+            Some("refresh") => Self::Refresh(panel()?),
+            Some("show-panel") => Self::ShowPanel(panel()?),
+            Some("hide-panel") => Self::HidePanel(panel()?),
+            Some("toggle-panel") => Self::TogglePanel(panel()?),
+            Some("enable-panel") => Self::EnablePanel(panel()?),
+            Some("disable-panel") => Self::DisablePanel(panel()?),
+            Some("theme") => match words.next() {
+                Some("toggle") => Self::ToggleTheme,
+                Some(theme) => Self::Theme(theme.parse()?),
+                None => {
+                    return Err(anyhow!(
+                        "`theme` requires `dark`, `light`, or `toggle`"
+                    ))
+                }
+            },
+            Some("debug") => Self::ToggleDebug,
+            Some("quit") => Self::Quit,
+            Some(command) => {
+                return Err(anyhow!("Unknown command `{command}`"))
+            }
+            None => return Err(anyhow!("No command given")),
+        };
+        if words.next().is_some() {
+            return Err(anyhow!("Too many arguments"));
+        }
+        Ok(command)
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Show => f.write_str("show"),
+            Self::Hide => f.write_str("hide"),
+            Self::Toggle => f.write_str("toggle"),
+            Self::Reload => f.write_str("reload"),
+            Self::Refresh(panel) => write!(f, "refresh {panel}"),
+            Self::ShowPanel(panel) => write!(f, "show-panel {panel}"),
+            Self::HidePanel(panel) => write!(f, "hide-panel {panel}"),
+            Self::TogglePanel(panel) => write!(f, "toggle-panel {panel}"),
+            Self::EnablePanel(panel) => write!(f, "enable-panel {panel}"),
+            Self::DisablePanel(panel) => write!(f, "disable-panel {panel}"),
+            Self::Theme(theme) => write!(f, "theme {theme}"),
+            Self::ToggleTheme => f.write_str("theme toggle"),
+            Self::ToggleDebug => f.write_str("debug"),
+            Self::Quit => f.write_str("quit"),
+        }
+    }
+}
+
+/// Returns the directory that holds the sockets of all running bars:
+/// `$XDG_RUNTIME_DIR/lazybar`, or `/tmp/lazybar-$USER` if `XDG_RUNTIME_DIR`
+/// isn't set. Bars only listen in it if it belongs to the current user and
+/// nobody else can access it.
+#[must_use]
+pub fn socket_dir() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR").map_or_else(
+        || {
+            PathBuf::from(format!(
+                "/tmp/lazybar-{}",
+                env::var("USER").unwrap_or_default()
+            ))
+        },
+        |dir| PathBuf::from(dir).join("lazybar"),
+    )
+}
+
+/// Returns the path of the socket that the bar named `bar_name` listens on.
+///
+/// # Errors
+///
+/// If `bar_name` is empty or contains a path separator or a NUL byte, since
+/// the socket could then end up outside of [`socket_dir`].
+pub fn socket_path(bar_name: &str) -> Result<PathBuf> {
+    if bar_name.is_empty() || bar_name.contains(['/', '\0']) {
+        return Err(anyhow!(
+            "Invalid bar name {bar_name:?}: names can't be empty or contain \
+             `/`"
+        ));
+    }
+    Ok(socket_dir().join(format!("{bar_name}.sock")))
+}
+
+/// Creates the socket directory `dir` with mode `0700` if it doesn't exist,
+/// then makes sure that it's a directory owned by the current user that no
+/// other user can access. Otherwise another user could have created it, e.g.
+/// in `/tmp`, to intercept commands or replace the socket.
+fn secure_dir(dir: &Path) -> Result<()> {
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("creating {} failed", dir.display()))?;
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() {
+        return Err(anyhow!("{} isn't a directory", dir.display()));
+    }
+    if metadata.uid() != Uid::current().as_raw() {
+        return Err(anyhow!(
+            "{} is owned by another user, refusing to use it",
+            dir.display()
+        ));
+    }
+    if metadata.mode() & 0o077 != 0 {
+        return Err(anyhow!(
+            "{} can be accessed by other users, refusing to use it",
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+/// The server side of the socket. The socket file is removed when this is
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct Listener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Listener {
+    /// Starts listening on the socket for `bar_name`, replacing a socket left
+    /// behind by a bar that didn't exit cleanly.
+    pub(crate) fn bind(bar_name: &str) -> Result<Self> {
+        let path = socket_path(bar_name)?;
+        if let Some(dir) = path.parent() {
+            secure_dir(dir)?;
+        }
+        if UnixStream::connect(path.as_path()).is_ok() {
+            return Err(anyhow!(
+                "Another bar is already listening on {}",
+                path.display()
+            ));
+        }
+        match fs::remove_file(path.as_path()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(e.into())
+            }
+            _ => {}
+        }
+        let listener = UnixListener::bind(path.as_path())
+            .with_context(|| format!("binding {} failed", path.display()))?;
+        Ok(Self { listener, path })
+    }
+
+    /// Waits for a client to send a valid command. Invalid commands are
+    /// answered with an error and skipped, and clients that don't send a
+    /// command within [`READ_TIMEOUT`] are disconnected so that they can't
+    /// block the others. The client is told that the command succeeded before
+    /// it's returned, since some commands end the process.
+    pub(crate) async fn next(&self) -> Result<Command> {
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            match time::timeout(READ_TIMEOUT, Self::read(stream)).await {
+                Ok(Ok(Some(command))) => return Ok(command),
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => log::warn!("Error reading IPC command: {e}"),
+                Err(_) => log::warn!("Timed out reading IPC command"),
+            }
+        }
+    }
+
+    async fn read(stream: net::UnixStream) -> Result<Option<Command>> {
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        match line.parse::<Command>() {
+            Ok(command) => {
+                stream.write_all(b"ok\n").await?;
+                Ok(Some(command))
+            }
+            Err(e) => {
+                stream.write_all(format!("error: {e}\n").as_bytes()).await?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.path.as_path());
+    }
+}
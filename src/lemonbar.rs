@@ -0,0 +1,116 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::{
+    bar::MouseButton,
+    popup::Anchor,
+    text::{self, Click, TextPanels},
+    Alignment, SeparatorStyle,
+};
+
+/// The buttons that lemonbar reports, in the order of their numbers.
+const BUTTONS: [MouseButton; 5] = [
+    MouseButton::Left,
+    MouseButton::Middle,
+    MouseButton::Right,
+    MouseButton::ScrollUp,
+    MouseButton::ScrollDown,
+];
+
+/// Writes the text of `panels` to stdout as lemonbar input each time it
+/// changes, with `separator` between adjacent panels. Panels that respond to
+/// clicks are wrapped in clickable areas whose command is the name of the
+/// panel followed by the number of the button, e.g. `clock 1`, which
+/// lemonbar writes to its stdout when the area is clicked. Those lines are
+/// read from stdin, so lemonbar's output has to be fed back, e.g. through a
+/// named pipe. Popups open at `anchor`, since lemonbar doesn't report where
+/// the pointer is.
+///
+/// This returns once the panels have all stopped and stdin is closed.
+#[allow(clippy::future_not_send)]
+pub(crate) async fn run(
+    panels: TextPanels,
+    separator: Option<&SeparatorStyle>,
+    anchor: Anchor,
+) -> Result<()> {
+    let separator = separator.map(separator_text).unwrap_or_default();
+    text::run(
+        panels,
+        |panels| format(panels, separator.as_str()),
+        |line| parse_click(line, anchor),
+    )
+    .await
+}
+
+/// Formats the panels as one line of lemonbar input.
+fn format(panels: &TextPanels, separator: &str) -> String {
+    let mut line = String::new();
+    let mut current = None;
+    for (alignment, panel) in panels.shown() {
+        let Some(draw_info) = &panel.draw_info else {
+            continue;
+        };
+        if current == Some(alignment) {
+            line.push_str(separator);
+        } else {
+            line.push_str(match alignment {
+                Alignment::Left => "%{l}",
+                Alignment::Center => "%{c}",
+                Alignment::Right => "%{r}",
+            });
+            current = Some(alignment);
+        }
+
+        let clickable = (1..)
+            .zip(BUTTONS)
+            .filter(|(_, button)| {
+                panel.events.is_some() || panel.actions.get(*button).is_some()
+            })
+            .map(|(number, _)| number)
+            .collect::<Vec<_>>();
+        // colons end the command of a clickable area
+        let name = panel.name.replace(':', "\\:");
+        for number in &clickable {
+            let _ = write!(line, "%{{A{number}:{name} {number}:}}");
+        }
+        line.push_str(escape(draw_info.text.as_str()).as_str());
+        for _ in &clickable {
+            line.push_str("%{A}");
+        }
+    }
+    line
+}
+
+/// Escapes text so that lemonbar doesn't mistake it for formatting.
+fn escape(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+/// Returns the text of a separator. Lines are drawn as a vertical bar.
+fn separator_text(separator: &SeparatorStyle) -> String {
+    match separator {
+        SeparatorStyle::Text { markup, .. } => {
+            let text = pango::parse_markup(markup.as_str(), '\0').map_or_else(
+                |_| markup.clone(),
+                |(_, text, _)| text.to_string(),
+            );
+            escape(text.as_str())
+        }
+        SeparatorStyle::Line { .. } => String::from(" | "),
+    }
+}
+
+/// Parses a line written by lemonbar when a clickable area is clicked, which
+/// is the name of the panel followed by the number of the button.
+fn parse_click(line: &str, anchor: Anchor) -> Option<Click> {
+    let (name, button) = line.trim().rsplit_once(' ')?;
+    let button = button.parse::<usize>().ok()?.checked_sub(1)?;
+    Some(Click {
+        name: name.to_owned(),
+        button: *BUTTONS.get(button)?,
+        x: 0.0,
+        y: 0.0,
+        anchor,
+    })
+}
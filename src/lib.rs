@@ -27,6 +27,59 @@
 //! any top level table with a different name. See <https://toml.io/> for more
 //! information.
 //!
+//! A large config can be split across several files with a top-level
+//! `include` key, e.g. `include = ["colors.toml", "panels/*.toml"]`. Paths are
+//! relative to the including file and may contain shell-style wildcards. The
+//! included files are read in order, followed by the including file, and
+//! values from later files override those from earlier ones.
+//!
+//! Values can be overridden on a particular machine in a `profile` table,
+//! e.g. `[profile.laptop.bars.example]` or `[profile.laptop.panels.clock]`.
+//! The profile named after the hostname is used, unless another one is chosen
+//! with `--profile <name>` on the command line.
+//!
+//! To get started, run `lazybar --dump-default-config` and save its output
+//! as the config file. It contains a bar with a few panels and every other
+//! panel commented out, along with every option and its default value. It's
+//! generated from the option lists in these docs, so it shows the defaults
+//! as they're documented here.
+//!
+//! `lazybar --schema` prints a [JSON schema](https://json-schema.org/) of the
+//! config, which editors with TOML support like Taplo can use for completion
+//! and validation. Like the default config, it's generated from the
+//! documented options rather than from the parsers.
+//!
+//! Run `lazybar --check` to find problems in the config without opening a
+//! bar. It exits with a non-zero status if there are any. Panels are parsed
+//! as they would be at startup, but they only connect to the X server or to
+//! the service they show once they start, so checking doesn't need either.
+//! See [`parser::check`].
+//!
+//! `lazybar --render bar.png <bar_name>` draws the first frame of the bar to
+//! a PNG image instead of opening a window, which doesn't need an X server.
+//! `monitors` is ignored, so the bar is drawn once, 1920 pixels wide unless
+//! `--width <pixels>` says otherwise. See [`headless`].
+//!
+//! `lazybar --bench <seconds> <bar_name>` runs the bar for that long, then
+//! prints how many times each panel was drawn and how long that took, along
+//! with the time spent laying out the bar and presenting it to the X server,
+//! and exits. The panel at the top of the list is the one to look at if the
+//! bar feels sluggish. Bars started on monitors that are added while it runs
+//! aren't measured. This is `--bench` rather than `--profile`, since
+//! `--profile` already chooses a config profile (see above), and passing it a
+//! number of seconds is an error that points to `--bench`.
+//!
+//! `lazybar --i3bar <bar_name>` doesn't open a window, but writes the text of
+//! the bar's panels to stdout for i3bar or swaybar, e.g. with `status_command
+//! lazybar --i3bar example` in the `bar` block of the i3 or sway config. See
+//! [`BarConfig::run_i3bar`].
+//!
+//! Similarly, `lazybar --lemonbar <bar_name> | lemonbar` writes the text of
+//! the panels as input for lemonbar. See [`BarConfig::run_lemonbar`].
+//!
+//! Bars are X11 windows, so on Wayland they need XWayland. Under sway,
+//! `--i3bar` lets swaybar show the same panels instead.
+//!
 //! Note: types are pretty flexible, and [`config`] will try its best to
 //! figure out what you mean, but if you have issues, make sure that your types
 //! are correct.
@@ -37,30 +90,112 @@
 //! ```
 #![deny(missing_docs)]
 
+mod animation;
 mod attrs;
 /// The bar itself and bar-related utility structs and functions.
 pub mod bar;
+mod dbus;
+mod expr;
+mod format;
+mod gauge;
+mod graph;
+/// Rendering bars to images instead of windows.
+///
+/// [`Headless`][headless::Headless] draws the first frame of each panel onto a cairo
+/// [`ImageSurface`][cairo::ImageSurface] without connecting to an X server, which makes it
+/// possible to check how panels are drawn in tests. Tests can stand in for a panel's real data
+/// source with fixed values, and [`compare_golden`][headless::compare_golden] compares a rendered bar with a reference image.
+///
+/// Panels that need an X server or another service that isn't available
+/// fail to start and are drawn as the error badge, if there is one.
+pub mod headless;
 mod highlight;
+mod i3;
+mod i3bar;
+mod icon;
+mod icon_theme;
+/// A Unix socket through which scripts and keybindings can control a running
+/// bar. The `lazybar-msg` binary is a small client for it.
+///
+/// Each bar listens on [`socket_path`]. A client connects, writes one
+/// command followed by a newline, and reads a single line in reply: either
+/// `ok` or `error: <message>`. The commands are:
+///
+/// - `show`: show the bar if it's hidden
+/// - `hide`: hide the bar and stop reserving space for it
+/// - `toggle`: show the bar if it's hidden, otherwise hide it
+/// - `reload`: read the config file again and rebuild the panels in place, as
+///   when the file changes
+/// - `refresh <panel>`: make the named panel update immediately, if it supports
+///   that
+/// - `show-panel <panel>`: show the named panel if it was hidden
+/// - `hide-panel <panel>`: hide the named panel. It keeps running while it's
+///   hidden, so it's up to date when it's shown again.
+/// - `toggle-panel <panel>`: show the named panel if it's hidden, otherwise
+///   hide it
+/// - `disable-panel <panel>`: stop the named panel. Its stream is dropped, so
+///   it takes up no space and doesn't update until it's enabled again.
+/// - `enable-panel <panel>`: build the named panel again from the config and
+///   start it if it was disabled
+/// - `theme <dark|light|toggle>`: switch the theme of the palette, which
+///   changes the colors of every bar in the process
+/// - `debug`: show or hide the debug overlay below the bar, which lists how
+///   often each panel updates and how long it takes to draw
+/// - `quit`: close the bar
+pub mod ipc;
+mod lemonbar;
+mod paint;
+mod palette;
+mod panel_log;
 /// The parser for the `config.toml` file.
 pub mod parser;
+/// Popup windows that panels can open to show more detail.
+pub mod popup;
+mod progress;
 mod ramp;
+mod state;
+mod style;
+mod text;
+mod theme;
 mod utils;
 mod x;
 
-use std::{collections::HashMap, fmt::Display, pin::Pin, rc::Rc};
+use std::{
+    collections::HashMap, fmt::Display, path::PathBuf, pin::Pin, rc::Rc,
+    time::Duration,
+};
 
+pub use animation::{Easing, Transition, TransitionKind};
 use anyhow::Result;
 pub use attrs::Attrs;
-use bar::{Bar, Panel, PanelDrawInfo};
+use bar::{Bar, MouseEvent, PanelDrawInfo};
 pub use builders::BarConfig;
 use config::{Config, Value};
 pub use csscolorparser::Color;
+pub use expr::{Comparison, Expr, Operand};
+pub use format::{human_bytes, human_duration, human_si, render_format};
+pub use gauge::{draw_gauge, Gauge};
 pub use glib::markup_escape_text;
+pub use graph::{draw_graph, Graph, History};
 pub use highlight::Highlight;
+pub use icon::Icon;
+pub use icon_theme::find_icon;
+pub use paint::Paint;
+pub use palette::parse_color;
+pub use panel_log::PanelLogger;
+pub use progress::{draw_progress_bar, ProgressBar};
 pub use ramp::Ramp;
+pub use state::{State, States};
+pub use style::Style;
+pub use theme::Theme;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio_stream::Stream;
 pub use utils::*;
-use x::{create_surface, create_window, map_window, set_wm_properties};
+use x::{
+    bar_geometry, clear_strut, create_surface, create_trigger, create_window,
+    detect_scale, get_monitors, map_window, set_wm_properties, shape_window,
+    trigger_y, wallpaper_surface, MonitorWatcher,
+};
 
 /// Panels that can be added to the bar. A new panel must implement
 /// [`PanelConfig`].
@@ -69,8 +204,13 @@ pub mod panels;
 /// A function that can be called repeatedly to draw the panel.
 pub type PanelDrawFn = Box<dyn Fn(&cairo::Context) -> Result<()>>;
 /// A stream that produces panel changes when the underlying data source
-/// changes.
+/// changes. It should run for as long as the bar does: if the bar can rebuild
+/// the panel, a stream that ends or produces an error is replaced by a new one
+/// after a delay.
 pub type PanelStream = Pin<Box<dyn Stream<Item = Result<PanelDrawInfo>>>>;
+/// A function that builds a panel again from its name, so that it can be
+/// restarted after it fails.
+pub type PanelRebuildFn = Rc<dyn Fn(&str) -> Result<Box<dyn PanelConfig>>>;
 
 /// The trait implemented by all panels. Provides support for parsing a panel
 /// and turning it into a [`PanelStream`].
@@ -88,6 +228,99 @@ pub trait PanelConfig {
         height: i32,
     ) -> Result<PanelStream>;
 
+    /// Returns a channel through which the bar will deliver [`MouseEvent`]s
+    /// that occur over this panel. This is called once, before
+    /// [`into_stream`][PanelConfig::into_stream].
+    ///
+    /// The default implementation returns [`None`], meaning the panel ignores
+    /// mouse input.
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        None
+    }
+
+    /// Returns a channel through which the bar will ask this panel to update
+    /// immediately, e.g. when `lazybar-msg refresh <panel>` is run. This is
+    /// called once, before [`into_stream`][PanelConfig::into_stream].
+    ///
+    /// The default implementation returns [`None`], meaning the panel can't
+    /// be refreshed.
+    fn refresh_sender(&mut self) -> Option<UnboundedSender<()>> {
+        None
+    }
+
+    /// Returns the options that this panel shares with most others, if it has
+    /// a [`PanelCommon`]. The methods below read their defaults from it.
+    ///
+    /// The default implementation returns [`None`].
+    fn common(&self) -> Option<&PanelCommon> {
+        None
+    }
+
+    /// Returns the commands to run when this panel is clicked or scrolled.
+    /// This is called once, before [`into_stream`][PanelConfig::into_stream].
+    ///
+    /// The default implementation returns [`PanelCommon::actions`], or no
+    /// actions if the panel has no [`PanelCommon`].
+    fn actions(&self) -> Actions {
+        self.common()
+            .map(|common| common.actions.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the box to draw around this panel. This is called once, before
+    /// [`into_stream`][PanelConfig::into_stream].
+    ///
+    /// The default implementation returns [`PanelCommon::decoration`], or a
+    /// decoration that draws nothing if the panel has no [`PanelCommon`].
+    fn decoration(&self) -> Decoration {
+        self.common()
+            .map(|common| common.decoration.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the animation shown when this panel's content changes. This is
+    /// called once, before [`into_stream`][PanelConfig::into_stream].
+    ///
+    /// The default implementation returns [`PanelCommon::transition`], or
+    /// [`None`] if the panel has no [`PanelCommon`], meaning changes aren't
+    /// animated.
+    fn transition(&self) -> Option<Transition> {
+        self.common().and_then(|common| common.transition)
+    }
+
+    /// Returns the condition under which this panel is shown. This is called
+    /// once, before [`into_stream`][PanelConfig::into_stream].
+    ///
+    /// The default implementation returns [`PanelCommon::visible_when`], or
+    /// [`None`] if the panel has no [`PanelCommon`], meaning the panel is
+    /// always shown.
+    fn visible_when(&self) -> Option<Expr> {
+        self.common().and_then(|common| common.visible_when.clone())
+    }
+
+    /// Returns the condition on other panels under which this panel is
+    /// shown. This is called once, before
+    /// [`into_stream`][PanelConfig::into_stream].
+    ///
+    /// The default implementation returns [`PanelCommon::depends_on`], or
+    /// [`None`] if the panel has no [`PanelCommon`], meaning the panel doesn't
+    /// depend on other panels.
+    fn depends_on(&self) -> Option<Expr> {
+        self.common().and_then(|common| common.depends_on.clone())
+    }
+
+    /// Returns how long this panel can go without drawing before it's
+    /// considered stalled. This is called once, before
+    /// [`into_stream`][PanelConfig::into_stream].
+    ///
+    /// The default implementation returns [`PanelCommon::watchdog`], or a
+    /// watchdog that never fires if the panel has no [`PanelCommon`].
+    fn watchdog(&self) -> Watchdog {
+        self.common()
+            .map(|common| common.watchdog)
+            .unwrap_or_default()
+    }
+
     /// Parses an instance of this type from a subset of the global [`Config`].
     fn parse(
         table: &mut HashMap<String, Value>,
@@ -106,6 +339,130 @@ pub enum Position {
     Bottom,
 }
 
+/// A monitor as reported by RandR. Coordinates are relative to the root
+/// window.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Monitor {
+    /// The name of the output, e.g. `DP-1`
+    pub name: String,
+    /// The x-coordinate of the left edge of the monitor
+    pub x: i16,
+    /// The y-coordinate of the top edge of the monitor
+    pub y: i16,
+    /// The width of the monitor in pixels
+    pub width: u16,
+    /// The height of the monitor in pixels
+    pub height: u16,
+}
+
+/// Fine-tunes where the bar appears along its edge of the monitor (or the
+/// screen).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Placement {
+    /// The distance in pixels from the left edge of the monitor
+    pub x: i16,
+    /// The distance in pixels from the edge of the monitor that the bar is
+    /// attached to. See [`Position`].
+    pub y: i16,
+    /// The width of the bar as a percentage of the width of the monitor
+    pub width: f64,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: 100.0,
+        }
+    }
+}
+
+/// Timing for a bar that hides itself when not in use.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Autohide {
+    /// How long the pointer has to stay at the edge of the monitor before the
+    /// bar appears
+    pub reveal_delay: Duration,
+    /// How long to wait after the pointer leaves the bar before hiding it
+    pub hide_timeout: Duration,
+}
+
+/// An image drawn over the background color of a bar, behind its panels.
+#[derive(Clone, Debug)]
+pub enum BackgroundImage {
+    /// An image file in any format that [`Icon`] can load, like PNG or JPEG
+    File {
+        /// The path to the file
+        path: PathBuf,
+        /// How the image is fitted to the bar
+        scale: ImageScale,
+    },
+    /// The part of the desktop wallpaper behind the bar, as set by tools like
+    /// `feh` and `nitrogen`. This is useful for transparent-looking bars
+    /// without a compositor.
+    Wallpaper,
+}
+
+/// How a [`BackgroundImage`] is fitted to a bar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageScale {
+    /// Repeat the image at its original size, starting in the top left corner
+    Tile,
+    /// Stretch the image to the size of the bar, ignoring its aspect ratio
+    Stretch,
+    /// Scale the image to cover the bar, keeping its aspect ratio, and center
+    /// it
+    Fill,
+    /// Center the image at its original size
+    Center,
+}
+
+/// What to draw in place of a panel that has failed.
+#[derive(Clone, Debug)]
+pub struct ErrorBadge {
+    /// [pango] markup, drawn like the text of a panel
+    pub markup: String,
+    /// The attributes of the text. Unset values fall back to the bar's
+    /// defaults.
+    pub attrs: Attrs,
+}
+
+/// A line drawn around the edge of a bar.
+#[derive(Clone, Debug)]
+pub struct Border {
+    /// The width of the line in pixels
+    pub width: f64,
+    /// The color of the line
+    pub color: Color,
+}
+
+/// What to draw between adjacent panels on a bar.
+#[derive(Clone, Debug)]
+pub enum SeparatorStyle {
+    /// [pango] markup, drawn like the text of a panel
+    Text {
+        /// The markup to draw
+        markup: String,
+        /// The attributes of the text. Unset values fall back to the bar's
+        /// defaults.
+        attrs: Attrs,
+    },
+    /// A vertical line, drawn in the foreground color
+    Line {
+        /// The width of the line in pixels
+        width: f64,
+        /// The space in pixels on each side of the line
+        padding: f64,
+        /// The distance in pixels between each end of the line and the edge
+        /// of the bar
+        inset: f64,
+        /// The attributes of the line. Unset values fall back to the bar's
+        /// defaults.
+        attrs: Attrs,
+    },
+}
+
 /// Describes where on the bar a panel should appear.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Alignment {
@@ -158,13 +515,46 @@ impl Margins {
 /// Builder structs for non-panel items, courtesy of [`derive_builder`]. See
 /// [`panels::builders`][crate::panels::builders] for panel builders.
 pub mod builders {
+    use std::{
+        cell::RefCell,
+        collections::{HashMap, HashSet},
+        env,
+        os::unix::process::CommandExt,
+        process,
+        process::Command,
+        rc::Rc,
+        sync::PoisonError,
+        time::Duration,
+    };
+
     use anyhow::Result;
+    use cairo::ImageSurface;
     use derive_builder::Builder;
-    use tokio::{runtime::Runtime, task};
-    use tokio_stream::{StreamExt, StreamMap};
+    use tokio::{
+        runtime::Runtime,
+        sync::{
+            broadcast,
+            mpsc::{self, UnboundedReceiver, UnboundedSender},
+            watch::{self, Receiver, Sender},
+        },
+        task::{self, JoinSet},
+        time::{self, Instant, MissedTickBehavior},
+    };
+    use tokio_stream::StreamExt;
 
     use crate::{
-        Alignment, Attrs, Bar, Color, Margins, Panel, PanelConfig, Position,
+        dbus::{self, PanelTexts},
+        get_monitors,
+        headless::Headless,
+        i3bar,
+        ipc::{self, Listener},
+        lemonbar,
+        parser::{self, ConfigWatcher},
+        popup::Anchor,
+        text::TextPanels,
+        theme, Alignment, Attrs, Autohide, BackgroundImage, Bar, Border, Color,
+        ErrorBadge, Margins, Monitor, MonitorWatcher, PanelConfig,
+        PanelRebuildFn, Placement, Position, SeparatorStyle,
     };
     pub use crate::{PanelCommonBuilder, PanelCommonBuilderError};
 
@@ -176,14 +566,23 @@ pub mod builders {
     pub struct BarConfig {
         /// The bar name to look for in the config file
         pub name: String,
-        left: Vec<Box<dyn PanelConfig>>,
-        center: Vec<Box<dyn PanelConfig>>,
-        right: Vec<Box<dyn PanelConfig>>,
+        left: Vec<(String, Box<dyn PanelConfig>)>,
+        center: Vec<(String, Box<dyn PanelConfig>)>,
+        right: Vec<(String, Box<dyn PanelConfig>)>,
         /// Whether the bar should be rendered at the top or bottom of the
         /// screen
         pub position: Position,
-        /// In pixels
+        /// Offsets and width of the bar. See [`Placement`] for details.
+        #[builder(default)]
+        pub placement: Placement,
+        /// In logical pixels. See `scale`.
         pub height: u16,
+        /// How many pixels on the screen make up one logical pixel. The
+        /// height, margins, fonts, icons, and everything else drawn on the
+        /// bar are multiplied by this. If this is [`None`], it's detected
+        /// from `Xft.dpi` or the size of the monitor.
+        #[builder(default = "Some(1.0)")]
+        pub scale: Option<f64>,
         /// Whether the bar can be transparent. The background color still
         /// applies!
         pub transparent: bool,
@@ -196,23 +595,218 @@ pub mod builders {
         /// The default attributes of panels on the bar. See [`Attrs`] for
         /// details.
         pub attrs: Attrs,
+        /// The monitor to place the bar on. If this is [`None`], the bar
+        /// spans the whole screen.
+        #[builder(default)]
+        pub monitor: Option<Monitor>,
+        /// Whether and how the bar hides itself. See [`Autohide`] for
+        /// details.
+        #[builder(default)]
+        pub autohide: Option<Autohide>,
+        /// What to draw between adjacent panels, if anything. Separators are
+        /// left out next to panels that are hidden or empty.
+        #[builder(default)]
+        pub separator: Option<SeparatorStyle>,
+        /// The radius in pixels of the corners of the bar. The corners are
+        /// cut off with the X shape extension.
+        #[builder(default)]
+        pub corner_radius: f64,
+        /// The line drawn around the edge of the bar, if any. It's drawn over
+        /// the edges of the panels.
+        #[builder(default)]
+        pub border: Option<Border>,
+        /// The image drawn behind the panels, if any
+        #[builder(default)]
+        pub bg_image: Option<BackgroundImage>,
+        /// The name of the icon theme used to find icons by name. If this is
+        /// [`None`], the theme chosen in the GTK settings is used. The theme
+        /// is shared by all bars. See [`find_icon`].
+        #[builder(default)]
+        pub icon_theme: Option<String>,
+        /// Whether to rebuild the bar's panels from the config file when it
+        /// changes or when the process receives SIGUSR1. This is set for bars
+        /// returned by [`parse`][crate::parser::parse].
+        #[builder(default)]
+        pub watch_config: bool,
+        /// What to draw in place of a panel that has failed. If this is
+        /// [`None`], the panel is left empty.
+        #[builder(default)]
+        pub error_badge: Option<ErrorBadge>,
+        /// How to build a panel again after it panics or fails to start. If
+        /// this is [`None`], the panel stays failed until the bar's panels
+        /// are replaced. This is set for bars returned by
+        /// [`parse`][crate::parser::parse], which build panels from the
+        /// config that the bar was parsed from, so that restarting a panel
+        /// doesn't read the config file again.
+        #[builder(default)]
+        pub rebuild_panel: Option<PanelRebuildFn>,
+        /// Whether to open the debug overlay when the bar starts. See
+        /// [`Bar::set_debug_overlay`].
+        #[builder(default)]
+        pub debug_overlay: bool,
+        /// How long to run before printing how long panel updates took and
+        /// closing the bar. See [`Bar::profile_summary`]. If this is
+        /// [`None`], the bar runs until it's closed.
+        #[builder(default)]
+        pub bench: Option<Duration>,
     }
 
     impl BarConfig {
         /// Add a panel to the bar with a given [`Alignment`]. It will appear to
-        /// the right of all other panels with the same alignment.
+        /// the right of all other panels with the same alignment. The name is
+        /// used to refer to the panel over IPC.
         pub fn add_panel(
             &mut self,
+            name: String,
             panel: Box<dyn PanelConfig>,
             alignment: Alignment,
         ) {
             match alignment {
-                Alignment::Left => self.left.push(panel),
-                Alignment::Center => self.center.push(panel),
-                Alignment::Right => self.right.push(panel),
+                Alignment::Left => self.left.push((name, panel)),
+                Alignment::Center => self.center.push((name, panel)),
+                Alignment::Right => self.right.push((name, panel)),
             };
         }
 
+        /// Replaces the panels, separator, error badge, and background color
+        /// of a running bar with those in this configuration, along with how
+        /// they're built again when they fail. The other options only take
+        /// effect when the bar is restarted.
+        fn swap_panels(self, bar: &mut Bar) -> Result<()> {
+            bar.set_bg(self.bg);
+            bar.set_rebuild_panel(self.rebuild_panel);
+            bar.set_separator(self.separator.as_ref(), &self.attrs)?;
+            bar.set_error_badge(self.error_badge.as_ref(), &self.attrs);
+            bar.set_panels(Alignment::Left, self.left, &self.attrs)?;
+            bar.set_panels(Alignment::Center, self.center, &self.attrs)?;
+            bar.set_panels(Alignment::Right, self.right, &self.attrs)?;
+            bar.redraw_bar()
+        }
+
+        /// Draws the first frame of the bar onto an image instead of opening a
+        /// window. The bar is as wide as its monitor, or `width` logical
+        /// pixels if it isn't placed on one. See [`Headless`] for what's
+        /// drawn.
+        ///
+        /// # Errors
+        ///
+        /// If the image can't be created or drawn on.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        pub fn render(self, width: u16) -> Result<ImageSurface> {
+            let scale = self.scale.unwrap_or(1.0);
+            let width = self.monitor.as_ref().map_or(width, |monitor| {
+                (f64::from(monitor.width) / scale).round() as u16
+            });
+            let mut headless = Headless::new(
+                width,
+                self.height,
+                scale,
+                self.bg,
+                self.margins,
+                self.attrs,
+            );
+            headless.set_separator(self.separator);
+            headless.set_error_badge(self.error_badge);
+            for (alignment, panels) in [
+                (Alignment::Left, self.left),
+                (Alignment::Center, self.center),
+                (Alignment::Right, self.right),
+            ] {
+                for (name, panel) in panels {
+                    headless.add_panel(name, panel, alignment);
+                }
+            }
+            headless.render()
+        }
+
+        /// Runs the bar's panels without opening a window, and writes their
+        /// text to stdout in the [i3bar
+        /// protocol](https://i3wm.org/docs/i3bar-protocol.html) so that i3bar
+        /// or swaybar can show it, e.g. with `status_command lazybar --i3bar
+        /// <bar_name>`. Clicks are read from stdin and handled like clicks on
+        /// the bar. Each panel is a block named after it.
+        ///
+        /// Only the text of the panels is used, so colors, fonts, and icons
+        /// come from i3bar or swaybar, and panels that only draw, like
+        /// graphs, are left out. The bar can't be controlled over IPC or
+        /// D-Bus.
+        ///
+        /// # Errors
+        ///
+        /// If stdout can't be written to.
+        pub fn run_i3bar(self) -> Result<()> {
+            let rt = Runtime::new()?;
+            let local = task::LocalSet::new();
+            local.block_on(&rt, async {
+                let panels = TextPanels::start(
+                    [self.left, self.center, self.right],
+                    &self.attrs,
+                    self.height,
+                    self.rebuild_panel,
+                )?;
+                i3bar::run(panels, self.separator.is_some(), self.position)
+                    .await
+            })?;
+            // panels may have blocking tasks that never finish
+            rt.shutdown_background();
+            Ok(())
+        }
+
+        /// Runs the bar's panels without opening a window, and writes their
+        /// text to stdout as [lemonbar](https://github.com/LemonBoy/bar)
+        /// input, e.g. `lazybar --lemonbar <bar_name> | lemonbar`. Panels are
+        /// aligned with `%{l}`, `%{c}`, and `%{r}`, and separated by the text
+        /// of the bar's separator.
+        ///
+        /// Panels that respond to clicks are wrapped in clickable areas.
+        /// When one is clicked, lemonbar writes the name of the panel and the
+        /// number of the button to its stdout, e.g. `clock 1`. To handle
+        /// those clicks, feed lemonbar's output back to this bar's stdin,
+        /// e.g. through a named pipe: `mkfifo clicks; lazybar --lemonbar
+        /// <bar_name> < clicks | lemonbar > clicks`. Popups open at the edge
+        /// of the monitor, since lemonbar doesn't report where the pointer
+        /// is.
+        ///
+        /// Only the text of the panels is used, so colors and fonts are set
+        /// with lemonbar's own options, and panels that only draw, like
+        /// graphs, are left out. The bar can't be controlled over IPC or
+        /// D-Bus.
+        ///
+        /// # Errors
+        ///
+        /// If stdout can't be written to.
+        #[allow(clippy::cast_possible_wrap)]
+        pub fn run_lemonbar(self) -> Result<()> {
+            let monitor = self.monitor.clone().or_else(|| {
+                get_monitors().ok().and_then(|m| m.into_iter().next())
+            });
+            let anchor = Anchor {
+                x: monitor.as_ref().map_or(0, |m| m.x),
+                y: match (self.position, &monitor) {
+                    (Position::Top, Some(m)) => m.y + self.height as i16,
+                    (Position::Bottom, Some(m)) => {
+                        m.y + m.height as i16 - self.height as i16
+                    }
+                    (_, None) => 0,
+                },
+                position: self.position,
+            };
+            let rt = Runtime::new()?;
+            let local = task::LocalSet::new();
+            local.block_on(&rt, async {
+                let panels = TextPanels::start(
+                    [self.left, self.center, self.right],
+                    &self.attrs,
+                    self.height,
+                    self.rebuild_panel,
+                )?;
+                lemonbar::run(panels, self.separator.as_ref(), anchor).await
+            })?;
+            // panels may have blocking tasks that never finish
+            rt.shutdown_background();
+            Ok(())
+        }
+
         /// Turn the provided [`BarConfig`] into a [`Bar`] and start the main
         /// event loop.
         ///
@@ -220,67 +814,170 @@ pub mod builders {
         ///
         /// In the case of unrecoverable runtime errors.
         pub fn run(self) -> Result<()> {
+            Self::run_all(vec![self])
+        }
+
+        /// Turn each of the provided [`BarConfig`]s into a [`Bar`] and run
+        /// them all on the same event loop, e.g. one per monitor.
+        ///
+        /// The bars follow changes to the monitor configuration. If a bar's
+        /// monitor is moved or resized, so is the bar. If it's removed, the
+        /// bar closes. When a monitor is added, bars from the config that are
+        /// placed on specific monitors are parsed again, and started on the
+        /// new monitor if it's one of theirs. This happens in the running
+        /// process, so the other bars keep their state. Bars with
+        /// [`bench`][BarConfig::bench] set aren't started again, so that
+        /// `run_all` returns once they've closed.
+        ///
+        /// Bars with [`watch_config`][BarConfig::watch_config] set have their
+        /// panels rebuilt in place when the config file changes, and when the
+        /// theme of the palette changes.
+        ///
+        /// Each bar can also be controlled through a Unix socket (see
+        /// [`ipc`][crate::ipc]) or through D-Bus. The `org.lazybar.Bar`
+        /// interface is served at `/org/lazybar/Bar` under the bus name
+        /// `org.lazybar.Bar.<bar name>`, with methods `Show`, `Hide`,
+        /// `Toggle`, `Refresh(panel)`, `ShowPanel(panel)`, `HidePanel(panel)`,
+        /// `TogglePanel(panel)`, `EnablePanel(panel)`, `DisablePanel(panel)`,
+        /// and `PanelTexts`, which returns the monitor, name, and current
+        /// text of each panel.
+        ///
+        /// # Errors
+        ///
+        /// In the case of unrecoverable runtime errors.
+        pub fn run_all(bars: Vec<Self>) -> Result<()> {
             let rt = Runtime::new()?;
             let local = task::LocalSet::new();
-            local.block_on(&rt, self.run_inner())?;
+            let (send, recv) = watch::channel(Vec::new());
+            // bars that were parsed from the config and placed on a specific
+            // monitor are started again when a monitor is added
+            let mut hotplug = bars
+                .iter()
+                // benchmarks exit once their bars have closed
+                .filter(|b| {
+                    b.watch_config && b.monitor.is_some() && b.bench.is_none()
+                })
+                .map(|b| b.name.clone())
+                .collect::<Vec<_>>();
+            hotplug.sort();
+            hotplug.dedup();
+            let watch = bars.iter().any(|b| b.watch_config);
+            local.block_on(&rt, async {
+                task::spawn_local(async move {
+                    if let Err(e) = watch_monitors(send).await {
+                        log::warn!("Stopped watching monitors: {e}");
+                    }
+                });
+                task::spawn_local(async move {
+                    if let Err(e) = theme::follow().await {
+                        log::warn!("Stopped following the theme: {e}");
+                    }
+                });
+                let mut running = Running::new(recv.clone());
+                if watch {
+                    let (send, requests) = mpsc::unbounded_channel();
+                    running.reload = Some(send);
+                    let reloads = running.reloads.clone();
+                    task::spawn_local(async move {
+                        if let Err(e) = watch_config(reloads, requests).await {
+                            log::warn!("Stopped watching config: {e}");
+                        }
+                    });
+                }
+                for bar in bars {
+                    running.start(bar);
+                }
+
+                let mut monitors = recv;
+                let mut names = get_monitors()
+                    .map(|monitors| monitor_names(monitors.as_slice()))
+                    .unwrap_or_default();
+                loop {
+                    if running.tasks.is_empty() && hotplug.is_empty() {
+                        break;
+                    }
+                    tokio::select! {
+                        Some(joined) = running.tasks.join_next(), if !running.tasks.is_empty() => {
+                            let (key, result) = joined?;
+                            running.stopped(&key);
+                            result?;
+                        },
+                        () = theme::changed(), if !running.tasks.is_empty() => reload(&running.reloads),
+                        Ok(()) = monitors.changed(), if !hotplug.is_empty() => {
+                            let current = monitor_names(monitors.borrow_and_update().as_slice());
+                            if current == names {
+                                continue;
+                            }
+                            names = current;
+                            for name in &hotplug {
+                                running.start_missing(name.as_str());
+                            }
+                        },
+                        else => break,
+                    }
+                }
+                Ok::<_, anyhow::Error>(())
+            })?;
+            // panels may have blocking tasks that never finish
+            rt.shutdown_background();
             Ok(())
         }
 
-        #[allow(clippy::future_not_send)]
-        async fn run_inner(self) -> Result<()> {
+        #[allow(
+            clippy::future_not_send,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        async fn run_inner(
+            self,
+            mut monitors: Receiver<Vec<Monitor>>,
+            mut commands: broadcast::Receiver<ipc::Command>,
+            texts: Sender<PanelTexts>,
+            mut reloads: UnboundedReceiver<Self>,
+        ) -> Result<()> {
+            let scale = self
+                .scale
+                .unwrap_or_else(|| detect_scale(self.monitor.as_ref()));
             let mut bar = Bar::new(
-                self.name,
+                self.name.clone(),
                 self.position,
-                self.height,
+                self.placement,
+                (f64::from(self.height) * scale).round() as u16,
+                scale,
                 self.transparent,
-                self.bg,
-                self.margins,
+                self.bg.clone(),
+                self.margins.clone(),
+                self.monitor.clone(),
             )?;
-
-            let mut left_panels = StreamMap::with_capacity(self.left.len());
-            for (idx, panel) in self.left.into_iter().enumerate() {
-                bar.left.push(Panel::new(None));
-                left_panels.insert(
-                    idx,
-                    panel.into_stream(
-                        bar.cr.clone(),
-                        self.attrs.clone(),
-                        i32::from(self.height),
-                    )?,
-                );
+            if let Some(autohide) = self.autohide {
+                bar.set_autohide(autohide)?;
             }
-            bar.streams.insert(Alignment::Left, left_panels);
-
-            let mut center_panels = StreamMap::with_capacity(self.center.len());
-            for (idx, panel) in self.center.into_iter().enumerate() {
-                bar.center.push(Panel::new(None));
-                center_panels.insert(
-                    idx,
-                    panel.into_stream(
-                        bar.cr.clone(),
-                        self.attrs.clone(),
-                        i32::from(self.height),
-                    )?,
-                );
+            bar.set_outline(self.corner_radius, self.border.clone())?;
+            if let Err(e) = bar.set_background_image(self.bg_image.clone()) {
+                log::warn!("Failed to load background image: {e}");
             }
-            bar.streams.insert(Alignment::Center, center_panels);
-
-            let mut right_panels = StreamMap::with_capacity(self.right.len());
-            for (idx, panel) in self.right.into_iter().enumerate() {
-                bar.right.push(Panel::new(None));
-                right_panels.insert(
-                    idx,
-                    panel.into_stream(
-                        bar.cr.clone(),
-                        self.attrs.clone(),
-                        i32::from(self.height),
-                    )?,
-                );
+            if let Some(theme) = &self.icon_theme {
+                crate::icon_theme::set_icon_theme(theme.clone());
+            }
+            let debug_overlay = self.debug_overlay;
+            let started = Instant::now();
+            let bench_end = self.bench.map(|bench| started + bench);
+
+            self.swap_panels(&mut bar)?;
+            texts.send_replace(bar.panel_texts());
+            if debug_overlay {
+                if let Err(e) = bar.set_debug_overlay(true) {
+                    log::warn!("Failed to open debug overlay: {e}");
+                }
             }
-            bar.streams.insert(Alignment::Right, right_panels);
 
             task::spawn_local(async move {
+            let mut overlay_tick = time::interval(Duration::from_secs(1));
+            overlay_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
             loop {
+                let autohide_deadline = bar.autohide_deadline();
+                let restart_deadline = bar.restart_deadline();
+                let stall_deadline = bar.stall_deadline();
                 tokio::select! {
                     Ok(Some(event)) = async { bar.conn.poll_for_event() } => {
                         if let Err(e) = bar.process_event(&event) {
@@ -291,14 +988,61 @@ pub mod builders {
                             std::process::exit(0);
                         }
                     },
-                    Some((alignment, result)) = bar.streams.next() => {
-                        match result {
-                            (idx, Ok(draw_info)) => if let Err(e) = bar.update_panel(alignment, idx, draw_info) {
-                                log::warn!("Error updating {alignment} panel at index {idx}: {e}");
-                            }
-                            (idx, Err(e)) =>
-                                log::warn!("Error produced by {alignment} panel at index {idx:?}: {e}"),
+                    () = time::sleep_until(autohide_deadline.unwrap_or_else(Instant::now)), if autohide_deadline.is_some() => {
+                        if let Err(e) = bar.autohide_fire() {
+                            log::warn!("Error showing or hiding bar: {e}");
+                        }
+                    },
+                    () = time::sleep_until(restart_deadline.unwrap_or_else(Instant::now)), if restart_deadline.is_some() => {
+                        if let Err(e) = bar.restart_panels() {
+                            log::warn!("Error restarting panels: {e}");
                         }
+                        texts.send_replace(bar.panel_texts());
+                    },
+                    () = time::sleep_until(stall_deadline.unwrap_or_else(Instant::now)), if stall_deadline.is_some() => {
+                        if let Err(e) = bar.check_stalled() {
+                            log::warn!("Error marking stalled panels: {e}");
+                        }
+                    },
+                    () = time::sleep_until(bench_end.unwrap_or_else(Instant::now)), if bench_end.is_some() => {
+                        print!("{}", bar.profile_summary(started.elapsed()));
+                        break;
+                    },
+                    _ = overlay_tick.tick(), if bar.debug_overlay_open() => {
+                        if let Err(e) = bar.update_debug_overlay() {
+                            log::warn!("Error updating debug overlay: {e}");
+                        }
+                    },
+                    Some(config) = reloads.recv() => {
+                        if let Err(e) = config.swap_panels(&mut bar) {
+                            log::warn!("Error reloading panels: {e}");
+                        }
+                        texts.send_replace(bar.panel_texts());
+                    },
+                    Ok(command) = commands.recv() => {
+                        if let Err(e) = bar.handle_command(&command) {
+                            log::warn!("Error handling IPC command `{command}`: {e}");
+                        }
+                    },
+                    Ok(()) = monitors.changed() => {
+                        let monitor = bar.monitor.as_ref().map(|current| {
+                            monitors.borrow().iter().find(|m| m.name == current.name).cloned()
+                        });
+                        // a bar whose monitor was removed closes, and is started
+                        // again if the monitor comes back
+                        if matches!(monitor, Some(None)) {
+                            log::info!("Monitor of bar {} was removed, closing it", bar.name);
+                            break;
+                        }
+                        if let Err(e) = bar.move_to_monitor(monitor.flatten()) {
+                            log::warn!("Error moving bar to new monitor layout: {e}");
+                        }
+                    },
+                    Some((alignment, (idx, result))) = bar.streams.next() => {
+                        if let Err(e) = bar.handle_panel_result(alignment, idx, result) {
+                            log::warn!("Error updating {alignment} panel at index {idx}: {e}");
+                        }
+                        texts.send_replace(bar.panel_texts());
                     },
                 }
             }
@@ -307,4 +1051,253 @@ pub mod builders {
             Ok(())
         }
     }
+
+    /// Identifies a running bar by its name and the name of its monitor.
+    type BarKey = (String, Option<String>);
+
+    /// Where to send new configurations for the running bars that watch the
+    /// config file.
+    type Reloads = Rc<RefCell<HashMap<BarKey, UnboundedSender<BarConfig>>>>;
+
+    /// The bars that are running, along with the channels that bars with the
+    /// same name share. Bars can be started at any time, e.g. when a monitor
+    /// is added.
+    struct Running {
+        monitors: Receiver<Vec<Monitor>>,
+        /// Bars with the same name, e.g. one per monitor, share a socket and
+        /// a D-Bus service
+        commands: HashMap<String, broadcast::Sender<ipc::Command>>,
+        texts: HashMap<String, dbus::BarTexts>,
+        reloads: Reloads,
+        /// Asks [`watch_config`] to reload the config, if it's running
+        reload: Option<UnboundedSender<()>>,
+        tasks: JoinSet<(BarKey, Result<()>)>,
+        keys: HashSet<BarKey>,
+    }
+
+    impl Running {
+        fn new(monitors: Receiver<Vec<Monitor>>) -> Self {
+            Self {
+                monitors,
+                commands: HashMap::new(),
+                texts: HashMap::new(),
+                reloads: Rc::new(RefCell::new(HashMap::new())),
+                reload: None,
+                tasks: JoinSet::new(),
+                keys: HashSet::new(),
+            }
+        }
+
+        /// Starts a bar, along with the socket and D-Bus service for its
+        /// name if it's the first bar with that name.
+        fn start(&mut self, bar: BarConfig) {
+            let monitor = bar.monitor.as_ref().map(|m| m.name.clone());
+            let key = (bar.name.clone(), monitor.clone());
+            if !self.commands.contains_key(&bar.name) {
+                self.start_services(bar.name.as_str());
+            }
+            let commands = self.commands[&bar.name].subscribe();
+
+            let (send, recv) = watch::channel(Vec::new());
+            self.texts[&bar.name]
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((monitor.unwrap_or_default(), recv));
+            let (reload_send, reload_recv) = mpsc::unbounded_channel();
+            if bar.watch_config {
+                self.reloads.borrow_mut().insert(key.clone(), reload_send);
+            }
+
+            self.keys.insert(key.clone());
+            let monitors = self.monitors.clone();
+            self.tasks.spawn_local(async move {
+                let result =
+                    bar.run_inner(monitors, commands, send, reload_recv).await;
+                (key, result)
+            });
+        }
+
+        /// Parses the bar called `name` and starts it on each of its monitors
+        /// that it isn't already running on.
+        fn start_missing(&mut self, name: &str) {
+            let configs = match parser::parse(Some(name)) {
+                Ok(configs) => configs,
+                Err(e) => {
+                    log::warn!("Failed to parse {name} for new monitors: {e}");
+                    return;
+                }
+            };
+            for config in configs {
+                let key = (
+                    config.name.clone(),
+                    config.monitor.as_ref().map(|m| m.name.clone()),
+                );
+                if !self.keys.contains(&key) {
+                    log::info!(
+                        "Starting bar {name} on monitor {}",
+                        key.1.as_deref().unwrap_or_default()
+                    );
+                    self.start(config);
+                }
+            }
+        }
+
+        /// Forgets a bar that has stopped, e.g. because its monitor was
+        /// removed.
+        fn stopped(&mut self, key: &BarKey) {
+            self.keys.remove(key);
+            self.reloads.borrow_mut().remove(key);
+            if let Some(texts) = self.texts.get(&key.0) {
+                let monitor = key.1.clone().unwrap_or_default();
+                texts
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .retain(|(m, _)| *m != monitor);
+            }
+        }
+
+        /// Listens for IPC commands and serves the D-Bus interface for the
+        /// bars called `name`.
+        fn start_services(&mut self, name: &str) {
+            let send = broadcast::channel(16).0;
+            let texts = dbus::BarTexts::default();
+            self.commands.insert(name.to_owned(), send.clone());
+            self.texts.insert(name.to_owned(), texts.clone());
+
+            match Listener::bind(name) {
+                Ok(listener) => {
+                    let send = send.clone();
+                    let reload = self.reload.clone();
+                    task::spawn_local(async move {
+                        if let Err(e) = serve(listener, send, reload).await {
+                            log::warn!(
+                                "Stopped listening for IPC commands: {e}"
+                            );
+                        }
+                    });
+                }
+                Err(e) => {
+                    log::warn!("Not listening for IPC commands: {e}");
+                }
+            }
+            let name = name.to_owned();
+            task::spawn_local(async move {
+                if let Err(e) = dbus::serve(name.as_str(), send, texts).await {
+                    log::warn!("Not serving D-Bus interface: {e}");
+                }
+            });
+        }
+    }
+
+    /// Parses the config again each time it changes or a reload is
+    /// requested, and sends the new configuration to the running bars.
+    #[allow(clippy::future_not_send)]
+    async fn watch_config(
+        bars: Reloads,
+        mut requests: UnboundedReceiver<()>,
+    ) -> Result<()> {
+        let mut watcher = ConfigWatcher::new()?;
+
+        loop {
+            tokio::select! {
+                result = watcher.changed() => result?,
+                Some(()) = requests.recv() => {},
+            }
+            reload(&bars);
+        }
+    }
+
+    /// Parses the config again and sends the new configuration to each
+    /// running bar in `bars` with the same name and monitor.
+    fn reload(bars: &Reloads) {
+        log::info!("Reloading config");
+        let mut names = bars
+            .borrow()
+            .keys()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        for name in &names {
+            let configs = match parser::parse(Some(name.as_str())) {
+                Ok(configs) => configs,
+                Err(e) => {
+                    log::warn!("Failed to reload {name}: {e}");
+                    continue;
+                }
+            };
+            for config in configs {
+                let key = (
+                    config.name.clone(),
+                    config.monitor.as_ref().map(|m| m.name.clone()),
+                );
+                if let Some(send) = bars.borrow().get(&key) {
+                    let _ = send.send(config);
+                }
+            }
+        }
+    }
+
+    /// Returns the sorted names of `monitors`.
+    fn monitor_names(monitors: &[Monitor]) -> Vec<String> {
+        let mut names =
+            monitors.iter().map(|m| m.name.clone()).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Sends the new list of monitors each time the monitor configuration
+    /// changes.
+    #[allow(clippy::future_not_send)]
+    async fn watch_monitors(send: Sender<Vec<Monitor>>) -> Result<()> {
+        let watcher = MonitorWatcher::new()?;
+        loop {
+            watcher.changed().await?;
+            send.send_replace(get_monitors()?);
+        }
+    }
+
+    /// Answers IPC commands. Those that affect the whole process are handled
+    /// here, and the rest are passed on to the bars. Reloads are passed on to
+    /// [`watch_config`] through `reload`.
+    #[allow(clippy::future_not_send)]
+    async fn serve(
+        listener: Listener,
+        send: broadcast::Sender<ipc::Command>,
+        reload: Option<UnboundedSender<()>>,
+    ) -> Result<()> {
+        loop {
+            match listener.next().await? {
+                ipc::Command::Quit => {
+                    drop(listener);
+                    process::exit(0);
+                }
+                ipc::Command::Reload => {
+                    if reload.as_ref().is_some_and(|r| r.send(()).is_ok()) {
+                        continue;
+                    }
+                    // bars that weren't parsed from the config file can only
+                    // be rebuilt by running the program again
+                    drop(listener);
+                    return Err(reexec());
+                }
+                ipc::Command::Theme(t) => theme::set(t),
+                ipc::Command::ToggleTheme => theme::toggle(),
+                command => {
+                    let _ = send.send(command);
+                }
+            }
+        }
+    }
+
+    /// Replaces the process with a fresh copy of itself, which parses the
+    /// configuration again. This only returns if that fails.
+    fn reexec() -> anyhow::Error {
+        match env::current_exe() {
+            Ok(exe) => {
+                Command::new(exe).args(env::args_os().skip(1)).exec().into()
+            }
+            Err(e) => e.into(),
+        }
+    }
 }
@@ -1,21 +1,151 @@
-use anyhow::Result;
-use lazybar::parser;
+use std::{path::Path, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use lazybar::{headless, parser, BarConfig, PanelLogger};
 use log::LevelFilter;
 use simple_logger::SimpleLogger;
 
-fn main() -> Result<()> {
-    SimpleLogger::new()
+/// Sets up logging from `RUST_LOG`, which is a comma-separated list of a
+/// default level and `target=level` pairs, e.g. `warn,panel::clock=debug`.
+/// Each panel logs through a target named after it.
+fn init_logger() -> Result<()> {
+    let mut logger = SimpleLogger::new()
         .with_level(LevelFilter::Warn)
-        .env()
-        .with_utc_timestamps()
-        .init()
-        .unwrap();
+        .with_utc_timestamps();
+    for directive in std::env::var("RUST_LOG").unwrap_or_default().split(',') {
+        match directive.split_once('=') {
+            Some((target, level)) => {
+                if let Ok(level) = level.trim().parse() {
+                    logger = logger.with_module_level(target.trim(), level);
+                }
+            }
+            None => {
+                if let Ok(level) = directive.trim().parse() {
+                    logger = logger.with_level(level);
+                }
+            }
+        }
+    }
+    log::set_max_level(logger.max_level());
+    log::set_boxed_logger(Box::new(PanelLogger::new(logger)))?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    init_logger()?;
+
+    let mut args = std::env::args().skip(1);
+    let mut name = None;
+    let mut check = false;
+    let mut render = None;
+    let mut width = 1920;
+    let mut bench = None;
+    let mut i3bar = false;
+    let mut lemonbar = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--i3bar" => i3bar = true,
+            "--lemonbar" => lemonbar = true,
+            "--dump-default-config" => {
+                print!("{}", parser::default_config());
+                return Ok(());
+            }
+            "--schema" => {
+                println!("{}", parser::schema());
+                return Ok(());
+            }
+            // `--profile` chooses a config profile, so measuring draw times
+            // is `--bench` instead
+            "--profile" => {
+                let profile =
+                    args.next().context("--profile requires a name")?;
+                if profile.parse::<f64>().is_ok() {
+                    return Err(anyhow!(
+                        "--profile chooses a config profile. To measure how \
+                         long the bar takes to draw, use --bench <seconds>"
+                    ));
+                }
+                parser::set_profile(profile);
+            }
+            "--render" => {
+                render = Some(args.next().context("--render requires a path")?);
+            }
+            "--bench" => {
+                let seconds = args
+                    .next()
+                    .context("--bench requires a number of seconds")?
+                    .parse()
+                    .context("--bench requires a number of seconds")?;
+                bench = Some(
+                    Duration::try_from_secs_f64(seconds)
+                        .context("--bench requires a number of seconds")?,
+                );
+            }
+            "--width" => {
+                width = args
+                    .next()
+                    .context("--width requires a number")?
+                    .parse()
+                    .context("--width requires a number")?;
+            }
+            _ => name = Some(arg),
+        }
+    }
+
+    if check {
+        let problems = parser::check()?;
+        if problems.is_empty() {
+            println!("No problems found");
+            return Ok(());
+        }
+        for problem in problems {
+            eprintln!("{problem}");
+        }
+        std::process::exit(1);
+    }
+
+    if render.is_some() {
+        // rendering doesn't need an X server to find monitors on
+        parser::set_headless();
+    }
+
+    let mut configs = parser::parse(name.as_deref())?;
+
+    if let Some(path) = render {
+        let config = configs.into_iter().next().context("No bar to render")?;
+        headless::write_png(&config.render(width)?, Path::new(path.as_str()))?;
+        return Ok(());
+    }
 
-    let name = std::env::args().nth(1);
+    if i3bar || lemonbar {
+        // i3bar, swaybar, and lemonbar each show one bar per process
+        if configs.len() > 1 {
+            log::warn!("Using the first of {} bars", configs.len());
+        }
+        let config = configs.into_iter().next().context("No bar to run")?;
+        return if i3bar {
+            config.run_i3bar()
+        } else {
+            config.run_lemonbar()
+        };
+    }
 
-    let config = parser::parse(name.as_deref())?;
+    // bars are X windows, so a Wayland session needs XWayland
+    if std::env::var_os("DISPLAY").is_none()
+        && std::env::var_os("WAYLAND_DISPLAY").is_some()
+    {
+        return Err(anyhow!(
+            "No X server found. Bars need XWayland on Wayland, or their \
+             panels can be shown by swaybar with `status_command lazybar \
+             --i3bar <bar_name>`"
+        ));
+    }
 
-    config.run()?;
+    for config in &mut configs {
+        config.bench = bench;
+    }
+    BarConfig::run_all(configs)?;
 
     Ok(())
 }
@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use csscolorparser::Color;
+
+use crate::parse_color;
+
+/// A solid color or a linear gradient. This is how the foreground and
+/// background of [`Attrs`][crate::Attrs] are drawn.
+#[derive(Clone, Debug)]
+pub enum Paint {
+    /// A solid color
+    Color(Color),
+    /// A linear gradient, like `linear-gradient` in CSS
+    Gradient {
+        /// The direction of the gradient in degrees, clockwise from pointing
+        /// up
+        angle: f64,
+        /// The colors of the gradient and their positions along it, from 0 to
+        /// 1
+        stops: Vec<(f64, Color)>,
+    },
+}
+
+impl Paint {
+    /// Sets the source of a [`cairo::Context`]. Gradients are stretched
+    /// across an area of the given size, starting at the origin.
+    pub fn apply(&self, cr: &cairo::Context, width: f64, height: f64) {
+        match self {
+            Self::Color(color) => {
+                cr.set_source_rgba(color.r, color.g, color.b, color.a);
+            }
+            Self::Gradient { angle, stops } => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                // the gradient reaches the corners of the area, as in CSS
+                let half = ((width * sin).abs() + (height * cos).abs()) / 2.0;
+                let (dx, dy) = (sin * half, -cos * half);
+                let gradient = cairo::LinearGradient::new(
+                    width / 2.0 - dx,
+                    height / 2.0 - dy,
+                    width / 2.0 + dx,
+                    height / 2.0 + dy,
+                );
+                for (offset, color) in stops {
+                    gradient.add_color_stop_rgba(
+                        *offset, color.r, color.g, color.b, color.a,
+                    );
+                }
+                if let Err(e) = cr.set_source(&gradient) {
+                    log::warn!("Failed to draw gradient: {e}");
+                }
+            }
+        }
+    }
+}
+
+impl From<Color> for Paint {
+    fn from(value: Color) -> Self {
+        Self::Color(value)
+    }
+}
+
+impl FromStr for Paint {
+    type Err = anyhow::Error;
+
+    /// Parses a color with [`parse_color`], or a gradient like
+    /// `gradient(90deg, #f00, #00f 80%)`. The angle can be given in `deg`,
+    /// `rad`, or `turn`, or as `to top`, `to right`, `to bottom`, or `to left`,
+    /// and defaults to `to bottom`. Each color can be followed by its position
+    /// along the gradient as a percentage. Colors without one are spaced
+    /// evenly.
+    #[allow(clippy::cast_precision_loss)]
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let Some(args) = s
+            .strip_prefix("gradient(")
+            .and_then(|args| args.strip_suffix(')'))
+        else {
+            return Ok(Self::Color(parse_color(s)?));
+        };
+
+        let mut args = split_args(args);
+        let angle = match args.first().and_then(|arg| parse_angle(arg)) {
+            Some(angle) => {
+                args.remove(0);
+                angle
+            }
+            None => 180.0,
+        };
+        if args.len() < 2 {
+            return Err(anyhow!("A gradient needs at least two colors: {s}"));
+        }
+
+        let last = (args.len() - 1) as f64;
+        let stops = args
+            .iter()
+            .enumerate()
+            .map(|(idx, arg)| {
+                let (color, offset) = match arg.rsplit_once(' ') {
+                    Some((color, offset)) if offset.ends_with('%') => (
+                        color.trim(),
+                        offset.trim_end_matches('%').parse::<f64>()? / 100.0,
+                    ),
+                    _ => (*arg, idx as f64 / last),
+                };
+                Ok((offset.clamp(0.0, 1.0), parse_color(color)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::Gradient { angle, stops })
+    }
+}
+
+/// Splits the arguments of a gradient at the commas that aren't inside
+/// parentheses, like those in `rgb(255, 0, 0)`.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut split = Vec::new();
+    for (idx, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(args[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(args[start..].trim());
+    split
+}
+
+/// Parses an angle in degrees.
+fn parse_angle(angle: &str) -> Option<f64> {
+    match angle {
+        "to top" => Some(0.0),
+        "to right" => Some(90.0),
+        "to bottom" => Some(180.0),
+        "to left" => Some(270.0),
+        _ => {
+            if let Some(degrees) = angle.strip_suffix("deg") {
+                degrees.trim().parse().ok()
+            } else if let Some(turns) = angle.strip_suffix("turn") {
+                turns.trim().parse::<f64>().ok().map(|t| t * 360.0)
+            } else if let Some(radians) = angle.strip_suffix("rad") {
+                radians.trim().parse::<f64>().ok().map(f64::to_degrees)
+            } else {
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use anyhow::{anyhow, Result};
+use config::Config;
+use csscolorparser::Color;
+use lazy_static::lazy_static;
+
+use crate::{
+    remove_bool_from_config, remove_string_from_config, theme, x::get_resources,
+};
+
+/// The X resources that are imported into the palette.
+const RESOURCES: [&str; 18] = [
+    "color0",
+    "color1",
+    "color2",
+    "color3",
+    "color4",
+    "color5",
+    "color6",
+    "color7",
+    "color8",
+    "color9",
+    "color10",
+    "color11",
+    "color12",
+    "color13",
+    "color14",
+    "color15",
+    "background",
+    "foreground",
+];
+
+/// How many palette entries can refer to each other in a row before a color
+/// is considered invalid. This stops entries that refer to themselves from
+/// looping forever.
+const MAX_DEPTH: usize = 8;
+
+lazy_static! {
+    static ref PALETTE: RwLock<HashMap<String, String>> =
+        RwLock::new(HashMap::new());
+    static ref WAL_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+/// Loads the palette from the `[palette]` table of the global [`Config`],
+/// replacing the previous one. This happens each time the config is read.
+///
+/// Configuration options:
+///
+/// - `xresources`: whether to import `color0` through `color15`,
+///   `background`, and `foreground` from the X resource database, e.g.
+///   `*.color4: #458588` in `~/.Xresources`. This is read from the X server,
+///   so run `xrdb -merge ~/.Xresources` first. Entries in `colors` take
+///   precedence.
+///   - type: bool
+///   - default: false
+///
+/// - `wal`: whether to import `color0` through `color15`, `background`,
+///   `foreground`, and `cursor` from the `colors.json` file written by pywal
+///   or wallust. The file is watched, and the bar is recolored whenever it
+///   changes, e.g. after `wal -i wallpaper.png`. These take precedence over
+///   X resources, and entries in `colors` take precedence over both.
+///   - type: bool
+///   - default: false
+///
+/// - `wal_path`: the path of the `colors.json` file. A leading `~/` is
+///   replaced with the home directory.
+///   - type: String
+///   - default: `$XDG_CACHE_HOME/wal/colors.json`
+///
+/// - `colors`: named colors, e.g. `accent = "#fe8019"` or
+///   `urgent = "$color1"`.
+///   - type: table of Strings
+///   - default: none
+///
+/// - `dark`, `light`: named colors for each theme, which take precedence over
+///   `colors` while that theme is in use. See `theme` below.
+///   - type: table of Strings
+///   - default: none
+///
+/// Anywhere a color is expected, `$name` refers to the palette entry called
+/// `name`, e.g. `fg = "$foreground"` or `bg = "gradient($color0, $color8)"`.
+/// Entries can refer to other entries.
+///
+/// The theme is chosen as described in [`theme::configure`]. When it
+/// changes, the palette is loaded again and the running bars rebuild their
+/// panels, so the default attributes of the bar change along with it.
+pub(crate) fn load(config: &Config) {
+    let mut palette = HashMap::new();
+    let mut table = config.get_table("palette").unwrap_or_default();
+
+    if remove_bool_from_config("xresources", &mut table).unwrap_or_default() {
+        match get_resources() {
+            Ok(resources) => palette.extend(parse_resources(&resources)),
+            Err(e) => log::warn!("Failed to read X resources: {e}"),
+        }
+    }
+
+    let wal_path = remove_bool_from_config("wal", &mut table)
+        .unwrap_or_default()
+        .then(|| {
+            remove_string_from_config("wal_path", &mut table)
+                .map_or_else(default_wal_path, |path| expand_home(&path))
+        });
+    if let Some(path) = &wal_path {
+        match read_wal(path) {
+            Ok(colors) => palette.extend(colors),
+            Err(e) => {
+                log::warn!(
+                    "Failed to read colors from {}: {e}",
+                    path.display()
+                );
+            }
+        }
+    }
+    WAL_PATH.write().unwrap().clone_from(&wal_path);
+
+    theme::configure(&mut table);
+    for key in ["colors", theme::current().name()] {
+        let Some(colors) = table
+            .remove(key)
+            .and_then(|colors| colors.into_table().ok())
+        else {
+            continue;
+        };
+        for (name, color) in colors {
+            match color.into_string() {
+                Ok(color) => {
+                    palette.insert(name, color);
+                }
+                Err(e) => log::warn!("Ignoring palette entry {name}: {e}"),
+            }
+        }
+    }
+
+    *PALETTE.write().unwrap() = palette;
+}
+
+/// Returns the `colors.json` file that the palette was last loaded from, if
+/// any, so that it can be watched for changes.
+pub(crate) fn wal_path() -> Option<PathBuf> {
+    WAL_PATH.read().unwrap().clone()
+}
+
+fn default_wal_path() -> PathBuf {
+    PathBuf::from(format!(
+        "{}/wal/colors.json",
+        env::var("XDG_CACHE_HOME").unwrap_or_else(|_| {
+            format!("{}/.cache", env::var("HOME").unwrap_or_default())
+        })
+    ))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    path.strip_prefix("~/").map_or_else(
+        || PathBuf::from(path),
+        |rest| {
+            PathBuf::from(format!(
+                "{}/{rest}",
+                env::var("HOME").unwrap_or_default()
+            ))
+        },
+    )
+}
+
+/// Reads the colors from a `colors.json` file in the format written by pywal,
+/// where `special` holds `background`, `foreground`, and `cursor`, and
+/// `colors` holds `color0` through `color15`.
+fn read_wal(path: &Path) -> Result<HashMap<String, String>> {
+    let json: serde_json::Value =
+        serde_json::from_str(fs::read_to_string(path)?.as_str())?;
+    let colors = ["special", "colors"]
+        .into_iter()
+        .filter_map(|key| json[key].as_object())
+        .flatten()
+        .filter_map(|(name, color)| {
+            Some((name.clone(), color.as_str()?.to_owned()))
+        })
+        .collect::<HashMap<_, _>>();
+    if colors.is_empty() {
+        return Err(anyhow!("No colors found"));
+    }
+    Ok(colors)
+}
+
+/// Finds the entries of [`RESOURCES`] in the contents of the X resource
+/// database. Both `*.color0` and `*color0` are accepted.
+fn parse_resources(resources: &str) -> HashMap<String, String> {
+    resources
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('!'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let name =
+                key.trim().trim_start_matches('*').trim_start_matches('.');
+            RESOURCES
+                .contains(&name)
+                .then(|| (name.to_owned(), value.trim().to_owned()))
+        })
+        .collect()
+}
+
+/// Parses a color with [csscolorparser], or looks it up in the palette if
+/// it's written as `$name`. The palette is defined in the `[palette]` table of
+/// the config, with named colors in `palette.colors` and, if enabled, colors
+/// from the X resource database and from pywal.
+///
+/// # Errors
+///
+/// If the color is invalid or the palette has no entry with the given name.
+pub fn parse_color(color: &str) -> Result<Color> {
+    let mut color = color.trim().to_owned();
+    for _ in 0..MAX_DEPTH {
+        let Some(name) = color.strip_prefix('$') else {
+            return Ok(color.parse()?);
+        };
+        color = PALETTE
+            .read()
+            .unwrap()
+            // keys are stored in lowercase
+            .get(name.to_lowercase().as_str())
+            .ok_or_else(|| anyhow!("No palette entry named {name}"))?
+            .trim()
+            .to_owned();
+    }
+    Err(anyhow!(
+        "Palette entries refer to each other in a loop at {color}"
+    ))
+}
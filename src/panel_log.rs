@@ -0,0 +1,118 @@
+use std::{
+    cell::RefCell,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use log::{Log, Metadata, Record};
+use tokio_stream::Stream;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<str>>> = const { RefCell::new(None) };
+}
+
+/// Puts the previous panel back when a scope ends, even if it panics.
+struct Restore(Option<Rc<str>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// Runs `f` with the log records it produces attributed to the panel called
+/// `name`.
+pub(crate) fn scoped<T>(name: &Rc<str>, f: impl FnOnce() -> T) -> T {
+    let _restore =
+        Restore(CURRENT.with(|current| current.replace(Some(name.clone()))));
+    f()
+}
+
+/// A stream that attributes the log records produced while it's polled to a
+/// panel. See [`PanelLogger`].
+pub(crate) struct Scoped<S> {
+    name: Rc<str>,
+    inner: S,
+}
+
+impl<S> Scoped<S> {
+    pub(crate) const fn new(name: Rc<str>, inner: S) -> Self {
+        Self { name, inner }
+    }
+}
+
+impl<S: Stream + Unpin> Stream for Scoped<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        scoped(&this.name, || Pin::new(&mut this.inner).poll_next(cx))
+    }
+}
+
+/// Wraps another [`Log`] implementation so that each panel logs through a
+/// target named after it, e.g. `panel::clock` for the panel defined in
+/// `[panels.clock]`. This covers everything a panel logs while it starts and
+/// while the bar waits for its next update, but not tasks or threads that
+/// the panel spawns.
+///
+/// Records that don't come from a panel keep their own target. Targets can
+/// be filtered like any other, e.g. with
+/// [`SimpleLogger::with_module_level`](https://docs.rs/simple_logger).
+#[derive(Debug)]
+pub struct PanelLogger<L> {
+    inner: L,
+}
+
+impl<L: Log> PanelLogger<L> {
+    /// Creates a logger that passes records on to `inner`.
+    #[must_use]
+    pub const fn new(inner: L) -> Self {
+        Self { inner }
+    }
+}
+
+impl<L: Log> Log for PanelLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        with_target(metadata.target(), |target| {
+            self.inner.enabled(
+                &Metadata::builder()
+                    .level(metadata.level())
+                    .target(target)
+                    .build(),
+            )
+        })
+    }
+
+    fn log(&self, record: &Record) {
+        with_target(record.target(), |target| {
+            self.inner.log(
+                &Record::builder()
+                    .args(*record.args())
+                    .level(record.level())
+                    .target(target)
+                    .module_path(record.module_path())
+                    .file(record.file())
+                    .line(record.line())
+                    .build(),
+            );
+        });
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Calls `f` with the target of the panel that's currently running, or with
+/// `target` if there isn't one.
+fn with_target<T>(target: &str, f: impl FnOnce(&str) -> T) -> T {
+    match CURRENT.with(|current| current.borrow().clone()) {
+        Some(name) => f(format!("panel::{name}").as_str()),
+        None => f(target),
+    }
+}
@@ -7,8 +7,9 @@ use tokio::time::interval;
 use tokio_stream::{wrappers::IntervalStream, StreamExt};
 
 use crate::{
-    bar::PanelDrawInfo, draw_common, remove_string_from_config,
-    remove_uint_from_config, Attrs, PanelCommon, PanelConfig, PanelStream,
+    bar::PanelDrawInfo, draw_common, draw_gauge, draw_progress_bar,
+    remove_string_from_config, remove_uint_from_config, render_format, Attrs,
+    PanelCommon, PanelConfig, PanelStream, Ramp, Style,
 };
 
 /// Shows the current battery level.
@@ -23,11 +24,19 @@ pub struct Battery {
     adapter: String,
     #[builder(default = "Duration::from_secs(10)")]
     duration: Duration,
+    #[builder(default)]
+    ramp: Option<Ramp>,
+    #[builder(default)]
+    style: Style,
     common: PanelCommon,
 }
 
 impl Battery {
-    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
         let mut capacity_f = File::open(format!(
             "/sys/class/power_supply/{}/capacity",
             self.battery
@@ -42,43 +51,83 @@ impl Battery {
         let mut status = String::new();
         status_f.read_to_string(&mut status)?;
 
-        let text =
-            match status.trim() {
-                "Charging" => self.common.formats[0]
-                    .replace("%percentage%", capacity.trim()),
-                "Discharging" => self.common.formats[1]
-                    .replace("%percentage%", capacity.trim()),
-                "Not charging" => self.common.formats[2]
-                    .replace("%percentage%", capacity.trim()),
-                "Full" => self.common.formats[3]
-                    .replace("%percentage%", capacity.trim()),
-                "Unknown" => self.common.formats[4]
-                    .replace("%percentage%", capacity.trim()),
-                _ => String::from("Unknown battery state"),
-            };
+        let percentage = capacity.trim().parse::<u32>().unwrap_or_default();
+        let ramp = self
+            .ramp
+            .as_ref()
+            .map(|r| r.choose(percentage, 0, 100))
+            .unwrap_or_default();
+        let values = HashMap::from([
+            (String::from("percentage"), capacity.trim().to_owned()),
+            (String::from("status"), status.trim().to_owned()),
+            (String::from("ramp"), ramp),
+        ]);
+        let text = match status.trim() {
+            "Charging" => render_format(&self.common.formats[0], &values),
+            "Discharging" => render_format(&self.common.formats[1], &values),
+            "Not charging" => render_format(&self.common.formats[2], &values),
+            "Full" => render_format(&self.common.formats[3], &values),
+            "Unknown" => render_format(&self.common.formats[4], &values),
+            _ => String::from("Unknown battery state"),
+        };
+        let attrs = self.ramp.as_ref().map_or_else(
+            || self.common.attrs[0].clone(),
+            |r| r.attrs(percentage, 0, 100, &self.common.attrs[0]),
+        );
+        let attrs = self.common.states.apply(f64::from(percentage), &attrs);
 
-        draw_common(
-            cr,
-            text.as_str(),
-            &self.common.attrs[0],
-            self.common.dependence,
-        )
+        let fraction = f64::from(percentage) / 100.0;
+        let mut draw_info = match &self.style {
+            Style::Text | Style::Graph(_) => draw_common(
+                cr,
+                text.as_str(),
+                &attrs,
+                self.common.dependence,
+                &self.common.ellipsize,
+            )?,
+            Style::Bar(bar) => draw_progress_bar(
+                cr,
+                fraction,
+                bar,
+                text.as_str(),
+                &attrs,
+                self.common.dependence,
+            )?,
+            Style::Gauge(gauge) => draw_gauge(
+                cr,
+                fraction,
+                gauge,
+                height,
+                text.as_str(),
+                &attrs,
+                self.common.dependence,
+            )?,
+        };
+        draw_info.values.extend(values);
+        if let Some(state) = self.common.states.find(f64::from(percentage)) {
+            draw_info.set_value("state", &state.name);
+        }
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Battery {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
         global_attrs: Attrs,
-        _height: i32,
+        height: i32,
     ) -> Result<PanelStream> {
         for attr in &mut self.common.attrs {
             attr.apply_to(&global_attrs);
         }
 
         let stream = IntervalStream::new(interval(self.duration))
-            .map(move |_| self.draw(&cr));
+            .map(move |_| self.draw(&cr, height));
 
         Ok(Box::pin(stream))
     }
@@ -97,37 +146,45 @@ impl PanelConfig for Battery {
     ///
     /// - `charging_format`: format string when the battery is charging
     ///   - type: String
-    ///   - formatting options: `%percentage%`
-    ///   - default: "CHG: %percentage%%"
+    ///   - formatting options: `{percentage}`, `{status}`, `{ramp}`
+    ///   - default: "CHG: {percentage}%"
     ///
     /// - `discharging_format`: format string when the battery is discharging
     ///   - type: String
-    ///   - formatting options: `%percentage%`
-    ///   - default: "DSCHG: %percentage%%"
+    ///   - formatting options: `{percentage}`, `{status}`, `{ramp}`
+    ///   - default: "DSCHG: {percentage}%"
     ///
     /// - `not_charging_format`: format string when the battery is not charging
     ///   - type: String
-    ///   - formatting options: `%percentage%`
-    ///   - default: "NCHG: %percentage%%"
+    ///   - formatting options: `{percentage}`, `{status}`, `{ramp}`
+    ///   - default: "NCHG: {percentage}%"
     ///
     /// - `full_format`: format string when the battery is full
     ///   - type: String
-    ///   - formatting options: `%percentage%`
-    ///   - default: "FULL: %percentage%%"
+    ///   - formatting options: `{percentage}`, `{status}`, `{ramp}`
+    ///   - default: "FULL: {percentage}%"
     ///
     /// - `unknown_format`: format string when the battery is unknown
     ///   - type: String
-    ///   - formatting options: `%percentage%`
-    ///   - default: "%percentage%%"
+    ///   - formatting options: `{percentage}`, `{status}`, `{ramp}`
+    ///   - default: "{percentage}%"
+    ///
+    /// - `ramp`: the ramp to display based on the battery level, e.g. to turn
+    ///   the panel from green to red as the battery drains. See
+    ///   [`Ramp::parse`] for parsing details.
     ///
     /// - `interval`: how often (in seconds) to poll for new values
     ///   - type: u64
     ///   - default: 10
     ///
-    /// - See [`PanelCommon::parse`].
+    /// - The battery level can be drawn as a progress bar or a gauge. See
+    ///   [`Style::parse`] for parsing options.
+    ///
+    /// - See [`PanelCommon::parse`]. The values `percentage` and `status` are
+    ///   available to `visible_when`.
     fn parse(
         table: &mut HashMap<String, config::Value>,
-        _global: &Config,
+        global: &Config,
     ) -> Result<Self> {
         let mut builder = BatteryBuilder::default();
         if let Some(battery) = remove_string_from_config("battery", table) {
@@ -139,6 +196,10 @@ impl PanelConfig for Battery {
         if let Some(duration) = remove_uint_from_config("interval", table) {
             builder.duration(Duration::from_secs(duration));
         }
+        if let Some(ramp) = remove_string_from_config("ramp", table) {
+            builder.ramp(Ramp::parse(ramp, global));
+        }
+        builder.style(Style::parse(table));
         builder.common(PanelCommon::parse(
             table,
             &[
@@ -149,11 +210,11 @@ impl PanelConfig for Battery {
                 "_unknown",
             ],
             &[
-                "CHG: %percentage%%",
-                "DSCHG: %percentage%%",
-                "NCHG: %percentage%%",
-                "FULL: %percentage%%",
-                "%percentage%%",
+                "CHG: {percentage}%",
+                "DSCHG: {percentage}%",
+                "NCHG: {percentage}%",
+                "FULL: {percentage}%",
+                "{percentage}%",
             ],
             &[""],
         )?);
@@ -0,0 +1,361 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    rc::Rc,
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use pangocairo::functions::{create_layout, show_layout};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Report(String),
+    Mouse(MouseEvent),
+}
+
+/// The state of a desktop, in the same order as the panel's [`Attrs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Focused,
+    Monocle,
+    Urgent,
+    Occupied,
+    Free,
+}
+
+#[derive(Debug)]
+struct Desktop {
+    monitor: String,
+    /// The 1-based index of the desktop on its monitor.
+    index: usize,
+    name: String,
+    state: State,
+}
+
+/// Parses a line of `bspc subscribe report` output. See the `REPORT FORMAT`
+/// section of bspc(1).
+fn parse_report(report: &str) -> Vec<Desktop> {
+    let mut desktops: Vec<Desktop> = Vec::new();
+    let mut monitor = "";
+    let mut index = 0;
+
+    for item in report.strip_prefix('W').unwrap_or(report).split(':') {
+        let mut chars = item.chars();
+        let Some(code) = chars.next() else { continue };
+        let value = chars.as_str();
+        let state = match code {
+            'M' | 'm' => {
+                monitor = value;
+                index = 0;
+                continue;
+            }
+            'L' => {
+                // the layout of the focused desktop on the current monitor
+                if value == "M" {
+                    if let Some(d) = desktops.iter_mut().find(|d| {
+                        d.monitor == monitor && d.state == State::Focused
+                    }) {
+                        d.state = State::Monocle;
+                    }
+                }
+                continue;
+            }
+            'O' | 'F' => State::Focused,
+            // urgency is more useful than focus, as with i3
+            'U' | 'u' => State::Urgent,
+            'o' => State::Occupied,
+            'f' => State::Free,
+            _ => continue,
+        };
+
+        index += 1;
+        desktops.push(Desktop {
+            monitor: monitor.to_owned(),
+            index,
+            name: value.to_owned(),
+            state,
+        });
+    }
+
+    desktops
+}
+
+/// Displays bspwm desktops using `bspc subscribe report`.
+///
+/// Unlike [`XWorkspaces`][super::XWorkspaces], this panel distinguishes
+/// occupied, urgent, and monocle desktops, and can show only the desktops on
+/// one monitor. Clicking a desktop focuses it.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Bspwm {
+    #[builder(default, setter(strip_option))]
+    monitor: Option<String>,
+    #[builder(default = "0")]
+    padding: i32,
+    #[builder(setter(strip_option))]
+    highlight: Option<Highlight>,
+    /// Each desktop's selector and the x-coordinate of its right edge, as of
+    /// the last draw.
+    #[builder(default, setter(skip))]
+    bounds: Vec<(String, f64)>,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Bspwm {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Report(report) => Some(self.draw(cr, height, &report)),
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                x,
+                ..
+            }) => {
+                let (selector, _) =
+                    self.bounds.iter().find(|(_, end)| x < *end)?;
+                // the resulting report triggers a redraw
+                match Command::new("bspc")
+                    .args(["desktop", "--focus", selector.as_str()])
+                    .stderr(Stdio::null())
+                    .status()
+                {
+                    Ok(status) if status.success() => None,
+                    Ok(status) => Some(Err(anyhow!(
+                        "Failed to focus desktop {selector}: bspc exited with \
+                         {status}"
+                    ))),
+                    Err(e) => Some(Err(e.into())),
+                }
+            }
+            Update::Mouse(_) => None,
+        }
+    }
+
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        report: &str,
+    ) -> Result<PanelDrawInfo> {
+        let desktops = parse_report(report)
+            .into_iter()
+            .filter(|d| {
+                self.monitor.as_deref().map_or(true, |m| d.monitor == m)
+            })
+            .collect::<Vec<_>>();
+
+        let layouts = desktops
+            .iter()
+            .map(|d| {
+                let layout = create_layout(cr);
+                self.common.attrs[d.state as usize].apply_font(&layout);
+                layout.set_text(d.name.as_str());
+                (d.state, layout)
+            })
+            .collect::<Vec<_>>();
+
+        let mut end = 0.0;
+        self.bounds = desktops
+            .into_iter()
+            .zip(&layouts)
+            .map(|(d, (_, layout))| {
+                end += f64::from(layout.pixel_size().0 + self.padding);
+                (format!("{}:^{}", d.monitor, d.index), end)
+            })
+            .collect();
+
+        let width = layouts
+            .iter()
+            .map(|l| l.1.pixel_size().0 + self.padding)
+            .sum::<i32>()
+            - self.padding;
+
+        let padding = self.padding;
+        let attrs = self.common.attrs.clone();
+        let highlight = self.highlight.clone();
+
+        Ok(PanelDrawInfo::new(
+            (width, height),
+            self.common.dependence,
+            Box::new(move |cr| {
+                for (state, layout) in &layouts {
+                    let attrs = &attrs[*state as usize];
+                    let size = layout.pixel_size();
+
+                    attrs.apply_bg(
+                        cr,
+                        f64::from(size.0 + padding),
+                        f64::from(height),
+                    );
+
+                    cr.save()?;
+                    cr.rectangle(
+                        0.0,
+                        0.0,
+                        f64::from(size.0 + padding),
+                        f64::from(height),
+                    );
+                    cr.fill()?;
+
+                    if matches!(state, State::Focused | State::Monocle) {
+                        if let Some(highlight) = &highlight {
+                            cr.rectangle(
+                                0.0,
+                                f64::from(height) - highlight.height,
+                                f64::from(size.0 + padding),
+                                highlight.height,
+                            );
+                            cr.set_source_rgba(
+                                highlight.color.r,
+                                highlight.color.g,
+                                highlight.color.b,
+                                highlight.color.a,
+                            );
+                            cr.fill()?;
+                        }
+                    }
+
+                    cr.translate(
+                        f64::from(padding / 2),
+                        f64::from(height - size.1) / 2.0,
+                    );
+
+                    attrs.apply_fg(cr, f64::from(size.0), f64::from(size.1));
+                    show_layout(cr, layout);
+                    cr.restore()?;
+
+                    cr.translate(f64::from(size.0 + padding), 0.0);
+                }
+                Ok(())
+            }),
+        ))
+    }
+}
+
+/// Runs `bspc subscribe report` on its own thread, sending each report.
+fn follow(send: UnboundedSender<Update>) -> Result<()> {
+    let mut child = Command::new("bspc")
+        .args(["subscribe", "report"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture bspc output"))?;
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            if send.send(Update::Report(line)).is_err() {
+                break;
+            }
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+        log::warn!("bspc exited");
+    });
+
+    Ok(())
+}
+
+impl PanelConfig for Bspwm {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<PanelStream> {
+        let (send, recv) = mpsc::unbounded_channel();
+        follow(send)?;
+
+        let focused = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&focused);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = UnboundedReceiverStream::new(recv)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .filter_map(move |u| self.update(&cr, height, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `monitor`: the monitor whose desktops should be shown, e.g. `DP-1`
+    ///   - type: String
+    ///   - default: none (shows desktops on all monitors)
+    ///
+    /// - `padding`: The space in pixels between two desktop names. The
+    ///   [`Attrs`] will change (if applicable) halfway between the two names.
+    ///   - type: u64
+    ///   - default: 0
+    ///
+    /// - `highlight`: The highlight that will appear on the focused desktop of
+    ///   each monitor. See [`Highlight::parse`] for parsing options.
+    ///
+    /// - See [`PanelCommon::parse`]. No format strings are used for this panel.
+    ///   Five instances of [`Attrs`] are parsed using the prefixes `focused_`,
+    ///   `monocle_` (focused and in the monocle layout), `urgent_`,
+    ///   `occupied_`, and `free_`. Unset values of `monocle_` fall back to
+    ///   `focused_`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = BspwmBuilder::default();
+
+        if let Some(monitor) = remove_string_from_config("monitor", table) {
+            builder.monitor(monitor);
+        }
+        if let Some(padding) = remove_uint_from_config("padding", table) {
+            builder.padding(padding as i32);
+        }
+
+        builder.common(PanelCommon::parse(
+            table,
+            &[],
+            &[],
+            &["focused_", "monocle_", "urgent_", "occupied_", "free_"],
+        )?);
+
+        builder.highlight(Highlight::parse(table));
+
+        Ok(builder.build()?)
+    }
+}
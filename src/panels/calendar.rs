@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+#[derive(Debug, Clone)]
+struct Event {
+    title: String,
+    start: DateTime<Local>,
+}
+
+/// Displays the next upcoming event from one or more iCalendar (`.ics`)
+/// files.
+///
+/// [khal](https://lostpackets.de/khal/) stores its calendars as vdirs (one
+/// `.ics` file per event), so pointing `path` at a khal calendar directory
+/// works as expected.
+///
+/// Recurring events (`RRULE`) are not expanded; only their first occurrence
+/// is considered.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Calendar {
+    paths: Vec<String>,
+    #[builder(default = "Duration::from_secs(60)")]
+    interval: Duration,
+    #[builder(default = "Duration::from_secs(15 * 60)")]
+    urgency: Duration,
+    #[builder(default = r#"String::from("%H:%M")"#)]
+    time_format: String,
+    common: PanelCommon,
+}
+
+impl Calendar {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let now = Local::now();
+        let next = self
+            .paths
+            .iter()
+            .flat_map(|p| read_events(Path::new(p.as_str())))
+            .filter(|e| e.start >= now)
+            .min_by_key(|e| e.start);
+
+        let (text, attrs, values) = next.map_or_else(
+            || {
+                (
+                    self.common.formats[1].clone(),
+                    &self.common.attrs[0],
+                    HashMap::new(),
+                )
+            },
+            |event| {
+                let remaining =
+                    (event.start - now).to_std().unwrap_or_default();
+                let values = HashMap::from([
+                    (
+                        String::from("title"),
+                        self.common.escape_markup.apply(event.title.as_str()),
+                    ),
+                    (
+                        String::from("time"),
+                        event
+                            .start
+                            .format(self.time_format.as_str())
+                            .to_string(),
+                    ),
+                    (String::from("countdown"), format_countdown(remaining)),
+                ]);
+                (
+                    render_format(&self.common.formats[0], &values),
+                    if remaining <= self.urgency {
+                        &self.common.attrs[1]
+                    } else {
+                        &self.common.attrs[0]
+                    },
+                    values,
+                )
+            },
+        );
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            attrs,
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Calendar {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `path`: an `.ics` file or a directory of `.ics` files (such as a
+    ///   khal/vdirsyncer calendar). Multiple paths may be separated by `:`.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `format`: the format string when an upcoming event exists
+    ///   - type: String
+    ///   - default: `{time} {title}`
+    ///   - formatting options: `{title}`, `{time}`, `{countdown}`
+    ///
+    /// - `format_none`: the format string when there are no upcoming events
+    ///   - type: String
+    ///   - default: ``
+    ///
+    /// - `time_format`: the format of `{time}`. See
+    ///   [`chrono::format::strftime`] for details.
+    ///   - type: String
+    ///   - default: `%H:%M`
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 60
+    ///
+    /// - `urgency`: how many minutes before an event the urgent attrs should be
+    ///   used
+    ///   - type: u64
+    ///   - default: 15
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `urgent_`. Any unset urgent attributes fall
+    ///   back to the normal ones. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = CalendarBuilder::default();
+
+        if let Some(path) = remove_string_from_config("path", table) {
+            builder.paths(path.split(':').map(ToOwned::to_owned).collect());
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(urgency) = remove_uint_from_config("urgency", table) {
+            builder.urgency(Duration::from_secs(urgency * 60));
+        }
+        if let Some(time_format) =
+            remove_string_from_config("time_format", table)
+        {
+            builder.time_format(time_format);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_none"],
+            &["{time} {title}", ""],
+            &["", "urgent_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
+
+fn format_countdown(remaining: Duration) -> String {
+    let minutes = remaining.as_secs().div_ceil(60);
+    match (minutes / (60 * 24), minutes / 60 % 24, minutes % 60) {
+        (0, 0, m) => format!("{m}m"),
+        (0, h, m) => format!("{h}h {m:02}m"),
+        (d, h, _) => format!("{d}d {h}h"),
+    }
+}
+
+fn read_events(path: &Path) -> Vec<Event> {
+    if path.is_dir() {
+        fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|e| e == "ics"))
+                    .flat_map(|p| read_events(p.as_path()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    } else {
+        let mut ics = String::new();
+        if let Err(e) =
+            File::open(path).and_then(|mut f| f.read_to_string(&mut ics))
+        {
+            log::warn!("Failed to read calendar file {path:?}: {e}");
+            return Vec::new();
+        }
+        parse_ics(ics.as_str())
+    }
+}
+
+fn parse_ics(ics: &str) -> Vec<Event> {
+    // lines beginning with whitespace continue the previous line (RFC 5545
+    // section 3.1)
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut title = None;
+    let mut start = None;
+    let mut in_event = false;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match (name, in_event) {
+            ("BEGIN", _) if value == "VEVENT" => {
+                in_event = true;
+                title = None;
+                start = None;
+            }
+            ("END", true) if value == "VEVENT" => {
+                in_event = false;
+                if let Some(start) = start.take() {
+                    events.push(Event {
+                        title: title.take().unwrap_or_default(),
+                        start,
+                    });
+                }
+            }
+            ("SUMMARY", true) => {
+                title = Some(
+                    value
+                        .replace("\\,", ",")
+                        .replace("\\;", ";")
+                        .replace("\\n", " ")
+                        .replace("\\\\", "\\"),
+                );
+            }
+            ("DTSTART", true) => start = parse_ics_time(value, params),
+            _ => {}
+        }
+    }
+
+    events
+}
+
+fn parse_ics_time(value: &str, params: &str) -> Option<DateTime<Local>> {
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") {
+        return Local
+            .from_local_datetime(
+                &NaiveDate::parse_from_str(value, "%Y%m%d")
+                    .ok()?
+                    .and_hms_opt(0, 0, 0)?,
+            )
+            .earliest();
+    }
+
+    // times with a TZID are treated as local, since resolving arbitrary
+    // VTIMEZONE definitions is out of scope
+    value.strip_suffix('Z').map_or_else(
+        || {
+            Local
+                .from_local_datetime(
+                    &NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+                        .ok()?,
+                )
+                .earliest()
+        },
+        |utc| {
+            Some(
+                Utc.from_utc_datetime(
+                    &NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+                        .ok()?,
+                )
+                .with_timezone(&Local),
+            )
+        },
+    )
+}
@@ -0,0 +1,232 @@
+use std::{collections::HashMap, fs, io, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, read_number, remove_string_from_config,
+    remove_uint_from_config, render_format, run_command, Attrs, PanelCommon,
+    PanelConfig, PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Mouse(MouseEvent),
+}
+
+/// Displays the battery charge limit (`charge_control_end_threshold`) on
+/// laptops that support one, such as ThinkPads and many ASUS models.
+///
+/// Left clicking the panel switches to the next of the configured presets.
+/// The threshold is only writable by root, so unless a udev rule makes it
+/// writable by the user, the panel runs `helper` to change it.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct ChargeThreshold {
+    #[builder(default = r#"String::from("BAT0")"#)]
+    battery: String,
+    #[builder(default = "vec![80, 100]")]
+    presets: Vec<u64>,
+    #[builder(
+        default = r#"String::from("echo %threshold% | pkexec tee %path% > /dev/null")"#
+    )]
+    helper: String,
+    #[builder(default = "Duration::from_secs(30)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl ChargeThreshold {
+    fn path(&self) -> String {
+        format!(
+            "/sys/class/power_supply/{}/charge_control_end_threshold",
+            self.battery
+        )
+    }
+
+    fn read(&self) -> Result<u64> {
+        read_number(self.path())
+    }
+
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Tick => {}
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                ..
+            }) => {
+                if let Err(e) = self.cycle() {
+                    return Some(Err(e));
+                }
+            }
+            Update::Mouse(_) => return None,
+        }
+
+        Some(self.draw(cr))
+    }
+
+    fn cycle(&self) -> Result<()> {
+        let current = self.read()?;
+        let next = self
+            .presets
+            .iter()
+            .position(|&p| p == current)
+            .map_or(self.presets[0], |i| {
+                self.presets[(i + 1) % self.presets.len()]
+            });
+
+        let path = self.path();
+        match fs::write(path.as_str(), next.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                // the new value shows up on the next update
+                run_command(
+                    self.helper
+                        .replace("%threshold%", next.to_string().as_str())
+                        .replace("%path%", path.as_str())
+                        .as_str(),
+                );
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let values = HashMap::from([(
+            String::from("threshold"),
+            self.read()?.to_string(),
+        )]);
+        let text = render_format(&self.common.formats[0], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for ChargeThreshold {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(|_| Update::Tick)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .filter_map(move |u| self.update(&cr, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `battery`: the name of the battery in `/sys/class/power_supply`
+    ///   - type: String
+    ///   - default: `BAT0`
+    ///
+    /// - `presets`: the thresholds to cycle through when the panel is clicked
+    ///   - type: array of u64
+    ///   - default: `[80, 100]`
+    ///
+    /// - `helper`: the shell command used to change the threshold when the
+    ///   panel can't write to it directly. `%threshold%` and `%path%` are
+    ///   replaced with the new value and the sysfs file. The default uses
+    ///   pkexec, which prompts for a password unless a polkit rule allows
+    ///   `org.freedesktop.policykit.exec` for `/usr/bin/tee` without
+    ///   authentication. Alternatively, a udev rule can make the file writable
+    ///   by the user, e.g. `SUBSYSTEM=="power_supply", KERNEL=="BAT0",
+    ///   RUN+="/bin/chmod 666 /sys%p/charge_control_end_threshold"`.
+    ///   - type: String
+    ///   - default: `echo %threshold% | pkexec tee %path% > /dev/null`
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 30
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `LIMIT: {threshold}%`
+    ///   - formatting options: `{threshold}`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = ChargeThresholdBuilder::default();
+
+        if let Some(battery) = remove_string_from_config("battery", table) {
+            builder.battery(battery);
+        }
+        if let Some(presets) = table.remove("presets") {
+            let presets = presets
+                .into_array()?
+                .into_iter()
+                .map(|p| Ok(p.into_uint()?.clamp(1, 100)))
+                .collect::<Result<Vec<_>>>()?;
+            if presets.is_empty() {
+                return Err(anyhow!("`presets` must not be empty"));
+            }
+            builder.presets(presets);
+        }
+        if let Some(helper) = remove_string_from_config("helper", table) {
+            builder.helper(helper);
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &[""],
+            &["LIMIT: {threshold}%"],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,463 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use pangocairo::functions::create_layout;
+use serde_json::Value as Json;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task,
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_string_from_config, remove_uint_from_config,
+    render_format, run_command, Actions, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Forge {
+    GitHub,
+    GitLab,
+}
+
+#[derive(Debug, Clone)]
+struct Repo {
+    forge: Forge,
+    /// `owner/repo` on GitHub, or the full project path on GitLab.
+    path: String,
+    branch: Option<String>,
+}
+
+impl Repo {
+    /// Parses `forge:path` or `forge:path@branch`.
+    fn parse(spec: &str) -> Result<Self> {
+        let (forge, rest) = spec.split_once(':').ok_or_else(|| {
+            anyhow!("Invalid repo {spec}, expected forge:path")
+        })?;
+        let forge = match forge {
+            "github" => Forge::GitHub,
+            "gitlab" => Forge::GitLab,
+            _ => return Err(anyhow!("Unknown forge {forge} in {spec}")),
+        };
+        let (path, branch) = match rest.split_once('@') {
+            Some((path, branch)) => (path, Some(branch.to_owned())),
+            None => (rest, None),
+        };
+
+        Ok(Self {
+            forge,
+            path: path.to_owned(),
+            branch,
+        })
+    }
+}
+
+/// Where and how to reach each forge's API.
+#[derive(Debug, Clone)]
+struct Api {
+    gitlab_url: String,
+    github_token: Option<String>,
+    gitlab_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success = 0,
+    Failure = 1,
+    Running = 2,
+    Unknown = 3,
+}
+
+#[derive(Debug)]
+struct Run {
+    status: Status,
+    url: Option<String>,
+}
+
+impl Api {
+    fn fetch(&self, repo: &Repo) -> Result<Run> {
+        match repo.forge {
+            Forge::GitHub => {
+                let mut request = ureq::get(format!(
+                    "https://api.github.com/repos/{}/actions/runs",
+                    repo.path
+                ))
+                .query("per_page", "1")
+                .header("Accept", "application/vnd.github+json")
+                .header("User-Agent", "lazybar");
+                if let Some(branch) = &repo.branch {
+                    request = request.query("branch", branch);
+                }
+                if let Some(token) = &self.github_token {
+                    request = request
+                        .header("Authorization", format!("Bearer {token}"));
+                }
+                let response: Json = request.call()?.body_mut().read_json()?;
+
+                let run = &response["workflow_runs"][0];
+                let status = match (
+                    run["status"].as_str(),
+                    run["conclusion"].as_str(),
+                ) {
+                    (Some("completed"), Some("success")) => Status::Success,
+                    (
+                        Some("completed"),
+                        Some("failure" | "timed_out" | "startup_failure"),
+                    ) => Status::Failure,
+                    (Some("queued" | "in_progress" | "waiting"), _) => {
+                        Status::Running
+                    }
+                    _ => Status::Unknown,
+                };
+                Ok(Run {
+                    status,
+                    url: run["html_url"].as_str().map(ToOwned::to_owned),
+                })
+            }
+            Forge::GitLab => {
+                let mut request = ureq::get(format!(
+                    "{}/api/v4/projects/{}/pipelines",
+                    self.gitlab_url,
+                    repo.path.replace('/', "%2F")
+                ))
+                .query("per_page", "1");
+                if let Some(branch) = &repo.branch {
+                    request = request.query("ref", branch);
+                }
+                if let Some(token) = &self.gitlab_token {
+                    request = request.header("PRIVATE-TOKEN", token);
+                }
+                let response: Json = request.call()?.body_mut().read_json()?;
+
+                let pipeline = &response[0];
+                let status = match pipeline["status"].as_str() {
+                    Some("success") => Status::Success,
+                    Some("failed") => Status::Failure,
+                    Some(
+                        "created"
+                        | "waiting_for_resource"
+                        | "preparing"
+                        | "pending"
+                        | "running",
+                    ) => Status::Running,
+                    _ => Status::Unknown,
+                };
+                Ok(Run {
+                    status,
+                    url: pipeline["web_url"].as_str().map(ToOwned::to_owned),
+                })
+            }
+        }
+    }
+
+    /// Fetches the latest run for each repo. A repo that can't be fetched is
+    /// shown with an unknown status rather than hiding the others.
+    fn fetch_all(&self, repos: &[Repo]) -> Vec<Run> {
+        repos
+            .iter()
+            .map(|repo| {
+                self.fetch(repo).unwrap_or_else(|e| {
+                    log::warn!(
+                        "Failed to fetch CI status for {}: {e}",
+                        repo.path
+                    );
+                    Run {
+                        status: Status::Unknown,
+                        url: None,
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+enum Update {
+    Runs(Result<Vec<Run>>),
+    Mouse(MouseEvent),
+}
+
+/// Displays the status of the latest GitHub Actions run or GitLab pipeline
+/// for each of a list of repos.
+///
+/// Left clicking a repo opens its latest run in a browser.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Ci {
+    repos: Vec<Repo>,
+    api: Api,
+    #[builder(default = "Duration::from_secs(120)")]
+    interval: Duration,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
+    #[builder(default = r#"String::from("xdg-open")"#)]
+    browser: String,
+    /// Each repo's run URL and the x-coordinate of its right edge, as of the
+    /// last draw.
+    #[builder(default, setter(skip))]
+    bounds: Vec<(Option<String>, f64)>,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Ci {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Runs(runs) => {
+                Some(runs.and_then(|runs| self.draw(cr, &runs)))
+            }
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                x,
+                ..
+            }) => {
+                let (url, _) = self.bounds.iter().find(|(_, end)| x < *end)?;
+                if let Some(url) = url {
+                    run_command(
+                        format!(
+                            "{} '{}'",
+                            self.browser,
+                            url.replace('\'', r"'\''")
+                        )
+                        .as_str(),
+                    );
+                }
+                None
+            }
+            Update::Mouse(_) => None,
+        }
+    }
+
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        runs: &[Run],
+    ) -> Result<PanelDrawInfo> {
+        let items = self
+            .repos
+            .iter()
+            .zip(runs)
+            .map(|(repo, run)| {
+                render_format(
+                    &self.common.formats[run.status as usize],
+                    &HashMap::from([
+                        (
+                            String::from("repo"),
+                            self.common.escape_markup.apply(repo.path.as_str()),
+                        ),
+                        (
+                            String::from("name"),
+                            self.common.escape_markup.apply(
+                                repo.path
+                                    .rsplit('/')
+                                    .next()
+                                    .unwrap_or_default(),
+                            ),
+                        ),
+                        (
+                            String::from("branch"),
+                            self.common.escape_markup.apply(
+                                repo.branch.as_deref().unwrap_or_default(),
+                            ),
+                        ),
+                    ]),
+                )
+            })
+            .collect::<Vec<_>>();
+        let attrs = &self.common.attrs
+            [usize::from(runs.iter().any(|r| r.status == Status::Failure))];
+
+        // measure each prefix of the text to find where each repo ends
+        let layout = create_layout(cr);
+        self.bounds = runs
+            .iter()
+            .enumerate()
+            .map(|(i, run)| {
+                layout.set_markup(
+                    items[..=i].join(self.separator.as_str()).as_str(),
+                );
+                attrs.apply_font(&layout);
+                (run.url.clone(), f64::from(layout.pixel_size().0))
+            })
+            .collect();
+
+        draw_common(
+            cr,
+            items.join(self.separator.as_str()).as_str(),
+            attrs,
+            self.common.dependence,
+            &self.common.ellipsize,
+        )
+    }
+}
+
+impl PanelConfig for Ci {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let api = self.api.clone();
+        let repos = self.repos.clone();
+        let runs = futures::StreamExt::then(
+            IntervalStream::new(interval(self.interval)),
+            move |_| {
+                let api = api.clone();
+                let repos = repos.clone();
+                // don't block the bar on a slow network
+                async move {
+                    task::spawn_blocking(move || {
+                        api.fetch_all(repos.as_slice())
+                    })
+                    .await
+                    .map_err(anyhow::Error::from)
+                }
+            },
+        )
+        .map(Update::Runs);
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = runs
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .filter_map(move |u| self.update(&cr, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `repos`: the repos to show, as `github:owner/repo` or
+    ///   `gitlab:group/project`. Append `@branch` to only show runs on that
+    ///   branch.
+    ///   - type: array of String
+    ///   - default: none
+    ///
+    /// - `github_token`: a GitHub token, needed for private repos and to avoid
+    ///   the low rate limit for anonymous requests
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `gitlab_url`: the GitLab instance to use
+    ///   - type: String
+    ///   - default: `https://gitlab.com`
+    ///
+    /// - `gitlab_token`: a GitLab token with the `read_api` scope, needed for
+    ///   private projects
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 120
+    ///
+    /// - `browser`: the command used to open a run when a repo is clicked. The
+    ///   URL is appended as an argument.
+    ///   - type: String
+    ///   - default: `xdg-open`
+    ///
+    /// - `separator`: the string placed between repos
+    ///   - type: String
+    ///   - default: ` `
+    ///
+    /// - `format_success`: the format string for a repo whose latest run passed
+    ///   - type: String
+    ///   - default: `{name} ✔`
+    ///   - formatting options: `{repo}` (the full path), `{name}` (the last
+    ///     part of the path), `{branch}`
+    ///
+    /// - `format_failure`: the format string for a repo whose latest run failed
+    ///   - type: String
+    ///   - default: `{name} ✘`
+    ///   - formatting options: same as `format_success`
+    ///
+    /// - `format_running`: the format string for a repo whose latest run hasn't
+    ///   finished
+    ///   - type: String
+    ///   - default: `{name} …`
+    ///   - formatting options: same as `format_success`
+    ///
+    /// - `format_unknown`: the format string for a repo whose latest run was
+    ///   cancelled or skipped, or whose status couldn't be fetched
+    ///   - type: String
+    ///   - default: `{name} ?`
+    ///   - formatting options: same as `format_success`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `failure_`. The latter is used when any
+    ///   repo's latest run failed, and its unset values fall back to the first
+    ///   instance.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = CiBuilder::default();
+
+        let repos = table
+            .remove("repos")
+            .ok_or_else(|| anyhow!("CI panel requires `repos`"))?
+            .into_array()?
+            .into_iter()
+            .map(|r| Repo::parse(r.into_string()?.as_str()))
+            .collect::<Result<Vec<_>>>()?;
+        builder.repos(repos);
+        builder.api(Api {
+            gitlab_url: remove_string_from_config("gitlab_url", table)
+                .unwrap_or_else(|| String::from("https://gitlab.com"))
+                .trim_end_matches('/')
+                .to_owned(),
+            github_token: remove_string_from_config("github_token", table),
+            gitlab_token: remove_string_from_config("gitlab_token", table),
+        });
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(browser) = remove_string_from_config("browser", table) {
+            builder.browser(browser);
+        }
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_success", "_failure", "_running", "_unknown"],
+            &["{name} ✔", "{name} ✘", "{name} …", "{name} ?"],
+            &["", "failure_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
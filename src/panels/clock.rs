@@ -8,16 +8,21 @@ use std::{
 };
 
 use anyhow::Result;
-use chrono::{Local, Timelike};
+use chrono::{Datelike, Local, NaiveDate, Timelike, Weekday};
 use config::{Config, Value};
 use derive_builder::Builder;
 use precision::*;
-use tokio::time::{interval, Instant, Interval};
-use tokio_stream::{Stream, StreamExt};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{interval, Instant, Interval},
+};
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
 
 use crate::{
-    bar::PanelDrawInfo, draw_common, Attrs, PanelCommon, PanelConfig,
-    PanelStream,
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common,
+    popup::Popup,
+    Attrs, PanelCommon, PanelConfig, PanelStream, Watchdog,
 };
 
 /// Defines options for a [`Clock`]'s precision.
@@ -42,12 +47,17 @@ pub mod precision {
 
     /// The trait implemented by all [`Clock`] subtypes.
     pub trait Precision {
+        /// The longest time between two updates.
+        const PERIOD: Duration;
+
         /// Determine how long until the next unit boundary.
         fn tick() -> Duration;
     }
 }
 
 impl Precision for Days {
+    const PERIOD: Duration = Duration::from_secs(86_400);
+
     fn tick() -> Duration {
         let now = Local::now();
         Duration::from_secs(u64::from(
@@ -57,6 +67,8 @@ impl Precision for Days {
 }
 
 impl Precision for Hours {
+    const PERIOD: Duration = Duration::from_secs(3600);
+
     fn tick() -> Duration {
         let now = Local::now();
         Duration::from_secs(u64::from(60 * (60 - now.minute())))
@@ -64,6 +76,8 @@ impl Precision for Hours {
 }
 
 impl Precision for Minutes {
+    const PERIOD: Duration = Duration::from_secs(60);
+
     fn tick() -> Duration {
         let now = Local::now();
         Duration::from_secs(u64::from(60 - now.second()))
@@ -71,6 +85,8 @@ impl Precision for Minutes {
 }
 
 impl Precision for Seconds {
+    const PERIOD: Duration = Duration::from_secs(1);
+
     fn tick() -> Duration {
         let now = Local::now();
         Duration::from_nanos(
@@ -110,19 +126,95 @@ impl Stream for ClockStream {
     }
 }
 
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Mouse(MouseEvent),
+}
+
+/// Lays out the month containing `today` as a grid, with today in bold.
+fn month_calendar(today: NaiveDate) -> String {
+    let mut text = format!(
+        "{:^20}\nMo Tu We Th Fr Sa Su\n",
+        today.format("%B %Y").to_string()
+    );
+    let mut date = today - chrono::Days::new(u64::from(today.day0()));
+    text.push_str(
+        "   "
+            .repeat(date.weekday().num_days_from_monday() as usize)
+            .as_str(),
+    );
+    while date.month() == today.month() {
+        if date == today {
+            text.push_str(format!("<b>{:>2}</b>", date.day()).as_str());
+        } else {
+            text.push_str(format!("{:>2}", date.day()).as_str());
+        }
+        text.push(if date.weekday() == Weekday::Sun {
+            '\n'
+        } else {
+            ' '
+        });
+        date = date + chrono::Days::new(1);
+    }
+    format!("<tt>{}</tt>", text.trim_end())
+}
+
 /// Displays the current time, updating at a given precision.
 ///
 /// Uses an [`Interval`] to update as close to the unit boundaries as possible.
+/// Left clicking the panel opens a calendar of the current month.
 #[derive(Builder, Debug)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
 pub struct Clock<P: Clone + Precision> {
     common: PanelCommon,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    #[builder(default, setter(skip))]
+    popup: Option<Popup>,
     #[builder(default)]
     phantom: PhantomData<P>,
 }
 
 impl<P: Precision + Clone> Clock<P> {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Tick => Some(self.draw(cr)),
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                anchor,
+                ..
+            }) => {
+                // a second click closes the calendar
+                if self.popup.take().is_some_and(|p| p.is_open()) {
+                    return None;
+                }
+                let text = month_calendar(Local::now().date_naive());
+                let result = draw_common(
+                    cr,
+                    text.as_str(),
+                    &self.common.attrs[1],
+                    self.common.dependence,
+                    &self.common.ellipsize,
+                )
+                .and_then(|content| Popup::open(anchor, content));
+                match result {
+                    Ok(popup) => {
+                        self.popup = Some(popup);
+                        None
+                    }
+                    Err(e) => Some(Err(e)),
+                }
+            }
+            Update::Mouse(_) => None,
+        }
+    }
+
     fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
         let now = chrono::Local::now();
         let text = now.format(&self.common.formats[0]).to_string();
@@ -132,6 +224,7 @@ impl<P: Precision + Clone> Clock<P> {
             text.as_str(),
             &self.common.attrs[0],
             self.common.dependence,
+            &self.common.ellipsize,
         )
     }
 }
@@ -140,22 +233,63 @@ impl<P> PanelConfig for Clock<P>
 where
     P: Precision + Clone + 'static,
 {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn watchdog(&self) -> Watchdog {
+        Watchdog(
+            self.common
+                .watchdog
+                .0
+                .or_else(|| Some((P::PERIOD * 2).max(Duration::from_secs(10)))),
+        )
+    }
+
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
         global_attrs: Attrs,
         _height: i32,
     ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
         for attr in &mut self.common.attrs {
             attr.apply_to(&global_attrs);
         }
-        let stream = ClockStream::new(P::tick).map(move |_| self.draw(&cr));
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = ClockStream::new(P::tick)
+            .map(|_| Update::Tick)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .filter_map(move |u| self.update(&cr, u));
         Ok(Box::pin(stream))
     }
 
     /// Configuration options:
     ///
-    /// - See [`PanelCommon::parse`].
+    /// - `precision`: how often the clock updates: `days`, `hours`, `minutes`,
+    ///   or `seconds`
+    ///   - type: String
+    ///   - default: `seconds`
+    ///
+    /// - `format`: the format string, see [`chrono::format::strftime`]
+    ///   - type: String
+    ///   - default: `%Y-%m-%d %T`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `popup_`. The latter is used for the
+    ///   calendar that opens when the panel is clicked, and its unset values
+    ///   fall back to the first instance.
     fn parse(
         table: &mut HashMap<String, Value>,
         _global: &Config,
@@ -166,7 +300,7 @@ where
             table,
             &[""],
             &["%Y-%m-%d %T"],
-            &[""],
+            &["", "popup_"],
         )?);
 
         Ok(builder.build()?)
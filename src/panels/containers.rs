@@ -0,0 +1,250 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::FutureExt;
+use serde_json::Value as Json;
+use tokio::task::{self, JoinHandle};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_bool_from_config,
+    remove_string_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+// {"type":["container"]}, url-encoded
+const EVENTS_PATH: &str =
+    "/events?filters=%7B%22type%22%3A%5B%22container%22%5D%7D";
+
+/// Performs a `GET` request over a Unix socket and returns the connection,
+/// positioned at the start of the response body.
+fn get(socket: &Path, path: &str) -> Result<BufReader<UnixStream>> {
+    let mut stream = UnixStream::connect(socket)?;
+    // HTTP/1.0 keeps the engine from using chunked transfer encoding
+    write!(stream, "GET {path} HTTP/1.0\r\nHost: localhost\r\n\r\n")?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(anyhow!("Request to {socket:?} failed: {}", status.trim()));
+    }
+
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    Ok(reader)
+}
+
+struct EventStream {
+    socket: PathBuf,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Stream for EventStream {
+    type Item = ();
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(handle) = &mut self.handle {
+            let value = handle.poll_unpin(cx).map(|_| Some(()));
+            if value.is_ready() {
+                self.handle = None;
+            }
+            value
+        } else {
+            let socket = self.socket.clone();
+            let waker = cx.waker().clone();
+            self.handle = Some(task::spawn_blocking(move || {
+                // return after the first event; the connection is cheap to
+                // reopen and this avoids sharing a reader across tasks
+                match get(socket.as_path(), EVENTS_PATH) {
+                    Ok(mut reader) => {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            // the engine went away, so don't spin
+                            thread::sleep(Duration::from_secs(5));
+                        }
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to subscribe to events: {e}");
+                        thread::sleep(Duration::from_secs(5));
+                    }
+                }
+                waker.wake();
+            }));
+            Poll::Pending
+        }
+    }
+}
+
+/// Displays the number of running and total containers managed by Docker or
+/// Podman.
+///
+/// Updates are driven by the engine's event stream rather than polling. For
+/// Podman, enable `podman.socket` and set `socket` to
+/// `$XDG_RUNTIME_DIR/podman/podman.sock`.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Containers {
+    #[builder(default = r#"PathBuf::from("/var/run/docker.sock")"#)]
+    socket: PathBuf,
+    #[builder(default = "false")]
+    all: bool,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
+    common: PanelCommon,
+}
+
+impl Containers {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let mut body = String::new();
+        get(self.socket.as_path(), "/containers/json?all=true")?
+            .read_to_string(&mut body)?;
+        let containers: Vec<Json> = serde_json::from_str(body.as_str())?;
+
+        let running = containers
+            .iter()
+            .filter(|c| c["State"].as_str() == Some("running"))
+            .count();
+
+        let details = containers
+            .iter()
+            .filter(|c| self.all || c["State"].as_str() == Some("running"))
+            .map(|c| {
+                let field = |value: &Json| {
+                    self.common
+                        .escape_markup
+                        .apply(value.as_str().unwrap_or_default())
+                };
+                render_format(
+                    &self.common.formats[1],
+                    &HashMap::from([
+                        (
+                            String::from("name"),
+                            field(&c["Names"][0])
+                                .trim_start_matches('/')
+                                .to_owned(),
+                        ),
+                        (String::from("image"), field(&c["Image"])),
+                        (String::from("state"), field(&c["State"])),
+                        (String::from("status"), field(&c["Status"])),
+                    ]),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(self.separator.as_str());
+
+        let values = HashMap::from([
+            (String::from("running"), running.to_string()),
+            (String::from("total"), containers.len().to_string()),
+            (String::from("containers"), details),
+        ]);
+        let text = render_format(&self.common.formats[0], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Containers {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = tokio_stream::once(())
+            .chain(EventStream {
+                socket: self.socket.clone(),
+                handle: None,
+            })
+            .map(move |()| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `socket`: the path to the Docker (or Podman) API socket
+    ///   - type: String
+    ///   - default: `/var/run/docker.sock`
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `CTR: {running}/{total}`
+    ///   - formatting options: `{running}`, `{total}`, `{containers}`
+    ///
+    /// - `format_container`: the format string for each container in
+    ///   `{containers}`
+    ///   - type: String
+    ///   - default: `{name}`
+    ///   - formatting options: `{name}`, `{image}`, `{state}`, `{status}`
+    ///
+    /// - `separator`: the string placed between containers in `{containers}`
+    ///   - type: String
+    ///   - default: ` `
+    ///
+    /// - `all`: whether `{containers}` should include stopped containers
+    ///   - type: bool
+    ///   - default: false
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = ContainersBuilder::default();
+
+        if let Some(socket) = remove_string_from_config("socket", table) {
+            builder.socket(PathBuf::from(socket));
+        }
+        if let Some(all) = remove_bool_from_config("all", table) {
+            builder.all(all);
+        }
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_container"],
+            &["CTR: {running}/{total}", "{name}"],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -8,8 +8,9 @@ use tokio::time::interval;
 use tokio_stream::{wrappers::IntervalStream, StreamExt};
 
 use crate::{
-    bar::PanelDrawInfo, draw_common, remove_string_from_config,
-    remove_uint_from_config, Attrs, PanelCommon, PanelConfig, PanelStream,
+    bar::PanelDrawInfo, draw_common, draw_graph, draw_progress_bar,
+    remove_string_from_config, remove_uint_from_config, render_format, Attrs,
+    History, PanelCommon, PanelConfig, PanelStream, Style,
 };
 
 lazy_static! {
@@ -27,31 +28,73 @@ pub struct Cpu {
     #[builder(default = r#"String::from("/proc/stat")"#)]
     path: String,
     last_load: Load,
+    #[builder(default)]
+    style: Style,
+    #[builder(default)]
+    history: History,
     common: PanelCommon,
 }
 
 impl Cpu {
-    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+    fn draw(&mut self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
         let load = read_current_load(self.path.as_str())?;
 
-        let diff = load.total - self.last_load.total;
+        let diff = (load.total - self.last_load.total).max(1);
         let percentage = (diff - (load.idle - self.last_load.idle)) as f64
             / diff as f64
             * 100.0;
-
-        let text = self.common.formats[0]
-            .replace("%percentage%", format!("{percentage:.0}").as_str());
-
-        draw_common(
-            cr,
-            text.as_str(),
-            &self.common.attrs[0],
-            self.common.dependence,
-        )
+        // the graph needs the usage since the last poll, not since boot
+        self.last_load = load;
+
+        let values = HashMap::from([(
+            String::from("percentage"),
+            format!("{percentage:.0}"),
+        )]);
+        let text = render_format(&self.common.formats[0], &values);
+
+        let attrs = self.common.states.apply(percentage, &self.common.attrs[0]);
+        let mut draw_info = match &self.style {
+            Style::Text | Style::Gauge(_) => draw_common(
+                cr,
+                text.as_str(),
+                &attrs,
+                self.common.dependence,
+                &self.common.ellipsize,
+            )?,
+            Style::Bar(bar) => draw_progress_bar(
+                cr,
+                percentage / 100.0,
+                bar,
+                text.as_str(),
+                &attrs,
+                self.common.dependence,
+            )?,
+            Style::Graph(graph) => {
+                self.history.push(percentage);
+                draw_graph(
+                    cr,
+                    &self.history,
+                    graph,
+                    Some(100.0),
+                    text.as_str(),
+                    &attrs,
+                    self.common.dependence,
+                )?
+            }
+        };
+        draw_info.values.extend(values);
+        if let Some(state) = self.common.states.find(percentage) {
+            draw_info.set_value("state", &state.name);
+        }
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Cpu {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
@@ -72,8 +115,8 @@ impl PanelConfig for Cpu {
     ///
     /// - `format`: the format string
     ///   - type: String
-    ///   - default: `CPU: %percentage%`
-    ///   - formatting options: `%percentage%`
+    ///   - default: `CPU: {percentage}%`
+    ///   - formatting options: `{percentage}`
     /// - `interval`: how long to wait in seconds between each check
     ///   - type: u64
     ///   - default: 10
@@ -82,7 +125,10 @@ impl PanelConfig for Cpu {
     ///   - default: `/proc/stat` - If you're considering changing this, you
     ///     might want to use a different panel like
     ///     [`Inotify`][crate::panels::Inotify]
-    /// - See [`PanelCommon::parse`].
+    /// - The usage can be drawn as a progress bar or a graph. See
+    ///   [`Style::parse`] for parsing options.
+    /// - See [`PanelCommon::parse`]. The value `percentage` is available to
+    ///   `visible_when`.
     fn parse(
         table: &mut HashMap<String, config::Value>,
         _global: &config::Config,
@@ -98,10 +144,15 @@ impl PanelConfig for Cpu {
         } else {
             builder.last_load(read_current_load("/proc/stat")?);
         }
+        let style = Style::parse(table);
+        if let Style::Graph(graph) = &style {
+            builder.history(graph.history());
+        }
+        builder.style(style);
         builder.common(PanelCommon::parse(
             table,
             &[""],
-            &["CPU: %percentage%%"],
+            &["CPU: {percentage}%"],
             &[""],
         )?);
 
@@ -0,0 +1,259 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use serde_json::Value as Json;
+use tokio::{task, time::interval};
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+const API: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+/// A coin and the currency its price is shown in, e.g. `bitcoin:usd`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Pair {
+    coin: String,
+    fiat: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    price: f64,
+    change: f64,
+}
+
+/// Fetches the price and 24 hour change for each pair from CoinGecko.
+fn fetch(pairs: &[Pair]) -> Result<HashMap<Pair, Quote>> {
+    let join = |f: fn(&Pair) -> &str| {
+        let mut items = pairs.iter().map(f).collect::<Vec<_>>();
+        items.sort_unstable();
+        items.dedup();
+        items.join(",")
+    };
+
+    let response: Json = ureq::get(API)
+        .query("ids", join(|p| p.coin.as_str()))
+        .query("vs_currencies", join(|p| p.fiat.as_str()))
+        .query("include_24hr_change", "true")
+        .call()?
+        .body_mut()
+        .read_json()?;
+
+    pairs
+        .iter()
+        .map(|pair| {
+            let coin = &response[pair.coin.as_str()];
+            let quote = Quote {
+                price: coin[pair.fiat.as_str()].as_f64().ok_or_else(|| {
+                    anyhow!("No price for {}:{}", pair.coin, pair.fiat)
+                })?,
+                change: coin[format!("{}_24h_change", pair.fiat)]
+                    .as_f64()
+                    .unwrap_or(0.0),
+            };
+            Ok((pair.clone(), quote))
+        })
+        .collect()
+}
+
+/// Displays cryptocurrency prices and their change over the last 24 hours.
+///
+/// Prices are fetched from CoinGecko's public API. If a request fails, the
+/// last known prices continue to be shown.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Crypto {
+    pairs: Vec<Pair>,
+    #[builder(default = "Duration::from_secs(5 * 60)")]
+    interval: Duration,
+    #[builder(default = "2")]
+    precision: usize,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
+    #[builder(default, setter(skip))]
+    cache: HashMap<Pair, Quote>,
+    common: PanelCommon,
+}
+
+impl Crypto {
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        quotes: Result<HashMap<Pair, Quote>>,
+    ) -> Result<PanelDrawInfo> {
+        match quotes {
+            Ok(quotes) => self.cache = quotes,
+            Err(e) if !self.cache.is_empty() => {
+                log::warn!("Failed to fetch prices, using cached values: {e}");
+            }
+            Err(e) => return Err(e),
+        }
+
+        let quotes = self
+            .pairs
+            .iter()
+            .filter_map(|p| Some((p, *self.cache.get(p)?)))
+            .collect::<Vec<_>>();
+        let text = quotes
+            .iter()
+            .map(|(pair, quote)| {
+                render_format(
+                    &self.common.formats[usize::from(quote.change < 0.0)],
+                    &HashMap::from([
+                        (
+                            String::from("coin"),
+                            self.common.escape_markup.apply(pair.coin.as_str()),
+                        ),
+                        (
+                            String::from("fiat"),
+                            self.common
+                                .escape_markup
+                                .apply(pair.fiat.to_uppercase().as_str()),
+                        ),
+                        (
+                            String::from("price"),
+                            format!("{:.*}", self.precision, quote.price),
+                        ),
+                        (
+                            String::from("change"),
+                            format!("{:+.1}", quote.change),
+                        ),
+                    ]),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(self.separator.as_str());
+
+        let average = quotes.iter().map(|(_, q)| q.change).sum::<f64>()
+            / quotes.len().max(1) as f64;
+
+        draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[usize::from(average < 0.0)],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )
+    }
+}
+
+impl PanelConfig for Crypto {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let pairs = self.pairs.clone();
+        let stream = futures::StreamExt::then(
+            IntervalStream::new(interval(self.interval)),
+            move |_| {
+                let pairs = pairs.clone();
+                // don't block the bar on a slow network
+                async move {
+                    task::spawn_blocking(move || fetch(pairs.as_slice()))
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|r| r)
+                }
+            },
+        )
+        .map(move |quotes| self.draw(&cr, quotes));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `pairs`: the coins to display and the currencies to display them in,
+    ///   separated by `:`. Coins are identified by their CoinGecko ids.
+    ///   - type: array of String
+    ///   - default: `["bitcoin:usd"]`
+    ///
+    /// - `interval`: how long to wait in seconds between each update. Keep this
+    ///   reasonably high to stay within the API's rate limits.
+    ///   - type: u64
+    ///   - default: 300
+    ///
+    /// - `precision`: the number of decimal places to show in prices
+    ///   - type: u64
+    ///   - default: 2
+    ///
+    /// - `format_up`: the format string for each pair whose price has risen
+    ///   - type: String
+    ///   - default: `{coin}: {price} ({change}%)`
+    ///   - formatting options: `{coin}`, `{fiat}`, `{price}`, `{change}`
+    ///
+    /// - `format_down`: the format string for each pair whose price has fallen
+    ///   - type: String
+    ///   - default: `{coin}: {price} ({change}%)`
+    ///   - formatting options: `{coin}`, `{fiat}`, `{price}`, `{change}`
+    ///
+    /// - `separator`: the string placed between pairs
+    ///   - type: String
+    ///   - default: ` `
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `up_` and `down_`, which are chosen based on the
+    ///   average change of all pairs.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = CryptoBuilder::default();
+
+        let pairs = match table.remove("pairs") {
+            Some(pairs) => pairs
+                .into_array()?
+                .into_iter()
+                .map(|p| {
+                    let p = p.into_string()?;
+                    let (coin, fiat) = p.split_once(':').ok_or_else(|| {
+                        anyhow!("Invalid pair {p}, expected coin:currency")
+                    })?;
+                    Ok(Pair {
+                        coin: coin.to_lowercase(),
+                        fiat: fiat.to_lowercase(),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => vec![Pair {
+                coin: String::from("bitcoin"),
+                fiat: String::from("usd"),
+            }],
+        };
+        builder.pairs(pairs);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(precision) = remove_uint_from_config("precision", table) {
+            builder.precision(precision as usize);
+        }
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_up", "_down"],
+            &["{coin}: {price} ({change}%)", "{coin}: {price} ({change}%)"],
+            &["up_", "down_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
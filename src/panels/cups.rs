@@ -0,0 +1,318 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+const GET_JOBS: u16 = 0x000a;
+const GET_PRINTER_ATTRIBUTES: u16 = 0x000b;
+const CUPS_GET_DEFAULT: u16 = 0x4001;
+
+const TAG_OPERATION: u8 = 0x01;
+const TAG_END: u8 = 0x03;
+const TAG_KEYWORD: u8 = 0x44;
+const TAG_URI: u8 = 0x45;
+const TAG_CHARSET: u8 = 0x47;
+const TAG_LANGUAGE: u8 = 0x48;
+
+// printer-state values
+const STATE_PROCESSING: i32 = 4;
+const STATE_STOPPED: i32 = 5;
+
+/// IPP attributes as (name, raw value) pairs.
+type Attributes = Vec<(String, Vec<u8>)>;
+
+/// A minimal IPP request encoder, supporting just enough to query CUPS.
+struct IppRequest(Vec<u8>);
+
+impl IppRequest {
+    fn new(operation: u16, printer_uri: &str) -> Self {
+        // version 1.1, request id 1
+        let mut buf = vec![1, 1];
+        buf.extend_from_slice(&operation.to_be_bytes());
+        buf.extend_from_slice(&1_u32.to_be_bytes());
+        buf.push(TAG_OPERATION);
+
+        let mut request = Self(buf);
+        request.attr(TAG_CHARSET, "attributes-charset", b"utf-8");
+        request.attr(TAG_LANGUAGE, "attributes-natural-language", b"en");
+        request.attr(TAG_URI, "printer-uri", printer_uri.as_bytes());
+        request
+    }
+
+    fn attr(&mut self, tag: u8, name: &str, value: &[u8]) {
+        self.0.push(tag);
+        self.0.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        self.0.extend_from_slice(name.as_bytes());
+        self.0
+            .extend_from_slice(&(value.len() as u16).to_be_bytes());
+        self.0.extend_from_slice(value);
+    }
+
+    /// Adds another value to the previous attribute.
+    fn value(&mut self, tag: u8, value: &[u8]) {
+        self.attr(tag, "", value);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.0.push(TAG_END);
+        self.0
+    }
+}
+
+/// Parses an IPP response, returning its status code and a flat list of
+/// attributes across all groups. Values are kept raw, and additional values
+/// of multi-valued attributes are listed under the same name.
+fn parse_response(buf: &[u8]) -> Result<(u16, Attributes)> {
+    let invalid = || anyhow!("Invalid IPP response");
+    let read_u16 = |pos: usize| -> Result<u16> {
+        Ok(u16::from_be_bytes(
+            buf.get(pos..pos + 2).ok_or_else(invalid)?.try_into()?,
+        ))
+    };
+
+    let status = read_u16(2)?;
+    let mut attrs = Vec::new();
+    let mut pos = 8;
+    let mut name = String::new();
+    loop {
+        let tag = *buf.get(pos).ok_or_else(invalid)?;
+        pos += 1;
+        if tag == TAG_END {
+            break;
+        }
+        // delimiter tags start a new group
+        if tag < 0x10 {
+            continue;
+        }
+
+        let name_len = read_u16(pos)? as usize;
+        pos += 2;
+        if name_len > 0 {
+            name = String::from_utf8_lossy(
+                buf.get(pos..pos + name_len).ok_or_else(invalid)?,
+            )
+            .into_owned();
+        }
+        pos += name_len;
+        let value_len = read_u16(pos)? as usize;
+        pos += 2;
+        let value = buf.get(pos..pos + value_len).ok_or_else(invalid)?;
+        pos += value_len;
+
+        attrs.push((name.clone(), value.to_vec()));
+    }
+
+    Ok((status, attrs))
+}
+
+/// Displays the number of queued print jobs and the state of a CUPS printer.
+///
+/// CUPS is queried over IPP, so no CUPS libraries are needed. The panel hides
+/// itself while the queue is empty, unless the printer reports an error.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Cups {
+    #[builder(default = r#"String::from("http://localhost:631")"#)]
+    server: String,
+    #[builder(default, setter(strip_option))]
+    printer: Option<String>,
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    common: PanelCommon,
+}
+
+impl Cups {
+    fn ipp(&self, path: &str, body: Vec<u8>) -> Result<Attributes> {
+        let response = ureq::post(format!("{}{path}", self.server))
+            .content_type("application/ipp")
+            .send(body.as_slice())?
+            .body_mut()
+            .read_to_vec()?;
+        let (status, attrs) = parse_response(response.as_slice())?;
+        // 0x0000-0x00ff are successful status codes
+        if status > 0x00ff {
+            return Err(anyhow!(
+                "IPP request failed with status {status:#06x}"
+            ));
+        }
+        Ok(attrs)
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let (path, uri) = self.printer.as_ref().map_or_else(
+            || (String::from("/"), format!("{}/", self.server)),
+            |p| {
+                let path = format!("/printers/{p}");
+                (path.clone(), format!("{}{path}", self.server))
+            },
+        );
+        let uri = uri.replacen("http://", "ipp://", 1);
+
+        let mut jobs = IppRequest::new(GET_JOBS, uri.as_str());
+        jobs.attr(TAG_KEYWORD, "requested-attributes", b"job-id");
+        let jobs = self
+            .ipp(path.as_str(), jobs.finish())?
+            .iter()
+            .filter(|(name, _)| name == "job-id")
+            .count();
+
+        // without a printer, report the state of the default printer
+        let mut printer = IppRequest::new(
+            if self.printer.is_some() {
+                GET_PRINTER_ATTRIBUTES
+            } else {
+                CUPS_GET_DEFAULT
+            },
+            uri.as_str(),
+        );
+        printer.attr(TAG_KEYWORD, "requested-attributes", b"printer-state");
+        printer.value(TAG_KEYWORD, b"printer-state-reasons");
+        printer.value(TAG_KEYWORD, b"printer-name");
+        let attrs = self.ipp(path.as_str(), printer.finish())?;
+
+        let state = attrs
+            .iter()
+            .find(|(name, _)| name == "printer-state")
+            .and_then(|(_, v)| {
+                Some(i32::from_be_bytes(v.as_slice().try_into().ok()?))
+            })
+            .unwrap_or_default();
+        let reasons = attrs
+            .iter()
+            .filter(|(name, _)| name == "printer-state-reasons")
+            .map(|(_, v)| String::from_utf8_lossy(v).into_owned())
+            .filter(|r| r != "none")
+            .collect::<Vec<_>>();
+        let name = attrs
+            .iter()
+            .find(|(name, _)| name == "printer-name")
+            .map(|(_, v)| String::from_utf8_lossy(v).into_owned())
+            .unwrap_or_default();
+
+        // reasons ending in -report are informational
+        let error = state == STATE_STOPPED
+            || reasons.iter().any(|r| r.ends_with("-error"));
+        let state = match state {
+            STATE_PROCESSING => "processing",
+            STATE_STOPPED => "stopped",
+            _ => "idle",
+        };
+
+        let values = HashMap::from([
+            (String::from("jobs"), jobs.to_string()),
+            (String::from("state"), state.to_owned()),
+            (
+                String::from("reasons"),
+                self.common.escape_markup.apply(reasons.join(", ").as_str()),
+            ),
+            (
+                String::from("printer"),
+                self.common.escape_markup.apply(name.as_str()),
+            ),
+        ]);
+        let text = if jobs == 0 && !error {
+            self.common.formats[1].clone()
+        } else {
+            render_format(&self.common.formats[0], &values)
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[usize::from(error)],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Cups {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `server`: the CUPS server to query
+    ///   - type: String
+    ///   - default: `http://localhost:631`
+    ///
+    /// - `printer`: the printer to display. If unset, jobs for all printers are
+    ///   counted and the state of the default printer is shown.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 10
+    ///
+    /// - `format`: the format string when jobs are queued or the printer has an
+    ///   error
+    ///   - type: String
+    ///   - default: `PRINT: {jobs}`
+    ///   - formatting options: `{jobs}`, `{state}` (one of `idle`,
+    ///     `processing`, or `stopped`), `{reasons}`, `{printer}`
+    ///
+    /// - `format_none`: the format string when the queue is empty
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `error_`. The latter is used when the
+    ///   printer reports an error, and its unset values fall back to the first
+    ///   instance. The formatting options are available to `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = CupsBuilder::default();
+
+        if let Some(server) = remove_string_from_config("server", table) {
+            builder.server(server.trim_end_matches('/').to_owned());
+        }
+        if let Some(printer) = remove_string_from_config("printer", table) {
+            builder.printer(printer);
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_none"],
+            &["PRINT: {jobs}", ""],
+            &["", "error_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
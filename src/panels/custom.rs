@@ -9,12 +9,16 @@ use std::{
 
 use anyhow::Result;
 use derive_builder::Builder;
-use tokio::time::{interval, Interval};
-use tokio_stream::{Stream, StreamExt};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{interval, Interval},
+};
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
 
 use crate::{
     bar::PanelDrawInfo, draw_common, remove_string_from_config,
-    remove_uint_from_config, Attrs, PanelCommon, PanelConfig, PanelStream,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
 };
 
 struct CustomStream {
@@ -52,7 +56,7 @@ impl Stream for CustomStream {
 }
 
 /// Runs a custom command with `sh -c <command>`, either once or on a given
-/// interval.
+/// interval. The command also runs whenever the panel is refreshed over IPC.
 #[derive(Builder, Debug)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
@@ -63,30 +67,50 @@ pub struct Custom {
     #[builder(setter(strip_option))]
     duration: Option<Duration>,
     common: PanelCommon,
+    #[builder(default, setter(skip))]
+    refresh: Option<UnboundedReceiver<()>>,
 }
 
 impl Custom {
     fn draw(&mut self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
         let output = self.command.output()?;
-        let text = self.common.formats[0]
-            .replace(
-                "%stdout%",
-                String::from_utf8_lossy(output.stdout.as_slice()).as_ref(),
-            )
-            .replace(
-                "%stderr%",
-                String::from_utf8_lossy(output.stderr.as_slice()).as_ref(),
-            );
-        draw_common(
+        let stdout = String::from_utf8_lossy(output.stdout.as_slice());
+        let stderr = String::from_utf8_lossy(output.stderr.as_slice());
+        let values = HashMap::from([
+            (String::from("stdout"), stdout.trim().to_owned()),
+            (String::from("stderr"), stderr.trim().to_owned()),
+            (String::from("output"), stdout.trim().to_owned()),
+        ]);
+        let escaped = values
+            .iter()
+            .map(|(name, value)| {
+                (name.clone(), self.common.escape_markup.apply(value))
+            })
+            .collect::<HashMap<_, _>>();
+        let text = render_format(&self.common.formats[0], &escaped);
+        let mut draw_info = draw_common(
             cr,
             text.trim(),
             &self.common.attrs[0],
             self.common.dependence,
-        )
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Custom {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn refresh_sender(&mut self) -> Option<UnboundedSender<()>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.refresh = Some(recv);
+        Some(send)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
@@ -97,9 +121,14 @@ impl PanelConfig for Custom {
             attr.apply_to(&global_attrs);
         }
 
+        let refresh = self
+            .refresh
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
         Ok(Box::pin(
             CustomStream::new(self.duration.map(|d| interval(d)))
-                .map(move |_| self.draw(&cr)),
+                .merge(UnboundedReceiverStream::new(refresh))
+                .map(move |()| self.draw(&cr)),
         ))
     }
 
@@ -107,8 +136,10 @@ impl PanelConfig for Custom {
     ///
     /// - `format`: the format string
     ///   - type: String
-    ///   - default: `%stdout%`
-    ///   - formatting options: `%stdout%`, `%stderr%`
+    ///   - default: `{stdout}`
+    ///   - formatting options: `{stdout}`, `{stderr}`. These are escaped unless
+    ///     `escape_markup = false`, which lets the command print [pango]
+    ///     markup.
     ///
     /// - `command`: the command to run
     ///   - type: String
@@ -119,7 +150,8 @@ impl PanelConfig for Custom {
     ///   - default: none
     ///   - if not present, the command will run exactly once.
     ///
-    /// - See [`PanelCommon::parse`].
+    /// - See [`PanelCommon::parse`]. The values `stdout`, `stderr`, and
+    ///   `output` (the same as `stdout`) are available to `visible_when`.
     fn parse(
         table: &mut HashMap<String, config::Value>,
         _global: &config::Config,
@@ -147,7 +179,7 @@ impl PanelConfig for Custom {
         };
 
         Ok(builder
-            .common(PanelCommon::parse(table, &[""], &["%stdout%"], &[""])?)
+            .common(PanelCommon::parse(table, &[""], &["{stdout}"], &[""])?)
             .build()?)
     }
 }
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::TryStreamExt;
+use tokio_stream::{Stream, StreamExt};
+use zbus::{
+    fdo::PropertiesProxy,
+    names::InterfaceName,
+    proxy::CacheProperties,
+    zvariant::{self, OwnedValue},
+    Connection,
+};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config, render_format,
+    Attrs, PanelCommon, PanelConfig, PanelStream,
+};
+
+/// Which bus to connect to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bus {
+    Session,
+    System,
+}
+
+/// The property to watch.
+#[derive(Debug, Clone)]
+struct Target {
+    bus: Bus,
+    service: String,
+    path: String,
+    interface: String,
+    property: String,
+}
+
+/// Converts a value to text. Containers are flattened into comma separated
+/// lists.
+fn render(value: &zvariant::Value) -> String {
+    match value {
+        zvariant::Value::U8(n) => n.to_string(),
+        zvariant::Value::Bool(b) => b.to_string(),
+        zvariant::Value::I16(n) => n.to_string(),
+        zvariant::Value::U16(n) => n.to_string(),
+        zvariant::Value::I32(n) => n.to_string(),
+        zvariant::Value::U32(n) => n.to_string(),
+        zvariant::Value::I64(n) => n.to_string(),
+        zvariant::Value::U64(n) => n.to_string(),
+        zvariant::Value::F64(n) => n.to_string(),
+        zvariant::Value::Str(s) => s.to_string(),
+        zvariant::Value::Signature(s) => s.to_string(),
+        zvariant::Value::ObjectPath(p) => p.to_string(),
+        zvariant::Value::Value(v) => render(v),
+        zvariant::Value::Array(a) => {
+            a.iter().map(render).collect::<Vec<_>>().join(", ")
+        }
+        zvariant::Value::Structure(s) => {
+            s.fields().iter().map(render).collect::<Vec<_>>().join(", ")
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Returns the current value of the property, followed by its new value each
+/// time it changes.
+async fn watch(
+    target: Target,
+) -> Result<impl Stream<Item = Result<OwnedValue>>> {
+    let conn = match target.bus {
+        Bus::Session => Connection::session().await?,
+        Bus::System => Connection::system().await?,
+    };
+    let proxy = PropertiesProxy::builder(&conn)
+        .destination(target.service)?
+        .path(target.path)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let interface = InterfaceName::try_from(target.interface)?;
+    let property = target.property;
+
+    // subscribe before reading the initial value so no change is missed
+    let signals = proxy.receive_properties_changed().await?;
+    let initial = proxy.get(interface.clone(), property.as_str()).await?;
+
+    let changes = futures::StreamExt::then(signals, move |signal| {
+        let proxy = proxy.clone();
+        let interface = interface.clone();
+        let property = property.clone();
+        async move {
+            let args = signal.args()?;
+            if *args.interface_name() != interface {
+                return Ok(None);
+            }
+            if let Some(value) =
+                args.changed_properties().get(property.as_str())
+            {
+                return Ok(Some(value.try_to_owned()?));
+            }
+            // the service only announced that the value changed
+            if args.invalidated_properties().contains(&property.as_str()) {
+                return Ok(Some(
+                    proxy.get(interface, property.as_str()).await?,
+                ));
+            }
+            Ok(None)
+        }
+    })
+    .filter_map(Result::transpose);
+
+    Ok(tokio_stream::once(Ok(initial)).chain(changes))
+}
+
+/// Displays any D-Bus property, updating whenever the service emits
+/// `PropertiesChanged`.
+///
+/// Tools like `busctl` or `d-feet` are helpful for finding properties. For
+/// example, `busctl --user introspect <service> <path>` lists every property
+/// of an object.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct DbusProperty {
+    target: Target,
+    common: PanelCommon,
+}
+
+impl DbusProperty {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        value: &OwnedValue,
+    ) -> Result<PanelDrawInfo> {
+        let values = HashMap::from([(
+            String::from("value"),
+            self.common.escape_markup.apply(render(value).as_str()),
+        )]);
+        let text = render_format(&self.common.formats[0], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for DbusProperty {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        // connecting is async, so it has to happen inside the stream
+        let stream = futures::stream::once(watch(self.target.clone()))
+            .try_flatten()
+            .map(move |value| self.draw(&cr, &value?));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `bus`: the bus to connect to, either `session` or `system`
+    ///   - type: String
+    ///   - default: `session`
+    ///
+    /// - `service`: the bus name of the service, e.g. `org.freedesktop.UPower`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `path`: the object path, e.g. `/org/freedesktop/UPower`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `interface`: the interface that the property belongs to, e.g.
+    ///   `org.freedesktop.UPower`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `property`: the name of the property, e.g. `OnBattery`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `{value}`
+    ///   - formatting options: `{value}` (arrays and structs are shown as comma
+    ///     separated lists)
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = DbusPropertyBuilder::default();
+
+        let mut required = |key: &str| {
+            remove_string_from_config(key, table)
+                .ok_or_else(|| anyhow!("D-Bus property panel requires `{key}`"))
+        };
+        let service = required("service")?;
+        let path = required("path")?;
+        let interface = required("interface")?;
+        let property = required("property")?;
+        let bus = match remove_string_from_config("bus", table).as_deref() {
+            None | Some("session") => Bus::Session,
+            Some("system") => Bus::System,
+            Some(bus) => return Err(anyhow!("Invalid D-Bus bus {bus}")),
+        };
+        builder.target(Target {
+            bus,
+            service,
+            path,
+            interface,
+            property,
+        });
+        builder.common(PanelCommon::parse(table, &[""], &["{value}"], &[""])?);
+
+        Ok(builder.build()?)
+    }
+}
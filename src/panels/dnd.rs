@@ -0,0 +1,227 @@
+use std::{
+    cell::Cell, collections::HashMap, env, fs, path::PathBuf, rc::Rc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        OnceCell,
+    },
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use super::dunst::{connect, DunstCmdProxy};
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_string_from_config, remove_uint_from_config, Attrs,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+/// Tracks the last known state and keeps the state file in sync with it.
+#[derive(Debug)]
+struct Persisted {
+    path: PathBuf,
+    last: Cell<Option<bool>>,
+    restored: Cell<bool>,
+}
+
+impl Persisted {
+    fn load(path: PathBuf) -> Self {
+        let last = fs::read_to_string(&path).ok().map(|s| s.trim() == "1");
+        Self {
+            path,
+            last: Cell::new(last),
+            restored: Cell::new(false),
+        }
+    }
+
+    fn store(&self, enabled: bool) {
+        if self.last.replace(Some(enabled)) == Some(enabled) {
+            return;
+        }
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| {
+                fs::write(&self.path, if enabled { "1\n" } else { "0\n" })
+            });
+        if let Err(e) = result {
+            log::warn!("Failed to write {:?}: {e}", self.path);
+        }
+    }
+}
+
+async fn update(
+    proxy: &DunstCmdProxy<'static>,
+    persisted: &Persisted,
+    event: Option<MouseEvent>,
+) -> Result<bool> {
+    // apply the saved state once dunst is reachable
+    if !persisted.restored.get() {
+        if let Some(enabled) = persisted.last.get() {
+            proxy.set_paused(enabled).await?;
+        }
+        persisted.restored.set(true);
+    }
+
+    if let Some(MouseEvent {
+        button: MouseButton::Left,
+        ..
+    }) = event
+    {
+        proxy.set_paused(!proxy.paused().await?).await?;
+    }
+
+    // dunstctl may have changed the state behind our back, so always check
+    let enabled = proxy.paused().await?;
+    persisted.store(enabled);
+    Ok(enabled)
+}
+
+/// A do-not-disturb toggle that pauses dunst notifications.
+///
+/// Left clicking the panel toggles do-not-disturb. The state is saved to a
+/// file and restored when the bar starts.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Dnd {
+    state_file: PathBuf,
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Dnd {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        enabled: bool,
+    ) -> Result<PanelDrawInfo> {
+        let idx = usize::from(enabled);
+        draw_common(
+            cr,
+            self.common.formats[idx].as_str(),
+            &self.common.attrs[idx],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )
+    }
+}
+
+impl PanelConfig for Dnd {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let updates = IntervalStream::new(interval(self.interval))
+            .map(|_| None)
+            .merge(UnboundedReceiverStream::new(events).map(Some));
+
+        let proxy = Rc::new(OnceCell::new());
+        let persisted = Rc::new(Persisted::load(self.state_file.clone()));
+        let stream = futures::StreamExt::then(updates, move |event| {
+            let proxy = proxy.clone();
+            let persisted = persisted.clone();
+            async move {
+                update(
+                    proxy.get_or_try_init(connect).await?,
+                    persisted.as_ref(),
+                    event,
+                )
+                .await
+            }
+        })
+        .map(move |enabled| self.draw(&cr, enabled?));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `state_file`: where to save the do-not-disturb state between runs
+    ///   - type: String
+    ///   - default: `$XDG_STATE_HOME/lazybar/dnd`
+    ///
+    /// - `interval`: how long to wait in seconds between checking for changes
+    ///   made outside the bar
+    ///   - type: u64
+    ///   - default: 2
+    ///
+    /// - `format_off`: the format string when do-not-disturb is off
+    ///   - type: String
+    ///   - default: `DND: off`
+    ///
+    /// - `format_on`: the format string when do-not-disturb is on
+    ///   - type: String
+    ///   - default: `DND: on`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `off_` and `on_`. Unset values in `on_` fall back
+    ///   to `off_`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = DndBuilder::default();
+
+        builder.state_file(
+            remove_string_from_config("state_file", table).map_or_else(
+                || {
+                    PathBuf::from(format!(
+                        "{}/lazybar/dnd",
+                        env::var("XDG_STATE_HOME").unwrap_or_else(|_| format!(
+                            "{}/.local/state",
+                            env::var("HOME").unwrap()
+                        ))
+                    ))
+                },
+                PathBuf::from,
+            ),
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_off", "_on"],
+            &["DND: off", "DND: on"],
+            &["off_", "on_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,198 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use xcb::{dpms, x};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_string_from_config, remove_uint_from_config, Attrs,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Mouse(MouseEvent),
+}
+
+/// Displays whether DPMS and screen blanking are enabled, like `xset q`.
+///
+/// Left clicking the panel toggles both of them, which makes this a simple
+/// "caffeine" indicator. Unlike the idle inhibitor, the change is made to the
+/// X server's settings, so it persists after the bar exits.
+#[derive(Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Dpms {
+    screen: Option<String>,
+    #[builder(default = "Duration::from_secs(5)")]
+    interval: Duration,
+    // the screensaver timeout to restore when re-enabling blanking
+    #[builder(default = "-1", setter(skip))]
+    timeout: i16,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Dpms {
+    fn enabled(
+        conn: &xcb::Connection,
+    ) -> Result<(bool, x::GetScreenSaverReply)> {
+        let dpms = conn.wait_for_reply(conn.send_request(&dpms::Info {}))?;
+        let screensaver =
+            conn.wait_for_reply(conn.send_request(&x::GetScreenSaver {}))?;
+        Ok((dpms.state() || screensaver.timeout() > 0, screensaver))
+    }
+
+    fn toggle(&mut self, conn: &xcb::Connection) -> Result<()> {
+        let (enabled, screensaver) = Self::enabled(conn)?;
+        let timeout = if enabled {
+            self.timeout = i16::try_from(screensaver.timeout()).unwrap_or(-1);
+            conn.send_and_check_request(&dpms::Disable {})?;
+            0
+        } else {
+            conn.send_and_check_request(&dpms::Enable {})?;
+            self.timeout
+        };
+        conn.send_and_check_request(&x::SetScreenSaver {
+            timeout,
+            interval: i16::try_from(screensaver.interval()).unwrap_or(-1),
+            prefer_blanking: screensaver.prefer_blanking(),
+            allow_exposures: screensaver.allow_exposures(),
+        })?;
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        conn: &xcb::Connection,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Tick => {}
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                ..
+            }) => {
+                if let Err(e) = self.toggle(conn) {
+                    return Some(Err(e));
+                }
+            }
+            Update::Mouse(_) => return None,
+        }
+
+        Some(self.draw(conn, cr))
+    }
+
+    fn draw(
+        &self,
+        conn: &xcb::Connection,
+        cr: &Rc<cairo::Context>,
+    ) -> Result<PanelDrawInfo> {
+        let idx = usize::from(!Self::enabled(conn)?.0);
+        draw_common(
+            cr,
+            self.common.formats[idx].as_str(),
+            &self.common.attrs[idx],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )
+    }
+}
+
+impl PanelConfig for Dpms {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let (conn, _) = xcb::Connection::connect_with_extensions(
+            self.screen.as_deref(),
+            &[xcb::Extension::Dpms],
+            &[],
+        )?;
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(|_| Update::Tick)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .filter_map(move |u| self.update(&conn, &cr, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `screen`: the name of the X screen to use
+    ///   - type: String
+    ///   - default: None (This will tell X to choose the default screen, which
+    ///     is probably what you want.)
+    ///
+    /// - `interval`: how long to wait in seconds between checking for changes
+    ///   made outside the bar (e.g. with `xset`)
+    ///   - type: u64
+    ///   - default: 5
+    ///
+    /// - `format_enabled`: the format string when the screen may blank
+    ///   - type: String
+    ///   - default: `DPMS: on`
+    ///
+    /// - `format_disabled`: the format string when DPMS and blanking are
+    ///   disabled
+    ///   - type: String
+    ///   - default: `DPMS: off`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `enabled_` and `disabled_`. Unset values in
+    ///   `disabled_` fall back to `enabled_`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = DpmsBuilder::default();
+
+        builder.screen(remove_string_from_config("screen", table));
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_enabled", "_disabled"],
+            &["DPMS: on", "DPMS: off"],
+            &["enabled_", "disabled_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,223 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        OnceCell,
+    },
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use zbus::{proxy::CacheProperties, Connection};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_uint_from_config, render_format, Attrs, PanelCommon,
+    PanelConfig, PanelStream,
+};
+
+#[zbus::proxy(
+    interface = "org.dunstproject.cmd0",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub(super) trait DunstCmd {
+    fn notification_show(&self) -> zbus::Result<()>;
+
+    #[zbus(property, name = "paused")]
+    fn paused(&self) -> zbus::Result<bool>;
+
+    #[zbus(property, name = "paused")]
+    fn set_paused(&self, paused: bool) -> zbus::Result<()>;
+
+    #[zbus(property, name = "displayedLength")]
+    fn displayed_length(&self) -> zbus::Result<u32>;
+
+    #[zbus(property, name = "historyLength")]
+    fn history_length(&self) -> zbus::Result<u32>;
+
+    #[zbus(property, name = "waitingLength")]
+    fn waiting_length(&self) -> zbus::Result<u32>;
+}
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Mouse(MouseEvent),
+}
+
+#[derive(Debug)]
+struct State {
+    paused: bool,
+    displayed: u32,
+    history: u32,
+    waiting: u32,
+}
+
+pub(super) async fn connect() -> Result<DunstCmdProxy<'static>> {
+    let conn = Connection::session().await?;
+    // not every property emits PropertiesChanged, so always ask dunst
+    Ok(DunstCmdProxy::builder(&conn)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?)
+}
+
+async fn update(
+    proxy: &DunstCmdProxy<'static>,
+    update: Update,
+) -> Result<State> {
+    match update {
+        Update::Mouse(MouseEvent {
+            button: MouseButton::Left,
+            ..
+        }) => proxy.notification_show().await?,
+        Update::Mouse(MouseEvent {
+            button: MouseButton::Right,
+            ..
+        }) => proxy.set_paused(!proxy.paused().await?).await?,
+        Update::Mouse(_) | Update::Tick => {}
+    }
+
+    Ok(State {
+        paused: proxy.paused().await?,
+        displayed: proxy.displayed_length().await?,
+        history: proxy.history_length().await?,
+        waiting: proxy.waiting_length().await?,
+    })
+}
+
+/// Displays notification counts from dunst and whether notifications are
+/// paused.
+///
+/// Left clicking the panel pops the most recent notification from the history
+/// (like `dunstctl history-pop`), and right clicking toggles whether
+/// notifications are paused (like `dunstctl set-paused toggle`).
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Dunst {
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Dunst {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        state: &State,
+    ) -> Result<PanelDrawInfo> {
+        let (format, attrs) = if state.paused {
+            (&self.common.formats[1], &self.common.attrs[1])
+        } else {
+            (&self.common.formats[0], &self.common.attrs[0])
+        };
+        let values = HashMap::from([
+            (String::from("displayed"), state.displayed.to_string()),
+            (String::from("history"), state.history.to_string()),
+            (String::from("waiting"), state.waiting.to_string()),
+        ]);
+        let text = render_format(format, &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            attrs,
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Dunst {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let updates = IntervalStream::new(interval(self.interval))
+            .map(|_| Update::Tick)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse));
+
+        // connecting is async, so it has to happen inside the stream
+        let proxy = Rc::new(OnceCell::new());
+        let stream = futures::StreamExt::then(updates, move |u| {
+            let proxy = proxy.clone();
+            async move { update(proxy.get_or_try_init(connect).await?, u).await }
+        })
+        .map(move |state| self.draw(&cr, &state?));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 2
+    ///
+    /// - `format`: the format string when notifications aren't paused
+    ///   - type: String
+    ///   - default: `NOTIF: {history}`
+    ///   - formatting options: `{displayed}`, `{history}`, `{waiting}`
+    ///
+    /// - `format_paused`: the format string when notifications are paused
+    ///   - type: String
+    ///   - default: `NOTIF: paused ({waiting})`
+    ///   - formatting options: `{displayed}`, `{history}`, `{waiting}`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `paused_`. Unset values in `paused_` fall
+    ///   back to the first instance. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = DunstBuilder::default();
+
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_paused"],
+            &["NOTIF: {history}", "NOTIF: paused ({waiting})"],
+            &["", "paused_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
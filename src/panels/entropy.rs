@@ -0,0 +1,128 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, read_number, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream, Ramp,
+};
+
+/// Displays the amount of entropy available in the kernel's random pool.
+///
+/// Since Linux 5.18, the pool is always full once initialized, so this is
+/// mostly useful on older kernels.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Entropy {
+    #[builder(default = "Duration::from_secs(5)")]
+    interval: Duration,
+    #[builder(default)]
+    ramp: Option<Ramp>,
+    common: PanelCommon,
+}
+
+impl Entropy {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let entropy =
+            read_number::<u32>("/proc/sys/kernel/random/entropy_avail")?;
+        let size = read_number::<u32>("/proc/sys/kernel/random/poolsize")?;
+
+        let values = HashMap::from([
+            (String::from("entropy"), entropy.to_string()),
+            (
+                String::from("percent"),
+                (entropy * 100 / size.max(1)).to_string(),
+            ),
+            (
+                String::from("ramp"),
+                self.ramp
+                    .as_ref()
+                    .map(|r| r.choose(entropy, 0, size))
+                    .unwrap_or_default(),
+            ),
+        ]);
+        let text = render_format(&self.common.formats[0], &values);
+        let attrs = self.ramp.as_ref().map_or_else(
+            || self.common.attrs[0].clone(),
+            |r| r.attrs(entropy, 0, size, &self.common.attrs[0]),
+        );
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &attrs,
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Entropy {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 5
+    ///
+    /// - `ramp`: the ramp to display based on the available entropy, relative
+    ///   to the size of the pool. See [`Ramp::parse`] for parsing details.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `ENT: {entropy}`
+    ///   - formatting options: `{entropy}` (in bits), `{percent}`, `{ramp}`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        global: &Config,
+    ) -> Result<Self> {
+        let mut builder = EntropyBuilder::default();
+
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(ramp) = remove_string_from_config("ramp", table) {
+            builder.ramp(Ramp::parse(ramp, global));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &[""],
+            &["ENT: {entropy}"],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
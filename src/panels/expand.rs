@@ -0,0 +1,56 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio_stream::StreamExt;
+
+use crate::{bar::PanelDrawInfo, Attrs, PanelCommon, PanelConfig};
+
+/// Takes up the space left over in its part of the bar. The space is shared
+/// equally between expanding panels, and the background of the bar shows
+/// through it.
+///
+/// In the left or right part of the bar, this pushes the following or
+/// preceding panels toward the center. In the center, the center panels
+/// spread out to fill the bar between the left and right panels. Expanding
+/// panels have no effect inside a [`Group`][crate::panels::Group].
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Expand {
+    common: PanelCommon,
+}
+
+impl PanelConfig for Expand {
+    fn into_stream(
+        self: Box<Self>,
+        _cr: Rc<cairo::Context>,
+        _global_attrs: Attrs,
+        height: i32,
+    ) -> Result<crate::PanelStream> {
+        let mut draw_info = PanelDrawInfo::new(
+            (0, height),
+            self.common.dependence,
+            Box::new(|_| Ok(())),
+        );
+        draw_info.expand = true;
+
+        Ok(Box::pin(
+            tokio_stream::once(Ok(draw_info)).chain(tokio_stream::pending()),
+        ))
+    }
+
+    /// Configuration options:
+    ///
+    /// - See [`PanelCommon::parse`]. Only `dependence` has an effect.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = ExpandBuilder::default();
+        builder.common(PanelCommon::parse(table, &[], &[], &[])?);
+
+        Ok(builder.build()?)
+    }
+}
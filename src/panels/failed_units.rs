@@ -0,0 +1,189 @@
+use std::{collections::HashMap, process::Command, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config, render_format, run_command, Attrs, PanelCommon,
+    PanelConfig, PanelStream,
+};
+
+/// Displays the number of failed systemd units, hiding itself when there are
+/// none.
+///
+/// Left clicking the panel runs a configurable command.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct FailedUnits {
+    #[builder(default = "Duration::from_secs(30)")]
+    interval: Duration,
+    #[builder(default = "false")]
+    user: bool,
+    #[builder(default, setter(strip_option))]
+    command: Option<String>,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl FailedUnits {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let system = count_failed(false)?;
+        let user = if self.user { count_failed(true)? } else { 0 };
+
+        let values = HashMap::from([
+            (String::from("count"), (system + user).to_string()),
+            (String::from("system"), system.to_string()),
+            (String::from("user"), user.to_string()),
+        ]);
+        let text = if system + user == 0 {
+            self.common.formats[1].clone()
+        } else {
+            render_format(&self.common.formats[0], &values)
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+
+    fn handle_event(&self, event: MouseEvent) {
+        if let (MouseButton::Left, Some(command)) =
+            (event.button, &self.command)
+        {
+            run_command(command.as_str());
+        }
+    }
+}
+
+fn count_failed(user: bool) -> Result<usize> {
+    let mut command = Command::new("systemctl");
+    if user {
+        command.arg("--user");
+    }
+    let output = command
+        .args(["--failed", "--no-legend", "--plain"])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "systemctl exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(output.stderr.as_slice()).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(output.stdout.as_slice())
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .count())
+}
+
+impl PanelConfig for FailedUnits {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(|_| None)
+            .merge(UnboundedReceiverStream::new(events).map(Some))
+            .filter_map(move |event| match event {
+                None => Some(self.draw(&cr)),
+                Some(event) => {
+                    self.handle_event(event);
+                    None
+                }
+            });
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 30
+    ///
+    /// - `user`: whether to include failed units from the user's service
+    ///   manager in addition to the system-wide ones
+    ///   - type: bool
+    ///   - default: false
+    ///
+    /// - `command`: a shell command to run when the panel is left clicked, e.g.
+    ///   `alacritty --hold -e systemctl list-units --failed`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `format`: the format string when any units have failed
+    ///   - type: String
+    ///   - default: `FAILED: {count}`
+    ///   - formatting options: `{count}`, `{system}`, `{user}`
+    ///
+    /// - `format_none`: the format string when no units have failed
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = FailedUnitsBuilder::default();
+
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(user) = remove_bool_from_config("user", table) {
+            builder.user(user);
+        }
+        if let Some(command) = remove_string_from_config("command", table) {
+            builder.command(command);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_none"],
+            &["FAILED: {count}", ""],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -17,8 +17,8 @@ use tokio::task::{self, JoinHandle};
 use tokio_stream::{Stream, StreamExt};
 
 use crate::{
-    bar::PanelDrawInfo, draw_common, remove_string_from_config, Attrs,
-    PanelCommon, PanelConfig, PanelStream,
+    bar::PanelDrawInfo, draw_common, remove_string_from_config, render_format,
+    Attrs, PanelCommon, PanelConfig, PanelStream,
 };
 
 struct FanotifyStream {
@@ -86,19 +86,29 @@ impl Fanotify {
         let mut buf = String::new();
         file.lock().unwrap().read_to_string(&mut buf)?;
         file.lock().unwrap().rewind()?;
-        let text = self.common.formats[0]
-            .replace("%file%", buf.lines().next().unwrap_or(""));
+        let values = HashMap::from([(
+            String::from("file"),
+            buf.lines().next().unwrap_or("").to_owned(),
+        )]);
+        let text = render_format(&self.common.formats[0], &values);
 
-        draw_common(
+        let mut draw_info = draw_common(
             cr,
             text.as_str(),
             &self.common.attrs[0],
             self.common.dependence,
-        )
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Fanotify {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
@@ -133,14 +143,15 @@ impl PanelConfig for Fanotify {
     ///
     /// - `format`: the format string
     ///   - type: String
-    ///   - default: `%file%`
-    ///   - formatting options: `%file%`
+    ///   - default: `{file}`
+    ///   - formatting options: `{file}`
     ///
     /// - `path`: the file to monitor
     ///   - type: String
     ///   - default: none
     ///
-    /// - See [`PanelCommon::parse`].
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
     fn parse(
         table: &mut HashMap<String, Value>,
         _global: &Config,
@@ -149,7 +160,7 @@ impl PanelConfig for Fanotify {
         if let Some(path) = remove_string_from_config("path", table) {
             builder.path(path);
         }
-        builder.common(PanelCommon::parse(table, &[""], &["%file%"], &[""])?);
+        builder.common(PanelCommon::parse(table, &[""], &["{file}"], &[""])?);
 
         Ok(builder.build()?)
     }
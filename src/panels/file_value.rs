@@ -0,0 +1,290 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::FutureExt;
+use nix::sys::inotify::{self, AddWatchFlags, InitFlags};
+use regex::Regex;
+use tokio::{
+    task::{self, JoinHandle},
+    time::interval,
+};
+use tokio_stream::{wrappers::IntervalStream, Stream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, read_number, remove_bool_from_config,
+    remove_float_from_config, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream, Ramp,
+};
+
+struct FileStream {
+    i: Arc<inotify::Inotify>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FileStream {
+    fn new(i: inotify::Inotify) -> Self {
+        Self {
+            i: Arc::new(i),
+            handle: None,
+        }
+    }
+}
+
+impl Stream for FileStream {
+    type Item = ();
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(handle) = &mut self.handle {
+            let value = handle.poll_unpin(cx).map(|_| Some(()));
+            if value.is_ready() {
+                self.handle = None;
+            }
+            value
+        } else {
+            let i = self.i.clone();
+            let waker = cx.waker().clone();
+            self.handle = Some(task::spawn_blocking(move || loop {
+                let result = i.read_events();
+                if result.is_ok() {
+                    waker.wake();
+                    break;
+                }
+            }));
+            Poll::Pending
+        }
+    }
+}
+
+/// Displays a number read from any file, such as a sensor in sysfs that no
+/// other panel knows about.
+///
+/// The number can be extracted from a larger file with a regex, then scaled
+/// and offset, e.g. to convert millidegrees to degrees.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct FileValue {
+    path: PathBuf,
+    #[builder(default = "Some(Duration::from_secs(10))")]
+    interval: Option<Duration>,
+    #[builder(default)]
+    regex: Option<Regex>,
+    #[builder(default = "1.0")]
+    scale: f64,
+    #[builder(default = "0.0")]
+    offset: f64,
+    #[builder(default = "0")]
+    precision: usize,
+    #[builder(default)]
+    ramp: Option<Ramp>,
+    #[builder(default = "0.0")]
+    min: f64,
+    #[builder(default = "100.0")]
+    max: f64,
+    common: PanelCommon,
+}
+
+impl FileValue {
+    fn read(&self) -> Result<f64> {
+        let Some(regex) = &self.regex else {
+            return read_number(&self.path);
+        };
+
+        let contents = fs::read_to_string(&self.path)?;
+        let captures = regex.captures(contents.as_str()).ok_or_else(|| {
+            anyhow!("{regex} doesn't match the contents of {:?}", self.path)
+        })?;
+        // use the first group if there is one, otherwise the whole match
+        let text = captures
+            .get(1)
+            .or_else(|| captures.get(0))
+            .map_or("", |m| m.as_str());
+        text.trim()
+            .parse()
+            .map_err(|e| anyhow!("Failed to parse {text:?}: {e}"))
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let value = self.read()?.mul_add(self.scale, self.offset);
+
+        let values = HashMap::from([
+            (
+                String::from("value"),
+                format!("{:.*}", self.precision, value),
+            ),
+            (
+                String::from("ramp"),
+                self.ramp
+                    .as_ref()
+                    .map(|r| r.choose(value, self.min, self.max))
+                    .unwrap_or_default(),
+            ),
+        ]);
+        let text = render_format(&self.common.formats[0], &values);
+        let attrs = self.ramp.as_ref().map_or_else(
+            || self.common.attrs[0].clone(),
+            |r| r.attrs(value, self.min, self.max, &self.common.attrs[0]),
+        );
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.states.apply(value, &attrs),
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        if let Some(state) = self.common.states.find(value) {
+            draw_info.set_value("state", &state.name);
+        }
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for FileValue {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        Ok(match self.interval {
+            Some(duration) => Box::pin(
+                IntervalStream::new(interval(duration))
+                    .map(move |_| self.draw(&cr)),
+            ),
+            None => {
+                let inotify = inotify::Inotify::init(InitFlags::empty())?;
+                inotify.add_watch(
+                    &self.path,
+                    AddWatchFlags::IN_MODIFY | AddWatchFlags::IN_CLOSE_WRITE,
+                )?;
+                Box::pin(
+                    tokio_stream::once(())
+                        .chain(FileStream::new(inotify))
+                        .map(move |()| self.draw(&cr)),
+                )
+            }
+        })
+    }
+
+    /// Configuration options:
+    ///
+    /// - `path`: the file to read
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `interval`: how long to wait in seconds between each read
+    ///   - type: u64
+    ///   - default: 10
+    ///
+    /// - `watch`: whether to read the file whenever it changes, using inotify,
+    ///   instead of on an interval. Most sysfs and procfs files don't report
+    ///   changes, so this is mainly useful for regular files and the few sysfs
+    ///   attributes that support polling.
+    ///   - type: bool
+    ///   - default: false
+    ///
+    /// - `regex`: a regex to find the number in the file. If it has a capture
+    ///   group, the first group is used. Otherwise, the whole match is used.
+    ///   - type: String
+    ///   - default: none (the whole file is used)
+    ///
+    /// - `scale`: the number to multiply the value by
+    ///   - type: f64
+    ///   - default: 1
+    ///
+    /// - `offset`: the number to add to the value after scaling
+    ///   - type: f64
+    ///   - default: 0
+    ///
+    /// - `precision`: the number of decimal places to show
+    ///   - type: u64
+    ///   - default: 0
+    ///
+    /// - `ramp`: the ramp to display based on the value. See [`Ramp::parse`]
+    ///   for parsing details.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `min`: the value at the bottom of the ramp
+    ///   - type: f64
+    ///   - default: 0
+    ///
+    /// - `max`: the value at the top of the ramp
+    ///   - type: f64
+    ///   - default: 100
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `{value}`
+    ///   - formatting options: `{value}`, `{ramp}`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        global: &Config,
+    ) -> Result<Self> {
+        let mut builder = FileValueBuilder::default();
+
+        builder.path(PathBuf::from(
+            remove_string_from_config("path", table)
+                .ok_or_else(|| anyhow!("File value panel requires `path`"))?,
+        ));
+        if remove_bool_from_config("watch", table).unwrap_or(false) {
+            builder.interval(None);
+        } else if let Some(interval) =
+            remove_uint_from_config("interval", table)
+        {
+            builder.interval(Some(Duration::from_secs(interval)));
+        }
+        if let Some(regex) = remove_string_from_config("regex", table) {
+            builder.regex(Some(Regex::new(regex.as_str())?));
+        }
+        if let Some(scale) = remove_float_from_config("scale", table) {
+            builder.scale(scale);
+        }
+        if let Some(offset) = remove_float_from_config("offset", table) {
+            builder.offset(offset);
+        }
+        if let Some(precision) = remove_uint_from_config("precision", table) {
+            builder.precision(precision as usize);
+        }
+        if let Some(ramp) = remove_string_from_config("ramp", table) {
+            builder.ramp(Ramp::parse(ramp, global));
+        }
+        if let Some(min) = remove_float_from_config("min", table) {
+            builder.min(min);
+        }
+        if let Some(max) = remove_float_from_config("max", table) {
+            builder.max(max);
+        }
+        builder.common(PanelCommon::parse(table, &[""], &["{value}"], &[""])?);
+
+        Ok(builder.build()?)
+    }
+}
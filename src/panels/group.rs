@@ -0,0 +1,342 @@
+use std::{collections::HashMap, fmt::Debug, rc::Rc};
+
+use anyhow::{anyhow, Context, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt, StreamMap};
+
+use crate::{
+    bar::{Dependence, MouseEvent, PanelDrawInfo},
+    get_table_from_config,
+    parser::{build_panel, panel_names},
+    popup::Anchor,
+    remove_float_from_config, remove_string_from_config, run_command_with_env,
+    Actions, Attrs, Decoration, PanelCommon, PanelConfig, PanelStream,
+};
+
+enum Update {
+    Panel(usize, Result<PanelDrawInfo>),
+    Mouse(MouseEvent),
+    Refresh,
+}
+
+/// A panel inside a [`Group`], once its stream has started.
+struct Member {
+    draw_info: Option<Rc<PanelDrawInfo>>,
+    shown: bool,
+    x: f64,
+    y: f64,
+    events: Option<UnboundedSender<MouseEvent>>,
+    refresh: Option<UnboundedSender<()>>,
+    actions: Actions,
+}
+
+impl Member {
+    /// Whether this panel would be shown on its own, ignoring its neighbors.
+    fn independent(member: Option<&Self>) -> bool {
+        member
+            .and_then(|m| m.draw_info.as_ref())
+            .is_some_and(|d| d.dependence == Dependence::None && d.width > 0)
+    }
+}
+
+/// Draws several panels as one module that shares a background and a
+/// [`Decoration`].
+///
+/// The panels in a group behave as they would on the bar: each one handles
+/// its own clicks and actions, and [`Dependence`] is resolved between the
+/// panels of the group. The group is hidden while none of them are shown.
+#[derive(Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+#[builder(pattern = "owned")]
+pub struct Group {
+    panels: Vec<(String, Box<dyn PanelConfig>)>,
+    #[builder(default)]
+    spacing: f64,
+    common: PanelCommon,
+    #[builder(default, setter(skip))]
+    members: Vec<Member>,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    #[builder(default, setter(skip))]
+    refresh: Option<UnboundedReceiver<()>>,
+}
+
+impl Debug for Group {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Group")
+            .field(
+                "panels",
+                &self.panels.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field("spacing", &self.spacing)
+            .field("common", &self.common)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Group {
+    /// Resolves the [`Dependence`] of the panel at `idx` the same way the bar
+    /// does.
+    fn shown(&self, idx: usize) -> bool {
+        let Some(draw_info) = &self.members[idx].draw_info else {
+            return false;
+        };
+        let left = || {
+            Member::independent(
+                idx.checked_sub(1).and_then(|i| self.members.get(i)),
+            )
+        };
+        let right = || Member::independent(self.members.get(idx + 1));
+        match draw_info.dependence {
+            Dependence::None => draw_info.width > 0,
+            Dependence::Left => left(),
+            Dependence::Right => right(),
+            Dependence::Both => left() && right(),
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn draw(&mut self, height: i32) -> PanelDrawInfo {
+        let mut x = 0.0;
+        let mut visible = Vec::new();
+        let mut texts = Vec::new();
+        let mut values = HashMap::new();
+        for idx in 0..self.members.len() {
+            let shown = self.shown(idx);
+            let member = &mut self.members[idx];
+            member.shown = shown;
+            let Some(draw_info) = member.draw_info.clone().filter(|_| shown)
+            else {
+                continue;
+            };
+            if !visible.is_empty() {
+                x += self.spacing;
+            }
+            member.x = x;
+            member.y = f64::from(height - draw_info.height) / 2.0;
+            if !draw_info.text.is_empty() {
+                texts.push(draw_info.text.clone());
+            }
+            values.extend(draw_info.values.clone());
+            visible.push((member.x, member.y, draw_info.clone()));
+            x += f64::from(draw_info.width);
+        }
+
+        if visible.is_empty() {
+            return PanelDrawInfo::new(
+                (0, height),
+                self.common.dependence,
+                Box::new(|_| Ok(())),
+            );
+        }
+
+        let width = x;
+        let attrs = self.common.attrs[0].clone();
+        let mut draw_info = PanelDrawInfo::new(
+            (width.ceil() as i32, height),
+            self.common.dependence,
+            Box::new(move |cr| {
+                attrs.apply_bg(cr, width, f64::from(height));
+                cr.rectangle(0.0, 0.0, width, f64::from(height));
+                cr.fill()?;
+
+                for (x, y, draw_info) in &visible {
+                    cr.save()?;
+                    cr.translate(*x, *y);
+                    (draw_info.draw_fn)(cr)?;
+                    cr.restore()?;
+                }
+                Ok(())
+            }),
+        );
+        draw_info.text = texts.join(" ");
+        draw_info.values = values;
+        draw_info
+    }
+
+    /// Passes a click on the group to the panel under the pointer, or runs
+    /// that panel's action for the button.
+    #[allow(clippy::cast_possible_truncation)]
+    fn click(&self, event: MouseEvent) {
+        let Some(member) = self.members.iter().find(|m| {
+            m.shown
+                && m.draw_info.as_ref().is_some_and(|d| {
+                    event.x >= m.x && event.x < m.x + f64::from(d.width)
+                })
+        }) else {
+            return;
+        };
+
+        if let Some(command) = member.actions.get(event.button) {
+            let text =
+                member.draw_info.as_ref().map_or("", |d| d.text.as_str());
+            run_command_with_env(command, &[("LAZYBAR_TEXT", text)]);
+        } else if let Some(events) = &member.events {
+            let _ = events.send(MouseEvent {
+                x: event.x - member.x,
+                y: event.y - member.y,
+                anchor: Anchor {
+                    x: event.anchor.x + member.x as i16,
+                    ..event.anchor
+                },
+                ..event
+            });
+        }
+    }
+
+    fn update(
+        &mut self,
+        height: i32,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Panel(idx, draw_info) => match draw_info {
+                Ok(draw_info) => {
+                    self.members[idx].draw_info = Some(Rc::new(draw_info));
+                    Some(Ok(self.draw(height)))
+                }
+                Err(e) => Some(Err(e)),
+            },
+            Update::Mouse(event) => {
+                self.click(event);
+                None
+            }
+            Update::Refresh => {
+                for refresh in
+                    self.members.iter().filter_map(|m| m.refresh.as_ref())
+                {
+                    let _ = refresh.send(());
+                }
+                None
+            }
+        }
+    }
+}
+
+impl PanelConfig for Group {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn refresh_sender(&mut self) -> Option<UnboundedSender<()>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.refresh = Some(recv);
+        Some(send)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<PanelStream> {
+        self.common.attrs[0].apply_to(&global_attrs);
+        let attrs = self.common.attrs[0].clone();
+
+        let mut streams = StreamMap::with_capacity(self.panels.len());
+        for (idx, (_, mut panel)) in
+            std::mem::take(&mut self.panels).into_iter().enumerate()
+        {
+            self.members.push(Member {
+                draw_info: None,
+                shown: false,
+                x: 0.0,
+                y: 0.0,
+                events: panel.event_sender(),
+                refresh: panel.refresh_sender(),
+                actions: panel.actions(),
+            });
+            let visible_when = panel.visible_when();
+            let mut stream =
+                panel.into_stream(cr.clone(), attrs.clone(), height)?;
+            if let Some(visible_when) = visible_when {
+                stream = Box::pin(stream.map(move |draw_info| {
+                    draw_info.map(|d| visible_when.apply(d))
+                }));
+            }
+            streams.insert(idx, stream);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let refresh = self
+            .refresh
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = streams
+            .map(|(idx, draw_info)| Update::Panel(idx, draw_info))
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .merge(
+                UnboundedReceiverStream::new(refresh).map(|()| Update::Refresh),
+            )
+            .filter_map(move |u| self.update(height, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `panels`: the names of the panels in the group, from left to right.
+    ///   Groups can't contain other groups.
+    ///   - type: array of String
+    ///   - default: none
+    ///
+    /// - `spacing`: the space in pixels between two panels in the group
+    ///   - type: f64
+    ///   - default: `0`
+    ///
+    /// - See [`PanelCommon::parse`]. No format strings are used, and one
+    ///   instance of [`Attrs`] is parsed with the prefix ``. Its background
+    ///   color fills the group, and the panels inside it fall back to its
+    ///   values instead of the bar's defaults. Use `margin`, `border_width`,
+    ///   `border_color`, `radius`, and `bg_padding` to draw a box around the
+    ///   group. The values of the panels that are shown are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        global: &Config,
+    ) -> Result<Self> {
+        let panels_table = global
+            .get_table("panels")
+            .context("`panels` doesn't exist or isn't a table")?;
+        let panels = panel_names(table, "panels")?
+            .into_iter()
+            .map(|name| {
+                let is_group =
+                    get_table_from_config(name.as_str(), &panels_table)
+                        .and_then(|mut t| {
+                            remove_string_from_config("type", &mut t)
+                        })
+                        .is_some_and(|t| t == "group");
+                if is_group {
+                    return Err(anyhow!(
+                        "Panel `{name}` is a group and can't be in another \
+                         group"
+                    ));
+                }
+                let panel = build_panel(name.as_str(), &panels_table, global)?;
+                Ok((name, panel))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut builder = GroupBuilder::default().panels(panels);
+        if let Some(spacing) = remove_float_from_config("spacing", table) {
+            builder = builder.spacing(spacing);
+        }
+
+        Ok(builder
+            .common(PanelCommon::parse(table, &[], &[], &[""])?)
+            .build()?)
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    rc::Rc,
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use pangocairo::functions::{create_layout, show_layout};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Refresh,
+    Mouse(MouseEvent),
+}
+
+/// The state of a tag, in the same order as the panel's [`Attrs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Focused,
+    Visible,
+    Other,
+    Urgent,
+    Occupied,
+    Empty,
+}
+
+impl State {
+    /// Maps the state characters used by `herbstclient tag_status`.
+    const fn from_char(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(Self::Focused),
+            '+' => Some(Self::Visible),
+            '-' | '%' => Some(Self::Other),
+            '!' => Some(Self::Urgent),
+            ':' => Some(Self::Occupied),
+            '.' => Some(Self::Empty),
+            _ => None,
+        }
+    }
+}
+
+fn herbstclient(args: &[&str]) -> Result<String> {
+    let output = Command::new("herbstclient").args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "herbstclient {} exited with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(output.stderr.as_slice()).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(output.stdout.as_slice()).into_owned())
+}
+
+/// Displays herbstluftwm tags, driven by `herbstclient --idle`.
+///
+/// Clicking a tag switches to it, and scrolling cycles through the tags.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Herbstluftwm {
+    #[builder(default, setter(strip_option))]
+    monitor: Option<String>,
+    #[builder(default = "0")]
+    padding: i32,
+    #[builder(setter(strip_option))]
+    highlight: Option<Highlight>,
+    /// Each tag's name and the x-coordinate of its right edge, as of the last
+    /// draw.
+    #[builder(default, setter(skip))]
+    bounds: Vec<(String, f64)>,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Herbstluftwm {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Refresh => Some(self.draw(cr, height)),
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                x,
+                ..
+            }) => {
+                let (tag, _) = self.bounds.iter().find(|(_, end)| x < *end)?;
+                // the resulting hook triggers a redraw
+                let result = match self.monitor.as_deref() {
+                    Some(monitor) => herbstclient(&[
+                        "chain",
+                        ":::",
+                        "focus_monitor",
+                        monitor,
+                        ":::",
+                        "use",
+                        tag,
+                    ]),
+                    None => herbstclient(&["use", tag]),
+                };
+                result.err().map(Err)
+            }
+            Update::Mouse(MouseEvent {
+                button:
+                    button @ (MouseButton::ScrollUp | MouseButton::ScrollDown),
+                ..
+            }) => {
+                let step = if button == MouseButton::ScrollUp {
+                    "-1"
+                } else {
+                    "+1"
+                };
+                let result = match self.monitor.as_deref() {
+                    Some(monitor) => herbstclient(&[
+                        "chain",
+                        ":::",
+                        "focus_monitor",
+                        monitor,
+                        ":::",
+                        "use_index",
+                        step,
+                        "--skip-visible",
+                    ]),
+                    None => {
+                        herbstclient(&["use_index", step, "--skip-visible"])
+                    }
+                };
+                result.err().map(Err)
+            }
+            Update::Mouse(_) => None,
+        }
+    }
+
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let mut args = vec!["tag_status"];
+        if let Some(monitor) = self.monitor.as_deref() {
+            args.push(monitor);
+        }
+        let status = herbstclient(args.as_slice())?;
+        let tags = status
+            .split('\t')
+            .filter_map(|t| {
+                let mut chars = t.chars();
+                let state = State::from_char(chars.next()?)?;
+                Some((chars.as_str().trim_end().to_owned(), state))
+            })
+            .collect::<Vec<_>>();
+
+        let layouts = tags
+            .iter()
+            .map(|(name, state)| {
+                let layout = create_layout(cr);
+                self.common.attrs[*state as usize].apply_font(&layout);
+                layout.set_text(name.as_str());
+                (*state, layout)
+            })
+            .collect::<Vec<_>>();
+
+        let mut end = 0.0;
+        self.bounds = tags
+            .into_iter()
+            .zip(&layouts)
+            .map(|((name, _), (_, layout))| {
+                end += f64::from(layout.pixel_size().0 + self.padding);
+                (name, end)
+            })
+            .collect();
+
+        let width = layouts
+            .iter()
+            .map(|l| l.1.pixel_size().0 + self.padding)
+            .sum::<i32>()
+            - self.padding;
+
+        let padding = self.padding;
+        let attrs = self.common.attrs.clone();
+        let highlight = self.highlight.clone();
+
+        Ok(PanelDrawInfo::new(
+            (width, height),
+            self.common.dependence,
+            Box::new(move |cr| {
+                for (state, layout) in &layouts {
+                    let attrs = &attrs[*state as usize];
+                    let size = layout.pixel_size();
+
+                    attrs.apply_bg(
+                        cr,
+                        f64::from(size.0 + padding),
+                        f64::from(height),
+                    );
+
+                    cr.save()?;
+                    cr.rectangle(
+                        0.0,
+                        0.0,
+                        f64::from(size.0 + padding),
+                        f64::from(height),
+                    );
+                    cr.fill()?;
+
+                    if *state == State::Focused {
+                        if let Some(highlight) = &highlight {
+                            cr.rectangle(
+                                0.0,
+                                f64::from(height) - highlight.height,
+                                f64::from(size.0 + padding),
+                                highlight.height,
+                            );
+                            cr.set_source_rgba(
+                                highlight.color.r,
+                                highlight.color.g,
+                                highlight.color.b,
+                                highlight.color.a,
+                            );
+                            cr.fill()?;
+                        }
+                    }
+
+                    cr.translate(
+                        f64::from(padding / 2),
+                        f64::from(height - size.1) / 2.0,
+                    );
+
+                    attrs.apply_fg(cr, f64::from(size.0), f64::from(size.1));
+                    show_layout(cr, layout);
+                    cr.restore()?;
+
+                    cr.translate(f64::from(size.0 + padding), 0.0);
+                }
+                Ok(())
+            }),
+        ))
+    }
+}
+
+/// Runs `herbstclient --idle` on its own thread, sending a refresh for each
+/// hook that may change the tags.
+fn follow(send: UnboundedSender<Update>) -> Result<()> {
+    let mut child = Command::new("herbstclient")
+        .arg("--idle")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture herbstclient output"))?;
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            let hook = line.split('\t').next().unwrap_or_default();
+            if (hook.starts_with("tag_")
+                || hook == "focus_changed"
+                || hook == "reload")
+                && send.send(Update::Refresh).is_err()
+            {
+                break;
+            }
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+        log::warn!("herbstclient exited");
+    });
+
+    Ok(())
+}
+
+impl PanelConfig for Herbstluftwm {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<PanelStream> {
+        let (send, recv) = mpsc::unbounded_channel();
+        follow(send)?;
+
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = tokio_stream::once(Update::Refresh)
+            .chain(UnboundedReceiverStream::new(recv))
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .filter_map(move |u| self.update(&cr, height, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `monitor`: the monitor whose tags should be shown, as accepted by
+    ///   `herbstclient tag_status`. Clicking a tag also focuses this monitor.
+    ///   - type: String
+    ///   - default: none (uses the focused monitor)
+    ///
+    /// - `padding`: The space in pixels between two tag names. The [`Attrs`]
+    ///   will change (if applicable) halfway between the two names.
+    ///   - type: u64
+    ///   - default: 0
+    ///
+    /// - `highlight`: The highlight that will appear on the focused tag. See
+    ///   [`Highlight::parse`] for parsing options.
+    ///
+    /// - See [`PanelCommon::parse`]. No format strings are used for this panel.
+    ///   Six instances of [`Attrs`] are parsed, one for each state character of
+    ///   `herbstclient tag_status`, using the prefixes `focused_` (`#`),
+    ///   `visible_` (`+`), `other_` (`-` and `%`, viewed on another monitor),
+    ///   `urgent_` (`!`), `occupied_` (`:`), and `empty_` (`.`).
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = HerbstluftwmBuilder::default();
+
+        if let Some(monitor) = remove_string_from_config("monitor", table) {
+            builder.monitor(monitor);
+        }
+        if let Some(padding) = remove_uint_from_config("padding", table) {
+            builder.padding(padding as i32);
+        }
+
+        builder.common(PanelCommon::parse(
+            table,
+            &[],
+            &[],
+            &[
+                "focused_",
+                "visible_",
+                "other_",
+                "urgent_",
+                "occupied_",
+                "empty_",
+            ],
+        )?);
+
+        builder.highlight(Highlight::parse(table));
+
+        Ok(builder.build()?)
+    }
+}
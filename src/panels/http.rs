@@ -0,0 +1,280 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use regex::Regex;
+use serde_json::Value as Json;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task,
+    time::{interval, Instant},
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+/// What to fetch and how to find the value in the response.
+#[derive(Debug, Clone)]
+struct Request {
+    url: String,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+    pointer: Option<String>,
+    regex: Option<Regex>,
+}
+
+impl Request {
+    fn fetch(&self) -> Result<String> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(self.timeout))
+            .build()
+            .into();
+        let mut request = agent.get(self.url.as_str());
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let body = request.call()?.body_mut().read_to_string()?;
+
+        let mut value = match &self.pointer {
+            Some(pointer) => {
+                match serde_json::from_str::<Json>(body.as_str())?
+                    .pointer(pointer.as_str())
+                {
+                    Some(Json::String(s)) => s.clone(),
+                    Some(value) => value.to_string(),
+                    None => {
+                        return Err(anyhow!(
+                            "{pointer} not found in response from {}",
+                            self.url
+                        ))
+                    }
+                }
+            }
+            None => body,
+        };
+        if let Some(regex) = &self.regex {
+            let captures = regex.captures(value.as_str()).ok_or_else(|| {
+                anyhow!("{regex} doesn't match response from {}", self.url)
+            })?;
+            // use the first group if there is one, otherwise the whole match
+            value = captures
+                .get(1)
+                .or_else(|| captures.get(0))
+                .map_or("", |m| m.as_str())
+                .to_owned();
+        }
+
+        Ok(value.trim().to_owned())
+    }
+}
+
+/// Formats a duration roughly, e.g. `45s`, `12m`, or `3h`.
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        s @ 0..=59 => format!("{s}s"),
+        s @ 60..=3599 => format!("{}m", s / 60),
+        s => format!("{}h", s / 3600),
+    }
+}
+
+/// Fetches a URL on an interval and displays a value from the response,
+/// found with a JSON pointer, a regex, or both.
+///
+/// If a request fails, the last value is shown with a separate format string
+/// so that stale data can be told apart from fresh data. Refreshing the panel
+/// over IPC sends a request immediately.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Http {
+    request: Request,
+    #[builder(default = "Duration::from_secs(60)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    last: Option<(String, Instant)>,
+    common: PanelCommon,
+    #[builder(default, setter(skip))]
+    refresh: Option<UnboundedReceiver<()>>,
+}
+
+impl Http {
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        value: Result<String>,
+    ) -> Result<PanelDrawInfo> {
+        let (stale, (value, fetched)) = match value {
+            Ok(value) => (false, &*self.last.insert((value, Instant::now()))),
+            Err(e) => match &self.last {
+                Some(last) => {
+                    log::warn!("Failed to fetch {}: {e}", self.request.url);
+                    (true, last)
+                }
+                None => return Err(e),
+            },
+        };
+
+        let values = HashMap::from([
+            (
+                String::from("value"),
+                self.common.escape_markup.apply(value.as_str()),
+            ),
+            (String::from("age"), format_age(fetched.elapsed())),
+        ]);
+        let text =
+            render_format(&self.common.formats[usize::from(stale)], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[usize::from(stale)],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Http {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn refresh_sender(&mut self) -> Option<UnboundedSender<()>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.refresh = Some(recv);
+        Some(send)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let refresh = self
+            .refresh
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let request = self.request.clone();
+        let stream = futures::StreamExt::then(
+            IntervalStream::new(interval(self.interval))
+                .map(|_| ())
+                .merge(UnboundedReceiverStream::new(refresh)),
+            move |()| {
+                let request = request.clone();
+                // don't block the bar on a slow network
+                async move {
+                    task::spawn_blocking(move || request.fetch())
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|r| r)
+                }
+            },
+        )
+        .map(move |value| self.draw(&cr, value));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `url`: the URL to fetch
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `headers`: extra headers to send, as a table of names and values
+    ///   - type: table of String
+    ///   - default: none
+    ///
+    /// - `interval`: how long to wait in seconds between each request
+    ///   - type: u64
+    ///   - default: 60
+    ///
+    /// - `timeout`: how long to wait in seconds for a response
+    ///   - type: u64
+    ///   - default: 10
+    ///
+    /// - `pointer`: a JSON pointer (RFC 6901) to the value in the response,
+    ///   e.g. `/current/temperature`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `regex`: a regex to find the value in the response, or in the value
+    ///   found by `pointer` if both are set. If it has a capture group, the
+    ///   first group is used. Otherwise, the whole match is used.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `{value}`
+    ///   - formatting options: `{value}`, `{age}` (the time since the value was
+    ///     fetched)
+    ///
+    /// - `format_stale`: the format string when the last request failed
+    ///   - type: String
+    ///   - default: `{value} ({age} ago)`
+    ///   - formatting options: same as `format`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `stale_`. The latter is used when the last
+    ///   request failed, and its unset values fall back to the first instance.
+    ///   The formatting options are available to `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = HttpBuilder::default();
+
+        let url = remove_string_from_config("url", table)
+            .ok_or_else(|| anyhow!("HTTP panel requires `url`"))?;
+        let headers = match table.remove("headers") {
+            Some(headers) => headers
+                .into_table()?
+                .into_iter()
+                .map(|(k, v)| Ok((k, v.into_string()?)))
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let timeout = Duration::from_secs(
+            remove_uint_from_config("timeout", table).unwrap_or(10),
+        );
+        let regex = remove_string_from_config("regex", table)
+            .map(|r| Regex::new(r.as_str()))
+            .transpose()?;
+        builder.request(Request {
+            url,
+            headers,
+            timeout,
+            pointer: remove_string_from_config("pointer", table),
+            regex,
+        });
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_stale"],
+            &["{value}", "{value} ({age} ago)"],
+            &["", "stale_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,122 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use serde_json::Value as Json;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, i3, render_format, Attrs, PanelCommon,
+    PanelConfig, PanelStream,
+};
+
+/// Displays the current i3 (or sway) binding mode, such as `resize`.
+///
+/// Mode changes are received as IPC events, and the panel is hidden in the
+/// default mode unless configured otherwise.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct I3Mode {
+    common: PanelCommon,
+}
+
+impl I3Mode {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        event: Json,
+    ) -> Result<PanelDrawInfo> {
+        // a new subscription, so the current mode is unknown
+        let (mode, markup) = if event.is_null() {
+            let state = i3::request(i3::GET_BINDING_STATE, "")?;
+            (state["name"].as_str().unwrap_or_default().to_owned(), false)
+        } else {
+            (
+                event["change"].as_str().unwrap_or_default().to_owned(),
+                event["pango_markup"] == true,
+            )
+        };
+
+        let default = mode == "default";
+        let values = HashMap::from([(
+            String::from("mode"),
+            if markup {
+                mode
+            } else {
+                glib::markup_escape_text(mode.as_str()).to_string()
+            },
+        )]);
+        let text = if default {
+            self.common.formats[1].clone()
+        } else {
+            render_format(&self.common.formats[0], &values)
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for I3Mode {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let (send, recv) = mpsc::unbounded_channel();
+        i3::subscribe(&["mode"], send);
+
+        let stream = UnboundedReceiverStream::new(recv)
+            .map(move |event| self.draw(&cr, event));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `{mode}`
+    ///   - formatting options: `{mode}`
+    ///
+    /// - `format_default`: the format string in the default mode
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = I3ModeBuilder::default();
+
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_default"],
+            &["{mode}", ""],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
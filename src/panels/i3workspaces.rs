@@ -0,0 +1,303 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use pangocairo::functions::{create_layout, show_layout};
+use serde_json::Value as Json;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    i3, remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Refresh,
+    Mouse(MouseEvent),
+}
+
+/// The state of a workspace, in the same order as the panel's [`Attrs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Focused,
+    Visible,
+    Urgent,
+    Inactive,
+}
+
+/// Displays workspaces using i3's (or sway's) IPC interface.
+///
+/// Unlike [`XWorkspaces`][super::XWorkspaces], this panel knows which output
+/// each workspace is on, so it can show only the workspaces on one monitor
+/// and distinguish workspaces that are visible on another monitor. Clicking a
+/// workspace switches to it, and scrolling cycles through the workspaces on
+/// its output.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct I3Workspaces {
+    #[builder(default, setter(strip_option))]
+    output: Option<String>,
+    #[builder(default = "0")]
+    padding: i32,
+    #[builder(setter(strip_option))]
+    highlight: Option<Highlight>,
+    /// Each workspace's name and the x-coordinate of its right edge, as of
+    /// the last draw.
+    #[builder(default, setter(skip))]
+    bounds: Vec<(String, f64)>,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl I3Workspaces {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Refresh => Some(self.draw(cr, height)),
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                x,
+                ..
+            }) => {
+                let (name, _) = self.bounds.iter().find(|(_, end)| x < *end)?;
+                // the resulting workspace event triggers a redraw
+                i3::run_command(
+                    format!(
+                        "workspace --no-auto-back-and-forth {}",
+                        i3::quote(name)
+                    )
+                    .as_str(),
+                )
+                .err()
+                .map(Err)
+            }
+            Update::Mouse(MouseEvent {
+                button:
+                    button @ (MouseButton::ScrollUp | MouseButton::ScrollDown),
+                ..
+            }) => {
+                let direction = if button == MouseButton::ScrollUp {
+                    "prev_on_output"
+                } else {
+                    "next_on_output"
+                };
+                let command = match self.output.as_deref() {
+                    Some(output) => format!(
+                        "focus output {}; workspace {direction}",
+                        i3::quote(output)
+                    ),
+                    None => format!("workspace {direction}"),
+                };
+                i3::run_command(command.as_str()).err().map(Err)
+            }
+            Update::Mouse(_) => None,
+        }
+    }
+
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let reply = i3::request(i3::GET_WORKSPACES, "")?;
+        let workspaces = reply
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|w| {
+                self.output
+                    .as_deref()
+                    .map_or(true, |o| w["output"].as_str() == Some(o))
+            })
+            .map(|w| {
+                let state = if w["urgent"] == true {
+                    State::Urgent
+                } else if w["focused"] == true {
+                    State::Focused
+                } else if w["visible"] == true {
+                    State::Visible
+                } else {
+                    State::Inactive
+                };
+                (w["name"].as_str().unwrap_or_default().to_owned(), state)
+            })
+            .collect::<Vec<_>>();
+
+        let layouts = workspaces
+            .iter()
+            .map(|(name, state)| {
+                let layout = create_layout(cr);
+                self.common.attrs[*state as usize].apply_font(&layout);
+                layout.set_text(name.as_str());
+                (*state, layout)
+            })
+            .collect::<Vec<_>>();
+
+        let mut end = 0.0;
+        self.bounds = workspaces
+            .into_iter()
+            .zip(&layouts)
+            .map(|((name, _), (_, layout))| {
+                end += f64::from(layout.pixel_size().0 + self.padding);
+                (name, end)
+            })
+            .collect();
+
+        let width = layouts
+            .iter()
+            .map(|l| l.1.pixel_size().0 + self.padding)
+            .sum::<i32>()
+            - self.padding;
+
+        let padding = self.padding;
+        let attrs = self.common.attrs.clone();
+        let highlight = self.highlight.clone();
+
+        Ok(PanelDrawInfo::new(
+            (width, height),
+            self.common.dependence,
+            Box::new(move |cr| {
+                for (state, layout) in &layouts {
+                    let attrs = &attrs[*state as usize];
+                    let size = layout.pixel_size();
+
+                    attrs.apply_bg(
+                        cr,
+                        f64::from(size.0 + padding),
+                        f64::from(height),
+                    );
+
+                    cr.save()?;
+                    cr.rectangle(
+                        0.0,
+                        0.0,
+                        f64::from(size.0 + padding),
+                        f64::from(height),
+                    );
+                    cr.fill()?;
+
+                    if *state == State::Focused {
+                        if let Some(highlight) = &highlight {
+                            cr.rectangle(
+                                0.0,
+                                f64::from(height) - highlight.height,
+                                f64::from(size.0 + padding),
+                                highlight.height,
+                            );
+                            cr.set_source_rgba(
+                                highlight.color.r,
+                                highlight.color.g,
+                                highlight.color.b,
+                                highlight.color.a,
+                            );
+                            cr.fill()?;
+                        }
+                    }
+
+                    cr.translate(
+                        f64::from(padding / 2),
+                        f64::from(height - size.1) / 2.0,
+                    );
+
+                    attrs.apply_fg(cr, f64::from(size.0), f64::from(size.1));
+                    show_layout(cr, layout);
+                    cr.restore()?;
+
+                    cr.translate(f64::from(size.0 + padding), 0.0);
+                }
+                Ok(())
+            }),
+        ))
+    }
+}
+
+impl PanelConfig for I3Workspaces {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let (send, recv) = mpsc::unbounded_channel::<Json>();
+        i3::subscribe(&["workspace", "output"], send);
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = UnboundedReceiverStream::new(recv)
+            .map(|_| Update::Refresh)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .filter_map(move |u| self.update(&cr, height, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `output`: the output (monitor) whose workspaces should be shown, e.g.
+    ///   `DP-1`
+    ///   - type: String
+    ///   - default: none (shows workspaces on all outputs)
+    ///
+    /// - `padding`: The space in pixels between two workspace names. The
+    ///   [`Attrs`] will change (if applicable) halfway between the two names.
+    ///   - type: u64
+    ///   - default: 0
+    ///
+    /// - `highlight`: The highlight that will appear on the focused workspace.
+    ///   See [`Highlight::parse`] for parsing options.
+    ///
+    /// - See [`PanelCommon::parse`]. No format strings are used for this panel.
+    ///   Four instances of [`Attrs`] are parsed using the prefixes `focused_`,
+    ///   `visible_` (visible on an output that doesn't have focus), `urgent_`,
+    ///   and `inactive_`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = I3WorkspacesBuilder::default();
+
+        if let Some(output) = remove_string_from_config("output", table) {
+            builder.output(output);
+        }
+        if let Some(padding) = remove_uint_from_config("padding", table) {
+            builder.padding(padding as i32);
+        }
+
+        builder.common(PanelCommon::parse(
+            table,
+            &[],
+            &[],
+            &["focused_", "visible_", "urgent_", "inactive_"],
+        )?);
+
+        builder.highlight(Highlight::parse(table));
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::sync::{
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+    OnceCell,
+};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use xcb::screensaver;
+use zbus::{proxy::CacheProperties, zvariant::OwnedFd, Connection};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_bool_from_config, remove_string_from_config, Attrs,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait LoginManager {
+    fn inhibit(
+        &self,
+        what: &str,
+        who: &str,
+        why: &str,
+        mode: &str,
+    ) -> zbus::Result<OwnedFd>;
+}
+
+async fn connect() -> Result<LoginManagerProxy<'static>> {
+    let conn = Connection::system().await?;
+    Ok(LoginManagerProxy::builder(&conn)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?)
+}
+
+/// The inhibitors currently held by the panel.
+struct Inhibitor {
+    conn: xcb::Connection,
+    logind: bool,
+    enabled: Cell<bool>,
+    proxy: OnceCell<LoginManagerProxy<'static>>,
+    // logind releases the inhibitor when this is closed
+    fd: RefCell<Option<OwnedFd>>,
+}
+
+impl Inhibitor {
+    async fn toggle(&self) -> Result<()> {
+        let enable = !self.enabled.get();
+
+        // the X server releases the suspension when our connection closes,
+        // so there's nothing to clean up on exit
+        self.conn.send_and_check_request(&screensaver::Suspend {
+            suspend: u32::from(enable),
+        })?;
+
+        if self.logind {
+            if enable {
+                let fd = self
+                    .proxy
+                    .get_or_try_init(connect)
+                    .await?
+                    .inhibit(
+                        "idle",
+                        "lazybar",
+                        "Idle inhibitor enabled",
+                        "block",
+                    )
+                    .await?;
+                self.fd.replace(Some(fd));
+            } else {
+                self.fd.replace(None);
+            }
+        }
+
+        self.enabled.set(enable);
+        Ok(())
+    }
+}
+
+/// A toggle that keeps the screen from blanking, e.g. while watching a movie.
+///
+/// Left clicking the panel toggles the inhibitor. While enabled, the X
+/// screensaver and DPMS are suspended, and optionally a systemd-logind idle
+/// inhibitor is held.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct IdleInhibit {
+    screen: Option<String>,
+    #[builder(default = "false")]
+    logind: bool,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl IdleInhibit {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        enabled: bool,
+    ) -> Result<PanelDrawInfo> {
+        let idx = usize::from(enabled);
+        draw_common(
+            cr,
+            self.common.formats[idx].as_str(),
+            &self.common.attrs[idx],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )
+    }
+}
+
+impl PanelConfig for IdleInhibit {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let (conn, _) = xcb::Connection::connect_with_extensions(
+            self.screen.as_deref(),
+            &[xcb::Extension::ScreenSaver],
+            &[],
+        )?;
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let inhibitor = Rc::new(Inhibitor {
+            conn,
+            logind: self.logind,
+            enabled: Cell::new(false),
+            proxy: OnceCell::new(),
+            fd: RefCell::new(None),
+        });
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let updates = tokio_stream::once(None)
+            .chain(UnboundedReceiverStream::new(events).map(Some));
+
+        let stream = futures::StreamExt::then(updates, move |event| {
+            let inhibitor = inhibitor.clone();
+            async move {
+                if let Some(MouseEvent {
+                    button: MouseButton::Left,
+                    ..
+                }) = event
+                {
+                    inhibitor.toggle().await?;
+                }
+                Ok(inhibitor.enabled.get())
+            }
+        })
+        .map(move |enabled: Result<bool>| self.draw(&cr, enabled?));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `screen`: the name of the X screen to use
+    ///   - type: String
+    ///   - default: None (This will tell X to choose the default screen, which
+    ///     is probably what you want.)
+    ///
+    /// - `logind`: whether to also hold a systemd-logind idle inhibitor, which
+    ///   prevents idle actions like automatic suspend
+    ///   - type: bool
+    ///   - default: false
+    ///
+    /// - `format_off`: the format string when the inhibitor is disabled
+    ///   - type: String
+    ///   - default: `IDLE: off`
+    ///
+    /// - `format_on`: the format string when the inhibitor is enabled
+    ///   - type: String
+    ///   - default: `IDLE: on`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `off_` and `on_`. Unset values in `on_` fall back
+    ///   to `off_`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = IdleInhibitBuilder::default();
+
+        builder.screen(remove_string_from_config("screen", table));
+        if let Some(logind) = remove_bool_from_config("logind", table) {
+            builder.logind(logind);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_off", "_on"],
+            &["IDLE: off", "IDLE: on"],
+            &["off_", "on_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,319 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        OnceCell,
+    },
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use zbus::{
+    fdo::{ManagedObjects, ObjectManagerProxy},
+    names::OwnedInterfaceName,
+    proxy::CacheProperties,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+    Connection,
+};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_string_from_config, remove_uint_from_config,
+    render_format, Attrs, PanelCommon, PanelConfig, PanelStream,
+};
+
+const SERVICE: &str = "net.connman.iwd";
+
+#[zbus::proxy(
+    interface = "net.connman.iwd.Station",
+    default_service = "net.connman.iwd"
+)]
+trait Station {
+    fn scan(&self) -> zbus::Result<()>;
+
+    /// Returns the visible networks, strongest first, with their signal
+    /// strength in 100 * dBm.
+    fn get_ordered_networks(&self)
+        -> zbus::Result<Vec<(OwnedObjectPath, i16)>>;
+}
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Mouse(MouseEvent),
+}
+
+#[derive(Debug)]
+struct Status {
+    device: String,
+    state: String,
+    ssid: Option<String>,
+    /// The signal strength of the connected network in dBm.
+    signal: Option<f64>,
+}
+
+type Interfaces = HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>>;
+
+fn properties<'a>(
+    interfaces: &'a Interfaces,
+    interface: &str,
+) -> Option<&'a HashMap<String, OwnedValue>> {
+    interfaces
+        .iter()
+        .find(|(name, _)| name.as_str() == interface)
+        .map(|(_, properties)| properties)
+}
+
+fn property<T: TryFrom<OwnedValue>>(
+    properties: &HashMap<String, OwnedValue>,
+    name: &str,
+) -> Option<T> {
+    T::try_from(properties.get(name)?.try_clone().ok()?).ok()
+}
+
+/// Finds the station for `device`, or the first station if `device` is
+/// `None`, returning its object path and name.
+fn find_station<'a>(
+    objects: &'a ManagedObjects,
+    device: Option<&str>,
+) -> Option<(&'a OwnedObjectPath, &'a HashMap<String, OwnedValue>, String)> {
+    let mut stations = objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            let station = properties(interfaces, "net.connman.iwd.Station")?;
+            let name = properties(interfaces, "net.connman.iwd.Device")
+                .and_then(|d| property::<String>(d, "Name"))
+                .unwrap_or_default();
+            Some((path, station, name))
+        })
+        .collect::<Vec<_>>();
+    // iterating a HashMap is unordered, so pick the first station
+    // consistently
+    stations.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+    stations
+        .into_iter()
+        .find(|(_, _, name)| device.map_or(true, |d| d == name))
+}
+
+async fn update(
+    conn: &Connection,
+    device: Option<&str>,
+    update: Update,
+) -> Result<Status> {
+    let objects = ObjectManagerProxy::builder(conn)
+        .destination(SERVICE)?
+        .path("/")?
+        .build()
+        .await?
+        .get_managed_objects()
+        .await?;
+    let (path, station, name) =
+        find_station(&objects, device).ok_or_else(|| {
+            anyhow!(
+                "No iwd station found for {}",
+                device.unwrap_or("any device")
+            )
+        })?;
+    let proxy = StationProxy::builder(conn)
+        .path(ObjectPath::from(path))?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    if let Update::Mouse(MouseEvent {
+        button: MouseButton::Left,
+        ..
+    }) = update
+    {
+        // this fails if a scan is already in progress
+        if let Err(e) = proxy.scan().await {
+            log::warn!("Failed to start iwd scan: {e}");
+        }
+    }
+
+    let state = property::<String>(station, "State").unwrap_or_default();
+    let network = property::<OwnedObjectPath>(station, "ConnectedNetwork");
+    let ssid = network
+        .as_ref()
+        .and_then(|n| objects.get(n))
+        .and_then(|n| properties(n, "net.connman.iwd.Network"))
+        .and_then(|n| property::<String>(n, "Name"));
+    let signal = match network {
+        Some(network) => proxy
+            .get_ordered_networks()
+            .await?
+            .into_iter()
+            .find(|(path, _)| *path == network)
+            .map(|(_, signal)| f64::from(signal) / 100.0),
+        None => None,
+    };
+
+    Ok(Status {
+        device: name,
+        state,
+        ssid,
+        signal,
+    })
+}
+
+/// Displays the network that an iwd station is connected to and its signal
+/// strength, for systems that use iwd without NetworkManager.
+///
+/// Left clicking the panel starts a scan for networks.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Iwd {
+    #[builder(default)]
+    device: Option<String>,
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Iwd {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        status: Status,
+    ) -> Result<PanelDrawInfo> {
+        let index = usize::from(status.ssid.is_none());
+        // a rough mapping from dBm to a percentage, as NetworkManager does
+        let percent = status
+            .signal
+            .map(|dbm| (2.0 * (dbm + 100.0)).clamp(0.0, 100.0));
+        let values = HashMap::from([
+            (
+                String::from("ssid"),
+                self.common
+                    .escape_markup
+                    .apply(status.ssid.unwrap_or_default().as_str()),
+            ),
+            (
+                String::from("signal"),
+                percent.map(|p| format!("{p:.0}")).unwrap_or_default(),
+            ),
+            (
+                String::from("dbm"),
+                status.signal.map(|s| format!("{s:.0}")).unwrap_or_default(),
+            ),
+            (
+                String::from("device"),
+                self.common.escape_markup.apply(status.device.as_str()),
+            ),
+            (String::from("state"), status.state),
+        ]);
+        let text = render_format(&self.common.formats[index], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[index],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Iwd {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let updates = IntervalStream::new(interval(self.interval))
+            .map(|_| Update::Tick)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse));
+
+        // connecting is async, so it has to happen inside the stream
+        let conn = Rc::new(OnceCell::new());
+        let device = self.device.clone();
+        let stream = futures::StreamExt::then(updates, move |u| {
+            let conn = conn.clone();
+            let device = device.clone();
+            async move {
+                let conn = conn.get_or_try_init(Connection::system).await?;
+                update(conn, device.as_deref(), u).await
+            }
+        })
+        .map(move |status| self.draw(&cr, status?));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `device`: the wireless device to show, such as `wlan0`
+    ///   - type: String
+    ///   - default: the first station iwd reports
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 10
+    ///
+    /// - `format_connected`: the format string when connected to a network
+    ///   - type: String
+    ///   - default: `WLAN: {ssid} {signal}%`
+    ///   - formatting options: `{ssid}`, `{signal}` (a percentage), `{dbm}`,
+    ///     `{device}`, `{state}`
+    ///
+    /// - `format_disconnected`: the format string when not connected
+    ///   - type: String
+    ///   - default: `WLAN: {state}`
+    ///   - formatting options: `{device}`, `{state}`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `connected_` and `disconnected_`. Unset values of
+    ///   the latter fall back to the former. The formatting options are
+    ///   available to `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = IwdBuilder::default();
+
+        builder.device(remove_string_from_config("device", table));
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_connected", "_disconnected"],
+            &["WLAN: {ssid} {signal}%", "WLAN: {state}"],
+            &["connected_", "disconnected_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use serde_json::Value as Json;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{interval, Instant},
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Entry(u8),
+    Tick,
+    Mouse(MouseEvent),
+}
+
+/// Counts journal messages at or above a given priority, either since the bar
+/// started or within a sliding window.
+///
+/// Left clicking the panel resets the count.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Journal {
+    #[builder(default = r#"String::from("warning")"#)]
+    priority: String,
+    #[builder(default = "false")]
+    user: bool,
+    #[builder(default, setter(strip_option))]
+    window: Option<Duration>,
+    #[builder(default = "1")]
+    threshold: usize,
+    #[builder(default, setter(skip))]
+    entries: VecDeque<(Instant, u8)>,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Journal {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Entry(priority) => {
+                self.entries.push_back((Instant::now(), priority));
+                self.prune();
+            }
+            Update::Tick => {
+                if !self.prune() {
+                    return None;
+                }
+            }
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                ..
+            }) => self.entries.clear(),
+            Update::Mouse(_) => return None,
+        }
+
+        Some(self.draw(cr))
+    }
+
+    /// Drops entries that have left the window, returning whether any were
+    /// removed.
+    fn prune(&mut self) -> bool {
+        let Some(window) = self.window else {
+            return false;
+        };
+        let len = self.entries.len();
+        let now = Instant::now();
+        while self
+            .entries
+            .front()
+            .is_some_and(|(time, _)| now.duration_since(*time) > window)
+        {
+            self.entries.pop_front();
+        }
+        self.entries.len() != len
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let count = self.entries.len();
+        // priorities 0 through 3 are emerg, alert, crit, and err
+        let errors = self.entries.iter().filter(|(_, p)| *p <= 3).count();
+
+        let values = HashMap::from([
+            (String::from("count"), count.to_string()),
+            (String::from("errors"), errors.to_string()),
+            (String::from("warnings"), (count - errors).to_string()),
+        ]);
+        let text = if count == 0 {
+            self.common.formats[1].clone()
+        } else {
+            render_format(&self.common.formats[0], &values)
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            if count >= self.threshold {
+                &self.common.attrs[1]
+            } else {
+                &self.common.attrs[0]
+            },
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+/// Runs `journalctl -f` on its own thread, sending the priority of each new
+/// entry.
+fn follow(
+    priority: &str,
+    user: bool,
+    send: UnboundedSender<Update>,
+) -> Result<()> {
+    let mut command = Command::new("journalctl");
+    if user {
+        command.arg("--user");
+    }
+    let mut child = command
+        .args(["--follow", "--lines=0", "--output=json", "--priority"])
+        .arg(priority)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Failed to capture journalctl output"))?;
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            let priority = serde_json::from_str::<Json>(line.as_str())
+                .ok()
+                .and_then(|entry| {
+                    entry["PRIORITY"].as_str().and_then(|p| p.parse().ok())
+                })
+                .unwrap_or(u8::MAX);
+            if send.send(Update::Entry(priority)).is_err() {
+                break;
+            }
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+        log::warn!("journalctl exited");
+    });
+
+    Ok(())
+}
+
+impl PanelConfig for Journal {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let (send, recv) = mpsc::unbounded_channel();
+        follow(self.priority.as_str(), self.user, send)?;
+
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let initial = self.draw(&cr);
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = tokio_stream::once(initial).chain(
+            UnboundedReceiverStream::new(recv)
+                .merge(
+                    IntervalStream::new(interval(Duration::from_secs(1)))
+                        .map(|_| Update::Tick),
+                )
+                .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+                .filter_map(move |u| self.update(&cr, u)),
+        );
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `priority`: the lowest priority to count, as accepted by `journalctl
+    ///   --priority`
+    ///   - type: String
+    ///   - default: `warning`
+    ///
+    /// - `user`: whether to follow the user's journal instead of the system
+    ///   journal
+    ///   - type: bool
+    ///   - default: false
+    ///
+    /// - `window`: if set, only messages from the last `window` seconds are
+    ///   counted. Otherwise, all messages since the bar started (or since the
+    ///   last reset) are counted.
+    ///   - type: u64
+    ///   - default: none
+    ///
+    /// - `threshold`: the count at which `alert_` attrs are used
+    ///   - type: u64
+    ///   - default: 1
+    ///
+    /// - `format`: the format string when any messages have been counted
+    ///   - type: String
+    ///   - default: `JOURNAL: {count}`
+    ///   - formatting options: `{count}`, `{errors}`, `{warnings}`
+    ///
+    /// - `format_none`: the format string when no messages have been counted
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `alert_`. Unset values in `alert_` fall back
+    ///   to the first instance. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = JournalBuilder::default();
+
+        if let Some(priority) = remove_string_from_config("priority", table) {
+            builder.priority(priority);
+        }
+        if let Some(user) = remove_bool_from_config("user", table) {
+            builder.user(user);
+        }
+        if let Some(window) = remove_uint_from_config("window", table) {
+            builder.window(Duration::from_secs(window));
+        }
+        if let Some(threshold) = remove_uint_from_config("threshold", table) {
+            builder.threshold(threshold as usize);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_none"],
+            &["JOURNAL: {count}", ""],
+            &["", "alert_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,270 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{sync::OnceCell, time::interval};
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+use zbus::{proxy::CacheProperties, Connection};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+#[zbus::proxy(
+    interface = "org.kde.kdeconnect.daemon",
+    default_service = "org.kde.kdeconnect",
+    default_path = "/modules/kdeconnect"
+)]
+trait Daemon {
+    fn devices(
+        &self,
+        only_reachable: bool,
+        only_paired: bool,
+    ) -> zbus::Result<Vec<String>>;
+}
+
+#[zbus::proxy(
+    interface = "org.kde.kdeconnect.device",
+    default_service = "org.kde.kdeconnect"
+)]
+trait Device {
+    #[zbus(property, name = "name")]
+    fn name(&self) -> zbus::Result<String>;
+}
+
+#[zbus::proxy(
+    interface = "org.kde.kdeconnect.device.battery",
+    default_service = "org.kde.kdeconnect"
+)]
+trait Battery {
+    #[zbus(property, name = "charge")]
+    fn charge(&self) -> zbus::Result<i32>;
+
+    #[zbus(property, name = "isCharging")]
+    fn is_charging(&self) -> zbus::Result<bool>;
+}
+
+#[zbus::proxy(
+    interface = "org.kde.kdeconnect.device.notifications",
+    default_service = "org.kde.kdeconnect"
+)]
+trait Notifications {
+    #[zbus(name = "activeNotifications")]
+    fn active_notifications(&self) -> zbus::Result<Vec<String>>;
+}
+
+#[derive(Debug)]
+struct Phone {
+    name: String,
+    charge: i32,
+    charging: bool,
+    notifications: usize,
+}
+
+/// Finds the configured device (by id or name) among the reachable, paired
+/// devices, or the first such device if none is configured.
+async fn query(
+    conn: &Connection,
+    device: Option<&str>,
+) -> Result<Option<Phone>> {
+    let daemon = DaemonProxy::builder(conn)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    let devices = match daemon.devices(true, true).await {
+        Ok(devices) => devices,
+        Err(e) => {
+            // treat the daemon being down like the phone being unreachable
+            log::warn!("Failed to list KDE Connect devices: {e}");
+            return Ok(None);
+        }
+    };
+
+    for id in devices {
+        let path = format!("/modules/kdeconnect/devices/{id}");
+        let name = DeviceProxy::builder(conn)
+            .path(path.as_str())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?
+            .name()
+            .await?;
+        if device.is_some_and(|d| d != id && d != name) {
+            continue;
+        }
+
+        // either plugin may be disabled on the phone
+        let battery = BatteryProxy::builder(conn)
+            .path(format!("{path}/battery"))?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        let notifications = NotificationsProxy::builder(conn)
+            .path(format!("{path}/notifications"))?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?
+            .active_notifications()
+            .await
+            .map_or(0, |n| n.len());
+
+        return Ok(Some(Phone {
+            name,
+            charge: battery.charge().await.unwrap_or(-1),
+            charging: battery.is_charging().await.unwrap_or(false),
+            notifications,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Displays the battery level and notification count of a phone connected
+/// with KDE Connect, hiding itself when the phone is unreachable.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct KdeConnect {
+    #[builder(default, setter(strip_option))]
+    device: Option<String>,
+    #[builder(default = "Duration::from_secs(30)")]
+    interval: Duration,
+    common: PanelCommon,
+}
+
+impl KdeConnect {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        phone: Option<Phone>,
+    ) -> Result<PanelDrawInfo> {
+        let (text, values) = match phone {
+            None => (self.common.formats[2].clone(), HashMap::new()),
+            Some(phone) => {
+                let values = HashMap::from([
+                    (
+                        String::from("name"),
+                        self.common.escape_markup.apply(phone.name.as_str()),
+                    ),
+                    (
+                        String::from("charge"),
+                        // -1 means the battery plugin has no data
+                        if phone.charge < 0 {
+                            String::from("?")
+                        } else {
+                            phone.charge.to_string()
+                        },
+                    ),
+                    (
+                        String::from("notifications"),
+                        phone.notifications.to_string(),
+                    ),
+                ]);
+                (
+                    render_format(
+                        &self.common.formats[usize::from(phone.charging)],
+                        &values,
+                    ),
+                    values,
+                )
+            }
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for KdeConnect {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let conn = Rc::new(OnceCell::new());
+        let device = self.device.clone();
+        let stream = futures::StreamExt::then(
+            IntervalStream::new(interval(self.interval)),
+            move |_| {
+                let conn = conn.clone();
+                let device = device.clone();
+                async move {
+                    let conn =
+                        conn.get_or_try_init(Connection::session).await?;
+                    query(conn, device.as_deref()).await
+                }
+            },
+        )
+        .map(move |phone| self.draw(&cr, phone?));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `device`: the id or name of the device to display
+    ///   - type: String
+    ///   - default: the first reachable, paired device
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 30
+    ///
+    /// - `format`: the format string when the phone isn't charging
+    ///   - type: String
+    ///   - default: `PHONE: {charge}%`
+    ///   - formatting options: `{name}`, `{charge}`, `{notifications}`
+    ///
+    /// - `format_charging`: the format string when the phone is charging
+    ///   - type: String
+    ///   - default: `PHONE: {charge}%+`
+    ///   - formatting options: `{name}`, `{charge}`, `{notifications}`
+    ///
+    /// - `format_unreachable`: the format string when the phone is unreachable
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = KdeConnectBuilder::default();
+
+        if let Some(device) = remove_string_from_config("device", table) {
+            builder.device(device);
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_charging", "_unreachable"],
+            &["PHONE: {charge}%", "PHONE: {charge}%+", ""],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::PathBuf,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::FutureExt;
+use nix::sys::inotify::{self, AddWatchFlags, InitFlags};
+use serde_yaml::Value as Yaml;
+use tokio::task::{self, JoinHandle};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config, render_format,
+    Attrs, PanelCommon, PanelConfig, PanelStream,
+};
+
+struct KubeStream {
+    i: Arc<inotify::Inotify>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl KubeStream {
+    fn new(i: inotify::Inotify) -> Self {
+        Self {
+            i: Arc::new(i),
+            handle: None,
+        }
+    }
+}
+
+impl Stream for KubeStream {
+    type Item = ();
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(handle) = &mut self.handle {
+            let value = handle.poll_unpin(cx).map(|_| Some(()));
+            if value.is_ready() {
+                self.handle = None;
+            }
+            value
+        } else {
+            let i = self.i.clone();
+            let waker = cx.waker().clone();
+            self.handle = Some(task::spawn_blocking(move || loop {
+                let result = i.read_events();
+                if result.is_ok() {
+                    waker.wake();
+                    break;
+                }
+            }));
+            Poll::Pending
+        }
+    }
+}
+
+/// Displays the current kubectl context and namespace.
+///
+/// The kubeconfig is watched with inotify, so changes made by `kubectl config
+/// use-context` (or tools like kubectx) show up immediately.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Kubernetes {
+    path: PathBuf,
+    #[builder(default)]
+    context_attrs: HashMap<String, Attrs>,
+    common: PanelCommon,
+}
+
+impl Kubernetes {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let config: Yaml =
+            serde_yaml::from_str(fs::read_to_string(&self.path)?.as_str())?;
+
+        let Some(current) =
+            config["current-context"].as_str().filter(|c| !c.is_empty())
+        else {
+            return draw_common(
+                cr,
+                self.common.formats[1].as_str(),
+                &self.common.attrs[0],
+                self.common.dependence,
+                &self.common.ellipsize,
+            );
+        };
+
+        let context = config["contexts"]
+            .as_sequence()
+            .and_then(|contexts| {
+                contexts
+                    .iter()
+                    .find(|c| c["name"].as_str() == Some(current))
+            })
+            .map(|c| &c["context"])
+            .ok_or_else(|| anyhow!("Context {current} not found"))?;
+
+        let values = HashMap::from([
+            (
+                String::from("context"),
+                self.common.escape_markup.apply(current),
+            ),
+            (
+                String::from("namespace"),
+                self.common
+                    .escape_markup
+                    .apply(context["namespace"].as_str().unwrap_or("default")),
+            ),
+            (
+                String::from("cluster"),
+                self.common
+                    .escape_markup
+                    .apply(context["cluster"].as_str().unwrap_or_default()),
+            ),
+            (
+                String::from("user"),
+                self.common
+                    .escape_markup
+                    .apply(context["user"].as_str().unwrap_or_default()),
+            ),
+        ]);
+        let text = render_format(&self.common.formats[0], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            self.context_attrs
+                .get(current)
+                .unwrap_or(&self.common.attrs[0]),
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Kubernetes {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        // kubectl replaces the file rather than writing to it, so watch the
+        // directory instead
+        let inotify = inotify::Inotify::init(InitFlags::empty())?;
+        inotify.add_watch(
+            self.path
+                .parent()
+                .ok_or_else(|| anyhow!("Invalid kubeconfig path"))?,
+            AddWatchFlags::IN_CLOSE_WRITE
+                | AddWatchFlags::IN_MOVED_TO
+                | AddWatchFlags::IN_CREATE,
+        )?;
+
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+        let default = self.common.attrs[0].clone();
+        for attr in self.context_attrs.values_mut() {
+            attr.apply_to(&default);
+        }
+
+        let stream = tokio_stream::once(())
+            .chain(KubeStream::new(inotify))
+            .map(move |()| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `path`: the kubeconfig file to read
+    ///   - type: String
+    ///   - default: the first entry in `$KUBECONFIG`, or `~/.kube/config`
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `{context}:{namespace}`
+    ///   - formatting options: `{context}`, `{namespace}`, `{cluster}`,
+    ///     `{user}`
+    ///
+    /// - `format_none`: the format string when no context is selected
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - `context_attrs`: a table mapping context names to [`Attrs`], used in
+    ///   place of the panel's attrs when that context is active. Unset values
+    ///   fall back to the panel's attrs. For example, setting `fg = "#f00"` in
+    ///   `[panels.kube.context_attrs.production]` shows the production context
+    ///   in red.
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = KubernetesBuilder::default();
+
+        builder.path(
+            remove_string_from_config("path", table)
+                .or_else(|| {
+                    env::var("KUBECONFIG").ok().and_then(|k| {
+                        k.split(':')
+                            .find(|p| !p.is_empty())
+                            .map(ToOwned::to_owned)
+                    })
+                })
+                .map_or_else(
+                    || {
+                        PathBuf::from(format!(
+                            "{}/.kube/config",
+                            env::var("HOME").unwrap()
+                        ))
+                    },
+                    PathBuf::from,
+                ),
+        );
+        if let Some(contexts) = table.remove("context_attrs") {
+            match contexts.clone().into_table() {
+                Ok(contexts) => {
+                    builder.context_attrs(
+                        contexts
+                            .into_iter()
+                            .filter_map(|(name, attrs)| {
+                                match attrs.clone().into_table() {
+                                    Ok(mut attrs) => Some((
+                                        name,
+                                        Attrs::parse(&mut attrs, ""),
+                                    )),
+                                    Err(_) => {
+                                        log::warn!(
+                                            "Ignoring non-table value \
+                                             {attrs:?}"
+                                        );
+                                        None
+                                    }
+                                }
+                            })
+                            .collect(),
+                    );
+                }
+                Err(_) => {
+                    log::warn!("Ignoring non-table value {contexts:?}");
+                }
+            }
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_none"],
+            &["{context}:{namespace}", ""],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
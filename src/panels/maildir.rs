@@ -0,0 +1,202 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::FutureExt;
+use nix::sys::inotify::{self, AddWatchFlags, InitFlags};
+use tokio::task::{self, JoinHandle};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config, render_format,
+    Attrs, PanelCommon, PanelConfig, PanelStream,
+};
+
+struct MaildirStream {
+    i: Arc<inotify::Inotify>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MaildirStream {
+    fn new(i: inotify::Inotify) -> Self {
+        Self {
+            i: Arc::new(i),
+            handle: None,
+        }
+    }
+}
+
+impl Stream for MaildirStream {
+    type Item = ();
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(handle) = &mut self.handle {
+            let value = handle.poll_unpin(cx).map(|_| Some(()));
+            if value.is_ready() {
+                self.handle = None;
+            }
+            value
+        } else {
+            let i = self.i.clone();
+            let waker = cx.waker().clone();
+            self.handle = Some(task::spawn_blocking(move || loop {
+                let result = i.read_events();
+                if result.is_ok() {
+                    waker.wake();
+                    break;
+                }
+            }));
+            Poll::Pending
+        }
+    }
+}
+
+/// Displays the number of unread messages in one or more local Maildirs.
+///
+/// Uses inotify to watch each Maildir's `new/` directory, so no polling or
+/// network access is necessary.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Maildir {
+    paths: Vec<PathBuf>,
+    common: PanelCommon,
+}
+
+impl Maildir {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let unread = self
+            .paths
+            .iter()
+            .map(|p| count_messages(p.join("new").as_path()))
+            .sum::<usize>();
+
+        let text = if unread == 0 {
+            self.common.formats[1].clone()
+        } else {
+            render_format(
+                &self.common.formats[0],
+                &HashMap::from([(String::from("unread"), unread.to_string())]),
+            )
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self
+                .common
+                .states
+                .apply(unread as f64, &self.common.attrs[0]),
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.set_value("unread", unread);
+        if let Some(state) = self.common.states.find(unread as f64) {
+            draw_info.set_value("state", &state.name);
+        }
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Maildir {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let inotify = inotify::Inotify::init(InitFlags::empty())?;
+        let watch_flags = AddWatchFlags::IN_CREATE
+            | AddWatchFlags::IN_DELETE
+            | AddWatchFlags::IN_MOVED_FROM
+            | AddWatchFlags::IN_MOVED_TO;
+        for path in &self.paths {
+            inotify.add_watch(path.join("new").as_path(), watch_flags)?;
+        }
+
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = tokio_stream::once(())
+            .chain(MaildirStream::new(inotify))
+            .map(move |()| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `path`: the Maildir to watch (the directory containing `cur/`, `new/`,
+    ///   and `tmp/`). Multiple paths may be separated by `:`, in which case the
+    ///   unread counts are summed.
+    ///   - type: String
+    ///   - default: `$HOME/Maildir`
+    ///
+    /// - `format`: the format string when there is unread mail
+    ///   - type: String
+    ///   - default: `MAIL: {unread}`
+    ///   - formatting options: `{unread}`
+    ///
+    /// - `format_none`: the format string when there is no unread mail
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. The value `unread` is available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = MaildirBuilder::default();
+
+        builder.paths(
+            remove_string_from_config("path", table)
+                .unwrap_or_else(|| {
+                    format!("{}/Maildir", std::env::var("HOME").unwrap())
+                })
+                .split(':')
+                .map(PathBuf::from)
+                .collect(),
+        );
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_none"],
+            &["MAIL: {unread}", ""],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
+
+fn count_messages(path: &Path) -> usize {
+    fs::read_dir(path).map_or_else(
+        |e| {
+            log::warn!("Failed to read maildir {path:?}: {e}");
+            0
+        },
+        |entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+                .count()
+        },
+    )
+}
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, rc::Rc, thread, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use serde_json::Value as Json;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+/// Only asks for what's needed to get unread counts, which keeps sync
+/// responses small.
+const FILTER: &str = r#"{"presence":{"types":[]},"account_data":{"types":[]},"room":{"timeline":{"limit":1},"state":{"types":[]},"ephemeral":{"types":[]},"account_data":{"types":[]}}}"#;
+
+/// How long the server may hold a sync request open, in milliseconds.
+const SYNC_TIMEOUT: u64 = 30_000;
+
+/// Unread counts summed across all joined rooms.
+#[derive(Debug, Default)]
+struct Unread {
+    notifications: u64,
+    highlights: u64,
+    rooms: usize,
+}
+
+#[derive(Debug)]
+struct Client {
+    homeserver: String,
+    access_token: String,
+    agent: ureq::Agent,
+    since: Option<String>,
+    /// Notification and highlight counts for each joined room.
+    rooms: HashMap<String, (u64, u64)>,
+}
+
+impl Client {
+    /// Runs one sync request and applies the rooms that changed.
+    fn sync(&mut self) -> Result<Unread> {
+        let mut request = self
+            .agent
+            .get(format!("{}/_matrix/client/v3/sync", self.homeserver))
+            .header(
+                "Authorization",
+                format!("Bearer {}", self.access_token).as_str(),
+            )
+            .query("filter", FILTER);
+        // the initial sync should return immediately
+        if let Some(since) = &self.since {
+            request = request
+                .query("since", since.as_str())
+                .query("timeout", SYNC_TIMEOUT.to_string());
+        }
+        let response: Json = request.call()?.body_mut().read_json()?;
+
+        let rooms = &response["rooms"];
+        if let Some(joined) = rooms["join"].as_object() {
+            for (id, room) in joined {
+                let counts = &room["unread_notifications"];
+                self.rooms.insert(
+                    id.clone(),
+                    (
+                        counts["notification_count"].as_u64().unwrap_or(0),
+                        counts["highlight_count"].as_u64().unwrap_or(0),
+                    ),
+                );
+            }
+        }
+        if let Some(left) = rooms["leave"].as_object() {
+            for id in left.keys() {
+                self.rooms.remove(id);
+            }
+        }
+        self.since = Some(
+            response["next_batch"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Matrix sync response has no batch"))?
+                .to_owned(),
+        );
+
+        Ok(Unread {
+            notifications: self.rooms.values().map(|(n, _)| n).sum(),
+            highlights: self.rooms.values().map(|(_, h)| h).sum(),
+            rooms: self.rooms.values().filter(|(n, _)| *n > 0).count(),
+        })
+    }
+}
+
+/// Syncs in a loop, backing off exponentially after errors.
+fn run(
+    mut client: Client,
+    max_backoff: Duration,
+    send: UnboundedSender<Result<Unread>>,
+) {
+    thread::spawn(move || {
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            let result = client.sync();
+            let failed = result.is_err();
+            if send.send(result).is_err() {
+                break;
+            }
+            if failed {
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(max_backoff);
+            } else {
+                backoff = Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+/// Displays unread message and highlight counts across all joined Matrix
+/// rooms.
+///
+/// Counts come from the homeserver and follow the account's push rules, so
+/// muted rooms aren't counted. The panel keeps a long-polling sync request
+/// open, so changes show up immediately.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Matrix {
+    homeserver: String,
+    access_token: String,
+    #[builder(default = "Duration::from_secs(300)")]
+    max_backoff: Duration,
+    common: PanelCommon,
+}
+
+impl Matrix {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        unread: Unread,
+    ) -> Result<PanelDrawInfo> {
+        let values = HashMap::from([
+            (String::from("unread"), unread.notifications.to_string()),
+            (String::from("highlights"), unread.highlights.to_string()),
+            (String::from("rooms"), unread.rooms.to_string()),
+        ]);
+        let text = render_format(
+            &self.common.formats[usize::from(unread.notifications == 0)],
+            &values,
+        );
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[usize::from(unread.highlights > 0)],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Matrix {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let client = Client {
+            homeserver: self.homeserver.clone(),
+            access_token: self.access_token.clone(),
+            agent: ureq::Agent::config_builder()
+                .timeout_global(Some(
+                    Duration::from_millis(SYNC_TIMEOUT)
+                        + Duration::from_secs(30),
+                ))
+                .build()
+                .new_agent(),
+            since: None,
+            rooms: HashMap::new(),
+        };
+        let (send, recv) = mpsc::unbounded_channel();
+        run(client, self.max_backoff, send);
+
+        let stream = UnboundedReceiverStream::new(recv)
+            .map(move |unread| self.draw(&cr, unread?));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `homeserver`: the base URL of the homeserver, e.g. `https://matrix.org`
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `access_token`: an access token for the account. In Element, one can
+    ///   be found under Settings > Help & About > Advanced.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `max_backoff`: the longest time in seconds to wait before retrying
+    ///   after a failed sync. The wait starts at one second and doubles after
+    ///   each consecutive failure.
+    ///   - type: u64
+    ///   - default: 300
+    ///
+    /// - `format`: the format string when there are unread messages
+    ///   - type: String
+    ///   - default: `MTX: {unread}`
+    ///   - formatting options: `{unread}`, `{highlights}`, `{rooms}` (the
+    ///     number of rooms with unread messages)
+    ///
+    /// - `format_idle`: the format string when there are no unread messages
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///   - formatting options: same as `format`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `highlight_`. The latter is used when there
+    ///   are highlights, and its unset values fall back to the first instance.
+    ///   The formatting options are available to `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = MatrixBuilder::default();
+
+        builder.homeserver(
+            remove_string_from_config("homeserver", table)
+                .ok_or_else(|| anyhow!("Matrix panel requires `homeserver`"))?
+                .trim_end_matches('/')
+                .to_owned(),
+        );
+        builder.access_token(
+            remove_string_from_config("access_token", table).ok_or_else(
+                || anyhow!("Matrix panel requires `access_token`"),
+            )?,
+        );
+        if let Some(max_backoff) = remove_uint_from_config("max_backoff", table)
+        {
+            builder.max_backoff(Duration::from_secs(max_backoff.max(1)));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_idle"],
+            &["MTX: {unread}", ""],
+            &["", "highlight_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -10,7 +10,8 @@ use tokio_stream::{wrappers::IntervalStream, StreamExt};
 
 use crate::{
     bar::PanelDrawInfo, draw_common, remove_string_from_config,
-    remove_uint_from_config, Attrs, PanelCommon, PanelConfig, PanelStream,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
 };
 
 lazy_static! {
@@ -77,85 +78,85 @@ impl Memory {
         let percentage_swap_used =
             (swap_used as f64 / swap_total as f64 * 100.0) as u64;
 
-        let text = self.common.formats[0]
-            .replace(
-                "%gb_used%",
-                format!("{:.2}", (mem_used as f64 / 1024.0 / 1024.0)).as_str(),
-            )
-            .replace(
-                "%gb_free%",
-                format!("{:.2}", (mem_free as f64 / 1024.0 / 1024.0)).as_str(),
-            )
-            .replace(
-                "%gb_total%",
-                format!("{:.2}", (mem_total as f64 / 1024.0 / 1024.0)).as_str(),
-            )
-            .replace(
-                "%mb_used%",
-                ((mem_used as f64 / 1024.0) as u64).to_string().as_str(),
-            )
-            .replace(
-                "%mb_free%",
-                ((mem_free as f64 / 1024.0) as u64).to_string().as_str(),
-            )
-            .replace(
-                "%mb_total%",
-                ((mem_total as f64 / 1024.0) as u64).to_string().as_str(),
-            )
-            .replace("%percentage_used%", percentage_used.to_string().as_str())
-            .replace(
-                "%percentage_free%",
-                (100 - percentage_used).to_string().as_str(),
-            )
-            .replace(
-                "%gb_swap_used%",
-                format!("{:.2}", ((swap_used as f64 / 1024.0 / 1024.0) as u64))
-                    .as_str(),
-            )
-            .replace(
-                "%gb_swap_free%",
-                format!("{:.2}", ((swap_free as f64 / 1024.0 / 1024.0) as u64))
-                    .as_str(),
-            )
-            .replace(
-                "%gb_swap_total%",
+        let values = [
+            (
+                "gb_used",
+                format!("{:.2}", (mem_used as f64 / 1024.0 / 1024.0)),
+            ),
+            (
+                "gb_free",
+                format!("{:.2}", (mem_free as f64 / 1024.0 / 1024.0)),
+            ),
+            (
+                "gb_total",
+                format!("{:.2}", (mem_total as f64 / 1024.0 / 1024.0)),
+            ),
+            ("mb_used", ((mem_used as f64 / 1024.0) as u64).to_string()),
+            ("mb_free", ((mem_free as f64 / 1024.0) as u64).to_string()),
+            ("mb_total", ((mem_total as f64 / 1024.0) as u64).to_string()),
+            ("percentage_used", percentage_used.to_string()),
+            ("percentage_free", (100 - percentage_used).to_string()),
+            (
+                "gb_swap_used",
+                format!("{:.2}", ((swap_used as f64 / 1024.0 / 1024.0) as u64)),
+            ),
+            (
+                "gb_swap_free",
+                format!("{:.2}", ((swap_free as f64 / 1024.0 / 1024.0) as u64)),
+            ),
+            (
+                "gb_swap_total",
                 format!(
                     "{:.2}",
                     ((swap_total as f64 / 1024.0 / 1024.0) as u64)
-                )
-                .as_str(),
-            )
-            .replace(
-                "%mb_swap_used%",
-                ((swap_used as f64 / 1024.0) as u64).to_string().as_str(),
-            )
-            .replace(
-                "%mb_swap_free%",
-                ((swap_free as f64 / 1024.0) as u64).to_string().as_str(),
-            )
-            .replace(
-                "%mb_swap_total%",
-                ((swap_total as f64 / 1024.0) as u64).to_string().as_str(),
-            )
-            .replace(
-                "%percentage_swap_used%",
-                percentage_swap_used.to_string().as_str(),
-            )
-            .replace(
-                "%percentage_swap_free%",
-                (100 - percentage_swap_used).to_string().as_str(),
-            );
-
-        draw_common(
+                ),
+            ),
+            (
+                "mb_swap_used",
+                ((swap_used as f64 / 1024.0) as u64).to_string(),
+            ),
+            (
+                "mb_swap_free",
+                ((swap_free as f64 / 1024.0) as u64).to_string(),
+            ),
+            (
+                "mb_swap_total",
+                ((swap_total as f64 / 1024.0) as u64).to_string(),
+            ),
+            ("used", (mem_used * 1024).to_string()),
+            ("free", (mem_free * 1024).to_string()),
+            ("total", (mem_total * 1024).to_string()),
+            ("swap_used", (swap_used * 1024).to_string()),
+            ("swap_free", (swap_free * 1024).to_string()),
+            ("swap_total", (swap_total * 1024).to_string()),
+            ("percentage_swap_used", percentage_swap_used.to_string()),
+            (
+                "percentage_swap_free",
+                (100 - percentage_swap_used).to_string(),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value))
+        .collect::<HashMap<_, _>>();
+        let text = render_format(&self.common.formats[0], &values);
+
+        let mut draw_info = draw_common(
             cr,
             text.as_str(),
             &self.common.attrs[0],
             self.common.dependence,
-        )
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Memory {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
@@ -176,11 +177,14 @@ impl PanelConfig for Memory {
     ///
     /// - `format`: the format string
     ///   - type: String
-    ///   - default: `RAM: %percentage_used%`
-    ///   - formatting options: `%{gb,mb}_[swap_]{total,used,free}%,
-    ///     %percentage_[swap_]{used,free}%` (where exactly one comma-separated
-    ///     value must be selected from each set of curly braces and the values
-    ///     in square brackets are optional)
+    ///   - default: `RAM: {percentage_used}%`
+    ///   - formatting options: `{gb_used}`, `{mb_swap_free}`, and so on:
+    ///     `gb` or `mb`, optionally `swap`, and `total`, `used`, or `free`.
+    ///     Also `{percentage_used}`, `{percentage_free}`,
+    ///     `{percentage_swap_used}`, and `{percentage_swap_free}`. `{used}`,
+    ///     `{free}`, `{total}`, `{swap_used}`, `{swap_free}`, and
+    ///     `{swap_total}` are in bytes, for use with `#bytes`, e.g.
+    ///     `{used#bytes:.1}`. See [`render_format`].
     /// - `interval`: how long to wait in seconds between each check
     ///   - type: u64
     ///   - default: 10
@@ -189,7 +193,8 @@ impl PanelConfig for Memory {
     ///   - default: `/proc/meminfo` - If you're considering changing this, you
     ///     might want to use a different panel like
     ///     [`Inotify`][crate::panels::Inotify]
-    /// - See [`PanelCommon::parse`].
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
     fn parse(
         table: &mut HashMap<String, config::Value>,
         _global: &Config,
@@ -205,7 +210,7 @@ impl PanelConfig for Memory {
         builder.common(PanelCommon::parse(
             table,
             &[""],
-            &["RAM: %percentage_used%%"],
+            &["RAM: {percentage_used}%"],
             &[""],
         )?);
 
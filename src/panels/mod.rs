@@ -1,32 +1,124 @@
 mod battery;
+mod bspwm;
+mod calendar;
+mod charge_threshold;
+mod ci;
 mod clock;
+mod containers;
 mod cpu;
+mod crypto;
+mod cups;
 mod custom;
+mod dbus_property;
+mod dnd;
+mod dpms;
+mod dunst;
+mod entropy;
+mod expand;
+mod failed_units;
 mod fanotify;
+mod file_value;
+mod group;
+mod herbstluftwm;
+mod http;
+mod i3mode;
+mod i3workspaces;
+mod idle_inhibit;
 mod inotify;
+mod iwd;
+mod journal;
+mod kdeconnect;
+mod kubernetes;
+mod maildir;
+mod matrix;
 mod memory;
 mod mpd;
+mod mqtt;
 mod network;
+mod nut;
 mod ping;
+mod pomodoro;
+mod power_draw;
+mod power_profile;
+mod privacy;
 mod pulseaudio;
+mod redshift;
+mod screen_capture;
 mod separator;
+mod spacer;
+mod stocks;
+mod storage_pool;
+mod syncthing;
 mod temp;
+mod timesync;
+mod todotxt;
+mod top;
+mod transmission;
+mod updates;
+mod weechat;
+mod xmonad;
 mod xwindow;
 mod xworkspaces;
 
 pub use battery::Battery;
+pub use bspwm::Bspwm;
+pub use calendar::Calendar;
+pub use charge_threshold::ChargeThreshold;
+pub use ci::Ci;
 pub use clock::{precision, Clock};
+pub use containers::Containers;
 pub use cpu::Cpu;
+pub use crypto::Crypto;
+pub use cups::Cups;
 pub use custom::Custom;
+pub use dbus_property::DbusProperty;
+pub use dnd::Dnd;
+pub use dpms::Dpms;
+pub use dunst::Dunst;
+pub use entropy::Entropy;
+pub use expand::Expand;
+pub use failed_units::FailedUnits;
 pub use fanotify::Fanotify;
+pub use file_value::FileValue;
+pub use group::Group;
+pub use herbstluftwm::Herbstluftwm;
+pub use http::Http;
+pub use i3mode::I3Mode;
+pub use i3workspaces::I3Workspaces;
+pub use idle_inhibit::IdleInhibit;
 pub use inotify::Inotify;
+pub use iwd::Iwd;
+pub use journal::Journal;
+pub use kdeconnect::KdeConnect;
+pub use kubernetes::Kubernetes;
+pub use maildir::Maildir;
+pub use matrix::Matrix;
 pub use memory::Memory;
 pub use mpd::Mpd;
+pub use mqtt::Mqtt;
 pub use network::Network;
+pub use nut::Nut;
 pub use ping::Ping;
+pub use pomodoro::Pomodoro;
+pub use power_draw::PowerDraw;
+pub use power_profile::PowerProfile;
+pub use privacy::Privacy;
 pub use pulseaudio::Pulseaudio;
+pub use redshift::Redshift;
+pub use screen_capture::ScreenCapture;
 pub use separator::Separator;
+pub use spacer::Spacer;
+pub use stocks::Stocks;
+pub use storage_pool::StoragePool;
+pub use syncthing::Syncthing;
 pub use temp::Temp;
+pub use timesync::Timesync;
+pub use todotxt::TodoTxt;
+pub use top::Top;
+pub use transmission::Transmission;
+pub use updates::Updates;
+pub use weechat::Weechat;
+pub use xmonad::XMonad;
 pub use xwindow::XWindow;
 pub use xworkspaces::XWorkspaces;
 
@@ -34,18 +126,65 @@ pub use xworkspaces::XWorkspaces;
 pub mod builders {
     pub use super::{
         battery::{BatteryBuilder, BatteryBuilderError},
+        bspwm::{BspwmBuilder, BspwmBuilderError},
+        calendar::{CalendarBuilder, CalendarBuilderError},
+        charge_threshold::{
+            ChargeThresholdBuilder, ChargeThresholdBuilderError,
+        },
+        ci::{CiBuilder, CiBuilderError},
         clock::{ClockBuilder, ClockBuilderError},
+        containers::{ContainersBuilder, ContainersBuilderError},
         cpu::{CpuBuilder, CpuBuilderError},
+        crypto::{CryptoBuilder, CryptoBuilderError},
+        cups::{CupsBuilder, CupsBuilderError},
         custom::{CustomBuilder, CustomBuilderError},
+        dbus_property::{DbusPropertyBuilder, DbusPropertyBuilderError},
+        dnd::{DndBuilder, DndBuilderError},
+        dpms::{DpmsBuilder, DpmsBuilderError},
+        dunst::{DunstBuilder, DunstBuilderError},
+        entropy::{EntropyBuilder, EntropyBuilderError},
+        expand::{ExpandBuilder, ExpandBuilderError},
+        failed_units::{FailedUnitsBuilder, FailedUnitsBuilderError},
         fanotify::{FanotifyBuilder, FanotifyBuilderError},
+        file_value::{FileValueBuilder, FileValueBuilderError},
+        herbstluftwm::{HerbstluftwmBuilder, HerbstluftwmBuilderError},
+        http::{HttpBuilder, HttpBuilderError},
+        i3mode::{I3ModeBuilder, I3ModeBuilderError},
+        i3workspaces::{I3WorkspacesBuilder, I3WorkspacesBuilderError},
+        idle_inhibit::{IdleInhibitBuilder, IdleInhibitBuilderError},
         inotify::{InotifyBuilder, InotifyBuilderError},
+        iwd::{IwdBuilder, IwdBuilderError},
+        journal::{JournalBuilder, JournalBuilderError},
+        kdeconnect::{KdeConnectBuilder, KdeConnectBuilderError},
+        kubernetes::{KubernetesBuilder, KubernetesBuilderError},
+        maildir::{MaildirBuilder, MaildirBuilderError},
+        matrix::{MatrixBuilder, MatrixBuilderError},
         memory::{MemoryBuilder, MemoryBuilderError},
         mpd::{MpdBuilder, MpdBuilderError},
+        mqtt::{MqttBuilder, MqttBuilderError},
         network::{NetworkBuilder, NetworkBuilderError},
+        nut::{NutBuilder, NutBuilderError},
         ping::{PingBuilder, PingBuilderError},
+        pomodoro::{PomodoroBuilder, PomodoroBuilderError},
+        power_draw::{PowerDrawBuilder, PowerDrawBuilderError},
+        power_profile::{PowerProfileBuilder, PowerProfileBuilderError},
+        privacy::{PrivacyBuilder, PrivacyBuilderError},
         pulseaudio::{PulseaudioBuilder, PulseaudioBuilderError},
+        redshift::{RedshiftBuilder, RedshiftBuilderError},
+        screen_capture::{ScreenCaptureBuilder, ScreenCaptureBuilderError},
         separator::{SeparatorBuilder, SeparatorBuilderError},
+        spacer::{SpacerBuilder, SpacerBuilderError},
+        stocks::{StocksBuilder, StocksBuilderError},
+        storage_pool::{StoragePoolBuilder, StoragePoolBuilderError},
+        syncthing::{SyncthingBuilder, SyncthingBuilderError},
         temp::{TempBuilder, TempBuilderError},
+        timesync::{TimesyncBuilder, TimesyncBuilderError},
+        todotxt::{TodoTxtBuilder, TodoTxtBuilderError},
+        top::{TopBuilder, TopBuilderError},
+        transmission::{TransmissionBuilder, TransmissionBuilderError},
+        updates::{UpdatesBuilder, UpdatesBuilderError},
+        weechat::{WeechatBuilder, WeechatBuilderError},
+        xmonad::{XMonadBuilder, XMonadBuilderError},
         xwindow::{XWindowBuilder, XWindowBuilderError},
         xworkspaces::{XWorkspacesBuilder, XWorkspacesBuilderError},
     };
@@ -12,7 +12,7 @@ use anyhow::Result;
 use config::Config;
 use csscolorparser::Color;
 use derive_builder::Builder;
-use mpd::{Client, Idle, State, Subsystem};
+use mpd::{Client, Idle, Song, State, Subsystem};
 use pango::EllipsizeMode;
 use pangocairo::functions::{create_layout, show_layout};
 use tokio::{
@@ -24,7 +24,8 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     bar::PanelDrawInfo, remove_bool_from_config, remove_color_from_config,
-    remove_string_from_config, remove_uint_from_config, Attrs, PanelCommon,
+    remove_float_from_config, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, Icon, PanelCommon,
     PanelConfig, PanelStream,
 };
 
@@ -42,16 +43,18 @@ enum EventType {
     Progress,
 }
 
+fn connect(address: &str) -> Result<Arc<Mutex<Client>>> {
+    Ok(Arc::new(Mutex::new(Client::connect(address)?)))
+}
+
 /// Displays information about music currently playing through
 /// [MPD](https://musicpd.org)
 #[derive(Builder, Debug)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
 pub struct Mpd {
-    conn: Arc<Mutex<Client>>,
-    noidle_conn: Arc<Mutex<Client>>,
-    #[builder(setter(strip_option))]
-    highlight_conn: Option<Arc<Mutex<Client>>>,
+    #[builder(default = r#"String::from("127.0.0.1:6600")"#)]
+    address: String,
     #[builder(default = "false")]
     progress_bar: bool,
     #[builder(default = "Strategy::Truncate")]
@@ -68,52 +71,88 @@ pub struct Mpd {
     // is ellipsize
     #[builder(default = "0")]
     max_width: usize,
+    #[builder(default = "false")]
+    album_art: bool,
+    #[builder(default = "4.0")]
+    album_art_spacing: f64,
+    // the file of the current song and its album art, if it has any
+    #[builder(default, setter(skip))]
+    art: Option<(String, Option<Icon>)>,
     common: PanelCommon,
 }
 
 impl Mpd {
+    /// Fetches the album art of a song from MPD, unless it's the song whose
+    /// art was fetched last.
+    fn update_art(
+        &mut self,
+        conn: &Mutex<Client>,
+        song: Option<&Song>,
+        height: i32,
+    ) -> Option<Icon> {
+        let song = song?;
+        if self.art.as_ref().map(|(file, _)| file) != Some(&song.file) {
+            let art = conn
+                .lock()
+                .unwrap()
+                .albumart(song)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| Icon::from_bytes(&data, f64::from(height)))
+                .map_err(|e| {
+                    log::info!("No album art for {}: {e}", song.file);
+                })
+                .ok();
+            self.art = Some((song.file.clone(), art));
+        }
+        self.art.as_ref().and_then(|(_, art)| art.clone())
+    }
+
     fn draw(
         &mut self,
+        conn: &Mutex<Client>,
         cr: &Rc<cairo::Context>,
         height: i32,
         event: EventType,
     ) -> Result<PanelDrawInfo> {
-        let conn = self.noidle_conn.clone();
         let status = conn.lock().unwrap().status()?;
         let song = conn.lock().unwrap().currentsong()?;
-        let mut text = self.common.formats[0]
-            .replace(
-                "%title%",
+        let values = HashMap::from([
+            (
+                String::from("title"),
                 match song {
                     None => String::from("Unknown"),
                     Some(ref s) => match &s.title {
                         None => String::from("Unknown"),
                         Some(t) => match self.strategy {
                             Strategy::Scroll { interval: _ } => t.to_string(),
-                            _ => {
-                                glib::markup_escape_text(t.as_str()).to_string()
-                            }
+                            _ => self
+                                .common
+                                .escape_markup
+                                .apply(t.as_str())
+                                .to_string(),
                         },
                     },
-                }
-                .as_str(),
-            )
-            .replace(
-                "%artist%",
+                },
+            ),
+            (
+                String::from("artist"),
                 match song {
                     None => String::from("Unknown"),
                     Some(ref s) => match &s.artist {
                         None => String::from("Unknown"),
                         Some(t) => match self.strategy {
                             Strategy::Scroll { interval: _ } => t.to_string(),
-                            _ => {
-                                glib::markup_escape_text(t.as_str()).to_string()
-                            }
+                            _ => self
+                                .common
+                                .escape_markup
+                                .apply(t.as_str())
+                                .to_string(),
                         },
                     },
-                }
-                .as_str(),
-            );
+                },
+            ),
+        ]);
+        let mut text = render_format(&self.common.formats[0], &values);
 
         let layout = create_layout(cr);
 
@@ -211,12 +250,25 @@ impl Mpd {
         let bar_width = self.last_progress_width;
         let attrs = self.common.attrs[0].clone();
         let progress_bg = self.progress_bg.clone();
+        let art = if self.album_art {
+            self.update_art(conn, song.as_ref(), height)
+        } else {
+            None
+        };
+        let offset = art
+            .as_ref()
+            .map_or(0.0, |art| art.width() + self.album_art_spacing);
 
-        Ok(PanelDrawInfo::new(
-            (size.0, height),
+        let mut draw_info = PanelDrawInfo::new(
+            ((offset + f64::from(size.0)).ceil() as i32, height),
             self.common.dependence,
             Box::new(move |cr| {
+                if let Some(art) = &art {
+                    art.draw(cr, 0.0, 0.0)?;
+                }
+
                 cr.save()?;
+                cr.translate(offset, 0.0);
                 cr.set_source_rgba(
                     progress_bg.r,
                     progress_bg.g,
@@ -233,23 +285,30 @@ impl Mpd {
 
                 cr.translate(0.0, f64::from(height - size.1) / 2.0);
 
-                attrs.apply_fg(cr);
+                attrs.apply_fg(cr, f64::from(size.0), f64::from(size.1));
                 show_layout(cr, &layout);
 
                 cr.restore()?;
                 Ok(())
             }),
-        ))
+        );
+        draw_info.values = values;
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Mpd {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
         global_attrs: Attrs,
         height: i32,
     ) -> Result<PanelStream> {
+        let noidle_conn = connect(self.address.as_str())?;
         let mut map = StreamMap::<
             EventType,
             Pin<Box<dyn Stream<Item = Result<()>>>>,
@@ -257,7 +316,7 @@ impl PanelConfig for Mpd {
         map.insert(
             EventType::Player,
             Box::pin(tokio_stream::once(Ok(())).chain(MpdStream {
-                conn: self.conn.clone(),
+                conn: connect(self.address.as_str())?,
                 handle: None,
             })),
         );
@@ -269,8 +328,8 @@ impl PanelConfig for Mpd {
                     song_length: None,
                     song_elapsed: None,
                     max_width: self.max_width,
-                    conn: self.highlight_conn.clone().unwrap(),
-                    noidle_conn: self.noidle_conn.clone(),
+                    conn: connect(self.address.as_str())?,
+                    noidle_conn: noidle_conn.clone(),
                     handle: None,
                     stale: Arc::new(Mutex::new(true)),
                     playing: true,
@@ -288,7 +347,7 @@ impl PanelConfig for Mpd {
         }
         Ok(Box::pin(map.map(move |(t, r)| {
             r?;
-            self.draw(&cr, height, t)
+            self.draw(&noidle_conn, &cr, height, t)
         })))
     }
 
@@ -300,9 +359,9 @@ impl PanelConfig for Mpd {
     ///   - default: `127.0.0.1:6600`
     /// - `format`: the format string to display on the panel
     ///   - type: String
-    ///   - formatting options: `%title%`, `%artist%`
+    ///   - formatting options: `{title}`, `{artist}`
     ///     - markup is disabled when `strategy == scroll`
-    ///   - default: `%title% - %artist%`
+    ///   - default: `{title} - {artist}`
     /// - `progress_bar`: whether to show a progress bar behind the text
     ///   - type: bool
     ///   - default: `false`
@@ -327,31 +386,30 @@ impl PanelConfig for Mpd {
     ///   [`pango::EllipsizeMode`] for details)
     ///   - type: String - one of `start`, `middle`, `end`, or `none`
     ///   - default: end
-    /// - See [`PanelCommon::parse`].
+    /// - `album_art`: whether to show the album art of the current song to the
+    ///   left of the text, scaled to the height of the bar. MPD reads the art
+    ///   from a file like `cover.jpg` in the song's directory.
+    ///   - type: bool
+    ///   - default: `false`
+    /// - `album_art_spacing`: the space in pixels between the album art and the
+    ///   text
+    ///   - type: f64
+    ///   - default: `4`
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
     fn parse(
         table: &mut HashMap<String, config::Value>,
         _global: &Config,
     ) -> Result<Self> {
         let mut builder = MpdBuilder::default();
 
-        let mut final_address = String::from("127.0.0.1:6600");
         if let Some(address) = remove_string_from_config("address", table) {
-            final_address = address;
+            builder.address(address);
         }
-
-        builder.conn(Arc::new(Mutex::new(Client::connect(
-            final_address.as_str(),
-        )?)));
-        builder.noidle_conn(Arc::new(Mutex::new(Client::connect(
-            final_address.as_str(),
-        )?)));
         if let Some(progress_bar) =
             remove_bool_from_config("progress_bar", table)
         {
             builder.progress_bar(progress_bar);
-            builder.highlight_conn(Arc::new(Mutex::new(Client::connect(
-                final_address.as_str(),
-            )?)));
         }
 
         if let Some(strategy) = remove_string_from_config("strategy", table) {
@@ -388,10 +446,18 @@ impl PanelConfig for Mpd {
         if let Some(max_width) = remove_uint_from_config("max_width", table) {
             builder.max_width(max_width as usize);
         }
+        if let Some(album_art) = remove_bool_from_config("album_art", table) {
+            builder.album_art(album_art);
+        }
+        if let Some(spacing) =
+            remove_float_from_config("album_art_spacing", table)
+        {
+            builder.album_art_spacing(spacing);
+        }
         builder.common(PanelCommon::parse(
             table,
             &[""],
-            &["%title% - %artist%"],
+            &["{title} - {artist}"],
             &[""],
         )?);
 
@@ -0,0 +1,474 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::TcpStream,
+    process,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use serde_json::Value as Json;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config, render_format,
+    Attrs, PanelCommon, PanelConfig, PanelStream,
+};
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+const PINGREQ: u8 = 0xc0;
+const PINGRESP: u8 = 0xd0;
+const DISCONNECT: u8 = 0xe0;
+
+/// How often the broker expects to hear from the client.
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+
+/// How long to wait for a packet before checking whether the panel has been
+/// dropped.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The longest time to wait before reconnecting.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The number of MQTT panels that have been parsed, which keeps their default
+/// client identifiers unique.
+static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+struct Message {
+    topic: String,
+    payload: Vec<u8>,
+}
+
+fn push_string(buf: &mut Vec<u8>, s: &str) -> Result<()> {
+    let len = u16::try_from(s.len())
+        .map_err(|_| anyhow!("MQTT string is too long: {s}"))?;
+    buf.extend(len.to_be_bytes());
+    buf.extend(s.as_bytes());
+    Ok(())
+}
+
+/// Builds a packet from its first byte and the rest of its contents.
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![kind];
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+/// Reads a packet, returning its first byte and the rest of its contents, or
+/// `None` if nothing arrived before the read timeout.
+fn read_packet(stream: &mut TcpStream) -> Result<Option<(u8, Vec<u8>)>> {
+    let mut kind = [0];
+    match stream.read(&mut kind) {
+        Ok(0) => return Err(anyhow!("MQTT broker closed the connection")),
+        Ok(_) => {}
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            return Ok(None)
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let mut len = 0;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0];
+        stream.read_exact(&mut byte)?;
+        len |= usize::from(byte[0] & 0x7f) << shift;
+        shift += 7;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        if shift > 21 {
+            return Err(anyhow!("Invalid MQTT packet length"));
+        }
+    }
+
+    let mut body = vec![0; len];
+    stream.read_exact(body.as_mut_slice())?;
+    Ok(Some((kind[0], body)))
+}
+
+/// A connection to a broker, which is closed cleanly when dropped.
+struct Connection(TcpStream);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // the broker may already be gone, in which case there's nothing to
+        // close
+        let _ = self.0.write_all(packet(DISCONNECT, &[]).as_slice());
+    }
+}
+
+/// A minimal MQTT 3.1.1 client that subscribes to one topic at QoS 0.
+#[derive(Debug, Clone)]
+struct Client {
+    address: String,
+    topic: String,
+    client_id: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl Client {
+    fn connect(&self) -> Result<Connection> {
+        let stream = TcpStream::connect(self.address.as_str())?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let mut connection = Connection(stream);
+        let stream = &mut connection.0;
+
+        let mut flags = 0x02; // clean session
+        let mut body = Vec::new();
+        push_string(&mut body, "MQTT")?;
+        body.push(4); // protocol level
+        if self.username.is_some() {
+            flags |= 0x80;
+        }
+        if self.password.is_some() {
+            flags |= 0x40;
+        }
+        body.push(flags);
+        body.extend((KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+        push_string(&mut body, self.client_id.as_str())?;
+        if let Some(username) = &self.username {
+            push_string(&mut body, username.as_str())?;
+        }
+        if let Some(password) = &self.password {
+            push_string(&mut body, password.as_str())?;
+        }
+        stream.write_all(packet(CONNECT, body.as_slice()).as_slice())?;
+
+        // the broker has until the keep alive period to acknowledge
+        let start = Instant::now();
+        let mut received = read_packet(stream)?;
+        while received.is_none() && start.elapsed() < KEEP_ALIVE / 2 {
+            received = read_packet(stream)?;
+        }
+        match received {
+            Some((CONNACK, body)) if body.get(1) == Some(&0) => {}
+            Some((CONNACK, body)) => {
+                return Err(anyhow!(
+                    "MQTT broker refused the connection with code {}",
+                    body.get(1).copied().unwrap_or_default()
+                ))
+            }
+            _ => {
+                return Err(anyhow!(
+                    "MQTT broker didn't acknowledge connection"
+                ))
+            }
+        }
+
+        let mut body = Vec::new();
+        body.extend(1u16.to_be_bytes()); // packet id
+        push_string(&mut body, self.topic.as_str())?;
+        body.push(0); // QoS
+        stream.write_all(packet(SUBSCRIBE, body.as_slice()).as_slice())?;
+
+        Ok(connection)
+    }
+
+    /// Receives messages until the connection fails or the panel is dropped,
+    /// which disconnects `stop`.
+    fn receive(
+        &self,
+        mut connection: Connection,
+        send: &UnboundedSender<Result<Message>>,
+        stop: &Receiver<()>,
+    ) -> Result<()> {
+        let stream = &mut connection.0;
+        let mut last_ping = Instant::now();
+        let mut waiting = false;
+        loop {
+            if matches!(stop.try_recv(), Err(TryRecvError::Disconnected)) {
+                return Ok(());
+            }
+
+            let received = read_packet(stream)?;
+            if matches!(received, Some((PINGRESP, _))) {
+                waiting = false;
+            }
+
+            // the broker only counts packets from the client toward the keep
+            // alive period, so ping even while messages are arriving
+            if last_ping.elapsed() >= KEEP_ALIVE / 2 {
+                if waiting {
+                    return Err(anyhow!("MQTT broker stopped responding"));
+                }
+                stream.write_all(packet(PINGREQ, &[]).as_slice())?;
+                last_ping = Instant::now();
+                waiting = true;
+            }
+
+            let Some((kind, body)) = received else {
+                continue;
+            };
+            match kind & 0xf0 {
+                PUBLISH => {
+                    let len = usize::from(u16::from_be_bytes(
+                        body.get(..2)
+                            .ok_or_else(|| anyhow!("Invalid MQTT publish"))?
+                            .try_into()?,
+                    ));
+                    let topic = body
+                        .get(2..2 + len)
+                        .ok_or_else(|| anyhow!("Invalid MQTT publish"))?;
+                    // QoS 1 and 2 messages have a packet id, but the broker
+                    // shouldn't send them since the subscription is QoS 0
+                    let start =
+                        if kind & 0x06 == 0 { 2 + len } else { 4 + len };
+                    let message = Message {
+                        topic: String::from_utf8_lossy(topic).into_owned(),
+                        payload: body.get(start..).unwrap_or_default().to_vec(),
+                    };
+                    if send.send(Ok(message)).is_err() {
+                        return Ok(());
+                    }
+                }
+                SUBACK if body.last() == Some(&0x80) => {
+                    return Err(anyhow!(
+                        "MQTT broker rejected subscription to {}",
+                        self.topic
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Connects and receives messages in a loop, backing off exponentially
+    /// after errors. This stops once the returned [`Sender`] is dropped.
+    fn run(self, send: UnboundedSender<Result<Message>>) -> Sender<()> {
+        let (stop_send, stop) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let mut backoff = Duration::from_secs(1);
+            loop {
+                let result = self.connect().and_then(|connection| {
+                    backoff = Duration::from_secs(1);
+                    self.receive(connection, &send, &stop)
+                });
+                match result {
+                    Ok(()) => break,
+                    Err(e) => {
+                        if send.send(Err(e)).is_err() {
+                            break;
+                        }
+                    }
+                }
+                if !matches!(
+                    stop.recv_timeout(backoff),
+                    Err(RecvTimeoutError::Timeout)
+                ) {
+                    break;
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+        stop_send
+    }
+}
+
+/// Looks up a value using a simple JSONPath like `$.sensors[0].temperature`.
+/// The leading `$` is optional.
+fn json_path<'a>(json: &'a Json, path: &str) -> Option<&'a Json> {
+    let path = path.strip_prefix('$').unwrap_or(path);
+    path.split('.')
+        .filter(|s| !s.is_empty())
+        .try_fold(json, |json, segment| {
+            let (key, indices) = segment
+                .split_once('[')
+                .map_or((segment, ""), |(k, i)| (k, i));
+            let mut json = if key.is_empty() { json } else { json.get(key)? };
+            for index in indices.split('[') {
+                let index = index.trim_end_matches(']');
+                if !index.is_empty() {
+                    json = json.get(index.parse::<usize>().ok()?)?;
+                }
+            }
+            Some(json)
+        })
+}
+
+/// Displays the last message published to an MQTT topic, e.g. a sensor
+/// reading from a home automation system.
+///
+/// Only unencrypted connections are supported. Messages are received at
+/// QoS 0, and the panel reconnects automatically if the connection drops.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Mqtt {
+    client: Client,
+    #[builder(default)]
+    json_path: Option<String>,
+    common: PanelCommon,
+}
+
+impl Mqtt {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        message: Message,
+    ) -> Result<PanelDrawInfo> {
+        let payload = String::from_utf8_lossy(message.payload.as_slice());
+        let value = match &self.json_path {
+            Some(path) => {
+                let json = serde_json::from_str::<Json>(payload.as_ref())?;
+                match json_path(&json, path.as_str()) {
+                    Some(Json::String(s)) => s.clone(),
+                    Some(value) => value.to_string(),
+                    None => {
+                        return Err(anyhow!(
+                            "{path} not found in MQTT message on {}",
+                            message.topic
+                        ))
+                    }
+                }
+            }
+            None => payload.trim().to_owned(),
+        };
+
+        let values = HashMap::from([
+            (
+                String::from("value"),
+                self.common.escape_markup.apply(value.as_str()),
+            ),
+            (
+                String::from("topic"),
+                self.common.escape_markup.apply(message.topic.as_str()),
+            ),
+        ]);
+        let text = render_format(&self.common.formats[0], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Mqtt {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let (send, recv) = mpsc::unbounded_channel();
+        // the client disconnects once this is dropped along with the stream
+        let stop = self.client.clone().run(send);
+
+        let stream = UnboundedReceiverStream::new(recv).map(move |message| {
+            let _ = &stop;
+            self.draw(&cr, message?)
+        });
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `address`: the address of the broker
+    ///   - type: String
+    ///   - default: `localhost:1883`
+    ///
+    /// - `topic`: the topic to subscribe to. Wildcards are allowed, in which
+    ///   case the panel shows whichever matching message arrived last.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `username`: the username to log in with
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `password`: the password to log in with
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `client_id`: the client identifier, which must be unique per broker
+    ///   - type: String
+    ///   - default: `lazybar-<pid>-<n>`, where `n` counts the MQTT panels in
+    ///     this process
+    ///
+    /// - `json_path`: if set, the message is parsed as JSON and this path is
+    ///   extracted, e.g. `$.temperature` or `$.sensors[0].value`
+    ///   - type: String
+    ///   - default: none (the whole message is shown)
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `{value}`
+    ///   - formatting options: `{value}`, `{topic}`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = MqttBuilder::default();
+
+        builder.client(Client {
+            address: remove_string_from_config("address", table)
+                .unwrap_or_else(|| String::from("localhost:1883")),
+            topic: remove_string_from_config("topic", table)
+                .ok_or_else(|| anyhow!("MQTT panel requires `topic`"))?,
+            client_id: remove_string_from_config("client_id", table)
+                .unwrap_or_else(|| {
+                    format!(
+                        "lazybar-{}-{}",
+                        process::id(),
+                        INSTANCES.fetch_add(1, Ordering::Relaxed)
+                    )
+                }),
+            username: remove_string_from_config("username", table),
+            password: remove_string_from_config("password", table),
+        });
+        builder.json_path(remove_string_from_config("json_path", table));
+        builder.common(PanelCommon::parse(table, &[""], &["{value}"], &[""])?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -18,8 +18,9 @@ use tokio::time::interval;
 use tokio_stream::{wrappers::IntervalStream, StreamExt};
 
 use crate::{
-    bar::PanelDrawInfo, draw_common, remove_string_from_config,
-    remove_uint_from_config, Attrs, PanelCommon, PanelConfig, PanelStream,
+    bar::PanelDrawInfo, draw_common, draw_graph, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, History, PanelCommon,
+    PanelConfig, PanelStream, Style,
 };
 
 #[repr(C)]
@@ -110,6 +111,19 @@ fn query_ip(if_name: &str) -> Option<IpAddr> {
     query_ipv4(if_name).or_else(|| query_ipv6(if_name))
 }
 
+fn query_bytes(if_name: &str) -> Option<(u64, u64)> {
+    let read = |file| {
+        std::fs::read_to_string(format!(
+            "/sys/class/net/{if_name}/statistics/{file}"
+        ))
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+    };
+    Some((read("rx_bytes")?, read("tx_bytes")?))
+}
+
 /// Displays information about the current network connection on a given
 /// interface.
 #[derive(Builder, Debug)]
@@ -120,42 +134,85 @@ pub struct Network {
     if_name: String,
     #[builder(default = r#"Duration::from_secs(10)"#)]
     duration: Duration,
+    #[builder(default)]
+    style: Style,
+    #[builder(default)]
+    history: History,
+    #[builder(default)]
+    last_bytes: Option<(u64, u64)>,
     common: PanelCommon,
 }
 
 impl Network {
-    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
-        let essid = glib::markup_escape_text(
+    fn draw(&mut self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let essid = self.common.escape_markup.apply(
             query_essid(self.if_name.as_str())
                 .unwrap_or_default()
                 .as_str(),
         );
         let ip = query_ip(self.if_name.as_str());
 
-        let text = ip.map_or_else(
-            || {
-                self.common.formats[1]
-                    .replace("%ifname%", self.if_name.as_str())
-                    .replace("%essid%", essid.as_str())
-            },
-            |ip| {
-                self.common.formats[0]
-                    .replace("%ifname%", self.if_name.as_str())
-                    .replace("%essid%", essid.as_str())
-                    .replace("%local_ip%", ip.to_string().as_str())
-            },
+        let mut values = HashMap::from([
+            (String::from("ifname"), self.if_name.clone()),
+            (String::from("essid"), essid),
+        ]);
+        if let Some(ip) = ip {
+            values.insert(String::from("local_ip"), ip.to_string());
+        }
+
+        // bytes per second received and sent since the last poll
+        let bytes = query_bytes(self.if_name.as_str());
+        let rate = if let (Some((rx, tx)), Some((last_rx, last_tx))) =
+            (bytes, self.last_bytes)
+        {
+            let secs = self.duration.as_secs_f64().max(1.0);
+            let rx = rx.saturating_sub(last_rx) as f64 / secs;
+            let tx = tx.saturating_sub(last_tx) as f64 / secs;
+            values.insert(String::from("rx"), rx.to_string());
+            values.insert(String::from("tx"), tx.to_string());
+            Some(rx + tx)
+        } else {
+            None
+        };
+        self.last_bytes = bytes;
+        let text = render_format(
+            &self.common.formats[usize::from(ip.is_none())],
+            &values,
         );
 
-        draw_common(
-            cr,
-            text.as_str(),
-            &self.common.attrs[0],
-            self.common.dependence,
-        )
+        let mut draw_info = match &self.style {
+            Style::Text | Style::Bar(_) | Style::Gauge(_) => draw_common(
+                cr,
+                text.as_str(),
+                &self.common.attrs[0],
+                self.common.dependence,
+                &self.common.ellipsize,
+            )?,
+            Style::Graph(graph) => {
+                if let Some(rate) = rate {
+                    self.history.push(rate);
+                }
+                draw_graph(
+                    cr,
+                    &self.history,
+                    graph,
+                    None,
+                    text.as_str(),
+                    &self.common.attrs[0],
+                    self.common.dependence,
+                )?
+            }
+        };
+        draw_info.values.extend(values);
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Network {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
@@ -181,18 +238,29 @@ impl PanelConfig for Network {
     /// - `format_connected`: the format string when there is a connection
     ///   present on the interface
     ///   - type: String
-    ///   - default: "%ifname% %essid% %local_ip%"
+    ///   - default: "{ifname} {essid} {local_ip}"
+    ///   - formatting options: `{ifname}`, `{essid}`, `{local_ip}`, `{rx}`,
+    ///     `{tx}`
     ///
     /// - `format_disconnected`: the format string when there is no connection
     ///   present on the interface
     ///   - type: String
-    ///   - default: "%ifname% disconnected"
+    ///   - default: "{ifname} disconnected"
+    ///   - formatting options: `{ifname}`, `{essid}`
+    ///
+    /// - `{rx}` and `{tx}` are the bytes per second received and sent since the
+    ///   last poll. They're numbers, so use `#bytes` to make them readable,
+    ///   e.g. `{rx#bytes}/s`. See [`render_format`].
     ///
     /// - `interval`: the amount of time in seconds to wait between polls
     ///   - type: u64
     ///   - default: 10
     ///
-    /// - See [`PanelCommon::parse`].
+    /// - The throughput of the interface in bytes per second can be drawn as a
+    ///   graph. See [`Style::parse`] for parsing options.
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
     fn parse(
         table: &mut HashMap<String, Value>,
         _global: &Config,
@@ -204,11 +272,16 @@ impl PanelConfig for Network {
         if let Some(duration) = remove_uint_from_config("interval", table) {
             builder.duration(Duration::from_secs(duration));
         }
+        let style = Style::parse(table);
+        if let Style::Graph(graph) = &style {
+            builder.history(graph.history());
+        }
+        builder.style(style);
 
         builder.common(PanelCommon::parse(
             table,
             &["_connected", "_disconnected"],
-            &["%ifname% %essid% %local_ip%", "%ifname% disconnected"],
+            &["{ifname} {essid} {local_ip}", "{ifname} disconnected"],
             &[""],
         )?);
 
@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+/// Displays the state of a UPS monitored by Network UPS Tools.
+///
+/// Variables are read from `upsd` over its network protocol, so the NUT
+/// client tools don't need to be installed.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Nut {
+    #[builder(default = r#"String::from("localhost:3493")"#)]
+    address: String,
+    #[builder(default = r#"String::from("ups")"#)]
+    ups: String,
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    common: PanelCommon,
+}
+
+impl Nut {
+    /// Fetches all variables for the UPS with `LIST VAR`.
+    fn vars(&self) -> Result<HashMap<String, String>> {
+        let mut stream = TcpStream::connect(self.address.as_str())?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        write!(stream, "LIST VAR {}\nLOGOUT\n", self.ups)?;
+
+        let mut vars = HashMap::new();
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if let Some(e) = line.strip_prefix("ERR ") {
+                return Err(anyhow!("upsd returned an error: {e}"));
+            }
+            if line.starts_with("END LIST VAR") {
+                break;
+            }
+            // VAR <ups> <name> "<value>"
+            if let Some((name, value)) = line
+                .strip_prefix("VAR ")
+                .and_then(|l| l.split_once(' '))
+                .and_then(|(_, l)| l.split_once(' '))
+            {
+                vars.insert(
+                    name.to_owned(),
+                    value
+                        .trim_matches('"')
+                        .replace("\\\"", "\"")
+                        .replace("\\\\", "\\"),
+                );
+            }
+        }
+
+        Ok(vars)
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let vars = self.vars()?;
+        let var = |name: &str| vars.get(name).map_or("", String::as_str);
+
+        // e.g. `OL CHRG` or `OB LB`
+        let status = var("ups.status");
+        let on_battery = status.split_whitespace().any(|s| s == "OB");
+        let idx = usize::from(on_battery);
+
+        let runtime = var("battery.runtime")
+            .parse::<u64>()
+            .map(|s| format!("{}:{:02}", s / 60, s % 60))
+            .unwrap_or_default();
+        let values = HashMap::from([
+            (String::from("charge"), var("battery.charge").to_owned()),
+            (String::from("load"), var("ups.load").to_owned()),
+            (String::from("runtime"), runtime),
+            (
+                String::from("status"),
+                self.common.escape_markup.apply(status),
+            ),
+        ]);
+        let text = render_format(&self.common.formats[idx], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[idx],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Nut {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `address`: the address of `upsd`
+    ///   - type: String
+    ///   - default: `localhost:3493`
+    ///
+    /// - `ups`: the name of the UPS, as configured in `ups.conf`
+    ///   - type: String
+    ///   - default: `ups`
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 10
+    ///
+    /// - `format`: the format string when the UPS is on line power
+    ///   - type: String
+    ///   - default: `UPS: {charge}%`
+    ///   - formatting options: `{charge}`, `{load}`, `{runtime}`, `{status}`
+    ///
+    /// - `format_battery`: the format string when the UPS is on battery
+    ///   - type: String
+    ///   - default: `UPS: {charge}% ({runtime})`
+    ///   - formatting options: `{charge}`, `{load}`, `{runtime}`, `{status}`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `alarm_`. The latter is used when the UPS is
+    ///   on battery, and its unset values fall back to the first instance.
+    ///   The formatting options are available to `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = NutBuilder::default();
+
+        if let Some(address) = remove_string_from_config("address", table) {
+            builder.address(address);
+        }
+        if let Some(ups) = remove_string_from_config("ups", table) {
+            builder.ups(ups);
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_battery"],
+            &["UPS: {charge}%", "UPS: {charge}% ({runtime})"],
+            &["", "alarm_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
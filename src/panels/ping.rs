@@ -19,9 +19,9 @@ use tokio::{
 use tokio_stream::{Stream, StreamExt};
 
 use crate::{
-    bar::PanelDrawInfo, draw_common, remove_string_from_config,
-    remove_uint_from_config, Attrs, PanelCommon, PanelConfig, PanelStream,
-    Ramp,
+    bar::PanelDrawInfo, draw_common, draw_graph, draw_progress_bar,
+    remove_string_from_config, remove_uint_from_config, render_format, Attrs,
+    History, PanelCommon, PanelConfig, PanelStream, Ramp, Style,
 };
 
 /// Displays the ping to a given address
@@ -42,48 +42,87 @@ pub struct Ping {
     ramp: Option<Ramp>,
     #[builder(default, setter(strip_option))]
     max_ping: Option<u32>,
+    #[builder(default)]
+    style: Style,
+    #[builder(default)]
+    history: History,
     common: PanelCommon,
 }
 
 impl Ping {
     fn draw(
-        &self,
+        &mut self,
         cr: &Rc<cairo::Context>,
         ping: Result<u128>,
     ) -> Result<PanelDrawInfo> {
-        let text = ping.map_or_else(
-            |_| self.common.formats[1].clone(),
-            |ping| {
-                self.common.formats[0]
-                    .replace("%ping%", ping.to_string().as_str())
-                    .replace(
-                        "%ramp%",
-                        self.ramp
-                            .as_ref()
-                            .map_or_else(String::new, |r| {
-                                r.choose::<u32>(
-                                    ping as u32,
-                                    0,
-                                    self.max_ping
-                                        .unwrap_or(2000)
-                                        .clamp(0, 2000),
-                                )
-                            })
-                            .as_str(),
-                    )
+        let max_ping = self.max_ping.unwrap_or(2000).clamp(0, 2000);
+        let values = ping.as_ref().map_or_else(
+            |_| HashMap::new(),
+            |&ping| {
+                HashMap::from([
+                    (String::from("ping"), ping.to_string()),
+                    (
+                        String::from("ramp"),
+                        self.ramp.as_ref().map_or_else(String::new, |r| {
+                            r.choose::<u32>(ping as u32, 0, max_ping)
+                        }),
+                    ),
+                ])
             },
         );
+        let text = render_format(
+            &self.common.formats[usize::from(ping.is_err())],
+            &values,
+        );
 
-        draw_common(
-            cr,
-            text.as_str(),
-            &self.common.attrs[0],
-            self.common.dependence,
-        )
+        let attrs = match (&self.ramp, &ping) {
+            (Some(r), Ok(ping)) => {
+                r.attrs::<u32>(*ping as u32, 0, max_ping, &self.common.attrs[0])
+            }
+            _ => self.common.attrs[0].clone(),
+        };
+        let max = f64::from(max_ping);
+        let mut draw_info = match &self.style {
+            Style::Text | Style::Gauge(_) => draw_common(
+                cr,
+                text.as_str(),
+                &attrs,
+                self.common.dependence,
+                &self.common.ellipsize,
+            )?,
+            Style::Bar(bar) => draw_progress_bar(
+                cr,
+                ping.map_or(0.0, |ping| ping as f64 / max),
+                bar,
+                text.as_str(),
+                &attrs,
+                self.common.dependence,
+            )?,
+            Style::Graph(graph) => {
+                if let Ok(ping) = ping {
+                    self.history.push(ping as f64);
+                }
+                draw_graph(
+                    cr,
+                    &self.history,
+                    graph,
+                    self.max_ping.map(|_| max),
+                    text.as_str(),
+                    &attrs,
+                    self.common.dependence,
+                )?
+            }
+        };
+        draw_info.values.extend(values);
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Ping {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
@@ -125,18 +164,22 @@ impl PanelConfig for Ping {
     ///   - default 5
     /// - `format_connected`: the format string
     ///   - type: String
-    ///   - formatting options: `%ping%`, `%ramp%`
-    ///   - default: `%ping%ms`
+    ///   - formatting options: `{ping}`, `{ramp}`
+    ///   - default: `{ping}ms`
     /// - `format_disconnected`: the format string when all pings fail
     ///   - type: String
     ///   - default: `disconnected`
     /// - `ramp`: the ramp to display based on the ping time. See
     ///   [`Ramp::parse`] for parsing details.
-    /// - `max_ping`: the value to use as the maximum for the ramp. Ignored if
-    ///   `ramp` is unset. Clamped to [0, 2000].
+    /// - `max_ping`: the value to use as the maximum for the ramp and the
+    ///   progress bar. Also used as the top of the graph if it's set. Clamped
+    ///   to [0, 2000].
     ///   - type: u64
     ///   - default: 2000
-    /// - See [`PanelCommon::parse`].
+    /// - The ping time can be drawn as a progress bar or a graph. See
+    ///   [`Style::parse`] for parsing options.
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
     fn parse(
         table: &mut HashMap<String, config::Value>,
         global: &Config,
@@ -162,11 +205,16 @@ impl PanelConfig for Ping {
         if let Some(max_ping) = remove_uint_from_config("max_ping", table) {
             builder.max_ping(max_ping as u32);
         }
+        let style = Style::parse(table);
+        if let Style::Graph(graph) = &style {
+            builder.history(graph.history());
+        }
+        builder.style(style);
 
         builder.common(PanelCommon::parse(
             table,
             &["_connected", "_disconnected"],
-            &["%ping%ms", "disconnected"],
+            &["{ping}ms", "disconnected"],
             &[""],
         )?);
 
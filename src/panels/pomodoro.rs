@@ -0,0 +1,292 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{interval, Instant},
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_uint_from_config, render_format, Attrs, PanelCommon,
+    PanelConfig, PanelStream,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Work,
+    Break,
+    LongBreak,
+}
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Mouse(MouseEvent),
+}
+
+/// A pomodoro timer that alternates between work and break phases.
+///
+/// Left click starts or pauses the timer, and right click resets it.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Pomodoro {
+    #[builder(default = "Duration::from_secs(25 * 60)")]
+    work: Duration,
+    #[builder(default = "Duration::from_secs(5 * 60)")]
+    short_break: Duration,
+    #[builder(default = "Duration::from_secs(15 * 60)")]
+    long_break: Duration,
+    #[builder(default = "4")]
+    long_break_after: u64,
+    #[builder(default = "Phase::Idle", setter(skip))]
+    phase: Phase,
+    #[builder(default = "Duration::ZERO", setter(skip))]
+    remaining: Duration,
+    #[builder(default, setter(skip))]
+    deadline: Option<Instant>,
+    #[builder(default = "0", setter(skip))]
+    completed: u64,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Pomodoro {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Tick => {
+                let deadline = self.deadline?;
+                let now = Instant::now();
+                if now >= deadline {
+                    self.advance(now);
+                } else {
+                    self.remaining = deadline - now;
+                }
+            }
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                ..
+            }) => match (self.phase, self.deadline) {
+                (Phase::Idle, _) => {
+                    self.phase = Phase::Work;
+                    self.deadline = Some(Instant::now() + self.work);
+                    self.remaining = self.work;
+                }
+                (_, Some(deadline)) => {
+                    self.remaining = deadline - Instant::now();
+                    self.deadline = None;
+                }
+                (_, None) => {
+                    self.deadline = Some(Instant::now() + self.remaining);
+                }
+            },
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Right,
+                ..
+            }) => {
+                self.phase = Phase::Idle;
+                self.deadline = None;
+                self.remaining = Duration::ZERO;
+                self.completed = 0;
+            }
+            Update::Mouse(_) => return None,
+        }
+
+        Some(self.draw(cr))
+    }
+
+    fn advance(&mut self, now: Instant) {
+        let next = match self.phase {
+            Phase::Work => {
+                self.completed += 1;
+                if self.long_break_after > 0
+                    && self.completed % self.long_break_after == 0
+                {
+                    Phase::LongBreak
+                } else {
+                    Phase::Break
+                }
+            }
+            Phase::Break | Phase::LongBreak | Phase::Idle => Phase::Work,
+        };
+        self.remaining = match next {
+            Phase::Work => self.work,
+            Phase::Break => self.short_break,
+            Phase::LongBreak => self.long_break,
+            Phase::Idle => Duration::ZERO,
+        };
+        self.phase = next;
+        self.deadline = Some(now + self.remaining);
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let (format, attrs) = match (self.phase, self.deadline) {
+            (Phase::Idle, _) => {
+                (&self.common.formats[0], &self.common.attrs[0])
+            }
+            (Phase::Work, Some(_)) => {
+                (&self.common.formats[1], &self.common.attrs[1])
+            }
+            (Phase::Break | Phase::LongBreak, Some(_)) => {
+                (&self.common.formats[2], &self.common.attrs[2])
+            }
+            (Phase::Work, None) => {
+                (&self.common.formats[3], &self.common.attrs[1])
+            }
+            (Phase::Break | Phase::LongBreak, None) => {
+                (&self.common.formats[3], &self.common.attrs[2])
+            }
+        };
+
+        // round up so that the timer doesn't show 0:00 for a full second
+        let secs = self.remaining.as_secs()
+            + u64::from(self.remaining.subsec_nanos() > 0);
+        let values = HashMap::from([
+            (
+                String::from("remaining"),
+                format!("{:02}:{:02}", secs / 60, secs % 60),
+            ),
+            (String::from("completed"), self.completed.to_string()),
+        ]);
+        let text = render_format(format, &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            attrs,
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Pomodoro {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let initial = self.draw(&cr);
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = tokio_stream::once(initial).chain(
+            IntervalStream::new(interval(Duration::from_secs(1)))
+                .map(|_| Update::Tick)
+                .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+                .filter_map(move |u| self.update(&cr, u)),
+        );
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `work`: the length of a work phase in minutes
+    ///   - type: u64
+    ///   - default: 25
+    ///
+    /// - `short_break`: the length of a short break in minutes
+    ///   - type: u64
+    ///   - default: 5
+    ///
+    /// - `long_break`: the length of a long break in minutes
+    ///   - type: u64
+    ///   - default: 15
+    ///
+    /// - `long_break_after`: how many work phases to complete before taking a
+    ///   long break instead of a short one (0 disables long breaks)
+    ///   - type: u64
+    ///   - default: 4
+    ///
+    /// - `format_idle`: the format string when the timer isn't running
+    ///   - type: String
+    ///   - default: `POMODORO`
+    ///   - formatting options: `{completed}`
+    ///
+    /// - `format_work`: the format string during a work phase
+    ///   - type: String
+    ///   - default: `WORK {remaining}`
+    ///   - formatting options: `{remaining}`, `{completed}`
+    ///
+    /// - `format_break`: the format string during a break
+    ///   - type: String
+    ///   - default: `BREAK {remaining}`
+    ///   - formatting options: `{remaining}`, `{completed}`
+    ///
+    /// - `format_paused`: the format string when the timer is paused
+    ///   - type: String
+    ///   - default: `PAUSED {remaining}`
+    ///   - formatting options: `{remaining}`, `{completed}`
+    ///
+    /// - See [`PanelCommon::parse`]. Three instances of [`Attrs`] are parsed
+    ///   using the prefixes `idle_`, `work_`, and `break_`. When paused, the
+    ///   attrs of the current phase are used. The formatting options are
+    ///   available to `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = PomodoroBuilder::default();
+
+        if let Some(work) = remove_uint_from_config("work", table) {
+            builder.work(Duration::from_secs(work * 60));
+        }
+        if let Some(short_break) = remove_uint_from_config("short_break", table)
+        {
+            builder.short_break(Duration::from_secs(short_break * 60));
+        }
+        if let Some(long_break) = remove_uint_from_config("long_break", table) {
+            builder.long_break(Duration::from_secs(long_break * 60));
+        }
+        if let Some(long_break_after) =
+            remove_uint_from_config("long_break_after", table)
+        {
+            builder.long_break_after(long_break_after);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_idle", "_work", "_break", "_paused"],
+            &[
+                "POMODORO",
+                "WORK {remaining}",
+                "BREAK {remaining}",
+                "PAUSED {remaining}",
+            ],
+            &["idle_", "work_", "break_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
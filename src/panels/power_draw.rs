@@ -0,0 +1,200 @@
+use std::{collections::HashMap, path::PathBuf, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::{interval_at, Instant};
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, read_number, remove_float_from_config,
+    remove_string_from_config, remove_uint_from_config, render_format, Attrs,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+/// Where power readings come from.
+#[derive(Debug, Clone)]
+enum Source {
+    /// A RAPL zone in `/sys/class/powercap`, which reports cumulative energy.
+    Rapl(PathBuf),
+    /// A hwmon `power*_input` or `power*_average` file, which reports power
+    /// directly.
+    Hwmon(PathBuf),
+}
+
+/// Displays the current power draw in watts, from Intel RAPL energy counters
+/// (which also cover recent AMD CPUs) or a hwmon power sensor.
+///
+/// On most systems, RAPL counters are only readable by root. Either run
+/// `chmod o+r` on `energy_uj` at boot (e.g. with a udev rule or a systemd
+/// tmpfiles entry) or use a hwmon sensor instead.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct PowerDraw {
+    source: Source,
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(default = "0.5")]
+    smoothing: f64,
+    #[builder(default = "1")]
+    precision: usize,
+    #[builder(default, setter(skip))]
+    last_energy: Option<(u64, Instant)>,
+    #[builder(default, setter(skip))]
+    average: Option<f64>,
+    common: PanelCommon,
+}
+
+impl PowerDraw {
+    /// Takes a new sample, returning the instantaneous power draw in watts.
+    fn sample(&mut self) -> Result<f64> {
+        match &self.source {
+            Source::Hwmon(path) => {
+                // microwatts
+                Ok(read_number::<u64>(path)? as f64 / 1_000_000.0)
+            }
+            Source::Rapl(zone) => {
+                let energy = read_number::<u64>(zone.join("energy_uj"))?;
+                let now = Instant::now();
+                let Some((last, then)) =
+                    self.last_energy.replace((energy, now))
+                else {
+                    return Err(anyhow!("No previous RAPL sample"));
+                };
+
+                // the counter wraps around at max_energy_range_uj
+                let delta = if energy >= last {
+                    energy - last
+                } else {
+                    read_number::<u64>(zone.join("max_energy_range_uj"))? - last
+                        + energy
+                };
+                Ok(delta as f64
+                    / 1_000_000.0
+                    / now.duration_since(then).as_secs_f64())
+            }
+        }
+    }
+
+    fn draw(&mut self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let watts = self.sample()?;
+        let average = self.average.map_or(watts, |average| {
+            average * self.smoothing + watts * (1.0 - self.smoothing)
+        });
+        self.average = Some(average);
+
+        let values = HashMap::from([(
+            String::from("watts"),
+            format!("{:.*}", self.precision, average),
+        )]);
+        let text = render_format(&self.common.formats[0], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for PowerDraw {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        // take a baseline now so that the first update has a full interval to
+        // measure
+        if let Source::Rapl(zone) = &self.source {
+            self.last_energy = Some((
+                read_number::<u64>(zone.join("energy_uj"))?,
+                Instant::now(),
+            ));
+        }
+
+        let stream = IntervalStream::new(interval_at(
+            Instant::now() + self.interval,
+            self.interval,
+        ))
+        .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `zone`: the RAPL zone in `/sys/class/powercap` to read. `intel-rapl:0`
+    ///   is the first CPU package.
+    ///   - type: String
+    ///   - default: `intel-rapl:0`
+    ///
+    /// - `hwmon`: the path to a hwmon power file, such as
+    ///   `/sys/class/hwmon/hwmon3/power1_average`. If set, `zone` is ignored.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `interval`: how long to wait in seconds between each sample
+    ///   - type: u64
+    ///   - default: 2
+    ///
+    /// - `smoothing`: how much weight the previous value has in the displayed
+    ///   value, from 0 (no smoothing) to just under 1 (very smooth)
+    ///   - type: f64
+    ///   - default: 0.5
+    ///
+    /// - `precision`: the number of decimal places to show
+    ///   - type: u64
+    ///   - default: 1
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `PWR: {watts} W`
+    ///   - formatting options: `{watts}`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = PowerDrawBuilder::default();
+
+        let zone = remove_string_from_config("zone", table)
+            .unwrap_or_else(|| String::from("intel-rapl:0"));
+        builder.source(remove_string_from_config("hwmon", table).map_or_else(
+            || Source::Rapl(PathBuf::from("/sys/class/powercap").join(zone)),
+            |hwmon| Source::Hwmon(PathBuf::from(hwmon)),
+        ));
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(smoothing) = remove_float_from_config("smoothing", table) {
+            builder.smoothing(smoothing.clamp(0.0, 0.99));
+        }
+        if let Some(precision) = remove_uint_from_config("precision", table) {
+            builder.precision(precision as usize);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &[""],
+            &["PWR: {watts} W"],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,222 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        OnceCell,
+    },
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use zbus::{proxy::CacheProperties, zvariant::OwnedValue, Connection};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, remove_uint_from_config, render_format, Attrs, PanelCommon,
+    PanelConfig, PanelStream,
+};
+
+#[zbus::proxy(
+    interface = "net.hadess.PowerProfiles",
+    default_service = "net.hadess.PowerProfiles",
+    default_path = "/net/hadess/PowerProfiles"
+)]
+trait PowerProfiles {
+    #[zbus(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn set_active_profile(&self, profile: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn profiles(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Mouse(MouseEvent),
+}
+
+async fn connect() -> Result<PowerProfilesProxy<'static>> {
+    let conn = Connection::system().await?;
+    Ok(PowerProfilesProxy::builder(&conn)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?)
+}
+
+/// Applies a click, if any, and returns the active profile.
+async fn update(
+    proxy: &PowerProfilesProxy<'static>,
+    update: Update,
+) -> Result<String> {
+    let step = match update {
+        Update::Mouse(MouseEvent {
+            button: MouseButton::Left | MouseButton::ScrollUp,
+            ..
+        }) => 1,
+        Update::Mouse(MouseEvent {
+            button: MouseButton::Right | MouseButton::ScrollDown,
+            ..
+        }) => -1,
+        Update::Mouse(_) | Update::Tick => {
+            return Ok(proxy.active_profile().await?)
+        }
+    };
+
+    let active = proxy.active_profile().await?;
+    let profiles = proxy
+        .profiles()
+        .await?
+        .into_iter()
+        .filter_map(|p| {
+            String::try_from(p.get("Profile")?.try_clone().ok()?).ok()
+        })
+        .collect::<Vec<_>>();
+    if profiles.is_empty() {
+        return Err(anyhow!("No power profiles available"));
+    }
+
+    // profiles are listed from least to most power hungry
+    let next = profiles.iter().position(|p| *p == active).map_or(0, |i| {
+        (i as isize + step).rem_euclid(profiles.len() as isize) as usize
+    });
+    proxy.set_active_profile(profiles[next].as_str()).await?;
+    Ok(profiles[next].clone())
+}
+
+/// Displays the active power profile from power-profiles-daemon.
+///
+/// Left clicking or scrolling up on the panel switches to the next profile, and
+/// right clicking or scrolling down switches to the previous one.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct PowerProfile {
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl PowerProfile {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        profile: &str,
+    ) -> Result<PanelDrawInfo> {
+        let index = match profile {
+            "performance" => 0,
+            "power-saver" => 2,
+            _ => 1,
+        };
+        let values = HashMap::from([(
+            String::from("profile"),
+            self.common.escape_markup.apply(profile),
+        )]);
+        let text = render_format(&self.common.formats[index], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[index],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for PowerProfile {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let updates = IntervalStream::new(interval(self.interval))
+            .map(|_| Update::Tick)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse));
+
+        // connecting is async, so it has to happen inside the stream
+        let proxy = Rc::new(OnceCell::new());
+        let stream = futures::StreamExt::then(updates, move |u| {
+            let proxy = proxy.clone();
+            async move { update(proxy.get_or_try_init(connect).await?, u).await }
+        })
+        .map(move |profile| self.draw(&cr, profile?.as_str()));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 10
+    ///
+    /// - `format_performance`: the format string for the `performance` profile
+    ///   - type: String
+    ///   - default: `PWR: performance`
+    ///   - formatting options: `{profile}`
+    ///
+    /// - `format_balanced`: the format string for the `balanced` profile, and
+    ///   for any profile not listed here
+    ///   - type: String
+    ///   - default: `PWR: {profile}`
+    ///   - formatting options: `{profile}`
+    ///
+    /// - `format_power_saver`: the format string for the `power-saver` profile
+    ///   - type: String
+    ///   - default: `PWR: power-saver`
+    ///   - formatting options: `{profile}`
+    ///
+    /// - See [`PanelCommon::parse`]. Three instances of [`Attrs`] are parsed
+    ///   using the prefixes `performance_`, `balanced_`, and `power_saver_`.
+    ///   The formatting options are available to `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = PowerProfileBuilder::default();
+
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_performance", "_balanced", "_power_saver"],
+            &["PWR: performance", "PWR: {profile}", "PWR: power-saver"],
+            &["performance_", "balanced_", "power_saver_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
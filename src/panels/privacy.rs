@@ -0,0 +1,257 @@
+use std::{collections::HashMap, fs, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use libpulse_binding::{
+    callbacks::ListResult,
+    context::{
+        self, introspect::Introspector, subscribe::InterestMaskSet, FlagSet,
+        State,
+    },
+    mainloop::threaded,
+    proplist::properties,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Microphone(Vec<String>),
+}
+
+/// Lists the applications recording from any source and sends their names.
+fn list_recording(introspector: &Introspector, send: UnboundedSender<Update>) {
+    let mut apps = Vec::new();
+    introspector.get_source_output_info_list(move |r| match r {
+        ListResult::Item(output) => {
+            // peak meters (e.g. in pavucontrol) don't count as recording
+            if !output.corked
+                && output.proplist.get_str("media.name").as_deref()
+                    != Some("Peak detect")
+            {
+                apps.push(
+                    output
+                        .proplist
+                        .get_str(properties::APPLICATION_NAME)
+                        .unwrap_or_else(|| String::from("unknown")),
+                );
+            }
+        }
+        ListResult::End => {
+            let _ = send.send(Update::Microphone(std::mem::take(&mut apps)));
+        }
+        ListResult::Error => log::warn!("Failed to list source outputs"),
+    });
+}
+
+/// Returns the names of processes with a video device open.
+fn camera_users() -> Result<Vec<String>> {
+    Ok(fs::read_dir("/proc")?
+        .filter_map(Result::ok)
+        .filter(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .bytes()
+                .all(|b| b.is_ascii_digit())
+        })
+        .filter_map(|e| {
+            // we can only see our own processes' fds, and processes can exit
+            // at any point, so ignore read errors
+            let uses_camera = fs::read_dir(e.path().join("fd"))
+                .ok()?
+                .filter_map(Result::ok)
+                .filter_map(|fd| fs::read_link(fd.path()).ok())
+                .any(|target| {
+                    target.to_string_lossy().starts_with("/dev/video")
+                });
+            if uses_camera {
+                fs::read_to_string(e.path().join("comm"))
+                    .ok()
+                    .map(|comm| comm.trim_end().to_owned())
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+/// Displays indicators while any application is using the camera or
+/// microphone.
+///
+/// Microphone use is reported by PulseAudio (or PipeWire's PulseAudio server),
+/// and camera use is detected by checking for open handles to `/dev/video*`.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Privacy {
+    #[builder(default, setter(strip_option))]
+    server: Option<String>,
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
+    #[builder(default, setter(skip))]
+    microphone: Vec<String>,
+    #[builder(default, setter(skip))]
+    camera: Vec<String>,
+    common: PanelCommon,
+}
+
+impl Privacy {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Result<PanelDrawInfo> {
+        match update {
+            Update::Tick => self.camera = camera_users()?,
+            Update::Microphone(apps) => self.microphone = apps,
+        }
+
+        let text = [(&self.microphone, 0), (&self.camera, 1)]
+            .into_iter()
+            .filter(|(apps, _)| !apps.is_empty())
+            .map(|(apps, idx)| {
+                let mut apps = apps.clone();
+                apps.sort_unstable();
+                apps.dedup();
+                render_format(
+                    &self.common.formats[idx],
+                    &HashMap::from([(
+                        String::from("apps"),
+                        self.common
+                            .escape_markup
+                            .apply(apps.join(", ").as_str()),
+                    )]),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(self.separator.as_str());
+
+        draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )
+    }
+}
+
+impl PanelConfig for Privacy {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let mut mainloop = threaded::Mainloop::new()
+            .ok_or_else(|| anyhow!("Failed to create pulseaudio mainloop"))?;
+        mainloop.start()?;
+        let mut context = context::Context::new(&mainloop, "lazybar")
+            .ok_or_else(|| anyhow!("Failed to create pulseaudio context"))?;
+        context.connect(self.server.as_deref(), FlagSet::NOFAIL, None)?;
+        while context.get_state() != State::Ready {}
+        let introspector = context.introspect();
+
+        let (send, recv) = mpsc::unbounded_channel();
+
+        mainloop.lock();
+
+        list_recording(&introspector, send.clone());
+        context.subscribe(InterestMaskSet::SOURCE_OUTPUT, |_| {});
+        let cb: Option<Box<dyn FnMut(_, _, _)>> =
+            Some(Box::new(move |_, _, _| {
+                list_recording(&introspector, send.clone());
+            }));
+        context.set_subscribe_callback(cb);
+
+        mainloop.unlock();
+
+        // prevent these structures from going out of scope
+        Box::leak(Box::new(context));
+        Box::leak(Box::new(mainloop));
+
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(|_| Update::Tick)
+            .merge(UnboundedReceiverStream::new(recv))
+            .map(move |u| self.update(&cr, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `server`: the pulseaudio server to which to connect
+    ///   - type: String
+    ///   - default: None (pulseaudio will make its best guess)
+    ///
+    /// - `interval`: how long to wait in seconds between checking for camera
+    ///   use. Microphone use is reported immediately.
+    ///   - type: u64
+    ///   - default: 2
+    ///
+    /// - `format_microphone`: the format string while the microphone is in use
+    ///   - type: String
+    ///   - default: `MIC`
+    ///   - formatting options: `{apps}`
+    ///
+    /// - `format_camera`: the format string while the camera is in use
+    ///   - type: String
+    ///   - default: `CAM`
+    ///   - formatting options: `{apps}`
+    ///
+    /// - `separator`: the string placed between the two indicators when both
+    ///   are shown
+    ///   - type: String
+    ///   - default: ` `
+    ///
+    /// - See [`PanelCommon::parse`]. The panel is hidden while neither device
+    ///   is in use.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = PrivacyBuilder::default();
+
+        if let Some(server) = remove_string_from_config("server", table) {
+            builder.server(server);
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_microphone", "_camera"],
+            &["MIC", "CAM"],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -24,8 +24,9 @@ use tokio_stream::{Stream, StreamExt};
 
 use crate::{
     bar::{Dependence, PanelDrawInfo},
-    draw_common, remove_string_from_config, Attrs, PanelCommon, PanelConfig,
-    PanelStream, Ramp,
+    draw_common, draw_gauge, draw_progress_bar, remove_string_from_config,
+    render_format, Attrs, Ellipsize, PanelCommon, PanelConfig, PanelStream,
+    Ramp, States, Style,
 };
 
 /// Displays the current volume and mute status of a given sink.
@@ -41,6 +42,8 @@ pub struct Pulseaudio {
     ramp: Option<Ramp>,
     #[builder(default, setter(strip_option))]
     ramp_muted: Option<Ramp>,
+    #[builder(default)]
+    style: Style,
     send: Sender<(Volume, bool)>,
     recv: Arc<Mutex<Receiver<(Volume, bool)>>>,
     #[builder(default, setter(skip))]
@@ -81,13 +84,19 @@ impl Stream for Pulseaudio {
 }
 
 impl Pulseaudio {
+    #[allow(clippy::too_many_arguments)]
     fn draw(
         cr: &Rc<cairo::Context>,
         data: (Volume, bool),
+        formats: &[String],
         ramp: Option<&Ramp>,
         muted_ramp: Option<&Ramp>,
+        style: &Style,
+        height: i32,
         attrs: &Attrs,
+        states: &States,
         dependence: Dependence,
+        ellipsize: &Ellipsize,
     ) -> Result<PanelDrawInfo> {
         let (volume, mute) = data;
         let ramp = match (mute, muted_ramp) {
@@ -97,27 +106,69 @@ impl Pulseaudio {
         let prefix = ramp
             .as_ref()
             .map(|r| r.choose(volume.0, Volume::MUTED.0, Volume::NORMAL.0));
-        let text = format!(
-            "{}{}",
-            prefix.as_deref().unwrap_or(""),
-            volume.to_string().as_str()
-        );
+        let fraction = f64::from(volume.0) / f64::from(Volume::NORMAL.0);
+        let values = HashMap::from([
+            (
+                String::from("volume"),
+                (fraction * 100.0).round().to_string(),
+            ),
+            (String::from("muted"), mute.to_string()),
+            (String::from("ramp"), prefix.unwrap_or_default()),
+        ]);
+        let text = render_format(&formats[usize::from(mute)], &values);
 
-        draw_common(cr, text.as_str(), attrs, dependence)
+        let attrs = &states.apply(
+            fraction * 100.0,
+            &ramp.map_or_else(
+                || attrs.clone(),
+                |r| r.attrs(volume.0, Volume::MUTED.0, Volume::NORMAL.0, attrs),
+            ),
+        );
+        let mut draw_info = match style {
+            Style::Text | Style::Graph(_) => {
+                draw_common(cr, text.as_str(), attrs, dependence, ellipsize)?
+            }
+            Style::Bar(bar) => draw_progress_bar(
+                cr,
+                fraction,
+                bar,
+                text.as_str(),
+                attrs,
+                dependence,
+            )?,
+            Style::Gauge(gauge) => draw_gauge(
+                cr,
+                fraction,
+                gauge,
+                height,
+                text.as_str(),
+                attrs,
+                dependence,
+            )?,
+        };
+        draw_info.values.extend(values);
+        if let Some(state) = states.find(fraction * 100.0) {
+            draw_info.set_value("state", &state.name);
+        }
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Pulseaudio {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
         global_attrs: Attrs,
-        _height: i32,
+        height: i32,
     ) -> Result<PanelStream> {
         let mut mainloop = threaded::Mainloop::new()
             .ok_or_else(|| anyhow!("Failed to create pulseaudio mainloop"))?;
         mainloop.start()?;
-        let mut context = context::Context::new(&mainloop, "omnibars")
+        let mut context = context::Context::new(&mainloop, "lazybar")
             .ok_or_else(|| anyhow!("Failed to create pulseaudio context"))?;
         context.connect(self.server.as_deref(), FlagSet::NOFAIL, None)?;
         while context.get_state() != State::Ready {}
@@ -166,17 +217,26 @@ impl PanelConfig for Pulseaudio {
         }
         let ramp = self.ramp.clone();
         let muted_ramp = self.ramp_muted.clone();
+        let style = self.style.clone();
+        let formats = self.common.formats.clone();
         let attrs = self.common.attrs[0].clone();
+        let states = self.common.states.clone();
         let dependence = self.common.dependence;
+        let ellipsize = self.common.ellipsize.clone();
 
         let stream = self.map(move |data| {
             Self::draw(
                 &cr,
                 data,
+                &formats,
                 ramp.as_ref(),
                 muted_ramp.as_ref(),
+                &style,
+                height,
                 &attrs,
+                &states,
                 dependence,
+                &ellipsize,
             )
         });
 
@@ -187,13 +247,13 @@ impl PanelConfig for Pulseaudio {
     ///
     /// - `format_unmuted`: the format string when the default sink is unmuted
     ///   - type: String
-    ///   - default: `%ramp%%volume%%`
-    ///   - formatting options: `%volume%`, `%ramp%`
+    ///   - default: `{ramp}{volume}%`
+    ///   - formatting options: `{volume}`, `{muted}`, `{ramp}`
     ///
     /// - `format_muted`: the format string when the default sink is muted
     ///   - type: String
-    ///   - default: `%ramp%%volume%%`
-    ///   - formatting options: `%volume%`, `%ramp%`
+    ///   - default: `{ramp}{volume}%`
+    ///   - formatting options: `{volume}`, `{muted}`, `{ramp}`
     ///
     /// - `sink`: the sink about which to display information
     ///   - type: String
@@ -213,7 +273,11 @@ impl PanelConfig for Pulseaudio {
     ///   [`Ramp::parse`] for parsing details. This ramp is used when the sink
     ///   is muted.
     ///
-    /// - See [`PanelCommon::parse`].
+    /// - The volume can be drawn as a progress bar or a gauge. See
+    ///   [`Style::parse`] for parsing options.
+    ///
+    /// - See [`PanelCommon::parse`]. The values `volume` and `muted` are
+    ///   available to `visible_when`.
     fn parse(
         table: &mut HashMap<String, Value>,
         global: &Config,
@@ -241,13 +305,15 @@ impl PanelConfig for Pulseaudio {
             }
         }
 
+        builder.style(Style::parse(table));
+
         let (send, recv) = channel();
         builder.send(send);
         builder.recv(Arc::new(Mutex::new(recv)));
         builder.common(PanelCommon::parse(
             table,
             &["_unmuted", "_muted"],
-            &["%ramp%%volume%%", "%ramp%%volume%%"],
+            &["{ramp}{volume}%", "{ramp}{volume}%"],
             &[""],
         )?);
 
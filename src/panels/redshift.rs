@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap, fs, path::Path, process::Command, rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use nix::{
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{MouseButton, MouseEvent, PanelDrawInfo},
+    draw_common, in_path, remove_string_from_config, remove_uint_from_config,
+    render_format, Attrs, PanelCommon, PanelConfig, PanelStream, Transition,
+};
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Mouse(MouseEvent),
+}
+
+/// Finds the pid of a running process by name.
+fn find_process(program: &str) -> Option<Pid> {
+    let name = Path::new(program).file_name()?.to_str()?;
+    fs::read_dir("/proc")
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|e| e.file_name().to_str()?.parse().ok())
+        .find(|&pid| {
+            fs::read_to_string(format!("/proc/{pid}/comm"))
+                .is_ok_and(|comm| comm.trim_end() == name)
+        })
+        .map(Pid::from_raw)
+}
+
+/// Displays the color temperature and period of the day as computed by
+/// gammastep or redshift.
+///
+/// The values are computed by running the program in print mode, which uses
+/// the location from its config file. Left clicking the panel toggles the
+/// running instance by sending it `SIGUSR1`.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Redshift {
+    program: String,
+    #[builder(default = "Duration::from_secs(60)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    pid: Option<Pid>,
+    #[builder(default = "true", setter(skip))]
+    enabled: bool,
+    #[builder(default, setter(skip))]
+    events: Option<UnboundedReceiver<MouseEvent>>,
+    common: PanelCommon,
+}
+
+impl Redshift {
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        let pid = find_process(self.program.as_str());
+        if pid != self.pid {
+            // a new instance always starts enabled
+            self.pid = pid;
+            self.enabled = true;
+        }
+
+        match update {
+            Update::Tick => {}
+            Update::Mouse(MouseEvent {
+                button: MouseButton::Left,
+                ..
+            }) => {
+                let pid = self.pid?;
+                if let Err(e) = kill(pid, Signal::SIGUSR1) {
+                    return Some(Err(e.into()));
+                }
+                self.enabled = !self.enabled;
+            }
+            Update::Mouse(_) => return None,
+        }
+
+        Some(self.draw(cr))
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let (text, values) = if self.pid.is_some() && self.enabled {
+            let output =
+                Command::new(self.program.as_str()).arg("-p").output()?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "{} exited with {}: {}",
+                    self.program,
+                    output.status,
+                    String::from_utf8_lossy(output.stderr.as_slice()).trim()
+                ));
+            }
+            let stdout = String::from_utf8_lossy(output.stdout.as_slice());
+
+            let mut period = "";
+            let mut temperature = "";
+            for line in stdout.lines() {
+                if let Some(p) = line.strip_prefix("Period: ") {
+                    // e.g. `Transition (61.34% day)`
+                    period = match p.split_whitespace().next() {
+                        Some("Daytime") => "day",
+                        Some("Night") => "night",
+                        Some("Transition") => "transition",
+                        _ => p,
+                    };
+                } else if let Some(t) = line.strip_prefix("Color temperature: ")
+                {
+                    temperature = t.trim_end_matches('K');
+                }
+            }
+
+            let values = HashMap::from([
+                (String::from("period"), period.to_owned()),
+                (String::from("temperature"), temperature.to_owned()),
+            ]);
+            (render_format(&self.common.formats[0], &values), values)
+        } else {
+            (self.common.formats[1].clone(), HashMap::new())
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Redshift {
+    fn event_sender(&mut self) -> Option<UnboundedSender<MouseEvent>> {
+        let (send, recv) = mpsc::unbounded_channel();
+        self.events = Some(recv);
+        Some(send)
+    }
+
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let events = self
+            .events
+            .take()
+            .unwrap_or_else(|| mpsc::unbounded_channel().1);
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(|_| Update::Tick)
+            .merge(UnboundedReceiverStream::new(events).map(Update::Mouse))
+            .filter_map(move |u| self.update(&cr, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `program`: the program to query and toggle
+    ///   - type: String
+    ///   - default: `gammastep` or `redshift`, whichever is available
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 60
+    ///
+    /// - `format`: the format string while color adjustment is active
+    ///   - type: String
+    ///   - default: `{temperature}K`
+    ///   - formatting options: `{temperature}`, `{period}` (one of `day`,
+    ///     `transition`, or `night`)
+    ///
+    /// - `format_disabled`: the format string while color adjustment is toggled
+    ///   off or the program isn't running
+    ///   - type: String
+    ///   - default: `off`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = RedshiftBuilder::default();
+
+        builder.program(
+            remove_string_from_config("program", table)
+                .or_else(|| {
+                    ["gammastep", "redshift"]
+                        .into_iter()
+                        .find(|bin| in_path(bin))
+                        .map(ToOwned::to_owned)
+                })
+                .ok_or_else(|| {
+                    anyhow!("Neither gammastep nor redshift found")
+                })?,
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_disabled"],
+            &["{temperature}K", "off"],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,183 @@
+use std::{collections::HashMap, fs, rc::Rc, time::Duration};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_uint_from_config, render_format,
+    Attrs, PanelCommon, PanelConfig, PanelStream,
+};
+
+// the kernel truncates process names to this many bytes
+const COMM_LEN: usize = 15;
+
+const DEFAULT_RECORDERS: &[&str] = &[
+    "byzanz-record",
+    "gpu-screen-recorder",
+    "kazam",
+    "peek",
+    "recordmydesktop",
+    "simplescreenrecorder",
+    "vokoscreenNG",
+    "wf-recorder",
+];
+
+/// Displays an indicator while the screen is being recorded.
+///
+/// Recording is detected by scanning `/proc` for known screen recorders, as
+/// well as any `ffmpeg` process grabbing the screen with `x11grab` or
+/// `kmsgrab`. Programs like OBS aren't included by default since they stay
+/// open while idle.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct ScreenCapture {
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(
+        default = "DEFAULT_RECORDERS.iter().map(|&s| s.to_owned()).collect()"
+    )]
+    recorders: Vec<String>,
+    common: PanelCommon,
+}
+
+impl ScreenCapture {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let mut apps = self.find_recorders()?;
+        apps.sort_unstable();
+        apps.dedup();
+
+        let values = HashMap::from([(
+            String::from("apps"),
+            self.common.escape_markup.apply(apps.join(", ").as_str()),
+        )]);
+        let text = if apps.is_empty() {
+            self.common.formats[1].clone()
+        } else {
+            render_format(&self.common.formats[0], &values)
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+
+    fn find_recorders(&self) -> Result<Vec<String>> {
+        Ok(fs::read_dir("/proc")?
+            .filter_map(Result::ok)
+            .filter(|e| {
+                e.file_name()
+                    .to_string_lossy()
+                    .bytes()
+                    .all(|b| b.is_ascii_digit())
+            })
+            .filter_map(|e| {
+                // processes can exit at any point, so ignore read errors
+                let comm = fs::read_to_string(e.path().join("comm")).ok()?;
+                let comm = comm.trim_end();
+                if comm == "ffmpeg" {
+                    let cmdline = fs::read(e.path().join("cmdline")).ok()?;
+                    cmdline
+                        .split(|&b| b == 0)
+                        .any(|arg| arg == b"x11grab" || arg == b"kmsgrab")
+                        .then(|| comm.to_owned())
+                } else {
+                    self.recorders
+                        .iter()
+                        .find(|r| {
+                            r.get(..COMM_LEN).unwrap_or(r.as_str()) == comm
+                        })
+                        .cloned()
+                }
+            })
+            .collect())
+    }
+}
+
+impl PanelConfig for ScreenCapture {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 2
+    ///
+    /// - `recorders`: the names of processes that indicate recording
+    ///   - type: array of String
+    ///   - default: `["byzanz-record", "gpu-screen-recorder", "kazam", "peek",
+    ///     "recordmydesktop", "simplescreenrecorder", "vokoscreenNG",
+    ///     "wf-recorder"]`
+    ///
+    /// - `format`: the format string while recording
+    ///   - type: String
+    ///   - default: `<span foreground='#f00'>REC</span> {apps}`
+    ///   - formatting options: `{apps}`
+    ///
+    /// - `format_none`: the format string while not recording
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = ScreenCaptureBuilder::default();
+
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(recorders) = table.remove("recorders") {
+            match recorders.clone().into_array() {
+                Ok(recorders) => {
+                    builder.recorders(
+                        recorders
+                            .into_iter()
+                            .filter_map(|r| r.into_string().ok())
+                            .collect(),
+                    );
+                }
+                Err(_) => {
+                    log::warn!("Ignoring non-array value {recorders:?}");
+                }
+            }
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_none"],
+            &["<span foreground='#f00'>REC</span> {apps}", ""],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
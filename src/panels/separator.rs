@@ -3,6 +3,7 @@ use std::{collections::HashMap, rc::Rc};
 use anyhow::Result;
 use config::{Config, Value};
 use derive_builder::Builder;
+use tokio_stream::StreamExt;
 
 use crate::{draw_common, Attrs, PanelCommon, PanelConfig};
 
@@ -15,6 +16,10 @@ pub struct Separator {
 }
 
 impl PanelConfig for Separator {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
@@ -25,12 +30,16 @@ impl PanelConfig for Separator {
             attr.apply_to(&global_attrs);
         }
 
-        Ok(Box::pin(tokio_stream::once(draw_common(
+        let draw_info = draw_common(
             &cr,
             self.common.formats[0].as_str(),
             &self.common.attrs[0],
             self.common.dependence,
-        ))))
+            &self.common.ellipsize,
+        );
+        Ok(Box::pin(
+            tokio_stream::once(draw_info).chain(tokio_stream::pending()),
+        ))
     }
 
     /// Configuration options:
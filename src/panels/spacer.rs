@@ -0,0 +1,74 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::PanelDrawInfo, remove_float_from_config, Attrs, PanelCommon,
+    PanelConfig,
+};
+
+/// Takes up a fixed amount of space, filled with its background color.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Spacer {
+    width: f64,
+    common: PanelCommon,
+}
+
+impl PanelConfig for Spacer {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn into_stream(
+        mut self: Box<Self>,
+        _cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<crate::PanelStream> {
+        self.common.attrs[0].apply_to(&global_attrs);
+        let attrs = self.common.attrs[0].clone();
+        let width = self.width;
+
+        let draw_info = PanelDrawInfo::new(
+            (width.ceil() as i32, height),
+            self.common.dependence,
+            Box::new(move |cr| {
+                attrs.apply_bg(cr, width, f64::from(height));
+                cr.rectangle(0.0, 0.0, width, f64::from(height));
+                cr.fill()?;
+                Ok(())
+            }),
+        );
+        Ok(Box::pin(
+            tokio_stream::once(Ok(draw_info)).chain(tokio_stream::pending()),
+        ))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `width`: the width of the spacer in pixels
+    ///   - type: f64
+    ///   - default: `10`
+    ///
+    /// - See [`PanelCommon::parse`].
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = SpacerBuilder::default();
+        builder.width(
+            remove_float_from_config("width", table)
+                .unwrap_or(10.0)
+                .max(0.0),
+        );
+        builder.common(PanelCommon::parse(table, &[], &[], &[""])?);
+
+        Ok(builder.build()?)
+    }
+}
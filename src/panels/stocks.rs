@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use serde_json::Value as Json;
+use tokio::{task, time::sleep};
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+const API: &str = "https://query1.finance.yahoo.com/v8/finance/chart";
+
+#[derive(Debug, Clone)]
+struct Quote {
+    price: f64,
+    change: f64,
+    currency: String,
+    /// Whether the symbol's exchange is in its regular trading session.
+    open: bool,
+    /// How long until the regular trading session starts, if known.
+    opens_in: Option<Duration>,
+}
+
+/// Fetches the latest price and the change since the previous close from
+/// Yahoo Finance.
+fn fetch(symbol: &str) -> Result<Quote> {
+    let mut response: Json = ureq::get(format!("{API}/{symbol}"))
+        // requests without a browser user agent are often rejected
+        .header("User-Agent", "Mozilla/5.0")
+        .query("range", "1d")
+        .query("interval", "1d")
+        .call()?
+        .body_mut()
+        .read_json()?;
+    let meta = response["chart"]["result"][0]["meta"].take();
+
+    let price = meta["regularMarketPrice"]
+        .as_f64()
+        .ok_or_else(|| anyhow!("No price for {symbol}"))?;
+    let previous = meta["chartPreviousClose"]
+        .as_f64()
+        .or_else(|| meta["previousClose"].as_f64())
+        .unwrap_or(price);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let session = &meta["currentTradingPeriod"]["regular"];
+    let start = session["start"].as_i64();
+    let open = matches!(
+        (start, session["end"].as_i64()),
+        (Some(start), Some(end)) if (start..end).contains(&now)
+    );
+
+    Ok(Quote {
+        price,
+        change: if previous == 0.0 {
+            0.0
+        } else {
+            (price - previous) / previous * 100.0
+        },
+        currency: meta["currency"].as_str().unwrap_or_default().to_owned(),
+        open,
+        opens_in: start
+            .filter(|&start| start > now)
+            .map(|start| Duration::from_secs((start - now) as u64)),
+    })
+}
+
+fn fetch_all(symbols: &[String]) -> Result<HashMap<String, Quote>> {
+    symbols
+        .iter()
+        .map(|s| Ok((s.clone(), fetch(s.as_str())?)))
+        .collect()
+}
+
+/// Displays stock prices and their change since the previous close.
+///
+/// Prices are fetched from Yahoo Finance, more frequently while any of the
+/// configured markets are open. If a request fails, the last known prices
+/// continue to be shown.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Stocks {
+    symbols: Vec<String>,
+    #[builder(default = "Duration::from_secs(5 * 60)")]
+    interval: Duration,
+    #[builder(default = "Duration::from_secs(60 * 60)")]
+    interval_closed: Duration,
+    #[builder(default = "2")]
+    precision: usize,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
+    #[builder(default, setter(skip))]
+    cache: HashMap<String, Quote>,
+    common: PanelCommon,
+}
+
+impl Stocks {
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        quotes: Result<HashMap<String, Quote>>,
+    ) -> Result<PanelDrawInfo> {
+        match quotes {
+            Ok(quotes) => self.cache = quotes,
+            Err(e) if !self.cache.is_empty() => {
+                log::warn!(
+                    "Failed to fetch stock prices, using cached values: {e}"
+                );
+            }
+            Err(e) => return Err(e),
+        }
+
+        let quotes = self
+            .symbols
+            .iter()
+            .filter_map(|s| Some((s, self.cache.get(s)?)))
+            .collect::<Vec<_>>();
+        let text = quotes
+            .iter()
+            .map(|(symbol, quote)| {
+                render_format(
+                    &self.common.formats[usize::from(quote.change < 0.0)],
+                    &HashMap::from([
+                        (
+                            String::from("symbol"),
+                            self.common.escape_markup.apply(symbol.as_str()),
+                        ),
+                        (
+                            String::from("currency"),
+                            self.common
+                                .escape_markup
+                                .apply(quote.currency.as_str()),
+                        ),
+                        (
+                            String::from("price"),
+                            format!("{:.*}", self.precision, quote.price),
+                        ),
+                        (
+                            String::from("change"),
+                            format!("{:+.1}", quote.change),
+                        ),
+                    ]),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(self.separator.as_str());
+
+        let gainers = quotes.iter().filter(|(_, q)| q.change >= 0.0).count();
+
+        draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[usize::from(gainers * 2 < quotes.len())],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )
+    }
+}
+
+impl PanelConfig for Stocks {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let symbols = self.symbols.clone();
+        let (open, closed) = (self.interval, self.interval_closed);
+        let updates =
+            futures::stream::unfold(None, move |delay: Option<Duration>| {
+                let symbols = symbols.clone();
+                async move {
+                    if let Some(delay) = delay {
+                        sleep(delay).await;
+                    }
+                    // don't block the bar on a slow network
+                    let quotes = task::spawn_blocking(move || {
+                        fetch_all(symbols.as_slice())
+                    })
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .and_then(|r| r);
+
+                    // keep polling frequently after errors, since whether
+                    // the markets are open is unknown
+                    let delay = match &quotes {
+                        Ok(quotes) if !quotes.values().any(|q| q.open) => {
+                            // wake up for the next session to open
+                            quotes
+                                .values()
+                                .filter_map(|q| q.opens_in)
+                                .min()
+                                .map_or(closed, |d| d.min(closed))
+                        }
+                        _ => open,
+                    };
+                    Some((quotes, Some(delay)))
+                }
+            });
+        let stream = updates.map(move |quotes| self.draw(&cr, quotes));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `symbols`: the ticker symbols to display, as used by Yahoo Finance
+    ///   (e.g. `AAPL`, `VOD.L`)
+    ///   - type: array of String
+    ///   - default: none (required)
+    ///
+    /// - `interval`: how long to wait in seconds between each update while any
+    ///   of the markets are open
+    ///   - type: u64
+    ///   - default: 300
+    ///
+    /// - `interval_closed`: how long to wait in seconds between each update
+    ///   while all of the markets are closed. Updates still happen as soon as a
+    ///   market is known to open.
+    ///   - type: u64
+    ///   - default: 3600
+    ///
+    /// - `precision`: the number of decimal places to show in prices
+    ///   - type: u64
+    ///   - default: 2
+    ///
+    /// - `format_up`: the format string for each symbol whose price has risen
+    ///   - type: String
+    ///   - default: `{symbol}: {price} ({change}%)`
+    ///   - formatting options: `{symbol}`, `{currency}`, `{price}`, `{change}`
+    ///
+    /// - `format_down`: the format string for each symbol whose price has
+    ///   fallen
+    ///   - type: String
+    ///   - default: `{symbol}: {price} ({change}%)`
+    ///   - formatting options: `{symbol}`, `{currency}`, `{price}`, `{change}`
+    ///
+    /// - `separator`: the string placed between symbols
+    ///   - type: String
+    ///   - default: ` `
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `up_` and `down_`. The latter is used when most of
+    ///   the symbols are losers.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = StocksBuilder::default();
+
+        builder.symbols(
+            table
+                .remove("symbols")
+                .ok_or_else(|| anyhow!("Stocks panel requires `symbols`"))?
+                .into_array()?
+                .into_iter()
+                .map(|s| Ok(s.into_string()?.to_uppercase()))
+                .collect::<Result<Vec<_>>>()?,
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(interval) =
+            remove_uint_from_config("interval_closed", table)
+        {
+            builder.interval_closed(Duration::from_secs(interval));
+        }
+        if let Some(precision) = remove_uint_from_config("precision", table) {
+            builder.precision(precision as usize);
+        }
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["_up", "_down"],
+            &[
+                "{symbol}: {price} ({change}%)",
+                "{symbol}: {price} ({change}%)",
+            ],
+            &["up_", "down_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
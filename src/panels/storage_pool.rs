@@ -0,0 +1,425 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    os::fd::AsRawFd,
+    path::PathBuf,
+    process::Command,
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use nix::{errno::Errno, sys::statvfs::statvfs};
+use serde_json::Value as Json;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+#[repr(C)]
+struct FsInfoArgs {
+    max_id: u64,
+    num_devices: u64,
+    fsid: [u8; 16],
+    nodesize: u32,
+    sectorsize: u32,
+    clone_alignment: u32,
+    csum_type: u16,
+    csum_size: u16,
+    flags: u64,
+    generation: u64,
+    metadata_uuid: [u8; 16],
+    reserved: [u8; 944],
+}
+
+#[repr(C)]
+struct DevInfoArgs {
+    devid: u64,
+    uuid: [u8; 16],
+    bytes_used: u64,
+    total_bytes: u64,
+    fsid: [u8; 16],
+    unused: [u64; 377],
+    path: [u8; 1024],
+}
+
+#[repr(C)]
+struct ScrubProgress {
+    data_extents_scrubbed: u64,
+    tree_extents_scrubbed: u64,
+    data_bytes_scrubbed: u64,
+    tree_bytes_scrubbed: u64,
+    read_errors: u64,
+    csum_errors: u64,
+    verify_errors: u64,
+    no_csum: u64,
+    csum_discards: u64,
+    super_errors: u64,
+    malloc_errors: u64,
+    uncorrectable_errors: u64,
+    corrected_errors: u64,
+    last_physical: u64,
+    unverified_errors: u64,
+}
+
+#[repr(C)]
+struct ScrubArgs {
+    devid: u64,
+    start: u64,
+    end: u64,
+    flags: u64,
+    progress: ScrubProgress,
+    unused: [u64; 109],
+}
+
+// can't use #[doc(hidden)] or #[allow(missing_docs)], so this hides the macro
+// away from docs.rs
+mod hidden {
+    use super::{DevInfoArgs, FsInfoArgs, ScrubArgs};
+
+    nix::ioctl_readwrite!(btrfs_scrub_progress, 0x94, 29, ScrubArgs);
+    nix::ioctl_readwrite!(btrfs_dev_info, 0x94, 30, DevInfoArgs);
+    nix::ioctl_read!(btrfs_fs_info, 0x94, 31, FsInfoArgs);
+}
+
+/// Which kind of storage to monitor.
+#[derive(Debug, Clone)]
+enum Source {
+    /// A ZFS pool, by name.
+    Zfs(String),
+    /// A mounted btrfs filesystem, by path.
+    Btrfs(PathBuf),
+}
+
+#[derive(Debug)]
+struct Status {
+    name: String,
+    health: String,
+    degraded: bool,
+    errors: u64,
+    /// The percentage of space used.
+    capacity: f64,
+    /// The progress of a running scrub or resilver, as a percentage.
+    scrub: Option<f64>,
+}
+
+/// ZFS prints numbers as strings unless `--json-int` is given, which older
+/// versions don't support.
+fn json_u64(value: &Json) -> Option<u64> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+fn run(command: &mut Command) -> Result<String> {
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{command:?} exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(output.stderr.as_slice()).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(output.stdout.as_slice()).into_owned())
+}
+
+/// Reads the pool state from `zpool status -j`, which requires OpenZFS 2.3 or
+/// later.
+fn query_zfs(pool: &str) -> Result<Status> {
+    let status: Json = serde_json::from_str(
+        run(Command::new("zpool").args(["status", "-j", pool]))?.as_str(),
+    )?;
+    let status = &status["pools"][pool];
+    let health = status["state"]
+        .as_str()
+        .ok_or_else(|| anyhow!("zpool status has no state for {pool}"))?
+        .to_owned();
+
+    let scan = &status["scan_stats"];
+    let scrub = (scan["state"].as_str() == Some("SCANNING"))
+        .then(|| {
+            let issued = json_u64(&scan["issued"])?;
+            let total = json_u64(&scan["to_examine"])?;
+            Some(issued as f64 / total.max(1) as f64 * 100.0)
+        })
+        .flatten();
+
+    // bytes, without headers, tab separated
+    let list = run(Command::new("zpool").args([
+        "list",
+        "-Hp",
+        "-o",
+        "size,allocated",
+        pool,
+    ]))?;
+    let (size, allocated) = list
+        .trim()
+        .split_once('\t')
+        .and_then(|(s, a)| {
+            Some((s.parse::<u64>().ok()?, a.parse::<u64>().ok()?))
+        })
+        .ok_or_else(|| anyhow!("Unexpected zpool list output: {list}"))?;
+
+    Ok(Status {
+        name: pool.to_owned(),
+        degraded: health != "ONLINE",
+        health,
+        errors: json_u64(&status["error_count"]).unwrap_or(0),
+        capacity: allocated as f64 / size.max(1) as f64 * 100.0,
+        scrub,
+    })
+}
+
+/// Reads the filesystem's health from sysfs, which requires Linux 5.14 or
+/// later for per-device error counts.
+fn query_btrfs(path: &PathBuf) -> Result<Status> {
+    let file = File::open(path)?;
+    let mut info: FsInfoArgs = unsafe { std::mem::zeroed() };
+    unsafe { hidden::btrfs_fs_info(file.as_raw_fd(), &mut info) }?;
+    let hex = info
+        .fsid
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    let sysfs = PathBuf::from(format!(
+        "/sys/fs/btrfs/{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ));
+
+    let devids = fs::read_dir(sysfs.join("devinfo"))?
+        .filter_map(|e| e.ok()?.file_name().to_str()?.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    let mut missing = false;
+    let mut errors = 0;
+    for devid in &devids {
+        let dir = sysfs.join("devinfo").join(devid.to_string());
+        missing |= fs::read_to_string(dir.join("missing"))
+            .is_ok_and(|m| m.trim() != "0");
+        // e.g. `write_errs 0`
+        errors += fs::read_to_string(dir.join("error_stats"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|l| l.split_whitespace().nth(1)?.parse::<u64>().ok())
+            .sum::<u64>();
+    }
+    let health = if missing {
+        "MISSING"
+    } else if errors > 0 {
+        "ERRORS"
+    } else {
+        "ONLINE"
+    };
+
+    let stat = statvfs(path)?;
+    let capacity = (stat.blocks() - stat.blocks_free()) as f64
+        / stat.blocks().max(1) as f64
+        * 100.0;
+
+    let name = fs::read_to_string(sysfs.join("label"))
+        .ok()
+        .map(|l| l.trim().to_owned())
+        .filter(|l| !l.is_empty())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned());
+
+    Ok(Status {
+        name,
+        health: health.to_owned(),
+        degraded: missing || errors > 0,
+        errors,
+        capacity,
+        scrub: btrfs_scrub(&file, devids.as_slice()),
+    })
+}
+
+/// Returns the progress of a running scrub, if any. This requires root, so it
+/// is `None` otherwise.
+fn btrfs_scrub(file: &File, devids: &[u64]) -> Option<f64> {
+    let mut scrubbed = 0;
+    let mut total = 0;
+    let mut running = false;
+    for &devid in devids {
+        let mut args: ScrubArgs = unsafe { std::mem::zeroed() };
+        args.devid = devid;
+        match unsafe {
+            hidden::btrfs_scrub_progress(file.as_raw_fd(), &mut args)
+        } {
+            Ok(_) => {}
+            // not scrubbing this device
+            Err(Errno::ENOTCONN | Errno::ENODEV) => continue,
+            Err(_) => return None,
+        }
+
+        let mut info: DevInfoArgs = unsafe { std::mem::zeroed() };
+        info.devid = devid;
+        unsafe { hidden::btrfs_dev_info(file.as_raw_fd(), &mut info) }.ok()?;
+
+        running = true;
+        scrubbed += args.progress.data_bytes_scrubbed
+            + args.progress.tree_bytes_scrubbed;
+        total += info.bytes_used;
+    }
+
+    running.then(|| scrubbed as f64 / total.max(1) as f64 * 100.0)
+}
+
+/// Displays the health, capacity, and scrub progress of a ZFS pool or a btrfs
+/// filesystem.
+///
+/// ZFS status comes from `zpool`, which must be OpenZFS 2.3 or later. btrfs
+/// status comes from ioctls and sysfs. Scrub progress on btrfs is only
+/// available to root.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct StoragePool {
+    source: Source,
+    #[builder(default = "Duration::from_secs(60)")]
+    interval: Duration,
+    common: PanelCommon,
+}
+
+impl StoragePool {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let status = match &self.source {
+            Source::Zfs(pool) => query_zfs(pool.as_str()),
+            Source::Btrfs(path) => query_btrfs(path),
+        }?;
+
+        let idx = if status.degraded {
+            2
+        } else {
+            usize::from(status.scrub.is_some())
+        };
+        let values = HashMap::from([
+            (
+                String::from("name"),
+                self.common.escape_markup.apply(status.name.as_str()),
+            ),
+            (String::from("health"), status.health),
+            (String::from("errors"), status.errors.to_string()),
+            (String::from("capacity"), format!("{:.0}", status.capacity)),
+            (
+                String::from("scrub"),
+                status.scrub.map(|s| format!("{s:.0}")).unwrap_or_default(),
+            ),
+        ]);
+        let text = render_format(&self.common.formats[idx], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for StoragePool {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `pool`: the name of a ZFS pool
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `btrfs`: the mount point of a btrfs filesystem. Exactly one of `pool`
+    ///   and `btrfs` must be set.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 60
+    ///
+    /// - `format`: the format string when healthy
+    ///   - type: String
+    ///   - default: `{name}: {capacity}%`
+    ///   - formatting options: `{name}` (the pool name, or the filesystem label
+    ///     if it has one), `{health}` (e.g. `ONLINE` or `DEGRADED` for ZFS, and
+    ///     `ONLINE`, `ERRORS`, or `MISSING` for btrfs), `{errors}`,
+    ///     `{capacity}`, `{scrub}`
+    ///
+    /// - `format_scrub`: the format string when healthy and a scrub or resilver
+    ///   is running
+    ///   - type: String
+    ///   - default: `{name}: {capacity}% (scrub {scrub}%)`
+    ///   - formatting options: same as `format`
+    ///
+    /// - `format_degraded`: the format string when the pool is degraded or has
+    ///   errors
+    ///   - type: String
+    ///   - default: `<span foreground='#f00'>{name}: {health}</span>`
+    ///   - formatting options: same as `format`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = StoragePoolBuilder::default();
+
+        builder.source(
+            match (
+                remove_string_from_config("pool", table),
+                remove_string_from_config("btrfs", table),
+            ) {
+                (Some(pool), None) => Source::Zfs(pool),
+                (None, Some(path)) => Source::Btrfs(PathBuf::from(path)),
+                _ => {
+                    return Err(anyhow!(
+                        "Storage pool panel requires exactly one of `pool` \
+                         and `btrfs`"
+                    ))
+                }
+            },
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_scrub", "_degraded"],
+            &[
+                "{name}: {capacity}%",
+                "{name}: {capacity}% (scrub {scrub}%)",
+                "<span foreground='#f00'>{name}: {health}</span>",
+            ],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
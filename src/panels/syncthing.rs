@@ -0,0 +1,316 @@
+use std::{collections::HashMap, rc::Rc, thread, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use serde_json::Value as Json;
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Refresh,
+    Spin,
+    /// A folder changed state, e.g. from `idle` to `syncing`.
+    State(String, String),
+}
+
+/// A minimal client for Syncthing's REST API.
+#[derive(Debug, Clone)]
+struct Client {
+    address: String,
+    api_key: String,
+}
+
+impl Client {
+    fn get(&self, path: &str) -> Result<Json> {
+        Ok(ureq::get(format!("{}{path}", self.address))
+            .header("X-API-Key", self.api_key.as_str())
+            .call()?
+            .body_mut()
+            .read_json()?)
+    }
+
+    /// Follows the event stream on its own thread, sending folder state
+    /// changes. The initial state of each folder is sent first.
+    fn follow(self, send: UnboundedSender<Update>) {
+        thread::spawn(move || {
+            let mut since = 0;
+            let mut initialized = false;
+            loop {
+                if !initialized {
+                    if let Err(e) = self.send_states(&send) {
+                        log::warn!(
+                            "Failed to get Syncthing folder states: {e}"
+                        );
+                        thread::sleep(Duration::from_secs(10));
+                        continue;
+                    }
+                    initialized = true;
+                }
+
+                // long polls for up to a minute
+                match self.get(
+                    format!("/rest/events?events=StateChanged&since={since}")
+                        .as_str(),
+                ) {
+                    Ok(Json::Array(events)) => {
+                        for event in events {
+                            since = event["id"].as_u64().unwrap_or(since);
+                            let data = &event["data"];
+                            if let (Some(folder), Some(to)) =
+                                (data["folder"].as_str(), data["to"].as_str())
+                            {
+                                if send
+                                    .send(Update::State(
+                                        folder.to_owned(),
+                                        to.to_owned(),
+                                    ))
+                                    .is_err()
+                                {
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("Failed to get Syncthing events: {e}");
+                        // Syncthing may have restarted, which resets event ids
+                        since = 0;
+                        initialized = false;
+                        thread::sleep(Duration::from_secs(10));
+                    }
+                }
+            }
+        });
+    }
+
+    fn send_states(&self, send: &UnboundedSender<Update>) -> Result<()> {
+        let folders = self.get("/rest/config/folders")?;
+        for id in folders
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|f| f["id"].as_str())
+        {
+            let status =
+                self.get(format!("/rest/db/status?folder={id}").as_str())?;
+            if let Some(state) = status["state"].as_str() {
+                let _ =
+                    send.send(Update::State(id.to_owned(), state.to_owned()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Displays Syncthing's sync completion and the number of connected devices.
+///
+/// Folder states are followed through Syncthing's event API, and a spinner is
+/// shown while any folder is syncing.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Syncthing {
+    client: Client,
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    #[builder(default = r#"String::from("◐◓◑◒")"#)]
+    spinner: String,
+    #[builder(default, setter(skip))]
+    frame: usize,
+    #[builder(default, setter(skip))]
+    folders: HashMap<String, String>,
+    #[builder(default, setter(skip))]
+    completion: f64,
+    #[builder(default, setter(skip))]
+    devices: usize,
+    common: PanelCommon,
+}
+
+impl Syncthing {
+    fn syncing(&self) -> bool {
+        self.folders
+            .values()
+            .any(|s| s == "syncing" || s == "sync-preparing")
+    }
+
+    fn update(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        update: Update,
+    ) -> Option<Result<PanelDrawInfo>> {
+        match update {
+            Update::Refresh => {
+                if let Err(e) = self.refresh() {
+                    return Some(Err(e));
+                }
+            }
+            Update::Spin => {
+                if !self.syncing() {
+                    return None;
+                }
+                self.frame = self.frame.wrapping_add(1);
+            }
+            Update::State(folder, state) => {
+                self.folders.insert(folder, state);
+            }
+        }
+
+        Some(self.draw(cr))
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        self.completion = self.client.get("/rest/db/completion")?["completion"]
+            .as_f64()
+            .ok_or_else(|| anyhow!("Invalid completion response"))?;
+        self.devices = self.client.get("/rest/system/connections")?
+            ["connections"]
+            .as_object()
+            .map_or(0, |c| {
+                c.values().filter(|d| d["connected"] == true).count()
+            });
+        Ok(())
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let syncing = self.syncing();
+        let frames = self.spinner.graphemes(true).collect::<Vec<_>>();
+        let spinner = if frames.is_empty() {
+            ""
+        } else {
+            frames[self.frame % frames.len()]
+        };
+
+        let values = HashMap::from([
+            (
+                String::from("completion"),
+                format!("{:.0}", self.completion),
+            ),
+            (String::from("devices"), self.devices.to_string()),
+            (String::from("spinner"), spinner.to_owned()),
+        ]);
+        let text =
+            render_format(&self.common.formats[usize::from(syncing)], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Syncthing {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let (send, recv) = mpsc::unbounded_channel();
+        self.client.clone().follow(send);
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(|_| Update::Refresh)
+            .merge(
+                IntervalStream::new(interval(Duration::from_millis(250)))
+                    .map(|_| Update::Spin),
+            )
+            .merge(UnboundedReceiverStream::new(recv))
+            .filter_map(move |u| self.update(&cr, u));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `address`: the address of Syncthing's GUI/REST API
+    ///   - type: String
+    ///   - default: `http://localhost:8384`
+    ///
+    /// - `api_key`: the API key, found in Syncthing's settings
+    ///   - type: String
+    ///   - default: none (required)
+    ///
+    /// - `interval`: how long to wait in seconds between refreshing the
+    ///   completion and connected devices. Folder states are updated as soon as
+    ///   they change.
+    ///   - type: u64
+    ///   - default: 10
+    ///
+    /// - `spinner`: the frames of the spinner, one per grapheme
+    ///   - type: String
+    ///   - default: `◐◓◑◒`
+    ///
+    /// - `format`: the format string when no folders are syncing
+    ///   - type: String
+    ///   - default: `SYNC: {completion}% ({devices})`
+    ///   - formatting options: `{completion}`, `{devices}`
+    ///
+    /// - `format_syncing`: the format string while any folder is syncing
+    ///   - type: String
+    ///   - default: `SYNC: {spinner} {completion}% ({devices})`
+    ///   - formatting options: `{completion}`, `{devices}`, `{spinner}`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = SyncthingBuilder::default();
+
+        builder.client(Client {
+            address: remove_string_from_config("address", table)
+                .unwrap_or_else(|| String::from("http://localhost:8384"))
+                .trim_end_matches('/')
+                .to_owned(),
+            api_key: remove_string_from_config("api_key", table)
+                .ok_or_else(|| anyhow!("Syncthing panel requires `api_key`"))?,
+        });
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(spinner) = remove_string_from_config("spinner", table) {
+            builder.spinner(spinner);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_syncing"],
+            &[
+                "SYNC: {completion}% ({devices})",
+                "SYNC: {spinner} {completion}% ({devices})",
+            ],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
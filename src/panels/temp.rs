@@ -1,4 +1,4 @@
-use std::{fs::File, io::Read, rc::Rc, time::Duration};
+use std::{collections::HashMap, rc::Rc, time::Duration};
 
 use anyhow::Result;
 use derive_builder::Builder;
@@ -6,8 +6,8 @@ use tokio::time::interval;
 use tokio_stream::{wrappers::IntervalStream, StreamExt};
 
 use crate::{
-    bar::PanelDrawInfo, draw_common, remove_uint_from_config, Attrs,
-    PanelCommon, PanelConfig,
+    bar::PanelDrawInfo, draw_common, read_number, remove_uint_from_config,
+    render_format, Attrs, PanelCommon, PanelConfig,
 };
 
 /// Displays the temperature of a provided thermal zone.
@@ -27,28 +27,36 @@ pub struct Temp {
 
 impl Temp {
     fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
-        let mut temp = String::new();
-        File::open(format!(
+        let temp = read_number::<u64>(format!(
             "/sys/class/thermal/thermal_zone{}/temp",
             self.zone
-        ))?
-        .read_to_string(&mut temp)?;
+        ))?;
 
-        let text = self.common.formats[0].replace(
-            "%temp%",
-            (temp.trim().parse::<u64>()? / 1000).to_string().as_str(),
-        );
+        let values =
+            HashMap::from([(String::from("temp"), (temp / 1000).to_string())]);
+        let text = render_format(&self.common.formats[0], &values);
 
-        draw_common(
+        let value = temp as f64 / 1000.0;
+        let mut draw_info = draw_common(
             cr,
             text.as_str(),
-            &self.common.attrs[0],
+            &self.common.states.apply(value, &self.common.attrs[0]),
             self.common.dependence,
-        )
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        if let Some(state) = self.common.states.find(value) {
+            draw_info.set_value("state", &state.name);
+        }
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for Temp {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
@@ -69,17 +77,18 @@ impl PanelConfig for Temp {
     ///
     /// - `format`: the format string
     ///   - type: String
-    ///   - default: `TEMP: %temp%`
-    ///   - formatting options: `%temp%`
+    ///   - default: `TEMP: {temp}`
+    ///   - formatting options: `{temp}`
     /// - `interval`: how long to wait in seconds between each check
     ///   - type: u64
     ///   - default: 10
     /// - `zone`: the thermal zone to check
     ///   - type: u64
     ///   - default: 0
-    /// - See [`PanelCommon::parse`].
+    /// - See [`PanelCommon::parse`]. The value `temp` is available to
+    ///   `visible_when`.
     fn parse(
-        table: &mut std::collections::HashMap<String, config::Value>,
+        table: &mut HashMap<String, config::Value>,
         _global: &config::Config,
     ) -> Result<Self> {
         let mut builder = TempBuilder::default();
@@ -93,7 +102,7 @@ impl PanelConfig for Temp {
         builder.common(PanelCommon::parse(
             table,
             &[""],
-            &["TEMP: %temp%"],
+            &["TEMP: {temp}"],
             &[""],
         )?);
 
@@ -0,0 +1,231 @@
+use std::{collections::HashMap, process::Command, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{sync::OnceCell, time::interval};
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+use zbus::{proxy::CacheProperties, Connection};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+#[zbus::proxy(
+    interface = "org.freedesktop.timedate1",
+    default_service = "org.freedesktop.timedate1",
+    default_path = "/org/freedesktop/timedate1"
+)]
+trait Timedate {
+    #[zbus(property, name = "NTPSynchronized")]
+    fn ntp_synchronized(&self) -> zbus::Result<bool>;
+}
+
+/// Where to get the synchronization status from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Timedated,
+    Chrony,
+}
+
+#[derive(Debug, Default)]
+struct Status {
+    synchronized: bool,
+    /// The offset of the system clock from true time in seconds, if known.
+    offset: Option<f64>,
+    stratum: Option<u32>,
+    server: Option<String>,
+}
+
+async fn query_timedated(conn: &Connection) -> Result<Status> {
+    let synchronized = TimedateProxy::builder(conn)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?
+        .ntp_synchronized()
+        .await?;
+    Ok(Status {
+        synchronized,
+        ..Status::default()
+    })
+}
+
+/// Parses the output of `chronyc -c tracking`. See chronyc(1) for the
+/// fields.
+fn query_chrony() -> Result<Status> {
+    let output = Command::new("chronyc").args(["-c", "tracking"]).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "chronyc exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(output.stderr.as_slice()).trim()
+        ));
+    }
+
+    let output = String::from_utf8_lossy(output.stdout.as_slice());
+    let fields = output.trim().split(',').collect::<Vec<_>>();
+    if fields.len() < 14 {
+        return Err(anyhow!("Unexpected chronyc output: {output}"));
+    }
+
+    Ok(Status {
+        synchronized: fields[13] != "Not synchronised",
+        offset: fields[4].parse().ok(),
+        stratum: fields[2].parse().ok(),
+        server: Some(fields[1].to_owned()).filter(|s| !s.is_empty()),
+    })
+}
+
+/// Displays whether the system clock is synchronized with NTP.
+///
+/// The status comes from systemd-timedated over D-Bus, which works with
+/// systemd-timesyncd, chrony, and ntpd. With `source = "chrony"`, `chronyc` is
+/// queried instead, which also provides the clock's offset.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Timesync {
+    #[builder(default = "Source::Timedated")]
+    source: Source,
+    #[builder(default = "Duration::from_secs(60)")]
+    interval: Duration,
+    common: PanelCommon,
+}
+
+impl Timesync {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        status: Status,
+    ) -> Result<PanelDrawInfo> {
+        let values = HashMap::from([
+            (
+                String::from("offset"),
+                status
+                    .offset
+                    .map(|o| format!("{:+.3}", o * 1000.0))
+                    .unwrap_or_default(),
+            ),
+            (
+                String::from("stratum"),
+                status.stratum.map(|s| s.to_string()).unwrap_or_default(),
+            ),
+            (
+                String::from("server"),
+                self.common
+                    .escape_markup
+                    .apply(status.server.unwrap_or_default().as_str()),
+            ),
+        ]);
+        let text = render_format(
+            &self.common.formats[usize::from(!status.synchronized)],
+            &values,
+        );
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[usize::from(!status.synchronized)],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Timesync {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let conn = Rc::new(OnceCell::new());
+        let source = self.source;
+        let stream = futures::StreamExt::then(
+            IntervalStream::new(interval(self.interval)),
+            move |_| {
+                let conn = conn.clone();
+                async move {
+                    match source {
+                        Source::Timedated => {
+                            let conn = conn
+                                .get_or_try_init(Connection::system)
+                                .await?;
+                            query_timedated(conn).await
+                        }
+                        Source::Chrony => query_chrony(),
+                    }
+                }
+            },
+        )
+        .map(move |status| self.draw(&cr, status?));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `source`: where to get the status from, either `timedated` or `chrony`
+    ///   - type: String
+    ///   - default: `timedated`
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 60
+    ///
+    /// - `format`: the format string when the clock is synchronized
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///   - formatting options: `{offset}` (in milliseconds), `{stratum}`,
+    ///     `{server}`. These are only available from chrony, and are empty
+    ///     otherwise.
+    ///
+    /// - `format_unsynced`: the format string when the clock isn't synchronized
+    ///   - type: String
+    ///   - default: `NTP: unsynced`
+    ///   - formatting options: same as `format`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `warning_`. The latter is used when the
+    ///   clock isn't synchronized, and its unset values fall back to the first
+    ///   instance. The formatting options are available to `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = TimesyncBuilder::default();
+
+        if let Some(source) = remove_string_from_config("source", table) {
+            builder.source(match source.as_str() {
+                "timedated" => Source::Timedated,
+                "chrony" => Source::Chrony,
+                _ => return Err(anyhow!("Invalid time sync source {source}")),
+            });
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_unsynced"],
+            &["", "NTP: unsynced"],
+            &["", "warning_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::PathBuf,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::FutureExt;
+use nix::sys::inotify::{self, AddWatchFlags, InitFlags};
+use tokio::task::{self, JoinHandle};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config, render_format,
+    Attrs, PanelCommon, PanelConfig, PanelStream,
+};
+
+struct TodoStream {
+    i: Arc<inotify::Inotify>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TodoStream {
+    fn new(i: inotify::Inotify) -> Self {
+        Self {
+            i: Arc::new(i),
+            handle: None,
+        }
+    }
+}
+
+impl Stream for TodoStream {
+    type Item = ();
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(handle) = &mut self.handle {
+            let value = handle.poll_unpin(cx).map(|_| Some(()));
+            if value.is_ready() {
+                self.handle = None;
+            }
+            value
+        } else {
+            let i = self.i.clone();
+            let waker = cx.waker().clone();
+            self.handle = Some(task::spawn_blocking(move || loop {
+                let result = i.read_events();
+                if result.is_ok() {
+                    waker.wake();
+                    break;
+                }
+            }));
+            Poll::Pending
+        }
+    }
+}
+
+/// An incomplete item from a todo.txt file.
+#[derive(Debug)]
+struct Item<'a> {
+    priority: Option<char>,
+    text: &'a str,
+}
+
+impl<'a> Item<'a> {
+    /// Parses a line, returning `None` for blank lines and completed items.
+    fn parse(line: &'a str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("x ") {
+            return None;
+        }
+
+        let mut chars = line.chars();
+        let priority = match (chars.next(), chars.next(), chars.next()) {
+            (Some('('), Some(p @ 'A'..='Z'), Some(')'))
+                if line[3..].starts_with(' ') =>
+            {
+                Some(p)
+            }
+            _ => None,
+        };
+        let mut text = if priority.is_some() { &line[4..] } else { line };
+
+        // skip the creation date
+        if let Some((date, rest)) = text.split_once(' ') {
+            if date.len() == 10
+                && date
+                    .chars()
+                    .enumerate()
+                    .all(|(i, c)| matches!(i, 4 | 7) == (c == '-'))
+                && date.chars().filter(char::is_ascii_digit).count() == 8
+            {
+                text = rest.trim_start();
+            }
+        }
+
+        Some(Self { priority, text })
+    }
+}
+
+/// Displays the number of incomplete items in a [todo.txt](http://todotxt.org)
+/// file, grouped by priority, along with the most important item.
+///
+/// The file is watched with inotify, so the panel updates as soon as it's
+/// saved.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct TodoTxt {
+    path: PathBuf,
+    common: PanelCommon,
+}
+
+impl TodoTxt {
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            // treat a missing file as an empty list
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let items =
+            contents.lines().filter_map(Item::parse).collect::<Vec<_>>();
+
+        // the first item with the highest priority, where any priority beats
+        // none
+        let top = items
+            .iter()
+            .min_by_key(|i| (i.priority.is_none(), i.priority));
+
+        let (text, values) = match top {
+            None => (self.common.formats[1].clone(), HashMap::new()),
+            Some(top) => {
+                let mut values = ('A'..='Z')
+                    .map(|p| {
+                        (
+                            String::from(p),
+                            items
+                                .iter()
+                                .filter(|i| i.priority == Some(p))
+                                .count()
+                                .to_string(),
+                        )
+                    })
+                    .collect::<HashMap<_, _>>();
+                values.extend([
+                    (String::from("count"), items.len().to_string()),
+                    (
+                        String::from("none"),
+                        items
+                            .iter()
+                            .filter(|i| i.priority.is_none())
+                            .count()
+                            .to_string(),
+                    ),
+                    (
+                        String::from("top_priority"),
+                        top.priority.map(String::from).unwrap_or_default(),
+                    ),
+                    (
+                        String::from("top"),
+                        self.common.escape_markup.apply(top.text),
+                    ),
+                ]);
+                (render_format(&self.common.formats[0], &values), values)
+            }
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for TodoTxt {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        // most editors replace the file rather than writing to it, so watch
+        // the directory instead
+        let inotify = inotify::Inotify::init(InitFlags::empty())?;
+        inotify.add_watch(
+            self.path
+                .parent()
+                .ok_or_else(|| anyhow!("Invalid todo.txt path"))?,
+            AddWatchFlags::IN_CLOSE_WRITE
+                | AddWatchFlags::IN_MOVED_TO
+                | AddWatchFlags::IN_CREATE
+                | AddWatchFlags::IN_DELETE,
+        )?;
+
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = tokio_stream::once(())
+            .chain(TodoStream::new(inotify))
+            .map(move |()| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `path`: the todo.txt file to read
+    ///   - type: String
+    ///   - default: `$TODO_FILE`, or `~/todo.txt`
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `TODO: {count}`
+    ///   - formatting options: `{count}`, `{A}` through `{Z}` (the number of
+    ///     items with that priority), `{none}` (the number of items without a
+    ///     priority), `{top}` (the highest priority item), `{top_priority}`
+    ///
+    /// - `format_empty`: the format string when there are no incomplete items
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = TodoTxtBuilder::default();
+
+        builder.path(PathBuf::from(
+            remove_string_from_config("path", table)
+                .or_else(|| env::var("TODO_FILE").ok())
+                .unwrap_or_else(|| {
+                    format!("{}/todo.txt", env::var("HOME").unwrap())
+                }),
+        ));
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_empty"],
+            &["TODO: {count}", ""],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
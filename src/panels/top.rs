@@ -0,0 +1,282 @@
+use std::{collections::HashMap, fs, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, format_bytes, remove_float_from_config,
+    remove_string_from_config, remove_uint_from_config, render_format, Attrs,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+/// What to rank processes by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Cpu,
+    Memory,
+}
+
+#[derive(Debug)]
+struct Process {
+    pid: u32,
+    name: String,
+    /// CPU time in clock ticks, or resident memory in bytes.
+    usage: u64,
+}
+
+/// Returns the total CPU time across all CPUs, in clock ticks, and the number
+/// of CPUs.
+fn read_cpu_time() -> Result<(u64, usize)> {
+    let stat = fs::read_to_string("/proc/stat")?;
+    let total = stat
+        .lines()
+        .next()
+        .filter(|l| l.starts_with("cpu "))
+        .ok_or_else(|| anyhow!("Failed to read /proc/stat"))?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|t| t.parse::<u64>().ok())
+        .sum();
+    let cpus = stat
+        .lines()
+        .filter(|l| l.starts_with("cpu") && !l.starts_with("cpu "))
+        .count();
+    Ok((total, cpus.max(1)))
+}
+
+fn read_mem_total() -> Result<u64> {
+    fs::read_to_string("/proc/meminfo")?
+        .lines()
+        .find_map(|l| l.strip_prefix("MemTotal:"))
+        .and_then(|kb| kb.trim().trim_end_matches("kB").trim().parse().ok())
+        .map(|kb: u64| kb * 1024)
+        .ok_or_else(|| anyhow!("Failed to read /proc/meminfo"))
+}
+
+fn read_process(pid: u32, mode: Mode) -> Option<Process> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // the name may contain spaces and parentheses
+    let (name, fields) = stat.split_once(" (")?.1.rsplit_once(") ")?;
+    let usage = match mode {
+        Mode::Cpu => {
+            let mut fields = fields.split_whitespace().skip(11);
+            let utime = fields.next()?.parse::<u64>().ok()?;
+            let stime = fields.next()?.parse::<u64>().ok()?;
+            utime + stime
+        }
+        Mode::Memory => {
+            fs::read_to_string(format!("/proc/{pid}/status"))
+                .ok()?
+                .lines()
+                .find_map(|l| l.strip_prefix("VmRSS:"))?
+                .trim()
+                .trim_end_matches("kB")
+                .trim()
+                .parse::<u64>()
+                .ok()?
+                * 1024
+        }
+    };
+
+    Some(Process {
+        pid,
+        name: name.to_owned(),
+        usage,
+    })
+}
+
+fn read_processes(mode: Mode) -> Result<Vec<Process>> {
+    Ok(fs::read_dir("/proc")?
+        .filter_map(|e| {
+            let pid = e.ok()?.file_name().to_str()?.parse().ok()?;
+            read_process(pid, mode)
+        })
+        .collect())
+}
+
+/// Displays the process using the most CPU or memory, sampled from `/proc`.
+///
+/// CPU usage is measured between samples, so the first update after startup
+/// only establishes a baseline.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Top {
+    #[builder(default = "Mode::Cpu")]
+    mode: Mode,
+    #[builder(default = "Duration::from_secs(5)")]
+    interval: Duration,
+    #[builder(default = "10.0")]
+    threshold: f64,
+    #[builder(default, setter(skip))]
+    last_usage: HashMap<u32, u64>,
+    #[builder(default, setter(skip))]
+    last_total: u64,
+    common: PanelCommon,
+}
+
+impl Top {
+    /// Finds the top process and its usage as a percentage of one CPU or of
+    /// total memory.
+    fn sample(&mut self) -> Result<Option<(Process, f64)>> {
+        let processes = read_processes(self.mode)?;
+
+        match self.mode {
+            Mode::Cpu => {
+                let (total, cpus) = read_cpu_time()?;
+                let elapsed = total.saturating_sub(self.last_total);
+                let top = processes
+                    .iter()
+                    .filter_map(|p| {
+                        // skip processes that started since the last sample
+                        let last = self.last_usage.get(&p.pid)?;
+                        Some((p, p.usage.saturating_sub(*last)))
+                    })
+                    .max_by_key(|(_, delta)| *delta)
+                    .map(|(p, delta)| (p.pid, delta));
+
+                let first = self.last_total == 0;
+                self.last_total = total;
+                self.last_usage =
+                    processes.iter().map(|p| (p.pid, p.usage)).collect();
+
+                if first || elapsed == 0 {
+                    return Ok(None);
+                }
+                Ok(top.and_then(|(pid, delta)| {
+                    let percent =
+                        delta as f64 / elapsed as f64 * cpus as f64 * 100.0;
+                    processes
+                        .into_iter()
+                        .find(|p| p.pid == pid)
+                        .map(|p| (p, percent))
+                }))
+            }
+            Mode::Memory => {
+                let total = read_mem_total()?;
+                Ok(processes.into_iter().max_by_key(|p| p.usage).map(|p| {
+                    let percent = p.usage as f64 / total as f64 * 100.0;
+                    (p, percent)
+                }))
+            }
+        }
+    }
+
+    fn draw(&mut self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let (text, values) = match self.sample()? {
+            Some((process, percent)) if percent >= self.threshold => {
+                let values = HashMap::from([
+                    (
+                        String::from("name"),
+                        self.common.escape_markup.apply(process.name.as_str()),
+                    ),
+                    (String::from("pid"), process.pid.to_string()),
+                    (String::from("percent"), format!("{percent:.0}")),
+                    (
+                        String::from("memory"),
+                        if self.mode == Mode::Memory {
+                            format_bytes(process.usage as f64)
+                        } else {
+                            String::new()
+                        },
+                    ),
+                ]);
+                (render_format(&self.common.formats[0], &values), values)
+            }
+            _ => (self.common.formats[1].clone(), HashMap::new()),
+        };
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Top {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `mode`: what to rank processes by, either `cpu` or `memory`
+    ///   - type: String
+    ///   - default: `cpu`
+    ///
+    /// - `interval`: how long to wait in seconds between each sample
+    ///   - type: u64
+    ///   - default: 5
+    ///
+    /// - `threshold`: the percentage below which the panel is hidden. CPU usage
+    ///   is a percentage of one CPU, so it can exceed 100 for multithreaded
+    ///   processes. Memory usage is a percentage of total memory.
+    ///   - type: f64
+    ///   - default: 10
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `TOP: {name} {percent}%`
+    ///   - formatting options: `{name}`, `{pid}`, `{percent}`, `{memory}` (the
+    ///     resident memory, in `memory` mode only)
+    ///
+    /// - `format_idle`: the format string when no process is above the
+    ///   threshold
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = TopBuilder::default();
+
+        if let Some(mode) = remove_string_from_config("mode", table) {
+            builder.mode(match mode.as_str() {
+                "cpu" => Mode::Cpu,
+                "memory" => Mode::Memory,
+                _ => return Err(anyhow!("Invalid top mode {mode}")),
+            });
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(threshold) = remove_float_from_config("threshold", table) {
+            builder.threshold(threshold);
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_idle"],
+            &["TOP: {name} {percent}%", ""],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::BuildHasher,
+    pin::Pin,
+    process::Command,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::FutureExt;
+use tokio::{
+    task::{self, JoinHandle},
+    time::{sleep, Sleep},
+};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, in_path, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+/// The program used to check for updates.
+#[derive(Debug, Clone)]
+enum Checker {
+    /// `checkupdates` from pacman-contrib (Arch Linux and derivatives)
+    Checkupdates,
+    /// `apt list --upgradable` (Debian, Ubuntu, and derivatives)
+    Apt,
+    /// `dnf check-update` (Fedora, RHEL, and derivatives)
+    Dnf,
+    /// A user-specified command that prints one line per update
+    Custom(String),
+}
+
+impl Checker {
+    fn detect() -> Option<Self> {
+        [
+            ("checkupdates", Self::Checkupdates),
+            ("apt", Self::Apt),
+            ("dnf", Self::Dnf),
+        ]
+        .into_iter()
+        .find(|(bin, _)| in_path(bin))
+        .map(|(_, checker)| checker)
+    }
+
+    fn check(&self) -> Result<usize> {
+        let output = match self {
+            Self::Checkupdates => Command::new("checkupdates").output()?,
+            Self::Apt => Command::new("apt")
+                .args(["list", "--upgradable"])
+                .env("LANG", "C")
+                .output()?,
+            Self::Dnf => Command::new("dnf")
+                .args(["check-update", "--quiet"])
+                .output()?,
+            Self::Custom(cmd) => {
+                Command::new("sh").arg("-c").arg(cmd).output()?
+            }
+        };
+
+        // checkupdates exits with 2 when there are no updates, and dnf exits
+        // with 100 when there are some
+        match (self, output.status.code()) {
+            (_, Some(0))
+            | (Self::Checkupdates, Some(2))
+            | (Self::Dnf, Some(100)) => {}
+            (_, code) => {
+                return Err(anyhow!(
+                    "Update checker {self:?} failed with exit code {code:?}"
+                ))
+            }
+        }
+
+        let stdout = String::from_utf8_lossy(output.stdout.as_slice());
+        let lines = stdout.lines().filter(|l| !l.trim().is_empty());
+        Ok(match self {
+            Self::Apt => lines.filter(|l| l.contains("[upgradable")).count(),
+            // dnf lists obsoleted packages in a separate section
+            Self::Dnf => lines
+                .take_while(|l| !l.starts_with("Obsoleting"))
+                .filter(|l| !l.starts_with(' '))
+                .count(),
+            Self::Checkupdates | Self::Custom(_) => lines.count(),
+        })
+    }
+}
+
+enum Status {
+    Checking,
+    Done(Result<usize>),
+}
+
+struct UpdatesStream {
+    checker: Checker,
+    interval: Duration,
+    jitter: Duration,
+    sleep: Pin<Box<Sleep>>,
+    handle: Option<JoinHandle<Result<usize>>>,
+}
+
+impl UpdatesStream {
+    fn new(checker: Checker, interval: Duration, jitter: Duration) -> Self {
+        Self {
+            checker,
+            interval,
+            jitter,
+            sleep: Box::pin(sleep(Duration::ZERO)),
+            handle: None,
+        }
+    }
+
+    fn next_delay(&self) -> Duration {
+        // RandomState is seeded randomly, which is plenty for spreading out
+        // checks without pulling in an RNG
+        let random = RandomState::new().hash_one(0_u8);
+        let jitter = u64::try_from(self.jitter.as_millis()).unwrap_or(u64::MAX);
+        self.interval
+            + Duration::from_millis(random.checked_rem(jitter).unwrap_or(0))
+    }
+}
+
+impl Stream for UpdatesStream {
+    type Item = Status;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(handle) = &mut self.handle {
+            let value = handle.poll_unpin(cx).map(|r| {
+                Some(Status::Done(
+                    r.map_err(anyhow::Error::from).and_then(|r| r),
+                ))
+            });
+            if value.is_ready() {
+                self.handle = None;
+                let deadline = tokio::time::Instant::now() + self.next_delay();
+                self.sleep.as_mut().reset(deadline);
+            }
+            value
+        } else {
+            let value = self.sleep.poll_unpin(cx);
+            if value.is_ready() {
+                let checker = self.checker.clone();
+                self.handle =
+                    Some(task::spawn_blocking(move || checker.check()));
+                // make sure the handle gets polled so that we're woken when
+                // the check finishes
+                cx.waker().wake_by_ref();
+                Poll::Ready(Some(Status::Checking))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Displays the number of pending package updates.
+///
+/// The checker is detected automatically from the programs available in
+/// `$PATH` (`checkupdates`, `apt`, then `dnf`), or can be specified manually.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Updates {
+    checker: Checker,
+    #[builder(default = "Duration::from_secs(60 * 60)")]
+    interval: Duration,
+    #[builder(default = "Duration::from_secs(5 * 60)")]
+    jitter: Duration,
+    common: PanelCommon,
+}
+
+impl Updates {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        status: Status,
+    ) -> Result<PanelDrawInfo> {
+        let (text, count) = match status {
+            Status::Checking => (self.common.formats[2].clone(), None),
+            Status::Done(Ok(0)) => (self.common.formats[1].clone(), Some(0)),
+            Status::Done(Ok(count)) => (
+                render_format(
+                    &self.common.formats[0],
+                    &HashMap::from([(
+                        String::from("count"),
+                        count.to_string(),
+                    )]),
+                ),
+                Some(count),
+            ),
+            Status::Done(Err(e)) => {
+                // don't leave the panel stuck in the checking state
+                log::warn!("Failed to check for updates: {e}");
+                (self.common.formats[1].clone(), Some(0))
+            }
+        };
+
+        let attrs = count.map_or_else(
+            || self.common.attrs[0].clone(),
+            |c| self.common.states.apply(c as f64, &self.common.attrs[0]),
+        );
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &attrs,
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        // the count is empty while checking
+        draw_info.set_value(
+            "count",
+            count.map(|c| c.to_string()).unwrap_or_default(),
+        );
+        if let Some(state) =
+            count.and_then(|c| self.common.states.find(c as f64))
+        {
+            draw_info.set_value("state", &state.name);
+        }
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Updates {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = UpdatesStream::new(
+            self.checker.clone(),
+            self.interval,
+            self.jitter,
+        )
+        .map(move |status| self.draw(&cr, status));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `command`: a shell command that prints one line per pending update. If
+    ///   this is unset, `checkupdates`, `apt`, or `dnf` is used, depending on
+    ///   which is available.
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 3600
+    ///
+    /// - `jitter`: the maximum random delay in seconds added to each interval,
+    ///   so that many machines don't hit the mirrors at the same moment
+    ///   - type: u64
+    ///   - default: 300
+    ///
+    /// - `format`: the format string when updates are available
+    ///   - type: String
+    ///   - default: `UPD: {count}`
+    ///   - formatting options: `{count}`
+    ///
+    /// - `format_none`: the format string when no updates are available
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///
+    /// - `format_checking`: the format string while a check is running
+    ///   - type: String
+    ///   - default: `UPD: checking…`
+    ///
+    /// - See [`PanelCommon::parse`]. The value `count` is available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = UpdatesBuilder::default();
+
+        builder.checker(
+            remove_string_from_config("command", table)
+                .map(Checker::Custom)
+                .or_else(Checker::detect)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No update checker found. Install checkupdates, apt, \
+                         or dnf, or specify a command."
+                    )
+                })?,
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(jitter) = remove_uint_from_config("jitter", table) {
+            builder.jitter(Duration::from_secs(jitter));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_none", "_checking"],
+            &["UPD: {count}", "", "UPD: checking…"],
+            &[""],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
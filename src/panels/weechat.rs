@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    net::TcpStream,
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    remove_uint_from_config, render_format, Attrs, PanelCommon, PanelConfig,
+    PanelStream,
+};
+
+/// An object in a relay message. Only integers are needed, so everything else
+/// is parsed and discarded.
+#[derive(Debug)]
+enum Object {
+    Int(i32),
+    Array(Vec<Object>),
+    Other,
+}
+
+/// Parses binary messages from the relay. See the WeeChat relay protocol
+/// documentation for the format.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(anyhow!("Truncated WeeChat relay message"));
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    /// Reads a string with a four byte length, which is negative for null.
+    fn string(&mut self) -> Result<String> {
+        let len = self.int()?;
+        let bytes = self.take(len.max(0) as usize)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Reads a string with a one byte length, as used for pointers, longs,
+    /// and times.
+    fn short_string(&mut self) -> Result<String> {
+        let len = self.take(1)?[0];
+        let bytes = self.take(len as usize)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn object_type(&mut self) -> Result<String> {
+        Ok(String::from_utf8_lossy(self.take(3)?).into_owned())
+    }
+
+    fn object(&mut self, object_type: &str) -> Result<Object> {
+        Ok(match object_type {
+            "chr" => {
+                self.take(1)?;
+                Object::Other
+            }
+            "int" => Object::Int(self.int()?),
+            "lon" | "ptr" | "tim" => {
+                self.short_string()?;
+                Object::Other
+            }
+            "str" | "buf" => {
+                self.string()?;
+                Object::Other
+            }
+            "arr" => {
+                let element_type = self.object_type()?;
+                let len = self.int()?;
+                Object::Array(
+                    (0..len)
+                        .map(|_| self.object(element_type.as_str()))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Unsupported WeeChat relay object type {object_type}"
+                ))
+            }
+        })
+    }
+}
+
+/// Message counts summed across all buffers in the hotlist.
+#[derive(Debug, Default)]
+struct Hotlist {
+    buffers: usize,
+    low: i64,
+    messages: i64,
+    private: i64,
+    highlights: i64,
+}
+
+/// Parses the response to `hdata hotlist:gui_hotlist(*) count`.
+fn parse_hotlist(message: &[u8]) -> Result<Hotlist> {
+    let mut reader = Reader { data: message };
+    if reader.take(1)?[0] != 0 {
+        return Err(anyhow!("Unexpected compressed WeeChat relay message"));
+    }
+    // message id
+    reader.string()?;
+    let object_type = reader.object_type()?;
+    if object_type != "hda" {
+        return Err(anyhow!(
+            "Expected hdata from WeeChat relay, got {object_type}"
+        ));
+    }
+
+    let path = reader.string()?;
+    let keys = reader.string()?;
+    let keys = keys
+        .split(',')
+        .filter_map(|k| k.split_once(':'))
+        .collect::<Vec<_>>();
+    let count = reader.int()?;
+
+    let mut hotlist = Hotlist {
+        buffers: count.max(0) as usize,
+        ..Hotlist::default()
+    };
+    for _ in 0..count {
+        // one pointer for each element of the path
+        for _ in path.split('/') {
+            reader.short_string()?;
+        }
+        for (name, object_type) in &keys {
+            let object = reader.object(object_type)?;
+            if let (&"count", Object::Array(counts)) = (name, object) {
+                // counts are indexed by priority: low, message, private,
+                // highlight
+                let counts = counts
+                    .into_iter()
+                    .map(|c| match c {
+                        Object::Int(c) => i64::from(c),
+                        _ => 0,
+                    })
+                    .collect::<Vec<_>>();
+                if let [low, messages, private, highlights] = counts[..] {
+                    hotlist.low += low;
+                    hotlist.messages += messages;
+                    hotlist.private += private;
+                    hotlist.highlights += highlights;
+                }
+            }
+        }
+    }
+
+    Ok(hotlist)
+}
+
+/// Displays unread message and highlight counts from a WeeChat relay.
+///
+/// The relay must use the `weechat` protocol without TLS, e.g. `/relay add
+/// weechat 9000`. To reach a remote WeeChat securely, forward the port over
+/// SSH.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Weechat {
+    #[builder(default = r#"String::from("localhost:9000")"#)]
+    address: String,
+    #[builder(default)]
+    password: Option<String>,
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    common: PanelCommon,
+}
+
+impl Weechat {
+    fn hotlist(&self) -> Result<Hotlist> {
+        let mut stream = TcpStream::connect(self.address.as_str())?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        match &self.password {
+            Some(password) => writeln!(
+                stream,
+                "init password={},compression=off",
+                // commas separate options
+                password.replace(',', "\\,")
+            )?,
+            None => writeln!(stream, "init compression=off")?,
+        }
+        write!(
+            stream,
+            "(hotlist) hdata hotlist:gui_hotlist(*) count\nquit\n"
+        )?;
+
+        let mut len = [0; 4];
+        stream.read_exact(&mut len).map_err(|e| {
+            anyhow!("Failed to read from WeeChat relay (wrong password?): {e}")
+        })?;
+        // the length includes itself
+        let len = (u32::from_be_bytes(len) as usize).saturating_sub(4);
+        let mut message = vec![0; len];
+        stream.read_exact(message.as_mut_slice())?;
+
+        parse_hotlist(message.as_slice())
+    }
+
+    fn draw(&self, cr: &Rc<cairo::Context>) -> Result<PanelDrawInfo> {
+        let hotlist = self.hotlist()?;
+        let idle = hotlist.messages + hotlist.private + hotlist.highlights == 0;
+        let values = HashMap::from([
+            (String::from("highlights"), hotlist.highlights.to_string()),
+            (String::from("private"), hotlist.private.to_string()),
+            (String::from("messages"), hotlist.messages.to_string()),
+            (String::from("low"), hotlist.low.to_string()),
+            (String::from("buffers"), hotlist.buffers.to_string()),
+        ]);
+        let text =
+            render_format(&self.common.formats[usize::from(idle)], &values);
+        let idx = usize::from(hotlist.private + hotlist.highlights > 0);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[idx],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for Weechat {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let normal = self.common.attrs[0].clone();
+        self.common.attrs[1].apply_to(&normal);
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr));
+
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `address`: the address of the relay
+    ///   - type: String
+    ///   - default: `localhost:9000`
+    ///
+    /// - `password`: the relay password (`relay.network.password`)
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `interval`: how long to wait in seconds between each update
+    ///   - type: u64
+    ///   - default: 10
+    ///
+    /// - `format`: the format string when there are unread messages
+    ///   - type: String
+    ///   - default: `IRC: {highlights}/{messages}`
+    ///   - formatting options: `{highlights}`, `{private}`, `{messages}`,
+    ///     `{low}` (joins, parts, etc.), `{buffers}` (the number of buffers in
+    ///     the hotlist)
+    ///
+    /// - `format_idle`: the format string when there are no unread messages,
+    ///   highlights, or private messages
+    ///   - type: String
+    ///   - default: `` (hides the panel)
+    ///   - formatting options: same as `format`
+    ///
+    /// - See [`PanelCommon::parse`]. Two instances of [`Attrs`] are parsed
+    ///   using the prefixes `` and `highlight_`. The latter is used when there
+    ///   are highlights or private messages, and its unset values fall back to
+    ///   the first instance. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = WeechatBuilder::default();
+
+        if let Some(address) = remove_string_from_config("address", table) {
+            builder.address(address);
+        }
+        builder.password(remove_string_from_config("password", table));
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.common(PanelCommon::parse(
+            table,
+            &["", "_idle"],
+            &["IRC: {highlights}/{messages}", ""],
+            &["", "highlight_"],
+        )?);
+
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::task::{self, JoinHandle};
+use tokio_stream::{Stream, StreamExt};
+use xcb::x;
+
+use crate::{
+    bar::PanelDrawInfo, draw_common, remove_bool_from_config,
+    remove_string_from_config, render_format, x::intern_named_atom, Attrs,
+    PanelCommon, PanelConfig, PanelStream,
+};
+
+struct XStream {
+    conn: Arc<xcb::Connection>,
+    log_atom: x::Atom,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl XStream {
+    const fn new(conn: Arc<xcb::Connection>, log_atom: x::Atom) -> Self {
+        Self {
+            conn,
+            log_atom,
+            handle: None,
+        }
+    }
+}
+
+impl Stream for XStream {
+    type Item = ();
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(handle) = &self.handle {
+            if handle.is_finished() {
+                self.handle = None;
+                Poll::Ready(Some(()))
+            } else {
+                Poll::Pending
+            }
+        } else {
+            let conn = self.conn.clone();
+            let waker = cx.waker().clone();
+            let log_atom = self.log_atom;
+            self.handle = Some(task::spawn_blocking(move || loop {
+                let event = conn.wait_for_event();
+                if let Ok(xcb::Event::X(x::Event::PropertyNotify(event))) =
+                    event
+                {
+                    if event.atom() == log_atom {
+                        waker.wake();
+                        break;
+                    }
+                }
+            }));
+            Poll::Pending
+        }
+    }
+}
+
+/// Displays the output of xmonad's status bar logger, read from the
+/// `_XMONAD_LOG` property of the root window.
+///
+/// This is the same mechanism xmobar's `XMonadLog` plugin uses, so
+/// `xmonad-contrib`'s `XMonad.Hooks.StatusBar` (with `xmonadPropLog`) works
+/// without changes. The log may contain pango markup, so a pretty-printer
+/// can style each workspace.
+#[derive(Builder, Debug)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct XMonad {
+    screen: Option<String>,
+    #[builder(default = "true")]
+    markup: bool,
+    common: PanelCommon,
+}
+
+impl XMonad {
+    fn draw(
+        &self,
+        conn: &xcb::Connection,
+        cr: &Rc<cairo::Context>,
+        root: x::Window,
+        log_atom: x::Atom,
+        utf8_atom: x::Atom,
+    ) -> Result<PanelDrawInfo> {
+        let reply =
+            conn.wait_for_reply(conn.send_request(&x::GetProperty {
+                delete: false,
+                window: root,
+                property: log_atom,
+                r#type: utf8_atom,
+                long_offset: 0,
+                long_length: u32::MAX / 4,
+            }))?;
+        let log = String::from_utf8_lossy(reply.value::<u8>());
+
+        let values = HashMap::from([(
+            String::from("log"),
+            if self.markup {
+                log.into_owned()
+            } else {
+                glib::markup_escape_text(log.as_ref()).to_string()
+            },
+        )]);
+        let text = render_format(&self.common.formats[0], &values);
+
+        let mut draw_info = draw_common(
+            cr,
+            text.as_str(),
+            &self.common.attrs[0],
+            self.common.dependence,
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
+    }
+}
+
+impl PanelConfig for XMonad {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
+    fn into_stream(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        _height: i32,
+    ) -> Result<PanelStream> {
+        let (conn, screen) = xcb::Connection::connect(self.screen.as_deref())?;
+        let conn = Arc::new(conn);
+        // the property may not exist until xmonad first logs, so make sure
+        // the atom does
+        let log_atom = conn
+            .wait_for_reply(conn.send_request(&x::InternAtom {
+                only_if_exists: false,
+                name: b"_XMONAD_LOG",
+            }))?
+            .atom();
+        let utf8_atom = intern_named_atom(&conn, b"UTF8_STRING")?;
+        let root = conn
+            .get_setup()
+            .roots()
+            .nth(screen as usize)
+            .ok_or_else(|| anyhow!("Screen not found"))?
+            .root();
+        conn.check_request(conn.send_request_checked(
+            &x::ChangeWindowAttributes {
+                window: root,
+                value_list: &[x::Cw::EventMask(x::EventMask::PROPERTY_CHANGE)],
+            },
+        ))?;
+
+        for attr in &mut self.common.attrs {
+            attr.apply_to(&global_attrs);
+        }
+
+        let stream = tokio_stream::once(())
+            .chain(XStream::new(conn.clone(), log_atom))
+            .map(move |()| self.draw(&conn, &cr, root, log_atom, utf8_atom));
+        Ok(Box::pin(stream))
+    }
+
+    /// Configuration options:
+    ///
+    /// - `screen`: the name of the X screen to monitor
+    ///   - type: String
+    ///   - default: None (This will tell X to choose the default screen, which
+    ///     is probably what you want.)
+    ///
+    /// - `markup`: whether to interpret the log as pango markup. Disable this
+    ///   if the log contains unescaped `<` or `&`.
+    ///   - type: bool
+    ///   - default: true
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `{log}`
+    ///   - formatting options: `{log}`
+    ///
+    /// - See [`PanelCommon::parse`]. The formatting options are available to
+    ///   `visible_when`.
+    fn parse(
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = XMonadBuilder::default();
+        builder.screen(remove_string_from_config("screen", table));
+        if let Some(markup) = remove_bool_from_config("markup", table) {
+            builder.markup(markup);
+        }
+
+        builder.common(PanelCommon::parse(table, &[""], &["{log}"], &[""])?);
+
+        Ok(builder.build()?)
+    }
+}
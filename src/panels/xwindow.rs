@@ -14,7 +14,7 @@ use tokio_stream::{Stream, StreamExt};
 use xcb::{x, XidNew};
 
 use crate::{
-    bar::PanelDrawInfo, draw_common, remove_string_from_config,
+    bar::PanelDrawInfo, draw_common, remove_string_from_config, render_format,
     x::intern_named_atom, Attrs, PanelCommon, PanelConfig, PanelStream,
 };
 
@@ -79,12 +79,11 @@ impl Stream for XStream {
 /// Displays the title (_NET_WM_NAME) of the focused window (_NET_ACTIVE_WINDOW)
 ///
 /// Requires an EWMH-compliant window manager
-#[derive(Builder)]
+#[derive(Builder, Debug)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
 pub struct XWindow {
-    conn: Arc<xcb::Connection>,
-    screen: i32,
+    screen: Option<String>,
     windows: HashSet<x::Window>,
     common: PanelCommon,
 }
@@ -92,15 +91,15 @@ pub struct XWindow {
 impl XWindow {
     fn draw(
         &mut self,
+        conn: &xcb::Connection,
         cr: &Rc<cairo::Context>,
         name_atom: x::Atom,
         window_atom: x::Atom,
         root: x::Window,
         utf8_atom: x::Atom,
     ) -> Result<PanelDrawInfo> {
-        let active: u32 = self
-            .conn
-            .wait_for_reply(self.conn.send_request(&x::GetProperty {
+        let active: u32 = conn
+            .wait_for_reply(conn.send_request(&x::GetProperty {
                 delete: false,
                 window: root,
                 property: window_atom,
@@ -115,7 +114,7 @@ impl XWindow {
             let window = unsafe { x::Window::new(active) };
 
             if self.windows.insert(window) {
-                self.conn.check_request(self.conn.send_request_checked(
+                conn.check_request(conn.send_request_checked(
                     &x::ChangeWindowAttributes {
                         window,
                         value_list: &[x::Cw::EventMask(
@@ -125,9 +124,8 @@ impl XWindow {
                 ))?;
             }
 
-            let bytes = self
-                .conn
-                .wait_for_reply(self.conn.send_request(&x::GetProperty {
+            let bytes = conn
+                .wait_for_reply(conn.send_request(&x::GetProperty {
                     delete: false,
                     window,
                     property: name_atom,
@@ -144,38 +142,47 @@ impl XWindow {
             unsafe { String::from_utf8_unchecked(bytes) }
         };
 
-        let text = self.common.formats[0].replace(
-            "%name%",
-            glib::markup_escape_text(name.as_str()).as_str(),
-        );
+        let values = HashMap::from([(
+            String::from("name"),
+            self.common.escape_markup.apply(name.as_str()),
+        )]);
+        let text = render_format(&self.common.formats[0], &values);
 
-        draw_common(
+        let mut draw_info = draw_common(
             cr,
             text.as_str(),
             &self.common.attrs[0],
             self.common.dependence,
-        )
+            &self.common.ellipsize,
+        )?;
+        draw_info.values.extend(values);
+        Ok(draw_info)
     }
 }
 
 impl PanelConfig for XWindow {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
         global_attrs: Attrs,
         _height: i32,
     ) -> Result<PanelStream> {
-        let name_atom = intern_named_atom(&self.conn, b"_NET_WM_NAME")?;
-        let window_atom = intern_named_atom(&self.conn, b"_NET_ACTIVE_WINDOW")?;
-        let utf8_atom = intern_named_atom(&self.conn, b"UTF8_STRING")?;
-        let root = self
-            .conn
+        let (conn, screen) = xcb::Connection::connect(self.screen.as_deref())?;
+        let conn = Arc::new(conn);
+        let name_atom = intern_named_atom(&conn, b"_NET_WM_NAME")?;
+        let window_atom = intern_named_atom(&conn, b"_NET_ACTIVE_WINDOW")?;
+        let utf8_atom = intern_named_atom(&conn, b"UTF8_STRING")?;
+        let root = conn
             .get_setup()
             .roots()
-            .nth(self.screen as usize)
+            .nth(screen as usize)
             .ok_or_else(|| anyhow!("Screen not found"))?
             .root();
-        self.conn.check_request(self.conn.send_request_checked(
+        conn.check_request(conn.send_request_checked(
             &x::ChangeWindowAttributes {
                 window: root,
                 value_list: &[x::Cw::EventMask(x::EventMask::PROPERTY_CHANGE)],
@@ -187,9 +194,9 @@ impl PanelConfig for XWindow {
         }
 
         let stream = tokio_stream::once(())
-            .chain(XStream::new(self.conn.clone(), name_atom, window_atom))
+            .chain(XStream::new(conn.clone(), name_atom, window_atom))
             .map(move |_| {
-                self.draw(&cr, name_atom, window_atom, root, utf8_atom)
+                self.draw(&conn, &cr, name_atom, window_atom, root, utf8_atom)
             });
         Ok(Box::pin(stream))
     }
@@ -202,8 +209,8 @@ impl PanelConfig for XWindow {
     ///     is probably what you want.)
     /// - `format`: the format string
     ///   - type: String
-    ///   - default: `%name%`
-    ///   - formatting options: `%name%`
+    ///   - default: `{name}`
+    ///   - formatting options: `{name}`
     ///
     /// - `attrs`: See [`Attrs::parse`] for parsing options
     fn parse(
@@ -211,16 +218,10 @@ impl PanelConfig for XWindow {
         _global: &Config,
     ) -> Result<Self> {
         let mut builder = XWindowBuilder::default();
-        let screen = remove_string_from_config("screen", table);
-        if let Ok((conn, screen)) = xcb::Connection::connect(screen.as_deref())
-        {
-            builder.conn(Arc::new(conn)).screen(screen);
-        } else {
-            log::error!("Failed to connect to X server");
-        }
+        builder.screen(remove_string_from_config("screen", table));
 
         builder.windows(HashSet::new());
-        builder.common(PanelCommon::parse(table, &[""], &["%name%"], &[""])?);
+        builder.common(PanelCommon::parse(table, &[""], &["{name}"], &[""])?);
 
         Ok(builder.build()?)
     }
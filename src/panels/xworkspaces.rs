@@ -4,20 +4,26 @@ use std::{
     rc::Rc,
     sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use anyhow::{anyhow, Result};
 use config::{Config, Value};
 use derive_builder::Builder;
 use pangocairo::functions::{create_layout, show_layout};
-use tokio::task::{self, JoinHandle};
+use tokio::{
+    task::{self, JoinHandle},
+    time::{self, Instant, Interval, MissedTickBehavior},
+};
 use tokio_stream::{Stream, StreamExt};
 use xcb::{x, XidNew};
 
 use crate::{
-    bar::PanelDrawInfo, remove_string_from_config, remove_uint_from_config,
-    x::intern_named_atom, Attrs, Highlight, PanelCommon, PanelConfig,
-    PanelStream,
+    animation::FRAME,
+    bar::{Dependence, PanelDrawInfo},
+    remove_string_from_config, remove_uint_from_config,
+    x::intern_named_atom,
+    Attrs, Easing, Highlight, PanelCommon, PanelConfig, PanelStream,
 };
 
 struct XStream {
@@ -87,22 +93,223 @@ impl Stream for XStream {
 /// Display information about workspaces
 ///
 /// Requires an EWMH-compliant window manager
-#[derive(Clone, Builder)]
+#[derive(Clone, Builder, Debug)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
 pub struct XWorkspaces {
-    conn: Arc<xcb::Connection>,
-    screen: i32,
+    screen: Option<String>,
     #[builder(default = "0")]
     padding: i32,
     #[builder(setter(strip_option))]
     highlight: Option<Highlight>,
+    #[builder(default)]
+    highlight_duration: Duration,
+    #[builder(default = "Easing::EaseOut")]
+    highlight_easing: Easing,
     common: PanelCommon,
 }
 
+/// The workspaces laid out and ready to be drawn
+struct Workspaces {
+    /// The name of each workspace and the [`Attrs`] it's drawn with
+    layouts: Vec<(pango::Layout, Attrs)>,
+    current: usize,
+    padding: i32,
+    height: i32,
+    highlight: Option<Highlight>,
+    dependence: Dependence,
+}
+
+impl Workspaces {
+    fn width(&self) -> i32 {
+        self.layouts
+            .iter()
+            .map(|(layout, _)| layout.pixel_size().0 + self.padding)
+            .sum::<i32>()
+            - self.padding
+    }
+
+    /// Returns the horizontal position and width of the current workspace.
+    fn current_extents(&self) -> Option<(f64, f64)> {
+        let mut x = 0;
+        for (i, (layout, _)) in self.layouts.iter().enumerate() {
+            let width = layout.pixel_size().0 + self.padding;
+            if i == self.current {
+                return Some((f64::from(x), f64::from(width)));
+            }
+            x += width;
+        }
+        None
+    }
+
+    /// Draws the workspaces with the highlight at `extents`, which may be
+    /// between two workspaces.
+    fn draw(self: &Rc<Self>, extents: Option<(f64, f64)>) -> PanelDrawInfo {
+        let workspaces = self.clone();
+        PanelDrawInfo::new(
+            (self.width(), self.height),
+            self.dependence,
+            Box::new(move |cr| workspaces.paint(cr, extents)),
+        )
+    }
+
+    fn paint(
+        &self,
+        cr: &cairo::Context,
+        extents: Option<(f64, f64)>,
+    ) -> Result<()> {
+        let height = f64::from(self.height);
+
+        cr.save()?;
+        for (layout, attrs) in &self.layouts {
+            let width = f64::from(layout.pixel_size().0 + self.padding);
+            attrs.apply_bg(cr, width, height);
+            cr.rectangle(0.0, 0.0, width, height);
+            cr.fill()?;
+            cr.translate(width, 0.0);
+        }
+        cr.restore()?;
+
+        if let (Some(highlight), Some((x, width))) = (&self.highlight, extents)
+        {
+            cr.rectangle(x, height - highlight.height, width, highlight.height);
+            cr.set_source_rgba(
+                highlight.color.r,
+                highlight.color.g,
+                highlight.color.b,
+                highlight.color.a,
+            );
+            cr.fill()?;
+        }
+
+        cr.save()?;
+        for (layout, attrs) in &self.layouts {
+            let size = layout.pixel_size();
+
+            cr.save()?;
+            cr.translate(
+                f64::from(self.padding / 2),
+                f64::from(self.height - size.1) / 2.0,
+            );
+            attrs.apply_fg(cr, f64::from(size.0), f64::from(size.1));
+            show_layout(cr, layout);
+            cr.restore()?;
+
+            cr.translate(f64::from(size.0 + self.padding), 0.0);
+        }
+        cr.restore()?;
+
+        Ok(())
+    }
+}
+
+/// The highlight moving from one workspace to another
+struct Slide {
+    from: (f64, f64),
+    to: (f64, f64),
+    start: Instant,
+}
+
+/// A [`PanelStream`] that produces frames while the highlight slides between
+/// workspaces
+struct SlideStream<S> {
+    inner: S,
+    duration: Duration,
+    easing: Easing,
+    workspaces: Option<Rc<Workspaces>>,
+    slide: Option<Slide>,
+    interval: Interval,
+}
+
+impl<S> SlideStream<S> {
+    fn new(inner: S, duration: Duration, easing: Easing) -> Self {
+        let mut interval = time::interval(FRAME);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        Self {
+            inner,
+            duration,
+            easing,
+            workspaces: None,
+            slide: None,
+            interval,
+        }
+    }
+
+    /// Returns where the highlight is right now, ending the slide if it's
+    /// done.
+    fn extents(&mut self) -> Option<(f64, f64)> {
+        if let Some(slide) = &self.slide {
+            let t = slide.start.elapsed().as_secs_f64()
+                / self.duration.as_secs_f64().max(f64::EPSILON);
+            if t < 1.0 {
+                let t = self.easing.apply(t);
+                return Some((
+                    (slide.to.0 - slide.from.0).mul_add(t, slide.from.0),
+                    (slide.to.1 - slide.from.1).mul_add(t, slide.from.1),
+                ));
+            }
+            self.slide = None;
+        }
+        self.workspaces.as_ref()?.current_extents()
+    }
+
+    fn frame(&mut self) -> PanelDrawInfo {
+        let Some(workspaces) = self.workspaces.clone() else {
+            unreachable!("frames are only produced after the first layout");
+        };
+        let extents = self.extents();
+        workspaces.draw(extents)
+    }
+}
+
+impl<S> Stream for SlideStream<S>
+where
+    S: Stream<Item = Result<Workspaces>> + Unpin,
+{
+    type Item = Result<PanelDrawInfo>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(workspaces))) => {
+                let from = this.extents();
+                let to = workspaces.current_extents();
+                this.slide = match (from, to) {
+                    (Some(from), Some(to))
+                        if from != to && !this.duration.is_zero() =>
+                    {
+                        this.interval.reset();
+                        Some(Slide {
+                            from,
+                            to,
+                            start: Instant::now(),
+                        })
+                    }
+                    _ => None,
+                };
+                this.workspaces = Some(Rc::new(workspaces));
+                Poll::Ready(Some(Ok(this.frame())))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+            Poll::Pending if this.slide.is_some() => {
+                match this.interval.poll_tick(cx) {
+                    Poll::Ready(_) => Poll::Ready(Some(Ok(this.frame()))),
+                    Poll::Pending => Poll::Pending,
+                }
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 impl XWorkspaces {
-    fn draw(
+    fn layout(
         &self,
+        conn: &xcb::Connection,
         cr: &Rc<cairo::Context>,
         root: x::Window,
         height: i32,
@@ -114,17 +321,12 @@ impl XWorkspaces {
         type_atom: x::Atom,
         normal_atom: x::Atom,
         desktop_atom: x::Atom,
-    ) -> Result<PanelDrawInfo> {
-        let workspaces = get_workspaces(
-            &self.conn,
-            root,
-            number_atom,
-            names_atom,
-            utf8_atom,
-        )?;
-        let current = get_current(&self.conn, root, current_atom)?;
+    ) -> Result<Workspaces> {
+        let workspaces =
+            get_workspaces(conn, root, number_atom, names_atom, utf8_atom)?;
+        let current = get_current(conn, root, current_atom)?;
         let nonempty_set = get_nonempty(
-            &self.conn,
+            conn,
             root,
             client_atom,
             type_atom,
@@ -132,138 +334,66 @@ impl XWorkspaces {
             desktop_atom,
         )?;
 
-        // TODO: avoid cloning?
-        let nonempty_set2 = nonempty_set.clone();
-
-        let active = self.common.attrs[0].clone();
-        let nonempty = self.common.attrs[1].clone();
-        let inactive = self.common.attrs[2].clone();
-        let layouts: Vec<_> = workspaces
+        let layouts = workspaces
             .into_iter()
             .enumerate()
-            .map(move |(i, w)| {
+            .map(|(i, w)| {
                 let i = i as u32;
-                let layout = create_layout(cr);
-                if i == current {
-                    active.apply_font(&layout);
-                } else if nonempty_set2.contains(&i) {
-                    nonempty.apply_font(&layout);
+                let attrs = if i == current {
+                    &self.common.attrs[0]
+                } else if nonempty_set.contains(&i) {
+                    &self.common.attrs[1]
                 } else {
-                    inactive.apply_font(&layout);
-                }
+                    &self.common.attrs[2]
+                };
+                let layout = create_layout(cr);
+                attrs.apply_font(&layout);
                 layout.set_text(w.as_str());
-                (i, layout)
+                (layout, attrs.clone())
             })
             .collect();
 
-        let width = layouts
-            .iter()
-            .map(|l| l.1.pixel_size().0 + self.padding)
-            .sum::<i32>()
-            - self.padding;
-
-        let padding = self.padding;
-        let active = self.common.attrs[0].clone();
-        let nonempty = self.common.attrs[1].clone();
-        let inactive = self.common.attrs[2].clone();
-        let highlight = self.highlight.clone();
-
-        Ok(PanelDrawInfo::new(
-            (width, height),
-            self.common.dependence,
-            Box::new(move |cr| {
-                for (i, layout) in &layouts {
-                    if *i == current {
-                        active.apply_bg(cr);
-                    } else if nonempty_set.contains(i) {
-                        nonempty.apply_bg(cr);
-                    } else {
-                        inactive.apply_bg(cr);
-                    }
-
-                    let size = layout.pixel_size();
-
-                    cr.save()?;
-                    cr.rectangle(
-                        0.0,
-                        0.0,
-                        f64::from(size.0 + padding),
-                        f64::from(height),
-                    );
-                    cr.fill()?;
-
-                    if *i == current {
-                        if let Some(highlight) = &highlight {
-                            cr.rectangle(
-                                0.0,
-                                f64::from(height) - highlight.height,
-                                f64::from(size.0 + padding),
-                                highlight.height,
-                            );
-                            cr.set_source_rgba(
-                                highlight.color.r,
-                                highlight.color.g,
-                                highlight.color.b,
-                                highlight.color.a,
-                            );
-                            cr.fill()?;
-                        }
-                    }
-
-                    cr.translate(
-                        f64::from(padding / 2),
-                        f64::from(height - size.1) / 2.0,
-                    );
-
-                    if *i == current {
-                        active.apply_fg(cr);
-                    } else if nonempty_set.contains(i) {
-                        nonempty.apply_fg(cr);
-                    } else {
-                        inactive.apply_fg(cr);
-                    }
-
-                    show_layout(cr, layout);
-                    cr.restore()?;
-
-                    cr.translate(
-                        f64::from(layout.pixel_size().0 + padding),
-                        0.0,
-                    );
-                }
-                Ok(())
-            }),
-        ))
+        Ok(Workspaces {
+            layouts,
+            current: current as usize,
+            padding: self.padding,
+            height,
+            highlight: self.highlight.clone(),
+            dependence: self.common.dependence,
+        })
     }
 }
 
 impl PanelConfig for XWorkspaces {
+    fn common(&self) -> Option<&PanelCommon> {
+        Some(&self.common)
+    }
+
     fn into_stream(
         mut self: Box<Self>,
         cr: Rc<cairo::Context>,
         global_attrs: Attrs,
         height: i32,
     ) -> Result<PanelStream> {
-        let number_atom =
-            intern_named_atom(&self.conn, b"_NET_NUMBER_OF_DESKTOPS")?;
-        let names_atom = intern_named_atom(&self.conn, b"_NET_DESKTOP_NAMES")?;
-        let utf8_atom = intern_named_atom(&self.conn, b"UTF8_STRING")?;
-        let current_atom =
-            intern_named_atom(&self.conn, b"_NET_CURRENT_DESKTOP")?;
-        let client_atom = intern_named_atom(&self.conn, b"_NET_CLIENT_LIST")?;
-        let type_atom = intern_named_atom(&self.conn, b"_NET_WM_WINDOW_TYPE")?;
+        let (conn, screen) = xcb::Connection::connect(self.screen.as_deref())?;
+        let conn = Arc::new(conn);
+        let number_atom = intern_named_atom(&conn, b"_NET_NUMBER_OF_DESKTOPS")?;
+        let names_atom = intern_named_atom(&conn, b"_NET_DESKTOP_NAMES")?;
+        let utf8_atom = intern_named_atom(&conn, b"UTF8_STRING")?;
+        let current_atom = intern_named_atom(&conn, b"_NET_CURRENT_DESKTOP")?;
+        let client_atom = intern_named_atom(&conn, b"_NET_CLIENT_LIST")?;
+        let type_atom = intern_named_atom(&conn, b"_NET_WM_WINDOW_TYPE")?;
         let normal_atom =
-            intern_named_atom(&self.conn, b"_NET_WM_WINDOW_TYPE_NORMAL")?;
-        let desktop_atom = intern_named_atom(&self.conn, b"_NET_WM_DESKTOP")?;
+            intern_named_atom(&conn, b"_NET_WM_WINDOW_TYPE_NORMAL")?;
+        let desktop_atom = intern_named_atom(&conn, b"_NET_WM_DESKTOP")?;
 
-        let root = self
-            .conn
+        let root = conn
             .get_setup()
             .roots()
-            .nth(self.screen as usize)
+            .nth(screen as usize)
             .ok_or_else(|| anyhow!("Screen not found"))?
             .root();
-        self.conn.check_request(self.conn.send_request_checked(
+        conn.check_request(conn.send_request_checked(
             &x::ChangeWindowAttributes {
                 window: root,
                 value_list: &[x::Cw::EventMask(x::EventMask::PROPERTY_CHANGE)],
@@ -274,15 +404,18 @@ impl PanelConfig for XWorkspaces {
             attr.apply_to(&global_attrs);
         }
 
-        let stream = tokio_stream::once(())
+        let duration = self.highlight_duration;
+        let easing = self.highlight_easing;
+        let workspaces = tokio_stream::once(())
             .chain(XStream::new(
-                self.conn.clone(),
+                conn.clone(),
                 number_atom,
                 current_atom,
                 names_atom,
             ))
             .map(move |_| {
-                self.draw(
+                self.layout(
+                    &conn,
                     &cr,
                     root,
                     height,
@@ -296,7 +429,7 @@ impl PanelConfig for XWorkspaces {
                     desktop_atom,
                 )
             });
-        Ok(Box::pin(stream))
+        Ok(Box::pin(SlideStream::new(workspaces, duration, easing)))
     }
 
     /// Configuration options:
@@ -314,6 +447,16 @@ impl PanelConfig for XWorkspaces {
     /// - `highlight`: The highlight that will appear on the active workspaces.
     ///   See [`Highlight::parse`] for parsing options.
     ///
+    /// - `highlight_duration`: how long in milliseconds the highlight takes to
+    ///   slide from one workspace to the next. `0` moves it immediately.
+    ///   - type: u64
+    ///   - default: `0`
+    ///
+    /// - `highlight_easing`: how the highlight speeds up and slows down as it
+    ///   slides: `linear`, `ease_in`, `ease_out`, or `ease_in_out`
+    ///   - type: String
+    ///   - default: `ease_out`
+    ///
     /// - See [`PanelCommon::parse`]. No format strings are used for this panel.
     ///   Three instances of [`Attrs`] are parsed using the prefixes `active_`,
     ///   `nonempty_`, and `inactive_`
@@ -322,16 +465,25 @@ impl PanelConfig for XWorkspaces {
         _global: &Config,
     ) -> Result<Self> {
         let mut builder = XWorkspacesBuilder::default();
-        let screen = remove_string_from_config("screen", table);
-        if let Ok((conn, screen)) = xcb::Connection::connect(screen.as_deref())
-        {
-            builder.conn(Arc::new(conn)).screen(screen);
-        } else {
-            log::error!("Failed to connect to X server");
-        }
+        builder.screen(remove_string_from_config("screen", table));
         if let Some(padding) = remove_uint_from_config("padding", table) {
             builder.padding(padding as i32);
         }
+        if let Some(duration) =
+            remove_uint_from_config("highlight_duration", table)
+        {
+            builder.highlight_duration(Duration::from_millis(duration));
+        }
+        if let Some(easing) =
+            remove_string_from_config("highlight_easing", table)
+        {
+            match easing.parse() {
+                Ok(easing) => {
+                    builder.highlight_easing(easing);
+                }
+                Err(e) => log::warn!("{e}, using `ease_out`"),
+            }
+        }
 
         builder.common(PanelCommon::parse(
             table,
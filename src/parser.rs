@@ -1,48 +1,361 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    os::fd::{AsFd, AsRawFd, RawFd},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
 
 use anyhow::{anyhow, Context, Result};
-use config::{Config, File, FileFormat, Value};
-use lazy_static::lazy_static;
+use config::{Config, ConfigError, File, FileFormat, Map, Source, Value};
+use nix::{
+    errno::Errno,
+    sys::inotify::{self, AddWatchFlags, InitFlags, WatchDescriptor},
+    unistd::gethostname,
+};
+use tokio::{
+    io::unix::AsyncFd,
+    signal::unix::{signal, Signal, SignalKind},
+    time,
+};
 
 use crate::{
     builders::BarConfigBuilder,
-    get_table_from_config,
+    get_monitors, get_table_from_config, palette,
     panels::{
         precision::{Days, Hours, Minutes, Seconds},
-        Battery, Clock, Cpu, Custom, Fanotify, Inotify, Memory, Mpd, Network,
-        Ping, Pulseaudio, Separator, Temp, XWindow, XWorkspaces,
+        Battery, Bspwm, Calendar, ChargeThreshold, Ci, Clock, Containers, Cpu,
+        Crypto, Cups, Custom, DbusProperty, Dnd, Dpms, Dunst, Entropy, Expand,
+        FailedUnits, Fanotify, FileValue, Group, Herbstluftwm, Http, I3Mode,
+        I3Workspaces, IdleInhibit, Inotify, Iwd, Journal, KdeConnect,
+        Kubernetes, Maildir, Matrix, Memory, Mpd, Mqtt, Network, Nut, Ping,
+        Pomodoro, PowerDraw, PowerProfile, Privacy, Pulseaudio, Redshift,
+        ScreenCapture, Separator, Spacer, Stocks, StoragePool, Syncthing, Temp,
+        Timesync, TodoTxt, Top, Transmission, Updates, Weechat, XMonad,
+        XWindow, XWorkspaces,
     },
-    remove_string_from_config, Alignment, Attrs, BarConfig, Margins,
-    PanelConfig, Position,
+    remove_bool_from_config, remove_color_from_config,
+    remove_float_from_config, remove_string_from_config, Alignment, Attrs,
+    Autohide, BackgroundImage, BarConfig, Border, Color, ErrorBadge,
+    ImageScale, Margins, Monitor, PanelConfig, Placement, Position,
+    SeparatorStyle,
 };
 
-lazy_static! {
-    static ref CONFIG: Config = {
-        Config::builder()
-            .add_source(
-                File::new(
-                    format!(
-                        "{}/lazybar/config.toml",
-                        std::env::var("XDG_CONFIG_HOME").unwrap_or_else(|_| {
-                            format!(
-                                "{}/.config",
-                                std::env::var("HOME").unwrap()
-                            )
-                        })
-                    )
-                    .as_str(),
-                    FileFormat::Toml,
-                )
-                .required(true),
-            )
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Whether bars are parsed to be rendered to images. See [`set_headless`].
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+/// The names of panels that can be used without a table.
+const BUILTIN_PANELS: &[&str] = &["spacer", "expand"];
+
+/// Returns the path of the config file,
+/// `$XDG_CONFIG_HOME/lazybar/config.toml`.
+#[must_use]
+pub fn config_path() -> PathBuf {
+    PathBuf::from(format!(
+        "{}/lazybar/config.toml",
+        std::env::var("XDG_CONFIG_HOME").unwrap_or_else(|_| {
+            format!("{}/.config", std::env::var("HOME").unwrap())
+        })
+    ))
+}
+
+/// Returns a config that lists every option of every bar and panel with its
+/// default value, most of them commented out. It's generated from the
+/// documentation of each `parse` function when lazybar is built.
+#[must_use]
+pub const fn default_config() -> &'static str {
+    include_str!(concat!(env!("OUT_DIR"), "/default_config.toml"))
+}
+
+/// Returns a [JSON schema](https://json-schema.org/) that describes the
+/// config file, for editors that can complete and validate TOML with one.
+/// Like [`default_config`], it's generated from the documentation of each
+/// `parse` function.
+#[must_use]
+pub const fn schema() -> &'static str {
+    include_str!(concat!(env!("OUT_DIR"), "/schema.json"))
+}
+
+/// Returns the config file and every file that it includes, in the order
+/// that they should be read. A file's includes come before the file itself,
+/// so that its own values take precedence.
+///
+/// `include` may be a path or an array of paths. Relative paths are resolved
+/// from the directory of the including file, and `*`, `?`, and `[...]` match
+/// like they do in a shell. Paths with wildcards may match nothing, but other
+/// paths must exist.
+fn config_files() -> Result<Vec<PathBuf>> {
+    fn visit(
+        path: PathBuf,
+        stack: &mut Vec<PathBuf>,
+        files: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        if stack.contains(&path) {
+            return Err(anyhow!("{} includes itself", path.display()));
+        }
+        if files.contains(&path) {
+            return Ok(());
+        }
+
+        let config = Config::builder()
+            .add_source(File::from(path.as_path()).format(FileFormat::Toml))
             .build()
-            .unwrap()
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let includes = match config.get::<Value>("include") {
+            Ok(value) => match value.clone().into_array() {
+                Ok(array) => array,
+                Err(_) => vec![value],
+            },
+            Err(_) => Vec::new(),
+        };
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        stack.push(path.clone());
+        for include in includes {
+            let include = include
+                .into_string()
+                .context("`include` must contain only strings")?;
+            let pattern = dir.join(include.as_str());
+            if glob::Pattern::escape(include.as_str()) == include {
+                visit(pattern, stack, files)?;
+            } else {
+                for entry in glob::glob(pattern.to_string_lossy().as_ref())? {
+                    visit(entry?, stack, files)?;
+                }
+            }
+        }
+        stack.pop();
+
+        files.push(path);
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    visit(config_path(), &mut Vec::new(), &mut files)?;
+    Ok(files)
+}
+
+/// Selects the profile to use instead of the one named after the hostname.
+/// This must be called before the config is parsed, and only the first call
+/// has any effect.
+pub fn set_profile(profile: String) {
+    let _ = PROFILE.set(profile);
+}
+
+/// Parses bars to be rendered to images from now on, e.g. with
+/// `BarConfig::render`. `monitors` is ignored, so parsing doesn't need an X
+/// server, and each bar is parsed once without a monitor.
+pub fn set_headless() {
+    HEADLESS.store(true, Ordering::Relaxed);
+}
+
+/// The values in a `[profile.<name>]` table, which override the rest of the
+/// config.
+#[derive(Debug, Clone)]
+struct Profile(Map<String, Value>);
+
+impl Source for Profile {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+        Ok(self.0.clone())
+    }
+}
+
+fn load_config() -> Result<Config> {
+    let mut builder = Config::builder();
+    for file in config_files()? {
+        builder = builder.add_source(
+            File::from(file.as_path())
+                .format(FileFormat::Toml)
+                .required(true),
+        );
+    }
+    let config = builder.build()?;
+    let config = apply_profile(config)?;
+    palette::load(&config);
+    Ok(config)
+}
+
+/// Merges the `[profile.<name>]` table for the selected profile, or the one
+/// named after the hostname, over the rest of the config.
+fn apply_profile(config: Config) -> Result<Config> {
+    let (name, explicit) = match PROFILE.get() {
+        Some(name) => (name.clone(), true),
+        None => match gethostname() {
+            Ok(name) => (name.to_string_lossy().into_owned(), false),
+            Err(_) => return Ok(config),
+        },
     };
+    // keys are stored in lowercase
+    let profile = config
+        .get_table("profile")
+        .ok()
+        .and_then(|mut profiles| profiles.remove(&name.to_lowercase()));
+    match profile {
+        Some(profile) => {
+            Ok(Config::builder()
+                .add_source(config)
+                .add_source(Profile(profile.into_table().with_context(
+                    || format!("Profile {name} isn't a table"),
+                )?))
+                .build()?)
+        }
+        None if explicit => Err(anyhow!("Profile {name} doesn't exist")),
+        None => Ok(config),
+    }
 }
 
-/// Parses a bar with a given name from the global [`Config`]
-pub fn parse(bar_name: Option<&str>) -> Result<BarConfig> {
-    let mut bars_table = CONFIG
+/// Waits for the config file, any file that it includes, or the colors file of
+/// the palette to change, or for the process to receive SIGUSR1.
+pub(crate) struct ConfigWatcher {
+    fd: AsyncFd<RawFd>,
+    inotify: inotify::Inotify,
+    dirs: HashMap<WatchDescriptor, PathBuf>,
+    files: Vec<PathBuf>,
+    signal: Signal,
+}
+
+impl ConfigWatcher {
+    pub(crate) fn new() -> Result<Self> {
+        let inotify = inotify::Inotify::init(InitFlags::IN_NONBLOCK)?;
+        let fd = AsyncFd::new(inotify.as_fd().as_raw_fd())?;
+        let mut watcher = Self {
+            fd,
+            inotify,
+            dirs: HashMap::new(),
+            files: Vec::new(),
+            signal: signal(SignalKind::user_defined1())?,
+        };
+        watcher.watch_files()?;
+        Ok(watcher)
+    }
+
+    /// Starts watching the directory of each config file. This is called
+    /// again after every change, since the set of included files may have
+    /// changed.
+    fn watch_files(&mut self) -> Result<()> {
+        self.files = config_files().unwrap_or_else(|e| {
+            log::warn!("Failed to find included config files: {e}");
+            vec![config_path()]
+        });
+        for file in &self.files {
+            let dir = file.parent().context("Invalid config path")?;
+            // editors often replace files rather than writing to them, so
+            // watch the directory instead
+            let wd = self.inotify.add_watch(
+                dir,
+                AddWatchFlags::IN_CLOSE_WRITE
+                    | AddWatchFlags::IN_MOVED_TO
+                    | AddWatchFlags::IN_CREATE,
+            )?;
+            self.dirs.insert(wd, dir.to_path_buf());
+        }
+        // the colors of pywal or wallust, which recolor the bar when they
+        // change. Their directory may not exist yet, so this isn't an error.
+        if let Some(file) = palette::wal_path() {
+            let dir = file.parent().context("Invalid colors path")?;
+            match self.inotify.add_watch(
+                dir,
+                AddWatchFlags::IN_CLOSE_WRITE
+                    | AddWatchFlags::IN_MOVED_TO
+                    | AddWatchFlags::IN_CREATE,
+            ) {
+                Ok(wd) => {
+                    self.dirs.insert(wd, dir.to_path_buf());
+                    self.files.push(file);
+                }
+                Err(e) => {
+                    log::warn!("Failed to watch {}: {e}", file.display());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits until a config file changes or SIGUSR1 arrives. Saving a file
+    /// can produce several events, so this waits for them to stop before
+    /// returning.
+    pub(crate) async fn changed(&mut self) -> Result<()> {
+        loop {
+            tokio::select! {
+                Some(()) = self.signal.recv() => break,
+                guard = self.fd.readable() => {
+                    guard?.clear_ready();
+                    if self.drain()? {
+                        time::sleep(Duration::from_millis(200)).await;
+                        self.drain()?;
+                        break;
+                    }
+                }
+            }
+        }
+        self.watch_files()
+    }
+
+    /// Discards all pending events and returns whether any of them concern
+    /// a config file.
+    fn drain(&self) -> Result<bool> {
+        let mut changed = false;
+        loop {
+            match self.inotify.read_events() {
+                Ok(events) => {
+                    changed |= events.iter().any(|e| {
+                        let (Some(dir), Some(name)) =
+                            (self.dirs.get(&e.wd), e.name.as_ref())
+                        else {
+                            return false;
+                        };
+                        self.files.contains(&dir.join(name))
+                    });
+                }
+                Err(Errno::EAGAIN) => return Ok(changed),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Parses a bar with a given name from the global [`Config`]. One
+/// [`BarConfig`] is returned for each monitor that the bar should appear on.
+///
+/// The `monitors` option may be `"all"`, a single output name, or an array of
+/// output names, e.g. `["DP-1", "eDP-1"]`. If it isn't set, a single bar spans
+/// the whole screen.
+///
+/// The bar can be moved away from the edge of the monitor with `offset_x` and
+/// `offset_y` (in pixels), and narrowed with `width` (a percentage of the
+/// width of the monitor). The space reserved for the bar accounts for all of
+/// these.
+///
+/// If `autohide` is true, the bar hides when the pointer leaves it for
+/// `hide_timeout` milliseconds (default 1000) and reappears when the pointer
+/// rests at the edge of the monitor for `reveal_delay` milliseconds (default
+/// 200).
+///
+/// Any option of the bar can be overridden for one output in a subtable named
+/// after it, e.g. `[bars.example.DP-1]`. This is useful for showing more
+/// panels on a larger monitor.
+///
+/// If `separator` or `separator_line` is set, it is drawn between adjacent
+/// panels in each part of the bar, skipping panels that are hidden or empty.
+///
+/// The running bars watch the config file and rebuild their panels,
+/// separator, and background color when it changes, when the colors of the
+/// palette change, when the process receives SIGUSR1, or when
+/// `lazybar-msg reload` is run. Changes to other options of the bar take
+/// effect after it's restarted.
+pub fn parse(bar_name: Option<&str>) -> Result<Vec<BarConfig>> {
+    let config = load_config()?;
+    let mut bars_table = config
         .get_table("bars")
         .context("`bars` doesn't exist or isn't a table")?;
 
@@ -60,8 +373,382 @@ pub fn parse(bar_name: Option<&str>) -> Result<BarConfig> {
         .into_table()
         .with_context(|| format!("`{bar_name}` isn't a table"))?;
 
-    let mut bar = BarConfigBuilder::default()
+    let monitors = match bar_table.remove("monitors") {
+        Some(_) if HEADLESS.load(Ordering::Relaxed) => vec![None],
+        Some(monitors) => {
+            parse_monitors(monitors)?.into_iter().map(Some).collect()
+        }
+        None => vec![None],
+    };
+
+    monitors
+        .into_iter()
+        .map(|monitor| {
+            let mut table = bar_table.clone();
+            // a subtable named after the output overrides the bar's options.
+            // keys are stored in lowercase, so `DP-1` is found as `dp-1`
+            if let Some(overrides) = monitor.as_ref().and_then(|m| {
+                get_table_from_config(
+                    m.name.to_lowercase().as_str(),
+                    &bar_table,
+                )
+            }) {
+                table.extend(overrides);
+            }
+            parse_bar(bar_name.clone(), table, monitor, &config)
+        })
+        .collect()
+}
+
+/// Parses every bar and panel in the config without opening any windows.
+/// Returns a description of each problem found, prefixed with its location in
+/// the config, e.g. `panels.clock: Unknown panel type clok`.
+///
+/// The bars themselves don't connect to the X server, so monitor names aren't
+/// checked. Instead, bars are checked once with their own options and once
+/// with each subtable that could be an override for an output.
+///
+/// Panels are parsed exactly as they are when a bar starts. Panels only
+/// connect to the X server or to the service they show in
+/// [`PanelConfig::into_stream`], so neither has to be reachable, and problems
+/// connecting aren't reported.
+///
+/// # Errors
+///
+/// If the config can't be read at all.
+pub fn check() -> Result<Vec<String>> {
+    let config = load_config()?;
+    let bars_table = config
+        .get_table("bars")
+        .context("`bars` doesn't exist or isn't a table")?;
+    let panels_table = config.get_table("panels").unwrap_or_default();
+    let mut problems = Vec::new();
+
+    let mut bar_names = bars_table.keys().collect::<Vec<_>>();
+    bar_names.sort();
+    for name in bar_names {
+        let bar_table = match bars_table[name].clone().into_table() {
+            Ok(table) => table,
+            Err(e) => {
+                problems.push(format!("bars.{name}: {e}"));
+                continue;
+            }
+        };
+
+        if let Some(monitors) = bar_table.get("monitors") {
+            let valid = monitors.clone().into_string().is_ok()
+                || monitors.clone().into_array().is_ok_and(|monitors| {
+                    monitors.into_iter().all(|m| m.into_string().is_ok())
+                });
+            if !valid {
+                problems.push(format!(
+                    "bars.{name}.monitors: `monitors` isn't a string or an \
+                     array of strings"
+                ));
+            }
+        }
+
+        let mut overrides = bar_table
+            .iter()
+            .filter_map(|(k, v)| Some((k, v.clone().into_table().ok()?)))
+            .collect::<Vec<_>>();
+        overrides.sort_by(|a, b| a.0.cmp(b.0));
+        // problems that an override doesn't fix are only reported once
+        let mut base = Vec::new();
+        for (output, overrides) in std::iter::once((None, Map::new()))
+            .chain(overrides.into_iter().map(|(k, v)| (Some(k), v)))
+        {
+            let location = output.map_or_else(
+                || format!("bars.{name}"),
+                |output| format!("bars.{name}.{output}"),
+            );
+            let mut table = bar_table.clone();
+            table.extend(overrides);
+            let mut found = Vec::new();
+            if let Err(e) = parse_bar_options(name.clone(), &mut table, None) {
+                found.push(e.to_string());
+            }
+            for key in ["panels_left", "panels_center", "panels_right"] {
+                match panel_names(&mut table, key) {
+                    Ok(names) => found.extend(
+                        names
+                            .into_iter()
+                            .filter(|p| {
+                                !panels_table.contains_key(p)
+                                    && !BUILTIN_PANELS.contains(&p.as_str())
+                            })
+                            .map(|p| {
+                                format!("`{key}`: Panel `{p}` doesn't exist")
+                            }),
+                    ),
+                    Err(e) => found.push(e.to_string()),
+                }
+            }
+            if output.is_none() {
+                base.clone_from(&found);
+            }
+            problems.extend(
+                found
+                    .into_iter()
+                    .filter(|p| output.is_none() || !base.contains(p))
+                    .map(|p| format!("{location}: {p}")),
+            );
+        }
+    }
+
+    let mut panel_names = panels_table.keys().collect::<Vec<_>>();
+    panel_names.sort();
+    for name in panel_names {
+        if let Err(e) = build_panel(name.as_str(), &panels_table, &config) {
+            problems.push(format!("panels.{name}: {e}"));
+        }
+    }
+
+    Ok(problems)
+}
+
+fn parse_monitors(monitors: Value) -> Result<Vec<Monitor>> {
+    let available = get_monitors()?;
+    let names = match monitors.clone().into_string() {
+        Ok(name) if name == "all" => {
+            available.iter().map(|m| m.name.clone()).collect()
+        }
+        Ok(name) => vec![name],
+        Err(_) => monitors
+            .into_array()
+            .context("`monitors` isn't a string or an array")?
+            .into_iter()
+            .filter_map(|m| {
+                m.clone().into_string().map_or_else(
+                    |_| {
+                        log::warn!(
+                            "Ignoring non-string value {m:?} in `monitors`"
+                        );
+                        None
+                    },
+                    Some,
+                )
+            })
+            .collect(),
+    };
+
+    let monitors = names
+        .into_iter()
+        .filter_map(|name| {
+            let monitor = available.iter().find(|m| m.name == name).cloned();
+            if monitor.is_none() {
+                log::warn!("Ignoring unknown monitor {name}");
+            }
+            monitor
+        })
+        .collect::<Vec<_>>();
+    if monitors.is_empty() {
+        return Err(anyhow!("No monitors matched `monitors`"));
+    }
+    Ok(monitors)
+}
+
+fn parse_bar(
+    bar_name: String,
+    mut bar_table: Map<String, Value>,
+    monitor: Option<Monitor>,
+    config: &Config,
+) -> Result<BarConfig> {
+    let mut bar = parse_bar_options(bar_name, &mut bar_table, monitor)?;
+
+    let left = panel_names(&mut bar_table, "panels_left")?;
+    let center = panel_names(&mut bar_table, "panels_center")?;
+    let right = panel_names(&mut bar_table, "panels_right")?;
+
+    let panels_table = config
+        .get_table("panels")
+        .context("`panels` doesn't exist or isn't a table")?;
+
+    for (names, alignment) in [
+        (left, Alignment::Left),
+        (center, Alignment::Center),
+        (right, Alignment::Right),
+    ] {
+        for name in names {
+            if let Some(panel) =
+                parse_panel(name.as_str(), &panels_table, config)
+            {
+                bar.add_panel(name, panel, alignment);
+            }
+        }
+    }
+
+    let config = config.clone();
+    bar.rebuild_panel = Some(Rc::new(move |name: &str| {
+        build_panel(name, &panels_table, &config)
+    }));
+
+    Ok(bar)
+}
+
+/// Parses the options of a bar other than its panels.
+///
+/// Configuration options:
+///
+/// - `position`: the edge of the monitor that the bar is attached to, `top` or
+///   `bottom`
+///   - type: String
+///   - default: `top`
+///
+/// - `height`: the height of the bar in pixels, before `scale` is applied
+///   - type: u16
+///   - default: `24`
+///
+/// - `scale`: how many pixels on the screen make up one pixel of the bar, for
+///   high-DPI monitors. The height, margins, fonts, icons, and everything else
+///   drawn on the bar are multiplied by this. `auto` detects it from `Xft.dpi`
+///   in the X resource database, or from the physical size of the monitor.
+///   - type: f64 or String
+///   - default: `1`
+///
+/// - `transparent`: whether the bar should be drawn with an alpha channel. This
+///   requires a compositor.
+///   - type: bool
+///   - default: `false`
+///
+/// - `bg`: the background color of the bar
+///   - type: String
+///   - default: black
+///
+/// - `default_fg`, `default_bg`, `default_font_features`: the attributes that
+///   panels fall back to. See [`Attrs::parse`].
+///   - type: String
+///   - default: white, black, and no font features
+///
+/// - `default_font`: the font that panels fall back to, or an array of fonts
+///   to fall back on for characters that the first one doesn't have, e.g.
+///   `["JetBrainsMono Nerd Font 10", "Noto Color Emoji"]`
+///   - type: String or array
+///   - default: a font chosen by [pango]
+///
+/// - `margin_left`, `margin_internal`, `margin_right`: the space in pixels at
+///   the left edge of the bar, between the groups of panels, and at the right
+///   edge of the bar
+///   - type: f64
+///   - default: `0`
+///
+/// - `monitors`: `"all"`, the name of an output, or an array of them
+///   - type: String or Array
+///   - default: none (one bar spans the whole screen)
+///
+/// - `offset_x`, `offset_y`: the distance in pixels between the bar and the
+///   edge of the monitor
+///   - type: i16
+///   - default: `0`
+///
+/// - `width`: the width of the bar, as a percentage of the width of the monitor
+///   - type: f64
+///   - default: `100`
+///
+/// - `autohide`: whether the bar hides when the pointer leaves it
+///   - type: bool
+///   - default: `false`
+///
+/// - `reveal_delay`: how long in milliseconds the pointer has to rest at the
+///   edge of the monitor to show a hidden bar
+///   - type: u64
+///   - default: `200`
+///
+/// - `hide_timeout`: how long in milliseconds after the pointer leaves the bar
+///   it hides
+///   - type: u64
+///   - default: `1000`
+///
+/// - `separator`: the text drawn between adjacent panels. This is parsed as
+///   pango markup.
+///   - type: String
+///   - default: none
+///
+/// - `separator_line`: the width in pixels of a vertical line drawn between
+///   adjacent panels instead of `separator`
+///   - type: f64
+///   - default: none
+///
+/// - `separator_padding`: the space in pixels on each side of the line
+///   - type: f64
+///   - default: `4`
+///
+/// - `separator_inset`: the space in pixels between the line and the top and
+///   bottom edges of the bar
+///   - type: f64
+///   - default: `4`
+///
+/// - `separator_fg`, `separator_bg`, `separator_font`: the attributes of the
+///   separator, falling back to the `default_` attributes. See
+///   [`Attrs::parse`].
+///   - type: String
+///   - default: none
+///
+/// - `error_badge`: the text drawn in place of a panel that has failed. This
+///   is parsed as pango markup. Panels that panic or fail to start are
+///   restarted, waiting longer each time they fail in a row. An empty string
+///   leaves failed panels empty.
+///   - type: String
+///   - default: `!`
+///
+/// - `error_fg`, `error_bg`, `error_font`: the attributes of the error badge,
+///   falling back to the `default_` attributes. See [`Attrs::parse`].
+///   - type: String
+///   - default: `#f00` for `error_fg`, none for the others
+///
+/// - `bg_image`: the path to an image (PNG, JPEG, etc.) to draw behind the
+///   panels, relative to the directory of the config file
+///   - type: String
+///   - default: none
+///
+/// - `bg_image_scale`: how the image is fitted to the bar: `tile`, `stretch`,
+///   `fill` (cover the bar, keeping the aspect ratio), or `center`
+///   - type: String
+///   - default: `fill`
+///
+/// - `bg_wallpaper`: whether to draw the part of the desktop wallpaper behind
+///   the bar instead of `bg_image`. This works with wallpapers set by tools
+///   like `feh` and `nitrogen`.
+///   - type: bool
+///   - default: `false`
+///
+/// - `icon_theme`: the name of the icon theme used for icons like
+///   `{icon:battery-full}` in format strings
+///   - type: String
+///   - default: the theme chosen in the GTK settings, or `hicolor`
+///
+/// - `corner_radius`: the radius in pixels of the corners of the bar
+///   - type: f64
+///   - default: `0`
+///
+/// - `border`: the width in pixels of the line around the edge of the bar. It's
+///   drawn over the edges of the panels.
+///   - type: f64
+///   - default: `0`
+///
+/// - `border_color`: the color of the line around the edge of the bar
+///   - type: String
+///   - default: `default_fg`, or white
+///
+/// - `debug_overlay`: whether to show how often each panel updates and how
+///   long it takes to draw in a window below the bar. This can also be toggled
+///   with `lazybar-msg debug`.
+///   - type: bool
+///   - default: `false`
+///
+/// - `panels_left`, `panels_center`, `panels_right`: the names of the panels in
+///   each part of the bar, in order
+///   - type: Array of String
+///   - default: none
+fn parse_bar_options(
+    bar_name: String,
+    bar_table: &mut Map<String, Value>,
+    monitor: Option<Monitor>,
+) -> Result<BarConfig> {
+    Ok(BarConfigBuilder::default()
         .name(bar_name)
+        .watch_config(true)
+        .monitor(monitor)
         .position(
             match bar_table
                 .remove("position")
@@ -75,6 +762,23 @@ pub fn parse(bar_name: Option<&str>) -> Result<BarConfig> {
                 _ => Position::Top,
             },
         )
+        .placement(Placement {
+            x: bar_table
+                .remove("offset_x")
+                .unwrap_or_default()
+                .into_int()
+                .unwrap_or_default() as i16,
+            y: bar_table
+                .remove("offset_y")
+                .unwrap_or_default()
+                .into_int()
+                .unwrap_or_default() as i16,
+            width: bar_table
+                .remove("width")
+                .unwrap_or_default()
+                .into_float()
+                .unwrap_or(100.0),
+        })
         .height(
             bar_table
                 .remove("height")
@@ -82,6 +786,18 @@ pub fn parse(bar_name: Option<&str>) -> Result<BarConfig> {
                 .into_uint()
                 .unwrap_or(24) as u16,
         )
+        .scale(bar_table.remove("scale").map_or(Some(1.0), |scale| {
+            match scale.clone().into_string().as_deref() {
+                Ok("auto") => None,
+                _ => Some(
+                    scale
+                        .into_float()
+                        .ok()
+                        .filter(|scale| *scale > 0.0)
+                        .unwrap_or(1.0),
+                ),
+            }
+        }))
         .transparent(
             bar_table
                 .remove("transparent")
@@ -113,182 +829,368 @@ pub fn parse(bar_name: Option<&str>) -> Result<BarConfig> {
                 .into_float()
                 .unwrap_or_default(),
         ))
-        .attrs(Attrs::parse_global(&mut bar_table, "default_"))
+        .autohide(
+            bar_table
+                .remove("autohide")
+                .unwrap_or_default()
+                .into_bool()
+                .unwrap_or_default()
+                .then(|| Autohide {
+                    reveal_delay: Duration::from_millis(
+                        bar_table
+                            .remove("reveal_delay")
+                            .unwrap_or_default()
+                            .into_uint()
+                            .unwrap_or(200),
+                    ),
+                    hide_timeout: Duration::from_millis(
+                        bar_table
+                            .remove("hide_timeout")
+                            .unwrap_or_default()
+                            .into_uint()
+                            .unwrap_or(1000),
+                    ),
+                }),
+        )
+        // the border falls back to `default_fg`, so it's parsed first
+        .corner_radius(
+            remove_float_from_config("corner_radius", bar_table)
+                .unwrap_or_default(),
+        )
+        .border(parse_border(bar_table))
+        .bg_image(parse_bg_image(bar_table))
+        .icon_theme(remove_string_from_config("icon_theme", bar_table))
+        .attrs(Attrs::parse_global(bar_table, "default_"))
+        .separator(parse_separator(bar_table))
+        .error_badge(parse_error_badge(bar_table))
+        .debug_overlay(
+            remove_bool_from_config("debug_overlay", bar_table)
+                .unwrap_or_default(),
+        )
         .left(Vec::new())
         .center(Vec::new())
         .right(Vec::new())
-        .build()?;
-
-    let mut left_final = Vec::new();
-    let mut center_final = Vec::new();
-    let mut right_final = Vec::new();
-
-    let panels_left = bar_table.remove("panels_left");
-    if let Some(pl) = panels_left {
-        let panel_list =
-            pl.into_array().context("`panels_left` isn't an array")?;
-        for p in panel_list {
-            if let Ok(name) = p.clone().into_string() {
-                left_final.push(name);
-            } else {
-                log::warn!("Ignoring non-string value {p:?} in `panels_left`");
-            }
-        }
-    }
+        .build()?)
+}
 
-    let panels_center = bar_table.remove("panels_center");
-    if let Some(pc) = panels_center {
-        let panel_list =
-            pc.into_array().context("`panels_center` isn't an array")?;
-        for p in panel_list {
-            if let Ok(name) = p.clone().into_string() {
-                center_final.push(name);
-            } else {
-                log::warn!(
-                    "Ignoring non-string value {p:?} in `panels_center`"
-                );
-            }
+/// Removes the background image options from a bar's options.
+fn parse_bg_image(
+    bar_table: &mut Map<String, Value>,
+) -> Option<BackgroundImage> {
+    let path = remove_string_from_config("bg_image", bar_table);
+    let scale = match remove_string_from_config("bg_image_scale", bar_table)
+        .as_deref()
+    {
+        Some("tile") => ImageScale::Tile,
+        Some("stretch") => ImageScale::Stretch,
+        Some("center") => ImageScale::Center,
+        Some("fill") | None => ImageScale::Fill,
+        Some(other) => {
+            log::warn!("Unknown image scale `{other}`, using `fill`");
+            ImageScale::Fill
         }
+    };
+    if remove_bool_from_config("bg_wallpaper", bar_table).unwrap_or_default() {
+        return Some(BackgroundImage::Wallpaper);
     }
+    path.map(|path| BackgroundImage::File {
+        path: config_path()
+            .parent()
+            .map_or_else(|| PathBuf::from(&path), |dir| dir.join(&path)),
+        scale,
+    })
+}
 
-    let panels_right = bar_table.remove("panels_right");
-    if let Some(pr) = panels_right {
-        let panel_list =
-            pr.into_array().context("`panels_right` isn't an array")?;
-        for p in panel_list {
-            if let Ok(name) = p.clone().into_string() {
-                right_final.push(name);
-            } else {
-                log::warn!("Ignoring non-string value {p:?} in `panels_right`");
-            }
-        }
+/// Removes the border options from a bar's options.
+fn parse_border(bar_table: &mut Map<String, Value>) -> Option<Border> {
+    let color = remove_color_from_config("border_color", bar_table)
+        .or_else(|| {
+            bar_table
+                .get("default_fg")
+                .and_then(|fg| fg.clone().into_string().ok())
+                .and_then(|fg| fg.parse().ok())
+        })
+        .unwrap_or_else(|| Color::new(1.0, 1.0, 1.0, 1.0));
+    remove_float_from_config("border", bar_table)
+        .filter(|width| *width > 0.0)
+        .map(|width| Border { width, color })
+}
+
+/// Removes the separator options from a bar's options.
+fn parse_separator(
+    bar_table: &mut Map<String, Value>,
+) -> Option<SeparatorStyle> {
+    let attrs = Attrs::parse(bar_table, "separator_");
+    let padding =
+        remove_float_from_config("separator_padding", bar_table).unwrap_or(4.0);
+    let inset =
+        remove_float_from_config("separator_inset", bar_table).unwrap_or(4.0);
+    let markup = remove_string_from_config("separator", bar_table);
+    if let Some(width) = remove_float_from_config("separator_line", bar_table) {
+        Some(SeparatorStyle::Line {
+            width,
+            padding,
+            inset,
+            attrs,
+        })
+    } else {
+        markup.map(|markup| SeparatorStyle::Text { markup, attrs })
     }
+}
 
-    let mut panels_table = CONFIG
-        .get_table("panels")
-        .context("`panels` doesn't exist or isn't a table")?;
+/// Removes the error badge options from a bar's options.
+fn parse_error_badge(bar_table: &mut Map<String, Value>) -> Option<ErrorBadge> {
+    bar_table
+        .entry(String::from("error_fg"))
+        .or_insert_with(|| Value::from("#f00"));
+    let attrs = Attrs::parse(bar_table, "error_");
+    let markup = remove_string_from_config("error_badge", bar_table)
+        .unwrap_or_else(|| String::from("!"));
+    (!markup.is_empty()).then_some(ErrorBadge { markup, attrs })
+}
 
-    left_final
-        .into_iter()
-        .filter_map(|p| parse_panel(p.as_str(), &mut panels_table))
-        .for_each(|p| bar.add_panel(p, Alignment::Left));
-    center_final
-        .into_iter()
-        .filter_map(|p| parse_panel(p.as_str(), &mut panels_table))
-        .for_each(|p| bar.add_panel(p, Alignment::Center));
-    right_final
+/// Removes a list of panel names from a bar's options.
+pub(crate) fn panel_names(
+    bar_table: &mut Map<String, Value>,
+    key: &str,
+) -> Result<Vec<String>> {
+    let Some(list) = bar_table.remove(key) else {
+        return Ok(Vec::new());
+    };
+    Ok(list
+        .into_array()
+        .with_context(|| format!("`{key}` isn't an array"))?
         .into_iter()
-        .filter_map(|p| parse_panel(p.as_str(), &mut panels_table))
-        .for_each(|p| bar.add_panel(p, Alignment::Right));
-
-    Ok(bar)
+        .filter_map(|p| {
+            p.clone().into_string().map_or_else(
+                |_| {
+                    log::warn!("Ignoring non-string value {p:?} in `{key}`");
+                    None
+                },
+                Some,
+            )
+        })
+        .collect())
 }
 
 fn parse_panel(
     p: &str,
     panels_table: &HashMap<String, Value>,
+    config: &Config,
 ) -> Option<Box<dyn PanelConfig>> {
-    if let Some(mut table) = get_table_from_config(p, panels_table) {
-        if let Some(s) = remove_string_from_config("type", &mut table) {
-            return match s.as_str() {
-                "battery" => {
-                    Battery::parse(&mut table, &CONFIG)
-                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
-                }
-                "clock" => {
-                    if let Some(precision) = &mut table.remove("precision") {
-                        if let Ok(precision) = precision.clone().into_string() {
-                            match precision.as_str() {
-                                "days" => {
-                                    Clock::<Days>::parse(&mut table, &CONFIG)
-                                        .map::<Box<dyn PanelConfig>, _>(|p| {
-                                            Box::new(p)
-                                        })
-                                }
-                                "hours" => {
-                                    Clock::<Hours>::parse(&mut table, &CONFIG)
-                                        .map::<Box<dyn PanelConfig>, _>(|p| {
-                                        Box::new(p)
-                                    })
-                                }
-                                "minutes" => {
-                                    Clock::<Minutes>::parse(&mut table, &CONFIG)
-                                        .map::<Box<dyn PanelConfig>, _>(|p| {
-                                            Box::new(p)
-                                        })
-                                }
-                                "seconds" | _ => {
-                                    Clock::<Seconds>::parse(&mut table, &CONFIG)
-                                        .map::<Box<dyn PanelConfig>, _>(|p| {
-                                            Box::new(p)
-                                        })
-                                }
-                            }
-                        } else {
-                            log::warn!(
-                                "Ignoring non-string value {precision:?} \
-                                 (location attempt: {:?})",
-                                precision.origin()
-                            );
-                            Clock::<Seconds>::parse(&mut table, &CONFIG)
+    build_panel(p, panels_table, config)
+        .map_err(|e| log::error!("{e}"))
+        .ok()
+}
+
+/// Parses the panel with the given name, using its `type` to decide which
+/// kind of panel it is. The names `spacer` and `expand` can be used without a
+/// table, giving a panel of that type with the default options.
+pub(crate) fn build_panel(
+    p: &str,
+    panels_table: &HashMap<String, Value>,
+    config: &Config,
+) -> Result<Box<dyn PanelConfig>> {
+    let mut table = match get_table_from_config(p, panels_table) {
+        Some(table) => table,
+        None if !panels_table.contains_key(p)
+            && BUILTIN_PANELS.contains(&p) =>
+        {
+            Map::from([(String::from("type"), Value::from(p))])
+        }
+        None => {
+            return Err(anyhow!("Panel `{p}` doesn't exist or isn't a table"))
+        }
+    };
+    let s = remove_string_from_config("type", &mut table)
+        .with_context(|| format!("Panel `{p}` has no `type`"))?;
+    match s.as_str() {
+        "battery" => Battery::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "bspwm" => Bspwm::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "calendar" => Calendar::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "charge_threshold" => ChargeThreshold::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "ci" => Ci::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "clock" => {
+            if let Some(precision) = &mut table.remove("precision") {
+                if let Ok(precision) = precision.clone().into_string() {
+                    match precision.as_str() {
+                        "days" => Clock::<Days>::parse(&mut table, config)
+                            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                        "hours" => Clock::<Hours>::parse(&mut table, config)
+                            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                        "minutes" => {
+                            Clock::<Minutes>::parse(&mut table, config)
+                                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                        }
+                        "seconds" | _ => {
+                            Clock::<Seconds>::parse(&mut table, config)
                                 .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                         }
-                    } else {
-                        Clock::<Seconds>::parse(&mut table, &CONFIG).map::<Box<
-                            dyn PanelConfig,
-                        >, _>(
-                            |p| Box::new(p),
-                        )
                     }
+                } else {
+                    log::warn!(
+                        "Ignoring non-string value {precision:?} (location \
+                         attempt: {:?})",
+                        precision.origin()
+                    );
+                    Clock::<Seconds>::parse(&mut table, config).map::<Box<
+                        dyn PanelConfig,
+                    >, _>(
+                        |p| Box::new(p)
+                    )
                 }
-                "cpu" => Cpu::parse(&mut table, &CONFIG)
-                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
-                "custom" => {
-                    Custom::parse(&mut table, &CONFIG)
-                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
-                }
-                "fanotify" => {
-                    Fanotify::parse(&mut table, &CONFIG)
-                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
-                }
-                "inotify" => {
-                    Inotify::parse(&mut table, &CONFIG)
-                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
-                }
-                "memory" => {
-                    Memory::parse(&mut table, &CONFIG)
-                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
-                }
-                "mpd" => Mpd::parse(&mut table, &CONFIG)
-                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
-                "network" => {
-                    Network::parse(&mut table, &CONFIG)
-                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
-                }
-                "ping" => Ping::parse(&mut table, &CONFIG)
-                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
-                "pulseaudio" => Pulseaudio::parse(&mut table, &CONFIG)
-                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
-                "separator" => {
-                    Separator::parse(&mut table, &CONFIG)
-                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
-                }
-                "temp" => Temp::parse(&mut table, &CONFIG)
-                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
-                "xwindow" => {
-                    XWindow::parse(&mut table, &CONFIG)
-                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
-                }
-                "xworkspaces" => XWorkspaces::parse(&mut table, &CONFIG)
-                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
-                s => Err(anyhow!("Unknown panel type {s}")),
+            } else {
+                Clock::<Seconds>::parse(&mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
             }
-            .map_err(|e| {
-                log::error!("{e}");
-                e
-            })
-            .ok();
         }
+        "containers" => {
+            Containers::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "cpu" => Cpu::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "crypto" => Crypto::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "cups" => Cups::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "custom" => Custom::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "dbus_property" => {
+            DbusProperty::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "dnd" => Dnd::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "dpms" => Dpms::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "dunst" => Dunst::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "entropy" => Entropy::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "expand" => Expand::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "failed_units" => {
+            FailedUnits::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "fanotify" => Fanotify::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "file_value" => {
+            FileValue::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "group" => Group::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "herbstluftwm" => {
+            Herbstluftwm::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "http" => Http::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "i3mode" => I3Mode::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "i3workspaces" => {
+            I3Workspaces::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "idle_inhibit" => {
+            IdleInhibit::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "inotify" => Inotify::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "iwd" => Iwd::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "journal" => Journal::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "kdeconnect" => {
+            KdeConnect::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "kubernetes" => {
+            Kubernetes::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "maildir" => Maildir::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "matrix" => Matrix::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "memory" => Memory::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "mpd" => Mpd::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "mqtt" => Mqtt::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "network" => Network::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "nut" => Nut::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "ping" => Ping::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "pomodoro" => Pomodoro::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "power_draw" => {
+            PowerDraw::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "power_profile" => {
+            PowerProfile::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "privacy" => Privacy::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "pulseaudio" => {
+            Pulseaudio::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "redshift" => Redshift::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "screen_capture" => {
+            ScreenCapture::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "separator" => Separator::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "spacer" => Spacer::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "stocks" => Stocks::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "storage_pool" => {
+            StoragePool::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "syncthing" => Syncthing::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "temp" => Temp::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "timesync" => Timesync::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "todotxt" => TodoTxt::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "top" => Top::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "transmission" => {
+            Transmission::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        "updates" => Updates::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "weechat" => Weechat::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "xmonad" => XMonad::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "xwindow" => XWindow::parse(&mut table, config)
+            .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+        "xworkspaces" => {
+            XWorkspaces::parse(&mut table, config)
+                .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+        }
+        s => Err(anyhow!("Unknown panel type {s}")),
     }
-    None
 }
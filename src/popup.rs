@@ -0,0 +1,234 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    os::fd::AsRawFd,
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Context, Result};
+use tokio::{
+    io::unix::AsyncFd,
+    task::{self, JoinHandle},
+};
+use xcb::{x, Xid};
+
+use crate::{bar::PanelDrawInfo, create_surface, Position};
+
+/// Where a [`Popup`] should appear. This is provided by the bar with each
+/// [`MouseEvent`][crate::bar::MouseEvent].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Anchor {
+    /// The x-coordinate of the left edge of the panel, relative to the root
+    /// window
+    pub x: i16,
+    /// The y-coordinate of the edge of the bar that faces the rest of the
+    /// screen, relative to the root window
+    pub y: i16,
+    /// Where the bar is on the screen. Popups open away from this edge.
+    pub position: Position,
+}
+
+impl Anchor {
+    /// Returns the position of the top left corner of a popup of the given
+    /// size, keeping it on the screen horizontally.
+    #[allow(clippy::cast_possible_truncation)]
+    fn origin(self, screen_width: i32, width: i32, height: i32) -> (i16, i16) {
+        let x = i32::from(self.x).min(screen_width - width).max(0);
+        let y = match self.position {
+            Position::Top => i32::from(self.y),
+            Position::Bottom => i32::from(self.y) - height,
+        };
+        (x as i16, y as i16)
+    }
+}
+
+struct Inner {
+    conn: xcb::Connection,
+    window: x::Window,
+    surface: cairo::XCBSurface,
+    cr: cairo::Context,
+    anchor: Anchor,
+    screen_width: i32,
+    content: RefCell<PanelDrawInfo>,
+    closed: Cell<bool>,
+}
+
+impl Inner {
+    fn draw(&self) -> Result<()> {
+        let content = self.content.borrow();
+        self.cr.save()?;
+        self.cr.set_operator(cairo::Operator::Clear);
+        self.cr.paint()?;
+        self.cr.restore()?;
+        (content.draw_fn)(&self.cr)?;
+        self.surface.flush();
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn close(&self) {
+        if !self.closed.replace(true) {
+            self.conn.send_request(&x::DestroyWindow {
+                window: self.window,
+            });
+            let _ = self.conn.flush();
+        }
+    }
+
+    /// Redraws the popup when it's exposed and closes it when it's clicked.
+    async fn run(&self) -> Result<()> {
+        let fd = AsyncFd::new(self.conn.as_raw_fd())?;
+        loop {
+            while let Some(event) = self.conn.poll_for_event()? {
+                match event {
+                    xcb::Event::X(x::Event::Expose(_)) => self.draw()?,
+                    xcb::Event::X(x::Event::ButtonPress(_)) => {
+                        self.close();
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+            fd.readable().await?.clear_ready();
+        }
+    }
+}
+
+/// A window that a panel can open next to itself to show more than fits on
+/// the bar, e.g. a month calendar or a list of devices.
+///
+/// The popup is drawn from a [`PanelDrawInfo`], so the usual
+/// [`draw_common`][crate::draw_common] works for text content. It closes when
+/// it's clicked or dropped, so a panel can toggle it by storing it in an
+/// [`Option`] and replacing it with [`None`].
+pub struct Popup {
+    inner: Rc<Inner>,
+    task: JoinHandle<()>,
+}
+
+impl Popup {
+    /// Opens a popup at `anchor` that displays `content`.
+    ///
+    /// # Errors
+    ///
+    /// If the window can't be created.
+    pub fn open(anchor: Anchor, content: PanelDrawInfo) -> Result<Self> {
+        let (conn, screen_idx) = xcb::Connection::connect(None)?;
+        let screen = conn
+            .get_setup()
+            .roots()
+            .nth(screen_idx as usize)
+            .ok_or_else(|| anyhow!("Failed to find screen"))?;
+        let root = screen.root();
+        let root_visual = screen.root_visual();
+        let black = screen.black_pixel();
+        let screen_width = i32::from(screen.width_in_pixels());
+        let visual = *screen
+            .allowed_depths()
+            .flat_map(x::Depth::visuals)
+            .find(|v| v.visual_id() == root_visual)
+            .ok_or_else(|| anyhow!("Failed to find root visual"))?;
+
+        let width = content.width.max(1);
+        let height = content.height.max(1);
+        let (x, y) = anchor.origin(screen_width, width, height);
+        let window: x::Window = conn.generate_id();
+        conn.check_request(conn.send_request_checked(&x::CreateWindow {
+            depth: x::COPY_FROM_PARENT as u8,
+            wid: window,
+            parent: root,
+            x,
+            y,
+            width: width as u16,
+            height: height as u16,
+            border_width: 0,
+            class: x::WindowClass::InputOutput,
+            visual: root_visual,
+            value_list: &[
+                x::Cw::BackPixel(black),
+                x::Cw::OverrideRedirect(true),
+                x::Cw::EventMask(
+                    x::EventMask::EXPOSURE | x::EventMask::BUTTON_PRESS,
+                ),
+            ],
+        }))
+        .context("creating popup window failed")?;
+        conn.check_request(conn.send_request_checked(&x::MapWindow { window }))
+            .context("mapping popup window failed")?;
+
+        let surface = create_surface(&conn, window, visual, width, height)?;
+        let cr = cairo::Context::new(&surface)?;
+        let inner = Rc::new(Inner {
+            conn,
+            window,
+            surface,
+            cr,
+            anchor,
+            screen_width,
+            content: RefCell::new(content),
+            closed: Cell::new(false),
+        });
+
+        let task = task::spawn_local({
+            let inner = inner.clone();
+            async move {
+                if let Err(e) = inner.run().await {
+                    log::warn!("Popup window failed: {e}");
+                    inner.close();
+                }
+            }
+        });
+
+        Ok(Self { inner, task })
+    }
+
+    /// Replaces the content of the popup, resizing it if necessary.
+    ///
+    /// # Errors
+    ///
+    /// If the popup can't be redrawn.
+    pub fn update(&self, content: PanelDrawInfo) -> Result<()> {
+        let width = content.width.max(1);
+        let height = content.height.max(1);
+        let (x, y) =
+            self.inner
+                .anchor
+                .origin(self.inner.screen_width, width, height);
+        self.inner.conn.send_request(&x::ConfigureWindow {
+            window: self.inner.window,
+            value_list: &[
+                x::ConfigWindow::X(i32::from(x)),
+                x::ConfigWindow::Y(i32::from(y)),
+                x::ConfigWindow::Width(width as u32),
+                x::ConfigWindow::Height(height as u32),
+            ],
+        });
+        self.inner.surface.set_size(width, height)?;
+        *self.inner.content.borrow_mut() = content;
+        self.inner.draw()
+    }
+
+    /// Returns whether the popup is still open. A popup closes itself when
+    /// it's clicked.
+    #[must_use]
+    pub fn is_open(&self) -> bool {
+        !self.inner.closed.get()
+    }
+}
+
+impl Drop for Popup {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.inner.close();
+    }
+}
+
+impl Debug for Popup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Popup")
+            .field("window", &self.inner.window.resource_id())
+            .field("anchor", &self.inner.anchor)
+            .field("open", &self.is_open())
+            .finish_non_exhaustive()
+    }
+}
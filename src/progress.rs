@@ -0,0 +1,178 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use config::Value;
+use csscolorparser::Color;
+use pangocairo::functions::{create_layout, show_layout};
+
+use crate::{
+    bar::{Dependence, PanelDrawInfo},
+    remove_bool_from_config, remove_color_from_config,
+    remove_float_from_config, remove_paint_from_config, rounded_rectangle,
+    Attrs, Paint,
+};
+
+/// A bar that fills from left to right as a value grows, e.g. the level of a
+/// battery. See [`draw_progress_bar`].
+#[derive(Clone, Debug)]
+pub struct ProgressBar {
+    /// The width of the bar in pixels
+    pub width: f64,
+    /// The height of the bar in pixels. It's centered vertically on the bar.
+    pub height: f64,
+    /// The color of the filled part. If this is [`None`], the foreground
+    /// color of the panel is used.
+    pub fill: Option<Paint>,
+    /// The color of the empty part. If this is [`None`], the background color
+    /// of the panel is used.
+    pub bg: Option<Paint>,
+    /// The width in pixels of the border around the bar
+    pub border_width: f64,
+    /// The color of the border. If this is [`None`], the foreground color of
+    /// the panel is used.
+    pub border_color: Option<Color>,
+    /// The radius in pixels of the corners of the bar
+    pub radius: f64,
+    /// Whether the text of the panel is drawn over the middle of the bar
+    pub text: bool,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self {
+            width: 60.0,
+            height: 10.0,
+            fill: None,
+            bg: None,
+            border_width: 0.0,
+            border_color: None,
+            radius: 0.0,
+            text: false,
+        }
+    }
+}
+
+impl ProgressBar {
+    /// Parses the options documented in [`Style::parse`][crate::Style::parse].
+    pub(crate) fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let default = Self::default();
+        Self {
+            width: remove_float_from_config("bar_width", table)
+                .unwrap_or(default.width),
+            height: remove_float_from_config("bar_height", table)
+                .unwrap_or(default.height),
+            fill: remove_paint_from_config("bar_fill", table),
+            bg: remove_paint_from_config("bar_bg", table),
+            border_width: remove_float_from_config("bar_border_width", table)
+                .unwrap_or_default(),
+            border_color: remove_color_from_config("bar_border_color", table),
+            radius: remove_float_from_config("bar_radius", table)
+                .unwrap_or_default(),
+            text: remove_bool_from_config("bar_text", table)
+                .unwrap_or(default.text),
+        }
+    }
+}
+
+/// Draws a [`ProgressBar`] filled to `fraction`, which is limited to the range
+/// from 0 to 1. If the bar shows text, `text` is interpreted as markup and
+/// drawn over it. Where the text overlaps the filled part, it's drawn in the
+/// background color so that it can still be read.
+#[allow(clippy::cast_possible_truncation)]
+pub fn draw_progress_bar(
+    cr: &Rc<cairo::Context>,
+    fraction: f64,
+    bar: &ProgressBar,
+    text: &str,
+    attrs: &Attrs,
+    dependence: Dependence,
+) -> Result<PanelDrawInfo> {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let layout = bar.text.then(|| {
+        let layout = create_layout(cr);
+        layout.set_markup(text);
+        attrs.apply_font(&layout);
+        layout
+    });
+    let plain = layout
+        .as_ref()
+        .map(|layout| layout.text().to_string())
+        .unwrap_or_default();
+    let bar = bar.clone();
+    let attrs = attrs.clone();
+
+    let mut draw_info = PanelDrawInfo::new(
+        (bar.width.ceil() as i32, bar.height.ceil() as i32),
+        dependence,
+        Box::new(move |cr| {
+            let (width, height) = (bar.width, bar.height);
+            let inset = bar.border_width / 2.0;
+            let outline = || {
+                rounded_rectangle(
+                    cr,
+                    inset,
+                    inset,
+                    width - bar.border_width,
+                    height - bar.border_width,
+                    bar.radius,
+                );
+            };
+
+            cr.save()?;
+            outline();
+            match &bar.bg {
+                Some(bg) => bg.apply(cr, width, height),
+                None => attrs.apply_bg(cr, width, height),
+            }
+            cr.fill_preserve()?;
+            cr.clip();
+
+            cr.rectangle(0.0, 0.0, width * fraction, height);
+            match &bar.fill {
+                Some(fill) => fill.apply(cr, width, height),
+                None => attrs.apply_fg(cr, width, height),
+            }
+            cr.fill()?;
+
+            if let Some(layout) = &layout {
+                let size = layout.pixel_size();
+                let (x, y) = (
+                    (width - f64::from(size.0)) / 2.0,
+                    (height - f64::from(size.1)) / 2.0,
+                );
+                for (start, end, filled) in [
+                    (width * fraction, width, false),
+                    (0.0, width * fraction, true),
+                ] {
+                    cr.save()?;
+                    cr.rectangle(start, 0.0, end - start, height);
+                    cr.clip();
+                    if filled {
+                        attrs.apply_bg(cr, width, height);
+                    } else {
+                        attrs.apply_fg(cr, width, height);
+                    }
+                    cr.move_to(x, y);
+                    show_layout(cr, layout);
+                    cr.restore()?;
+                }
+            }
+            cr.restore()?;
+
+            if bar.border_width > 0.0 {
+                outline();
+                match &bar.border_color {
+                    Some(c) => cr.set_source_rgba(c.r, c.g, c.b, c.a),
+                    None => attrs.apply_fg(cr, width, height),
+                }
+                cr.set_line_width(bar.border_width);
+                cr.stroke()?;
+            }
+            Ok(())
+        }),
+    );
+    draw_info.text = plain;
+    Ok(draw_info)
+}
@@ -2,57 +2,140 @@ use std::ops::Sub;
 
 use config::Config;
 
+use crate::Attrs;
+
+#[derive(Clone, Debug, Default)]
+struct Step {
+    icon: String,
+    attrs: Attrs,
+}
+
 /// Utility data structure to display one of several strings based on a value in
-/// a range, like a volume icon.
+/// a range, like a volume icon. Each step can also change the attributes of
+/// the panel, e.g. to turn it red when the battery is low.
 #[derive(Clone, Debug)]
 pub struct Ramp {
-    icons: Vec<String>,
+    steps: Vec<Step>,
+    interpolate: bool,
 }
 
 impl Ramp {
+    fn proportion<T>(value: T, min: T, max: T) -> f64
+    where
+        T: Sub + Copy,
+        f64: From<T>,
+    {
+        (f64::from(value) - f64::from(min)) / (f64::from(max) - f64::from(min))
+    }
+
+    fn step<T>(&self, value: T, min: T, max: T) -> &Step
+    where
+        T: Sub + Copy,
+        f64: From<T>,
+    {
+        let idx = Self::proportion(value, min, max) * (self.steps.len()) as f64;
+        self.steps
+            .get((idx.trunc() as usize).min(self.steps.len() - 1))
+            .unwrap()
+    }
+
     /// Given a value and a range, chooses the appropriate icon.
     pub fn choose<T>(&self, value: T, min: T, max: T) -> String
     where
         T: Sub + Copy,
         f64: From<T>,
     {
-        let prop = (f64::from(value) - f64::from(min))
-            / (f64::from(max) - f64::from(min));
-        let idx = prop * (self.icons.len()) as f64;
-        self.icons
-            .get((idx.trunc() as usize).min(self.icons.len() - 1))
-            .unwrap()
-            .clone()
+        self.step(value, min, max).icon.clone()
+    }
+
+    /// Given a value and a range, chooses the appropriate attributes, falling
+    /// back to `base` for any that the step doesn't set.
+    ///
+    /// If the ramp interpolates, the foreground color blends between the
+    /// colors of the two nearest steps instead of jumping from one to the
+    /// next. The first step's color is used at `min` and the last step's at
+    /// `max`. This only applies when both steps have a solid foreground
+    /// color.
+    #[must_use]
+    pub fn attrs<T>(&self, value: T, min: T, max: T, base: &Attrs) -> Attrs
+    where
+        T: Sub + Copy,
+        f64: From<T>,
+    {
+        let mut attrs = self.step(value, min, max).attrs.clone();
+        if self.interpolate && self.steps.len() > 1 {
+            let position = Self::proportion(value, min, max).clamp(0.0, 1.0)
+                * (self.steps.len() - 1) as f64;
+            let idx = (position.trunc() as usize).min(self.steps.len() - 2);
+            if let (Some(from), Some(to)) = (
+                self.steps[idx].attrs.fg_color(),
+                self.steps[idx + 1].attrs.fg_color(),
+            ) {
+                attrs.set_fg(
+                    from.interpolate_oklab(&to, position - idx as f64).into(),
+                );
+            }
+        }
+        attrs.apply_to(base);
+        attrs
     }
 
     /// Parses a new instance with a given name from the global [`Config`].
     ///
     /// Ramps should be defined in a table called `[ramps]`. Each ramp should be
     /// a table with keys ranging from 0 to any number. The values should be
-    /// [pango] markup strings.
+    /// [pango] markup strings, or tables with the markup string as `icon` and
+    /// any of the options of [`Attrs::parse`], e.g.
+    /// `0 = { icon = "", fg = "#f00" }`.
+    ///
+    /// If the ramp table has `interpolate = true`, the foreground color blends
+    /// smoothly between the colors of the steps. See [`Ramp::attrs`].
     #[must_use]
     pub fn parse(name: impl AsRef<str>, global: &Config) -> Option<Self> {
         let ramps_table = global.get_table("ramps").ok()?;
         let ramp_table =
             ramps_table.get(name.as_ref())?.clone().into_table().ok()?;
         let mut key = 0;
-        let mut icons = Vec::new();
-        while let Some(icon) = ramp_table.get(&key.to_string()) {
-            if let Ok(icon) = icon.clone().into_string() {
-                icons.push(icon);
-                key += 1;
+        let mut steps = Vec::new();
+        while let Some(step) = ramp_table.get(&key.to_string()) {
+            if let Ok(icon) = step.clone().into_string() {
+                steps.push(Step {
+                    icon,
+                    attrs: Attrs::default(),
+                });
+            } else if let Ok(mut table) = step.clone().into_table() {
+                let icon = table
+                    .remove("icon")
+                    .and_then(|icon| icon.into_string().ok())
+                    .unwrap_or_default();
+                steps.push(Step {
+                    icon,
+                    attrs: Attrs::parse(&mut table, ""),
+                });
             } else {
                 break;
             }
+            key += 1;
         }
-        Some(Self { icons })
+        let interpolate = ramp_table
+            .get("interpolate")
+            .and_then(|interpolate| interpolate.clone().into_bool().ok())
+            .unwrap_or_default();
+        Some(Self { steps, interpolate })
     }
 }
 
 impl FromIterator<String> for Ramp {
     fn from_iter<T: IntoIterator<Item = String>>(iter: T) -> Self {
         Self {
-            icons: iter.into_iter().collect(),
+            steps: iter
+                .into_iter()
+                .map(|icon| Step {
+                    icon,
+                    attrs: Attrs::default(),
+                })
+                .collect(),
+            interpolate: false,
         }
     }
 }
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use config::Value;
+
+use crate::Attrs;
+
+/// A named range of values with its own [`Attrs`], e.g. `critical` for a
+/// CPU usage of 95% or more.
+#[derive(Clone, Debug)]
+pub struct State {
+    /// The name of the state, which is available to `visible_when` as `state`
+    pub name: String,
+    /// The smallest value in the state. If this is [`None`], the range has no
+    /// lower bound.
+    pub min: Option<f64>,
+    /// The value above the range, which isn't part of it. If this is [`None`],
+    /// the range has no upper bound.
+    pub max: Option<f64>,
+    /// The attributes that replace those of the panel while it's in this
+    /// state
+    pub attrs: Attrs,
+}
+
+impl State {
+    /// Whether `value` is in the range of the state
+    #[must_use]
+    pub fn contains(&self, value: f64) -> bool {
+        self.min.map_or(true, |min| value >= min)
+            && self.max.map_or(true, |max| value < max)
+    }
+}
+
+/// The [`State`]s of a panel. Panels that show a single number, like a
+/// percentage, take on the attributes of the state that the number is in.
+#[derive(Clone, Debug, Default)]
+pub struct States(Vec<State>);
+
+impl States {
+    /// Configuration options:
+    ///
+    /// - `states`: named ranges of the panel's value, each with attributes
+    ///   that are used while the value is in that range, e.g.
+    ///   `critical = { range = "95..", fg = "#f00" }`. Ranges are written as
+    ///   `min..max`, where `min` is included, `max` isn't, and either can be
+    ///   left out. If ranges overlap, the one with the highest `min` wins.
+    ///   The name of the current state is available to `visible_when` as
+    ///   `state`. See [`Attrs::parse`] for the attributes.
+    ///   - type: table of tables
+    ///   - default: none
+    pub(crate) fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Result<Self> {
+        let Some(states) = table.remove("states") else {
+            return Ok(Self::default());
+        };
+        let mut states = states
+            .into_table()?
+            .into_iter()
+            .map(|(name, state)| {
+                let mut state = state.into_table()?;
+                let range = state
+                    .remove("range")
+                    .ok_or_else(|| anyhow!("State `{name}` has no range"))?
+                    .into_string()?;
+                let (min, max) = parse_range(range.as_str())?;
+                Ok(State {
+                    attrs: Attrs::parse(&mut state, ""),
+                    name,
+                    min,
+                    max,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        states.sort_by(|a, b| {
+            a.min
+                .unwrap_or(f64::NEG_INFINITY)
+                .total_cmp(&b.min.unwrap_or(f64::NEG_INFINITY))
+        });
+        Ok(Self(states))
+    }
+
+    /// Returns the state that `value` is in, if any.
+    #[must_use]
+    pub fn find(&self, value: f64) -> Option<&State> {
+        self.0.iter().rev().find(|state| state.contains(value))
+    }
+
+    /// Returns the attributes of the state that `value` is in, falling back
+    /// to `base` for any that the state doesn't set.
+    #[must_use]
+    pub fn apply(&self, value: f64, base: &Attrs) -> Attrs {
+        self.find(value).map_or_else(
+            || base.clone(),
+            |state| {
+                let mut attrs = state.attrs.clone();
+                attrs.apply_to(base);
+                attrs
+            },
+        )
+    }
+}
+
+fn parse_range(range: &str) -> Result<(Option<f64>, Option<f64>)> {
+    let (min, max) = range
+        .split_once("..")
+        .ok_or_else(|| anyhow!("Invalid range `{range}`"))?;
+    let bound = |bound: &str| {
+        let bound = bound.trim();
+        (!bound.is_empty())
+            .then(|| bound.parse::<f64>())
+            .transpose()
+            .map_err(|_| anyhow!("Invalid range `{range}`"))
+    };
+    Ok((bound(min)?, bound(max)?))
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use config::Value;
+
+use crate::{remove_string_from_config, Gauge, Graph, ProgressBar};
+
+/// How a panel that shows a single value, like a percentage, draws it.
+#[derive(Clone, Debug, Default)]
+pub enum Style {
+    /// The panel's format string
+    #[default]
+    Text,
+    /// A [`ProgressBar`]
+    Bar(ProgressBar),
+    /// A [`Graph`] of recent values. Panels that don't keep a history of
+    /// their values draw their format string instead.
+    Graph(Graph),
+    /// A [`Gauge`]. Panels that don't support gauges draw their format
+    /// string instead.
+    Gauge(Gauge),
+}
+
+impl Style {
+    /// Parses an instance of this type from a subset of the global
+    /// [`Config`][config::Config].
+    ///
+    /// Configuration options:
+    ///
+    /// - `style`: how the value is drawn: `text` for the format string, `bar`
+    ///   for a progress bar, `graph` for a rolling graph of recent values, or
+    ///   `gauge` for a ring. Graphs are supported by the cpu, network, and
+    ///   ping panels, and gauges by the battery and pulseaudio panels.
+    ///   - type: String
+    ///   - default: `text`
+    ///
+    /// - `bar_width`, `bar_height`: the size of the bar in pixels
+    ///   - type: f64
+    ///   - default: `60` and `10`
+    ///
+    /// - `bar_fill`: the color of the filled part of the bar
+    ///   - type: String
+    ///   - default: the foreground color of the panel
+    ///
+    /// - `bar_bg`: the color of the empty part of the bar
+    ///   - type: String
+    ///   - default: the background color of the panel
+    ///
+    /// - `bar_border_width`: the width in pixels of the border around the bar
+    ///   - type: f64
+    ///   - default: `0`
+    ///
+    /// - `bar_border_color`: the color of the border
+    ///   - type: String
+    ///   - default: the foreground color of the panel
+    ///
+    /// - `bar_radius`: the radius in pixels of the corners of the bar
+    ///   - type: f64
+    ///   - default: `0`
+    ///
+    /// - `bar_text`: whether the panel's format string is drawn over the bar
+    ///   - type: bool
+    ///   - default: `false`
+    ///
+    /// - `graph_width`, `graph_height`: the size of the graph in pixels
+    ///   - type: f64
+    ///   - default: `60` and `16`
+    ///
+    /// - `graph_history`: how many values the graph shows
+    ///   - type: u64
+    ///   - default: `30`
+    ///
+    /// - `graph_max`: the value at the top of the graph
+    ///   - type: f64
+    ///   - default: the panel's maximum, e.g. 100 for cpu, or the largest
+    ///     value shown if there isn't one
+    ///
+    /// - `graph_area`: whether the area under the line is filled
+    ///   - type: bool
+    ///   - default: `true`
+    ///
+    /// - `graph_line_width`: the width of the line in pixels
+    ///   - type: f64
+    ///   - default: `1`
+    ///
+    /// - `graph_color`: the color of the line
+    ///   - type: String
+    ///   - default: the foreground color of the panel
+    ///
+    /// - `graph_fill`: the color of the area under the line
+    ///   - type: String
+    ///   - default: `graph_color` at half opacity
+    ///
+    /// - `graph_bg`: the color behind the graph
+    ///   - type: String
+    ///   - default: none
+    ///
+    /// - `gauge_size`: the diameter of the gauge in pixels
+    ///   - type: f64
+    ///   - default: the height of the bar
+    ///
+    /// - `gauge_thickness`: the width of the ring in pixels
+    ///   - type: f64
+    ///   - default: `3`
+    ///
+    /// - `gauge_sweep`: how far around the ring reaches in degrees. Anything
+    ///   less than 360 leaves a gap at the bottom.
+    ///   - type: f64
+    ///   - default: `270`
+    ///
+    /// - `gauge_fill`: the color of the filled part of the ring
+    ///   - type: String
+    ///   - default: the foreground color of the panel
+    ///
+    /// - `gauge_bg`: the color of the empty part of the ring
+    ///   - type: String
+    ///   - default: the foreground color of the panel at a quarter of its
+    ///     opacity
+    ///
+    /// - `gauge_text`: whether the panel's format string is drawn in the
+    ///   middle of the ring
+    ///   - type: bool
+    ///   - default: `false`
+    pub fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        match remove_string_from_config("style", table).as_deref() {
+            Some("text") | None => Self::Text,
+            Some("bar") => Self::Bar(ProgressBar::parse(table)),
+            Some("graph") => Self::Graph(Graph::parse(table)),
+            Some("gauge") => Self::Gauge(Gauge::parse(table)),
+            Some(other) => {
+                log::warn!("Unknown style `{other}`, using `text`");
+                Self::Text
+            }
+        }
+    }
+}
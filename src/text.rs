@@ -0,0 +1,275 @@
+use std::{
+    future,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use anyhow::Result;
+use cairo::{Format, ImageSurface};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    time,
+};
+use tokio_stream::{StreamExt, StreamMap};
+
+use crate::{
+    bar::{Bar, MouseButton, Panel, PanelStatus, Restarts},
+    popup::Anchor,
+    Alignment, Attrs, PanelConfig, PanelRebuildFn, PanelStream,
+};
+
+/// The order in which the parts of a bar are stored and shown.
+const ALIGNMENTS: [Alignment; 3] =
+    [Alignment::Left, Alignment::Center, Alignment::Right];
+
+/// A click on a panel, reported by the program that shows the panels.
+pub(crate) struct Click {
+    /// The name of the panel
+    pub(crate) name: String,
+    pub(crate) button: MouseButton,
+    /// The x-coordinate of the pointer, relative to the panel
+    pub(crate) x: f64,
+    /// The y-coordinate of the pointer, relative to the panel
+    pub(crate) y: f64,
+    /// Where to open a popup for the panel
+    pub(crate) anchor: Anchor,
+}
+
+/// Writes `panels` to stdout as a line of text made by `format` each time
+/// that line changes, and passes on the clicks that `parse_click` finds in
+/// the lines read from stdin.
+///
+/// This returns once the panels have all stopped and stdin is closed.
+#[allow(clippy::future_not_send)]
+pub(crate) async fn run(
+    mut panels: TextPanels,
+    format: impl Fn(&TextPanels) -> String,
+    parse_click: impl Fn(&str) -> Option<Click>,
+) -> Result<()> {
+    let mut stdout = io::stdout();
+    let mut clicks = BufReader::new(tokio::io::stdin()).lines();
+    let mut running = true;
+    let mut reading = true;
+    let mut last = None;
+    while running || reading {
+        let line = format(&panels);
+        if last.as_ref() != Some(&line) {
+            writeln!(stdout, "{line}")?;
+            stdout.flush()?;
+            last = Some(line);
+        }
+
+        tokio::select! {
+            updated = panels.update(), if running => running = updated,
+            line = clicks.next_line(), if reading => match line {
+                Ok(Some(line)) => {
+                    if let Some(click) = parse_click(line.as_str()) {
+                        panels.click(
+                            click.name.as_str(),
+                            click.button,
+                            click.x,
+                            click.y,
+                            click.anchor,
+                        );
+                    }
+                }
+                Ok(None) => reading = false,
+                Err(e) => {
+                    log::warn!("Stopped reading clicks: {e}");
+                    reading = false;
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// The panels of a bar that has no window of its own, so that another
+/// program can show their text instead. The panels run as they would on a
+/// bar with a window, but only the text they produce is used.
+///
+/// Panels that fail are restarted as they would be on a bar with a window,
+/// keeping their old text until they produce new content. `depends_on` is
+/// only evaluated when the panels are drawn.
+pub(crate) struct TextPanels {
+    /// The panels with each alignment, in the order of [`ALIGNMENTS`]
+    sections: Vec<Vec<Panel>>,
+    streams: StreamMap<(usize, usize), PanelStream>,
+    /// What the panels lay out their text with
+    cr: Rc<cairo::Context>,
+    attrs: Attrs,
+    height: u16,
+    restarts: Restarts,
+}
+
+impl TextPanels {
+    /// Starts the left, center, and right panels of a bar. `height` is
+    /// passed on to panels that size their content to fit the bar. Panels
+    /// that fail are built again with `rebuild`, if there is one.
+    pub(crate) fn start(
+        sections: [Vec<(String, Box<dyn PanelConfig>)>; 3],
+        attrs: &Attrs,
+        height: u16,
+        rebuild: Option<PanelRebuildFn>,
+    ) -> Result<Self> {
+        // panels lay out their text with cairo even though it isn't drawn
+        let surface = ImageSurface::create(Format::ARgb32, 1, 1)?;
+        let cr = Rc::new(cairo::Context::new(&surface)?);
+        let mut streams = StreamMap::new();
+        let mut restarts = Restarts::new(rebuild);
+        let sections = sections
+            .into_iter()
+            .enumerate()
+            .map(|(section, panels)| {
+                panels
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, (name, config))| {
+                        let (mut panel, stream) = Bar::start_panel(
+                            &cr,
+                            attrs,
+                            i32::from(height),
+                            name,
+                            config,
+                        );
+                        match stream {
+                            Ok(stream) => {
+                                streams.insert((section, idx), stream);
+                            }
+                            Err(e) => {
+                                restarts.fail(
+                                    &mut panel,
+                                    ALIGNMENTS[section],
+                                    idx,
+                                    &e,
+                                );
+                            }
+                        }
+                        panel
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            sections,
+            streams,
+            cr,
+            attrs: attrs.clone(),
+            height,
+            restarts,
+        })
+    }
+
+    /// Waits for a panel to produce new content and stores it, or restarts
+    /// the failed panels whose delay has passed. Returns `false` once every
+    /// panel has stopped and none are waiting to be restarted.
+    pub(crate) async fn update(&mut self) -> bool {
+        let deadline = self.restarts.deadline();
+        if self.streams.is_empty() && deadline.is_none() {
+            return false;
+        }
+        tokio::select! {
+            Some(((section, idx), result)) = self.streams.next() => {
+                let panel = &mut self.sections[section][idx];
+                match result {
+                    Ok(draw_info) => {
+                        panel.draw_info = Some(draw_info);
+                        panel.failures = 0;
+                    }
+                    Err(e) => {
+                        if self.restarts.fail(
+                            panel,
+                            ALIGNMENTS[section],
+                            idx,
+                            &e,
+                        ) {
+                            self.streams.remove(&(section, idx));
+                        }
+                    }
+                }
+            },
+            () = async {
+                match deadline {
+                    Some(at) => time::sleep_until(at).await,
+                    None => future::pending().await,
+                }
+            } => self.restart_panels(),
+        }
+        true
+    }
+
+    /// Builds the failed panels whose delay has passed again and starts
+    /// their streams. If one can't be built or started, it fails again.
+    fn restart_panels(&mut self) {
+        for (alignment, idx) in self.restarts.take_due() {
+            let section = ALIGNMENTS
+                .iter()
+                .position(|a| *a == alignment)
+                .expect("panels are only restarted with known alignments");
+            let panel = &mut self.sections[section][idx];
+            let started = self.restarts.rebuild(
+                &self.cr,
+                &self.attrs,
+                i32::from(self.height),
+                panel.name.clone(),
+            );
+            match started {
+                Ok((new, Ok(stream))) => {
+                    panel.replace(new);
+                    self.streams.insert((section, idx), stream);
+                }
+                Ok((new, Err(e))) => {
+                    panel.replace(new);
+                    self.restarts.fail(panel, alignment, idx, &e);
+                }
+                Err(e) => {
+                    self.restarts.fail(panel, alignment, idx, &e);
+                }
+            }
+        }
+    }
+
+    /// Returns the panels that are shown and have text, from left to right,
+    /// along with their alignment. Panels are hidden by their
+    /// [`Dependence`][crate::bar::Dependence] as they would be on a bar with
+    /// a window.
+    pub(crate) fn shown(&self) -> Vec<(Alignment, &Panel)> {
+        ALIGNMENTS
+            .into_iter()
+            .zip(&self.sections)
+            .flat_map(|(alignment, panels)| {
+                Bar::apply_dependence(panels)
+                    .into_iter()
+                    .zip(panels)
+                    .filter(|(status, panel)| {
+                        *status == PanelStatus::Shown
+                            && panel
+                                .draw_info
+                                .as_ref()
+                                .is_some_and(|d| !d.text.is_empty())
+                    })
+                    .map(move |(_, panel)| (alignment, panel))
+            })
+            .collect()
+    }
+
+    /// Passes a click on to the panel called `name`. See [`Panel::click`].
+    pub(crate) fn click(
+        &self,
+        name: &str,
+        button: MouseButton,
+        x: f64,
+        y: f64,
+        anchor: Anchor,
+    ) {
+        match self
+            .sections
+            .iter()
+            .flatten()
+            .find(|panel| panel.name == name)
+        {
+            Some(panel) => panel.click(button, x, y, anchor),
+            None => log::warn!("Click on unknown panel `{name}`"),
+        }
+    }
+}
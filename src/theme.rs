@@ -0,0 +1,257 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::RwLock};
+
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveTime, TimeDelta};
+use config::Value;
+use futures::StreamExt;
+use lazy_static::lazy_static;
+use tokio::{sync::Notify, time};
+use zbus::{zvariant, Connection};
+
+use crate::remove_string_from_config;
+
+/// One of the two themes of the palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /// Light text on a dark background
+    Dark,
+    /// Dark text on a light background
+    Light,
+}
+
+impl Theme {
+    /// Returns the other theme.
+    #[must_use]
+    pub const fn toggled(self) -> Self {
+        match self {
+            Self::Dark => Self::Light,
+            Self::Light => Self::Dark,
+        }
+    }
+
+    /// Returns the name of the theme, which is also the name of its table in
+    /// `[palette]`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dark" => Ok(Self::Dark),
+            "light" => Ok(Self::Light),
+            _ => Err(anyhow!("Unknown theme `{s}`")),
+        }
+    }
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How the theme is chosen.
+#[derive(Clone, Copy, Debug)]
+enum Mode {
+    /// Always the same theme, unless it's changed over IPC
+    Fixed(Theme),
+    /// The `color-scheme` setting of the freedesktop settings portal
+    Portal,
+    /// The light theme from `light` until `dark`, and the dark theme the rest
+    /// of the day
+    Time { light: NaiveTime, dark: NaiveTime },
+}
+
+impl Mode {
+    /// The theme to use until something changes it.
+    fn initial(self) -> Theme {
+        match self {
+            Self::Fixed(theme) => theme,
+            Self::Portal => Theme::Dark,
+            Self::Time { light, dark } => by_time(light, dark).0,
+        }
+    }
+}
+
+lazy_static! {
+    static ref MODE: RwLock<Mode> = RwLock::new(Mode::Fixed(Theme::Dark));
+    static ref CURRENT: RwLock<Option<Theme>> = RwLock::new(None);
+    static ref CHANGED: Notify = Notify::new();
+}
+
+/// Parses the options of `[palette]` that choose the theme.
+///
+/// Configuration options:
+///
+/// - `theme`: which of the `[palette.dark]` and `[palette.light]` tables of
+///   colors is used on top of `colors`. This is `dark` or `light`, `portal` to
+///   follow the dark style preference of the desktop (through the
+///   freedesktop settings portal), or `time` to switch at `light_at` and
+///   `dark_at`. The theme can also be changed with `lazybar-msg theme`.
+///   - type: String
+///   - default: `dark`
+///
+/// - `light_at`, `dark_at`: when to switch themes if `theme = "time"`, as
+///   `HH:MM`
+///   - type: String
+///   - default: `07:00` and `19:00`
+pub(crate) fn configure<S: std::hash::BuildHasher>(
+    table: &mut HashMap<String, Value, S>,
+) {
+    let time = |key: &str, default: (u32, u32)| {
+        remove_string_from_config(key, table)
+            .and_then(|time| {
+                NaiveTime::parse_from_str(time.as_str(), "%H:%M")
+                    .map_err(|e| log::warn!("Invalid {key} `{time}`: {e}"))
+                    .ok()
+            })
+            .unwrap_or_else(|| {
+                NaiveTime::from_hms_opt(default.0, default.1, 0).unwrap()
+            })
+    };
+    let mode = match remove_string_from_config("theme", table).as_deref() {
+        Some("portal") => Mode::Portal,
+        Some("time") => Mode::Time {
+            light: time("light_at", (7, 0)),
+            dark: time("dark_at", (19, 0)),
+        },
+        Some(theme) => Mode::Fixed(theme.parse().unwrap_or_else(|e| {
+            log::warn!("{e}, using `dark`");
+            Theme::Dark
+        })),
+        None => Mode::Fixed(Theme::Dark),
+    };
+    *MODE.write().unwrap() = mode;
+}
+
+/// Returns the theme that's currently in use.
+#[must_use]
+pub(crate) fn current() -> Theme {
+    CURRENT
+        .read()
+        .unwrap()
+        .unwrap_or_else(|| MODE.read().unwrap().initial())
+}
+
+/// Switches to `theme`. If it isn't already in use, the running bars parse
+/// their config again with the new colors.
+pub(crate) fn set(theme: Theme) {
+    if current() != theme {
+        log::info!("Switching to the {theme} theme");
+        *CURRENT.write().unwrap() = Some(theme);
+        CHANGED.notify_one();
+    }
+}
+
+/// Switches to the theme that isn't in use.
+pub(crate) fn toggle() {
+    set(current().toggled());
+}
+
+/// Waits until the theme changes.
+pub(crate) async fn changed() {
+    CHANGED.notified().await;
+}
+
+/// Changes the theme whenever the time of day or the portal setting calls
+/// for it, depending on the `theme` option. This never returns unless
+/// there's an error.
+pub(crate) async fn follow() -> Result<()> {
+    let mode = *MODE.read().unwrap();
+    match mode {
+        Mode::Fixed(_) => futures::future::pending().await,
+        Mode::Time { light, dark } => loop {
+            let (theme, until) = by_time(light, dark);
+            set(theme);
+            time::sleep(until.to_std().unwrap_or_default()).await;
+        },
+        Mode::Portal => follow_portal().await,
+    }
+}
+
+/// Returns the theme for the current time of day, and how long it lasts.
+fn by_time(light: NaiveTime, dark: NaiveTime) -> (Theme, TimeDelta) {
+    let now = Local::now().time();
+    let is_light = if light <= dark {
+        light <= now && now < dark
+    } else {
+        now >= light || now < dark
+    };
+    let (theme, next) = if is_light {
+        (Theme::Light, dark)
+    } else {
+        (Theme::Dark, light)
+    };
+    let until = next - now;
+    (
+        theme,
+        if until <= TimeDelta::zero() {
+            until + TimeDelta::days(1)
+        } else {
+            until
+        },
+    )
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait Settings {
+    fn read_one(
+        &self,
+        namespace: &str,
+        key: &str,
+    ) -> zbus::Result<zvariant::OwnedValue>;
+
+    #[zbus(signal)]
+    fn setting_changed(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: zvariant::Value<'_>,
+    ) -> zbus::Result<()>;
+}
+
+/// Converts the value of `org.freedesktop.appearance.color-scheme`, where 1
+/// means dark and 2 means light. 0 means there's no preference, which
+/// leaves the theme as it is.
+fn color_scheme(value: &zvariant::Value<'_>) -> Option<Theme> {
+    match value {
+        zvariant::Value::U32(1) => Some(Theme::Dark),
+        zvariant::Value::U32(2) => Some(Theme::Light),
+        // older portals wrap the value in another variant
+        zvariant::Value::Value(value) => color_scheme(value),
+        _ => None,
+    }
+}
+
+async fn follow_portal() -> Result<()> {
+    const NAMESPACE: &str = "org.freedesktop.appearance";
+    const KEY: &str = "color-scheme";
+
+    let conn = Connection::session().await?;
+    let proxy = SettingsProxy::new(&conn).await?;
+    let mut changes = proxy.receive_setting_changed().await?;
+    if let Some(theme) = color_scheme(&proxy.read_one(NAMESPACE, KEY).await?) {
+        set(theme);
+    }
+    while let Some(change) = changes.next().await {
+        let args = change.args()?;
+        if args.namespace() == &NAMESPACE && args.key() == &KEY {
+            if let Some(theme) = color_scheme(args.value()) {
+                set(theme);
+            }
+        }
+    }
+    Err(anyhow!("The settings portal stopped sending changes"))
+}
@@ -1,51 +1,186 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    f64::consts::{FRAC_PI_2, PI},
+    fs,
+    path::Path,
+    process::Command,
+    rc::Rc,
+    str::FromStr,
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use config::{Map, Value};
 use csscolorparser::Color;
 use derive_builder::Builder;
+use pango::{prelude::FontMapExt, FontDescription};
 use pangocairo::functions::show_layout;
+use tokio::task;
 
 use crate::{
-    bar::{Dependence, PanelDrawInfo},
-    Attrs,
+    bar::{Dependence, MouseButton, PanelDrawInfo},
+    icon::{extract_icons, place_icons, PLACEHOLDER},
+    parse_color, Attrs, Expr, Paint, States, Transition,
 };
 
 /// The end of a typical draw function. Takes a cairo context, a string to
-/// display, and attributes to use, and returns a closure that will do the
-/// drawing and a tuple representing the final width and height.
+/// display, attributes to use, and a width limit, and returns a closure that
+/// will do the drawing and a tuple representing the final width and height.
 ///
 /// The text will be interpreted as markup. If this is not your intended
 /// behavior, use [`markup_escape_text`][crate::markup_escape_text] to display
-/// what you want.
+/// what you want. PNG and SVG images can be shown inline with
+/// `{icon:/path/to/icon.png}`, which is as tall as the text, or
+/// `{icon:/path/to/icon.png:HEIGHT}` for a height in pixels. See
+/// [`Icon`][crate::Icon].
 pub fn draw_common(
     cr: &Rc<cairo::Context>,
     text: &str,
     attrs: &Attrs,
     dependence: Dependence,
+    ellipsize: &Ellipsize,
 ) -> Result<PanelDrawInfo> {
+    let (text, icons) = extract_icons(text);
     let layout = pangocairo::functions::create_layout(cr);
-    layout.set_markup(text);
+    layout.set_markup(text.as_str());
     attrs.apply_font(&layout);
+    ellipsize.apply(&layout);
+    let icons = place_icons(&layout, icons);
     let dims = layout.pixel_size();
     let attrs = attrs.clone();
+    let plain = layout.text().replace(PLACEHOLDER, "");
 
-    Ok(PanelDrawInfo::new(
+    let mut draw_info = PanelDrawInfo::new(
         dims,
         dependence,
         Box::new(move |cr| {
-            attrs.apply_bg(cr);
-            cr.rectangle(0.0, 0.0, f64::from(dims.0), f64::from(dims.1));
+            let (width, height) = (f64::from(dims.0), f64::from(dims.1));
+            attrs.apply_bg(cr, width, height);
+            cr.rectangle(0.0, 0.0, width, height);
             cr.fill()?;
-            attrs.apply_fg(cr);
+            attrs.apply_fg(cr, width, height);
             show_layout(cr, &layout);
+            for (icon, x, y) in &icons {
+                icon.draw(cr, *x, *y)?;
+            }
             Ok(())
         }),
-    ))
+    );
+    draw_info.text = plain;
+    Ok(draw_info)
+}
+
+/// Runs a shell command with `sh -c <command>` without blocking the caller.
+/// Failures are logged rather than returned, since there is usually nobody to
+/// report them to (e.g. when the command is bound to a click).
+pub fn run_command(command: &str) {
+    run_command_with_env(command, &[]);
+}
+
+/// Like [`run_command`], but sets extra environment variables for the
+/// command.
+pub fn run_command_with_env(command: &str, env: &[(&str, &str)]) {
+    let command = command.to_owned();
+    let env = env
+        .iter()
+        .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+        .collect::<Vec<_>>();
+    task::spawn_blocking(move || {
+        match Command::new("sh")
+            .arg("-c")
+            .arg(command.as_str())
+            .envs(env)
+            .status()
+        {
+            Ok(status) if !status.success() => {
+                log::warn!("`{command}` exited with {status}");
+            }
+            Err(e) => log::warn!("Failed to run `{command}`: {e}"),
+            Ok(_) => {}
+        }
+    });
+}
+
+/// Returns whether an executable named `bin` exists in any directory in
+/// `$PATH`.
+pub fn in_path(bin: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path).any(|dir| Path::new(&dir).join(bin).is_file())
+    })
+}
+
+/// Reads a file containing a single number, as most files in sysfs and procfs
+/// do.
+pub fn read_number<T>(path: impl AsRef<Path>) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Error + Send + Sync + 'static,
+{
+    let path = path.as_ref();
+    fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {path:?}: {e}"))?
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("Failed to parse {path:?}: {e}"))
+}
+
+/// Formats a number of bytes using binary prefixes, e.g. `1.5 MiB`.
+#[must_use]
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024.0 {
+        return format!("{bytes:.0} B");
+    }
+    let mut value = bytes / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Shell commands to run when a panel is clicked or scrolled, keyed by the
+/// [`MouseButton`] that triggers them.
+#[derive(Debug, Clone, Default)]
+pub struct Actions(HashMap<MouseButton, String>);
+
+impl Actions {
+    /// Returns the command bound to `button`, if any.
+    #[must_use]
+    pub fn get(&self, button: MouseButton) -> Option<&str> {
+        self.0.get(&button).map(String::as_str)
+    }
+
+    fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        Self(
+            [
+                (MouseButton::Left, "left"),
+                (MouseButton::Middle, "middle"),
+                (MouseButton::Right, "right"),
+                (MouseButton::ScrollUp, "scroll_up"),
+                (MouseButton::ScrollDown, "scroll_down"),
+            ]
+            .into_iter()
+            .filter_map(|(button, name)| {
+                remove_string_from_config(
+                    format!("action_{name}").as_str(),
+                    table,
+                )
+                .map(|command| (button, command))
+            })
+            .collect(),
+        )
+    }
 }
 
 /// The common part of most [`PanelConfigs`][crate::PanelConfig]. Stores format
-/// strings, [`Attrs`], and [`Dependence`]
+/// strings, [`Attrs`], [`Dependence`], [`Actions`], and a [`Decoration`]
 #[derive(Debug, Clone, Builder)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
@@ -56,6 +191,33 @@ pub struct PanelCommon {
     pub dependence: Dependence,
     /// The instances of [`Attrs`] used by the panel
     pub attrs: Vec<Attrs>,
+    /// The commands to run when the panel is clicked or scrolled
+    #[builder(default)]
+    pub actions: Actions,
+    /// The box drawn around the panel
+    #[builder(default)]
+    pub decoration: Decoration,
+    /// How the panel's text is shortened when it's too long
+    #[builder(default)]
+    pub ellipsize: Ellipsize,
+    /// The animation shown when the panel's content changes
+    #[builder(default)]
+    pub transition: Option<Transition>,
+    /// The condition under which the panel is shown
+    #[builder(default)]
+    pub visible_when: Option<Expr>,
+    /// The condition on other panels under which the panel is shown
+    #[builder(default)]
+    pub depends_on: Option<Expr>,
+    /// The ranges of the panel's value that change its attributes
+    #[builder(default)]
+    pub states: States,
+    /// Whether text from outside the bar is escaped before it's shown
+    #[builder(default)]
+    pub escape_markup: EscapeMarkup,
+    /// How long the panel can go without drawing before it's restarted
+    #[builder(default)]
+    pub watchdog: Watchdog,
 }
 
 impl PanelCommon {
@@ -64,8 +226,50 @@ impl PanelCommon {
     /// and attrs prefixes are documented by each panel.
     ///
     /// Format strings should be specified as `format{suffix} = "value"`.
+    /// Panels whose formatting options are written like `{name}` fill them in
+    /// with [`render_format`][crate::render_format], which also supports
+    /// padding, precision, and conditional text.
+    ///
     /// Dependence should be specified as `dependence = "value"`, where value is
     /// a valid variant of [`Dependence`].
+    ///
+    /// Actions should be specified as `action_left`, `action_middle`,
+    /// `action_right`, `action_scroll_up`, or `action_scroll_down` with a
+    /// shell command as the value. The command is run with `sh -c` when the
+    /// panel is clicked or scrolled, and the text that the panel is showing is
+    /// available in the `LAZYBAR_TEXT` environment variable. A configured
+    /// action replaces any built-in behavior of the panel for that button.
+    ///
+    /// A box can be drawn around the panel with `margin`, `border_width`,
+    /// `border_color`, `radius`, and `bg_padding`. It is filled with the
+    /// background color of the first instance of [`Attrs`]. See
+    /// [`Decoration`].
+    ///
+    /// The text of the panel can be limited with `max_width` or `max_chars`,
+    /// and is then ellipsized as specified by `ellipsize`. See [`Ellipsize`].
+    ///
+    /// Changes to the panel's content can be animated with `transition`,
+    /// `transition_duration`, and `transition_easing`. See [`Transition`].
+    ///
+    /// The panel can be hidden while it's idle with `visible_when`, e.g.
+    /// `visible_when = "count > 0"`, or while other panels aren't shown with
+    /// `depends_on`, e.g. `depends_on = "call"`. See [`Expr`].
+    ///
+    /// Panels that show a single number can change their attributes when it
+    /// crosses a threshold with `states`. See [`States`].
+    ///
+    /// Text from outside the bar, like window titles, is escaped so it can't
+    /// be mistaken for markup unless `escape_markup = false`. See
+    /// [`EscapeMarkup`].
+    ///
+    /// Panels that are expected to draw regularly can be restarted when they
+    /// stop with `watchdog`. See [`Watchdog`].
+    ///
+    /// `font` sets the font of every instance of [`Attrs`] of the panel,
+    /// unless it sets its own, e.g. with `charging_font`. Like any font, it
+    /// can be an array of fonts to fall back on. See
+    /// [`remove_font_from_config`].
+    ///
     /// See [`Attrs::parse`] for more parsing details.
     pub fn parse<S: std::hash::BuildHasher>(
         table: &mut HashMap<String, Value, S>,
@@ -101,17 +305,404 @@ impl PanelCommon {
             },
         );
 
-        builder.attrs(
-            attrs_prefixes
-                .iter()
-                .map(|p| Attrs::parse(table, p))
-                .collect(),
-        );
+        // applies to every instance of `Attrs` that doesn't set its own font
+        let font = remove_font_from_config("font", table);
+        let attrs = attrs_prefixes
+            .iter()
+            .map(|p| {
+                let mut attrs = Attrs::parse(table, p);
+                if let Some(font) = &font {
+                    attrs.set_font_if_unset(font);
+                }
+                attrs
+            })
+            .collect::<Vec<_>>();
+        builder.decoration(Decoration::parse(
+            table,
+            attrs.first().cloned().unwrap_or_default(),
+        ));
+        builder.attrs(attrs);
+        builder.ellipsize(Ellipsize::parse(table));
+        builder.transition(Transition::parse(table));
+        builder.visible_when(Expr::parse("visible_when", table)?);
+        builder.depends_on(Expr::parse("depends_on", table)?);
+        builder.states(States::parse(table)?);
+        builder.escape_markup(EscapeMarkup::parse(table));
+        builder.watchdog(Watchdog::parse(table));
+
+        builder.actions(Actions::parse(table));
 
         Ok(builder.build()?)
     }
 }
 
+/// The narrowest the content of a panel can be
+#[derive(Debug, Clone)]
+pub enum MinWidth {
+    /// A width in pixels
+    Pixels(f64),
+    /// A string whose width in the panel's font is used, e.g. `00:00:00` for a
+    /// clock
+    Text(String),
+}
+
+/// A box drawn around a panel, with a background, an optional border, and
+/// space on either side. Panels that aren't shown aren't decorated.
+#[derive(Debug, Clone, Default)]
+pub struct Decoration {
+    /// The space in pixels on each side of the box, which separates it from
+    /// the neighboring panels
+    pub margin: f64,
+    /// The width in pixels of the border
+    pub border_width: f64,
+    /// The color of the border. If this is [`None`], the foreground color of
+    /// [`Decoration::attrs`] is used.
+    pub border_color: Option<Color>,
+    /// The radius in pixels of the corners of the box
+    pub radius: f64,
+    /// The space in pixels between the border and the content of the panel
+    pub padding: f64,
+    /// The narrowest the content of the panel can be. Narrower content is
+    /// centered, so that the panel doesn't change size as its text changes.
+    pub min_width: Option<MinWidth>,
+    /// The colors of the box
+    pub attrs: Attrs,
+}
+
+impl Decoration {
+    /// Configuration options:
+    ///
+    /// - `margin`: the space in pixels on each side of the panel, outside of
+    ///   its background, which separates it from the neighboring panels
+    ///   - type: f64
+    ///   - default: `0`
+    ///
+    /// - `border_width`: the width in pixels of the border around the panel
+    ///   - type: f64
+    ///   - default: `0`
+    ///
+    /// - `border_color`: the color of the border
+    ///   - type: String
+    ///   - default: the foreground color of the panel
+    ///
+    /// - `radius`: the radius in pixels of the corners of the background. It is
+    ///   limited to half the height of the panel, so a large value draws it as
+    ///   a pill.
+    ///   - type: f64
+    ///   - default: `0`
+    ///
+    /// - `bg_padding`: the space in pixels between the border and the content
+    ///   of the panel, filled with its background color
+    ///   - type: f64
+    ///   - default: `0`
+    ///
+    /// - `min_width`: the narrowest the content of the panel can be, either in
+    ///   pixels or as a string whose width in the panel's font is used (e.g.
+    ///   `"00:00:00"` for a clock). Narrower content is centered. This keeps
+    ///   the bar from shifting as the text of the panel changes.
+    ///   - type: f64 or String
+    ///   - default: none
+    fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+        attrs: Attrs,
+    ) -> Self {
+        Self {
+            margin: remove_float_from_config("margin", table)
+                .unwrap_or_default(),
+            border_width: remove_float_from_config("border_width", table)
+                .unwrap_or_default(),
+            border_color: remove_color_from_config("border_color", table),
+            radius: remove_float_from_config("radius", table)
+                .unwrap_or_default(),
+            padding: remove_float_from_config("bg_padding", table)
+                .unwrap_or_default(),
+            min_width: table.remove("min_width").and_then(|val| {
+                val.clone()
+                    .into_float()
+                    .map(MinWidth::Pixels)
+                    .or_else(|_| val.into_string().map(MinWidth::Text))
+                    .map_err(|e| log::warn!("Invalid min_width: {e}"))
+                    .ok()
+            }),
+            attrs,
+        }
+    }
+
+    /// Whether the decoration draws nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.margin <= 0.0
+            && self.border_width <= 0.0
+            && self.radius <= 0.0
+            && self.padding <= 0.0
+            && self.min_width.is_none()
+    }
+
+    /// The horizontal distance in pixels between the edge of a decorated
+    /// panel and its content.
+    #[must_use]
+    pub fn inset(&self) -> f64 {
+        self.margin + self.border_width + self.padding
+    }
+
+    /// Wraps the draw function of a panel so that the box is drawn around it.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn apply(&self, draw_info: PanelDrawInfo) -> PanelDrawInfo {
+        if draw_info.width == 0 || self.is_empty() {
+            return draw_info;
+        }
+
+        let content = f64::from(draw_info.width);
+        let min_width = self.min_width().max(content);
+        // narrower content is centered
+        let inset = self.inset() + (min_width - content) / 2.0;
+        let width = 2.0f64.mul_add(self.inset(), min_width);
+        let height = f64::from(draw_info.height);
+        let decoration = self.clone();
+        let PanelDrawInfo {
+            height: panel_height,
+            dependence,
+            draw_fn,
+            text,
+            expand,
+            values,
+            ..
+        } = draw_info;
+
+        let mut decorated = PanelDrawInfo::new(
+            (width.ceil() as i32, panel_height),
+            dependence,
+            Box::new(move |cr| {
+                decoration.outline(cr, width, height);
+                decoration.attrs.apply_bg(cr, width, height);
+                cr.fill_preserve()?;
+                cr.save()?;
+                cr.clip();
+                cr.translate(inset, 0.0);
+                draw_fn(cr)?;
+                cr.restore()?;
+
+                if decoration.border_width > 0.0 {
+                    decoration.outline(cr, width, height);
+                    match &decoration.border_color {
+                        Some(c) => cr.set_source_rgba(c.r, c.g, c.b, c.a),
+                        None => decoration.attrs.apply_fg(cr, width, height),
+                    }
+                    cr.set_line_width(decoration.border_width);
+                    cr.stroke()?;
+                }
+                Ok(())
+            }),
+        );
+        decorated.text = text;
+        decorated.expand = expand;
+        decorated.values = values;
+        decorated
+    }
+
+    /// The minimum width in pixels of the content of the panel.
+    fn min_width(&self) -> f64 {
+        match &self.min_width {
+            None => 0.0,
+            Some(MinWidth::Pixels(px)) => *px,
+            Some(MinWidth::Text(text)) => {
+                let context = pangocairo::FontMap::default().create_context();
+                let layout = pango::Layout::new(&context);
+                layout.set_markup(text);
+                self.attrs.apply_font(&layout);
+                f64::from(layout.pixel_size().0)
+            }
+        }
+    }
+
+    /// Replaces the current path with the outline of the box, centered on the
+    /// border, for a decorated panel of the given size.
+    fn outline(&self, cr: &cairo::Context, width: f64, height: f64) {
+        let half = self.border_width / 2.0;
+        let x = self.margin + half;
+        let y = half;
+        let width = 2.0f64.mul_add(-self.margin, width) - self.border_width;
+        let height = height - self.border_width;
+        rounded_rectangle(cr, x, y, width, height, self.radius);
+    }
+}
+
+/// The longest a panel's text can be before it's ellipsized
+#[derive(Clone, Copy, Debug)]
+pub enum MaxWidth {
+    /// A width in pixels
+    Pixels(i32),
+    /// A number of characters, measured with the average width of a character
+    /// in the panel's font
+    Chars(i32),
+}
+
+/// How the text of a panel is shortened when it's too long, so that long
+/// dynamic content doesn't push other panels off the bar. Only panels drawn
+/// with [`draw_common`] are affected.
+#[derive(Clone, Debug)]
+pub struct Ellipsize {
+    /// Where the text is cut off
+    pub mode: pango::EllipsizeMode,
+    /// The longest the text can be, or [`None`] to never shorten it
+    pub max_width: Option<MaxWidth>,
+}
+
+impl Default for Ellipsize {
+    fn default() -> Self {
+        Self {
+            mode: pango::EllipsizeMode::End,
+            max_width: None,
+        }
+    }
+}
+
+impl Ellipsize {
+    /// Configuration options:
+    ///
+    /// - `max_width`: the maximum width in pixels of the panel's text
+    ///   - type: u64
+    ///   - default: none
+    ///
+    /// - `max_chars`: the maximum width of the panel's text, in characters of
+    ///   average width. This is ignored if `max_width` is set.
+    ///   - type: u64
+    ///   - default: none
+    ///
+    /// - `ellipsize`: where text that's too long is cut off: `start`, `middle`,
+    ///   or `end`
+    ///   - type: String
+    ///   - default: `end`
+    fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let max_width = remove_uint_from_config("max_width", table)
+            .map(|px| MaxWidth::Pixels(px as i32));
+        let max_chars = remove_uint_from_config("max_chars", table)
+            .map(|chars| MaxWidth::Chars(chars as i32));
+        let mode =
+            match remove_string_from_config("ellipsize", table).as_deref() {
+                Some("start") => pango::EllipsizeMode::Start,
+                Some("middle") => pango::EllipsizeMode::Middle,
+                Some("end") | None => pango::EllipsizeMode::End,
+                Some(other) => {
+                    log::warn!("Unknown ellipsize mode `{other}`, using `end`");
+                    pango::EllipsizeMode::End
+                }
+            };
+        Self {
+            mode,
+            max_width: max_width.or(max_chars),
+        }
+    }
+
+    /// Limits the width of a [`pango::Layout`]. This should be called after
+    /// its font is set.
+    pub fn apply(&self, layout: &pango::Layout) {
+        let width = match self.max_width {
+            None => return,
+            Some(MaxWidth::Pixels(px)) => px * pango::SCALE,
+            Some(MaxWidth::Chars(chars)) => {
+                layout
+                    .context()
+                    .metrics(layout.font_description().as_ref(), None)
+                    .approximate_char_width()
+                    * chars
+            }
+        };
+        layout.set_width(width);
+        layout.set_ellipsize(self.mode);
+    }
+}
+
+/// Whether text that comes from outside the bar, like window titles or the
+/// output of a command, is escaped before it's interpreted as markup. Without
+/// escaping, a title like `<b>` or `Q&A` could change how the panel looks or
+/// stop it from being drawn at all.
+#[derive(Clone, Copy, Debug)]
+pub struct EscapeMarkup(pub bool);
+
+impl Default for EscapeMarkup {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+impl EscapeMarkup {
+    /// Configuration options:
+    ///
+    /// - `escape_markup`: whether text from outside the bar, like window
+    ///   titles or command output, is escaped before it's shown. Turn this off
+    ///   to let trusted sources use [pango] markup. The format strings
+    ///   themselves are always interpreted as markup.
+    ///   - type: bool
+    ///   - default: true
+    fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        remove_bool_from_config("escape_markup", table)
+            .map_or_else(Self::default, Self)
+    }
+
+    /// Escapes `text` with [`markup_escape_text`][crate::markup_escape_text]
+    /// if escaping is enabled, or returns it as it is otherwise.
+    #[must_use]
+    pub fn apply(self, text: &str) -> String {
+        if self.0 {
+            glib::markup_escape_text(text).to_string()
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+/// How long a panel can go without drawing before the bar considers it
+/// stalled. A stalled panel is dimmed and its stream is started again, so
+/// this should only be set for panels that draw on a schedule, like those
+/// with an `interval`. If this is [`None`], the panel is never considered
+/// stalled.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Watchdog(pub Option<Duration>);
+
+impl Watchdog {
+    /// Configuration options:
+    ///
+    /// - `watchdog`: how long in seconds the panel can go without drawing
+    ///   before it's dimmed and restarted. Clocks set this to twice their
+    ///   precision, or 10 seconds, whichever is longer.
+    ///   - type: u64
+    ///   - default: none
+    fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        Self(
+            remove_uint_from_config("watchdog", table)
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        )
+    }
+}
+
+/// Replaces the current path with a rectangle whose corners are rounded with
+/// the given radius, or as close to it as the size of the rectangle allows.
+pub(crate) fn rounded_rectangle(
+    cr: &cairo::Context,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    radius: f64,
+) {
+    let r = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+    cr.new_path();
+    cr.arc(x + width - r, y + r, r, -FRAC_PI_2, 0.0);
+    cr.arc(x + width - r, y + height - r, r, 0.0, FRAC_PI_2);
+    cr.arc(x + r, y + height - r, r, FRAC_PI_2, PI);
+    cr.arc(x + r, y + r, r, PI, 3.0 * FRAC_PI_2);
+    cr.close_path();
+}
+
 /// Removes a value from a given config table and returns an attempt at parsing
 /// it into a table
 pub fn get_table_from_config<S: std::hash::BuildHasher>(
@@ -146,6 +737,43 @@ pub fn remove_string_from_config<S: std::hash::BuildHasher>(
     })
 }
 
+/// Removes a value from a given config table and returns an attempt at parsing
+/// it into a font. The value can be a single font, or an array of fonts to
+/// fall back on, e.g. `["JetBrainsMono Nerd Font 10", "Noto Color Emoji"]`.
+/// The first font in the array sets the size, weight, and style, and the
+/// family of each one is tried in order for characters that the previous ones
+/// don't have.
+pub fn remove_font_from_config<S: std::hash::BuildHasher>(
+    id: &str,
+    table: &mut HashMap<String, Value, S>,
+) -> Option<FontDescription> {
+    let val = table.remove(id)?;
+    if let Ok(font) = val.clone().into_string() {
+        return Some(FontDescription::from_string(font.as_str()));
+    }
+    let fonts = val
+        .clone()
+        .into_array()
+        .and_then(|fonts| {
+            fonts
+                .into_iter()
+                .map(|font| {
+                    font.into_string()
+                        .map(|font| FontDescription::from_string(font.as_str()))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|_| log::warn!("Ignoring invalid font {val:?}"))
+        .ok()?;
+    let mut font = fonts.first()?.clone();
+    let families = fonts
+        .iter()
+        .filter_map(|font| Some(font.family()?.to_string()))
+        .collect::<Vec<_>>();
+    font.set_family(families.join(",").as_str());
+    Some(font)
+}
+
 /// Removes a value from a given config table and returns an attempt at parsing
 /// it into a uint
 pub fn remove_uint_from_config<S: std::hash::BuildHasher>(
@@ -210,7 +838,7 @@ pub fn remove_color_from_config<S: std::hash::BuildHasher>(
                 None
             },
             |val| {
-                val.parse().map_or_else(
+                parse_color(val.as_str()).map_or_else(
                     |_| {
                         log::warn!("Invalid color {val}");
                         None
@@ -221,3 +849,28 @@ pub fn remove_color_from_config<S: std::hash::BuildHasher>(
         )
     })
 }
+
+/// Removes a value from a given config table and returns an attempt at parsing
+/// it into a [`Paint`], either a color or a gradient
+pub fn remove_paint_from_config<S: std::hash::BuildHasher>(
+    id: &str,
+    table: &mut HashMap<String, Value, S>,
+) -> Option<Paint> {
+    table.remove(id).and_then(|val| {
+        val.clone().into_string().map_or_else(
+            |_| {
+                log::warn!("Ignoring non-string value {val:?}");
+                None
+            },
+            |val| {
+                val.parse().map_or_else(
+                    |e| {
+                        log::warn!("Invalid color {val}: {e}");
+                        None
+                    },
+                    Some,
+                )
+            },
+        )
+    })
+}
@@ -1,12 +1,19 @@
+use std::{
+    os::fd::{AsRawFd, RawFd},
+    time::Duration,
+};
+
 use anyhow::{Context, Result};
 use cairo::{XCBConnection, XCBSurface};
 use csscolorparser::Color;
+use tokio::{io::unix::AsyncFd, time};
 use xcb::{
+    randr, shape,
     x::{self, Visualtype, Window},
-    Connection, Xid,
+    Connection, Xid, XidNew,
 };
 
-use crate::Position;
+use crate::{Monitor, Placement, Position};
 
 pub fn intern_named_atom(
     conn: &xcb::Connection,
@@ -66,16 +73,23 @@ pub fn find_visual(screen: &x::Screen, depth: u8) -> Option<&x::Visualtype> {
 )]
 pub fn create_window(
     position: Position,
+    placement: Placement,
     height: u16,
     transparent: bool,
     background: &Color,
     name: &str,
-) -> Result<(xcb::Connection, i32, x::Window, u16, x::Visualtype)> {
-    let (conn, screen_idx) = xcb::Connection::connect(None)?;
+    monitor: Option<&Monitor>,
+) -> Result<(xcb::Connection, i32, x::Window, Geometry, x::Visualtype)> {
+    let (conn, screen_idx) = xcb::Connection::connect_with_extensions(
+        None,
+        &[],
+        &[xcb::Extension::Shape],
+    )?;
     let window: x::Window = conn.generate_id();
     let colormap: x::Colormap = conn.generate_id();
     let screen = conn.get_setup().roots().nth(screen_idx as usize).unwrap();
-    let width = screen.width_in_pixels();
+    let geometry =
+        bar_geometry(&conn, screen_idx, position, placement, height, monitor)?;
 
     let depth = if transparent { 32 } else { 24 };
     let visual = *find_visual(screen, depth).expect("Failed to find visual");
@@ -105,13 +119,9 @@ pub fn create_window(
         depth,
         wid: window,
         parent: screen.root(),
-        x: 0,
-        y: if position == Position::Top {
-            0
-        } else {
-            (screen.height_in_pixels() - height) as i16
-        },
-        width,
+        x: geometry.x,
+        y: geometry.y,
+        width: geometry.width,
         height,
         border_width: 0,
         class: x::WindowClass::InputOutput,
@@ -119,7 +129,9 @@ pub fn create_window(
         value_list: &[
             x::Cw::BackPixel(bg),
             x::Cw::BorderPixel(bg),
-            x::Cw::EventMask(x::EventMask::EXPOSURE),
+            x::Cw::EventMask(
+                x::EventMask::EXPOSURE | x::EventMask::BUTTON_PRESS,
+            ),
             x::Cw::Colormap(colormap),
         ],
     }))?;
@@ -132,14 +144,16 @@ pub fn create_window(
         data: format!("lazybar_{name}").as_bytes(),
     }))?;
 
-    Ok((conn, screen_idx, window, width, visual))
+    Ok((conn, screen_idx, window, geometry, visual))
 }
 
+#[allow(clippy::cast_sign_loss)]
 pub fn set_wm_properties(
     conn: &Connection,
+    screen: i32,
     window: x::Window,
     position: Position,
-    width: u32,
+    geometry: Geometry,
     height: u32,
 ) -> Result<()> {
     let window_type_atom = intern_named_atom(conn, b"_NET_WM_WINDOW_TYPE")?;
@@ -153,10 +167,18 @@ pub fn set_wm_properties(
     )?;
 
     let strut_partial_atom = intern_named_atom(conn, b"_NET_WM_STRUT_PARTIAL")?;
+    // struts are measured from the edges of the whole screen, so any gap
+    // between the bar and the edge has to be reserved as well
+    let start = geometry.x.max(0) as u32;
+    let end = start + u32::from(geometry.width) - 1;
     let strut = if position == Position::Top {
-        &[0, 0, height, 0, 0, 0, 0, 0, 0, width - 1, 0, 0]
+        let top = (i32::from(geometry.y) + height as i32).max(0) as u32;
+        &[0, 0, top, 0, 0, 0, 0, 0, start, end, 0, 0]
     } else {
-        &[0, 0, 0, height, 0, 0, 0, 0, 0, 0, 0, width - 1]
+        let (_, screen_height) = screen_size(conn, screen)?;
+        let bottom =
+            (i32::from(screen_height) - i32::from(geometry.y)).max(0) as u32;
+        &[0, 0, 0, bottom, 0, 0, 0, 0, 0, 0, start, end]
     };
     change_property(conn, window, strut_partial_atom, x::ATOM_CARDINAL, strut)?;
     Ok(())
@@ -169,7 +191,7 @@ pub fn set_wm_properties(
 )]
 pub fn create_surface(
     conn: &Connection,
-    window: Window,
+    drawable: impl Xid,
     mut visual: Visualtype,
     width: i32,
     height: i32,
@@ -180,7 +202,7 @@ pub fn create_surface(
                 conn.get_raw_conn(),
             ))
         },
-        &cairo::XCBDrawable(window.resource_id()),
+        &cairo::XCBDrawable(drawable.resource_id()),
         unsafe {
             &cairo::XCBVisualType::from_raw_none(std::mem::transmute(
                 &mut visual as *mut _,
@@ -191,7 +213,421 @@ pub fn create_surface(
     )?)
 }
 
+/// Cuts the corners of a window with the given size off with the shape
+/// extension, so that it has rounded corners with the given radius. A radius of
+/// zero makes the window rectangular again.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+pub fn shape_window(
+    conn: &Connection,
+    window: Window,
+    width: u16,
+    height: u16,
+    radius: f64,
+) -> Result<()> {
+    if !conn.active_extensions().any(|e| e == xcb::Extension::Shape) {
+        if radius > 0.0 {
+            log::warn!("The X server doesn't support rounded corners");
+        }
+        return Ok(());
+    }
+
+    if radius <= 0.0 {
+        conn.check_request(conn.send_request_checked(&shape::Mask {
+            operation: shape::So::Set,
+            destination_kind: shape::Sk::Bounding,
+            destination_window: window,
+            x_offset: 0,
+            y_offset: 0,
+            source_bitmap: x::Pixmap::none(),
+        }))?;
+        return Ok(());
+    }
+
+    let r = radius.min(f64::from(width.min(height)) / 2.0);
+    let rows = r.ceil() as u16;
+    let mut rectangles = Vec::with_capacity(2 * rows as usize + 1);
+    for row in 0..rows {
+        // how far the curve is from the side of the window in the middle of
+        // this row
+        let dy = r - f64::from(row) - 0.5;
+        let inset = (r - r.mul_add(r, -dy * dy).max(0.0).sqrt()).round() as u16;
+        let inner = width.saturating_sub(2 * inset);
+        rectangles.push(x::Rectangle {
+            x: inset as i16,
+            y: row as i16,
+            width: inner,
+            height: 1,
+        });
+        rectangles.push(x::Rectangle {
+            x: inset as i16,
+            y: (height - row - 1) as i16,
+            width: inner,
+            height: 1,
+        });
+    }
+    rectangles.push(x::Rectangle {
+        x: 0,
+        y: rows as i16,
+        width,
+        height: height.saturating_sub(2 * rows),
+    });
+
+    conn.check_request(conn.send_request_checked(&shape::Rectangles {
+        operation: shape::So::Set,
+        destination_kind: shape::Sk::Bounding,
+        ordering: x::ClipOrdering::Unsorted,
+        destination_window: window,
+        x_offset: 0,
+        y_offset: 0,
+        rectangles: &rectangles,
+    }))?;
+
+    Ok(())
+}
+
+/// Creates a surface for the pixmap that holds the desktop wallpaper, which
+/// tools like `feh` and `nitrogen` store in the `atom` property of the root
+/// window. Returns [`None`] if no wallpaper is set.
+#[allow(clippy::cast_sign_loss)]
+pub fn wallpaper_surface(
+    conn: &Connection,
+    screen_idx: i32,
+    atom: x::Atom,
+) -> Result<Option<XCBSurface>> {
+    let screen = conn
+        .get_setup()
+        .roots()
+        .nth(screen_idx as usize)
+        .context("Failed to find screen")?;
+    let reply = conn.wait_for_reply(conn.send_request(&x::GetProperty {
+        delete: false,
+        window: screen.root(),
+        property: atom,
+        r#type: x::ATOM_PIXMAP,
+        long_offset: 0,
+        long_length: 1,
+    }))?;
+    let Some(&id) = reply.value::<u32>().first() else {
+        return Ok(None);
+    };
+    // SAFETY: the property holds the id of a pixmap
+    let pixmap = unsafe { x::Pixmap::new(id) };
+    let geometry = conn.wait_for_reply(conn.send_request(&x::GetGeometry {
+        drawable: x::Drawable::Pixmap(pixmap),
+    }))?;
+    let visual = *find_visual(screen, geometry.depth())
+        .context("Failed to find visual for wallpaper")?;
+
+    Ok(Some(create_surface(
+        conn,
+        pixmap,
+        visual,
+        geometry.width().into(),
+        geometry.height().into(),
+    )?))
+}
+
+pub fn clear_strut(conn: &Connection, window: x::Window) -> Result<()> {
+    let strut_partial_atom = intern_named_atom(conn, b"_NET_WM_STRUT_PARTIAL")?;
+    conn.check_request(conn.send_request_checked(&x::DeleteProperty {
+        window,
+        property: strut_partial_atom,
+    }))
+    .with_context(|| "clearing strut failed")
+}
+
+/// Creates an invisible window along the outer edge of a bar. The pointer
+/// entering it reveals the bar when autohide is enabled.
+pub fn create_trigger(
+    conn: &Connection,
+    screen: i32,
+    position: Position,
+    placement: Placement,
+    height: u16,
+    geometry: Geometry,
+) -> Result<x::Window> {
+    let root = conn
+        .get_setup()
+        .roots()
+        .nth(screen as usize)
+        .context("Failed to find screen")?
+        .root();
+    let window: x::Window = conn.generate_id();
+    conn.check_request(conn.send_request_checked(&x::CreateWindow {
+        depth: 0,
+        wid: window,
+        parent: root,
+        x: geometry.x,
+        y: trigger_y(position, placement, height, geometry),
+        width: geometry.width,
+        height: 1,
+        border_width: 0,
+        class: x::WindowClass::InputOnly,
+        visual: x::COPY_FROM_PARENT,
+        value_list: &[
+            x::Cw::OverrideRedirect(true),
+            x::Cw::EventMask(
+                x::EventMask::ENTER_WINDOW | x::EventMask::LEAVE_WINDOW,
+            ),
+        ],
+    }))
+    .with_context(|| "creating trigger window failed")?;
+    Ok(window)
+}
+
+/// Returns the y-coordinate of the edge of the monitor next to a bar.
+pub fn trigger_y(
+    position: Position,
+    placement: Placement,
+    height: u16,
+    geometry: Geometry,
+) -> i16 {
+    match position {
+        Position::Top => geometry.y - placement.y,
+        Position::Bottom => geometry.y + height as i16 - 1 + placement.y,
+    }
+}
+
 pub fn map_window(conn: &Connection, window: Window) -> Result<()> {
     conn.check_request(conn.send_request_checked(&x::MapWindow { window }))
         .with_context(|| "mapping window failed")
 }
+
+/// Returns the current size of the root window. Unlike the values in the
+/// connection setup, this reflects any changes made through RandR.
+pub fn screen_size(conn: &Connection, screen: i32) -> Result<(u16, u16)> {
+    let root = conn
+        .get_setup()
+        .roots()
+        .nth(screen as usize)
+        .context("Failed to find screen")?
+        .root();
+    let geometry = conn.wait_for_reply(conn.send_request(&x::GetGeometry {
+        drawable: x::Drawable::Window(root),
+    }))?;
+    Ok((geometry.width(), geometry.height()))
+}
+
+/// The position and width of a bar window, relative to the root window.
+#[derive(Clone, Copy, Debug)]
+pub struct Geometry {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+}
+
+/// Returns the geometry of a bar window on `monitor`, or on the whole screen
+/// if `monitor` is [`None`].
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+pub fn bar_geometry(
+    conn: &Connection,
+    screen: i32,
+    position: Position,
+    placement: Placement,
+    height: u16,
+    monitor: Option<&Monitor>,
+) -> Result<Geometry> {
+    let (x, y, width, monitor_height) = match monitor {
+        Some(m) => (m.x, m.y, m.width, m.height),
+        None => {
+            let (width, height) = screen_size(conn, screen)?;
+            (0, 0, width, height)
+        }
+    };
+    let y = if position == Position::Top {
+        y + placement.y
+    } else {
+        y + monitor_height.saturating_sub(height) as i16 - placement.y
+    };
+    Ok(Geometry {
+        x: x + placement.x,
+        y,
+        width: (f64::from(width) * placement.width / 100.0)
+            .round()
+            .clamp(1.0, f64::from(width)) as u16,
+    })
+}
+
+/// Returns the contents of the X resource database, as loaded by `xrdb`.
+pub fn get_resources() -> Result<String> {
+    let (conn, screen_idx) = xcb::Connection::connect(None)?;
+    let root = conn
+        .get_setup()
+        .roots()
+        .nth(screen_idx as usize)
+        .context("Failed to find screen")?
+        .root();
+    let reply = conn.wait_for_reply(conn.send_request(&x::GetProperty {
+        delete: false,
+        window: root,
+        property: x::ATOM_RESOURCE_MANAGER,
+        r#type: x::ATOM_STRING,
+        long_offset: 0,
+        long_length: u32::MAX,
+    }))?;
+    Ok(String::from_utf8_lossy(reply.value::<u8>()).into_owned())
+}
+
+/// Guesses the scale factor for a bar on `monitor`, or on the whole screen if
+/// `monitor` is [`None`]. `Xft.dpi` from the X resource database is used if
+/// it's set, and otherwise the DPI is worked out from the physical size that
+/// RandR reports. 96 DPI is a scale factor of 1, and the result is rounded to
+/// the nearest quarter, but never less than 1.
+pub fn detect_scale(monitor: Option<&Monitor>) -> f64 {
+    let dpi = get_resources()
+        .ok()
+        .and_then(|resources| {
+            resources.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                (key.trim() == "Xft.dpi")
+                    .then(|| value.trim().parse::<f64>().ok())
+                    .flatten()
+            })
+        })
+        .or_else(|| {
+            physical_dpi(monitor)
+                .map_err(|e| log::warn!("Failed to detect DPI: {e}"))
+                .ok()
+                .flatten()
+        });
+    dpi.filter(|dpi| *dpi > 0.0)
+        .map_or(1.0, |dpi| ((dpi / 96.0) * 4.0).round().max(4.0) / 4.0)
+}
+
+/// Returns the horizontal DPI of `monitor`, or of the screen, if the X server
+/// knows its physical size.
+fn physical_dpi(monitor: Option<&Monitor>) -> Result<Option<f64>> {
+    let (conn, screen_idx) = xcb::Connection::connect_with_extensions(
+        None,
+        &[xcb::Extension::RandR],
+        &[],
+    )?;
+    let screen = conn
+        .get_setup()
+        .roots()
+        .nth(screen_idx as usize)
+        .context("Failed to find screen")?;
+    let (pixels, millimeters) = match monitor {
+        None => (
+            screen.width_in_pixels(),
+            u32::from(screen.width_in_millimeters()),
+        ),
+        Some(monitor) => {
+            let reply =
+                conn.wait_for_reply(conn.send_request(&randr::GetMonitors {
+                    window: screen.root(),
+                    get_active: true,
+                }))?;
+            let Some(info) = reply.monitors().find(|m| {
+                m.x() == monitor.x
+                    && m.y() == monitor.y
+                    && m.width() == monitor.width
+            }) else {
+                return Ok(None);
+            };
+            (info.width(), info.width_in_millimeters())
+        }
+    };
+    Ok((millimeters > 0)
+        .then(|| f64::from(pixels) / (f64::from(millimeters) / 25.4)))
+}
+
+/// Lists the active monitors in the order that RandR reports them.
+pub fn get_monitors() -> Result<Vec<Monitor>> {
+    let (conn, screen_idx) = xcb::Connection::connect_with_extensions(
+        None,
+        &[xcb::Extension::RandR],
+        &[],
+    )?;
+    let root = conn
+        .get_setup()
+        .roots()
+        .nth(screen_idx as usize)
+        .context("Failed to find screen")?
+        .root();
+    let reply =
+        conn.wait_for_reply(conn.send_request(&randr::GetMonitors {
+            window: root,
+            get_active: true,
+        }))?;
+
+    reply
+        .monitors()
+        .map(|m| {
+            let name = conn
+                .wait_for_reply(
+                    conn.send_request(&x::GetAtomName { atom: m.name() }),
+                )?
+                .name()
+                .to_utf8()
+                .into_owned();
+            Ok(Monitor {
+                name,
+                x: m.x(),
+                y: m.y(),
+                width: m.width(),
+                height: m.height(),
+            })
+        })
+        .collect()
+}
+
+/// Receives notifications from RandR when monitors are added, removed, or
+/// reconfigured.
+pub struct MonitorWatcher {
+    conn: Connection,
+    fd: AsyncFd<RawFd>,
+}
+
+impl MonitorWatcher {
+    pub fn new() -> Result<Self> {
+        let (conn, screen_idx) = xcb::Connection::connect_with_extensions(
+            None,
+            &[xcb::Extension::RandR],
+            &[],
+        )?;
+        let root = conn
+            .get_setup()
+            .roots()
+            .nth(screen_idx as usize)
+            .context("Failed to find screen")?
+            .root();
+        conn.check_request(conn.send_request_checked(&randr::SelectInput {
+            window: root,
+            enable: randr::NotifyMask::SCREEN_CHANGE
+                | randr::NotifyMask::CRTC_CHANGE
+                | randr::NotifyMask::OUTPUT_CHANGE,
+        }))?;
+        let fd = AsyncFd::new(conn.as_raw_fd())?;
+        Ok(Self { conn, fd })
+    }
+
+    /// Waits until the monitor configuration changes. A single change
+    /// usually produces a burst of events, so this waits for the burst to
+    /// end before returning.
+    pub async fn changed(&self) -> Result<()> {
+        while !self.drain()? {
+            self.fd.readable().await?.clear_ready();
+        }
+        time::sleep(Duration::from_millis(500)).await;
+        self.drain()?;
+        Ok(())
+    }
+
+    /// Discards all pending events and returns whether any came from RandR.
+    fn drain(&self) -> Result<bool> {
+        let mut changed = false;
+        while let Some(event) = self.conn.poll_for_event()? {
+            changed |= matches!(event, xcb::Event::RandR(_));
+        }
+        Ok(changed)
+    }
+}
@@ -0,0 +1,225 @@
+//! Checks that the defaults documented in the "Configuration options" lists,
+//! which `lazybar --dump-default-config` and `lazybar --schema` are generated
+//! from, are the ones the parsers actually use. Each panel is parsed once
+//! without any options and once with every documented default set
+//! explicitly, and the two results have to be the same.
+
+use std::collections::HashMap;
+
+use config::{Config, File, FileFormat, Value};
+use lazybar::{
+    panels::{
+        precision::Seconds, Battery, Bspwm, Calendar, ChargeThreshold, Ci,
+        Clock, Containers, Cpu, Crypto, Cups, Custom, DbusProperty, Dnd, Dpms,
+        Dunst, Entropy, Expand, FailedUnits, Fanotify, FileValue, Herbstluftwm,
+        Http, I3Mode, I3Workspaces, IdleInhibit, Inotify, Iwd, Journal,
+        KdeConnect, Kubernetes, Maildir, Matrix, Memory, Mpd, Mqtt, Network,
+        Nut, Ping, Pomodoro, PowerDraw, PowerProfile, Privacy, Pulseaudio,
+        Redshift, ScreenCapture, Separator, Spacer, Stocks, StoragePool,
+        Syncthing, Temp, Timesync, TodoTxt, Top, Transmission, Updates,
+        Weechat, XMonad, XWindow, XWorkspaces,
+    },
+    parser, PanelConfig,
+};
+
+/// Panel types that can't be compared. A group only holds other panels.
+const SKIPPED: &[&str] = &["group"];
+
+macro_rules! panels {
+    ($($kind:literal => $panel:ty),* $(,)?) => {
+        /// Parses a panel of type `kind` from `table` and describes the
+        /// result, or returns [`None`] if the type isn't listed here.
+        fn describe(
+            kind: &str,
+            mut table: HashMap<String, Value>,
+        ) -> Option<String> {
+            let global = Config::default();
+            match kind {
+                $($kind => Some(format!(
+                    "{:?}",
+                    <$panel>::parse(&mut table, &global)
+                        .map_err(|e| e.to_string())
+                )),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+panels! {
+    "battery" => Battery,
+    "bspwm" => Bspwm,
+    "calendar" => Calendar,
+    "charge_threshold" => ChargeThreshold,
+    "ci" => Ci,
+    "clock" => Clock<Seconds>,
+    "containers" => Containers,
+    "cpu" => Cpu,
+    "crypto" => Crypto,
+    "cups" => Cups,
+    "custom" => Custom,
+    "dbus_property" => DbusProperty,
+    "dnd" => Dnd,
+    "dpms" => Dpms,
+    "dunst" => Dunst,
+    "entropy" => Entropy,
+    "expand" => Expand,
+    "failed_units" => FailedUnits,
+    "fanotify" => Fanotify,
+    "file_value" => FileValue,
+    "herbstluftwm" => Herbstluftwm,
+    "http" => Http,
+    "i3mode" => I3Mode,
+    "i3workspaces" => I3Workspaces,
+    "idle_inhibit" => IdleInhibit,
+    "inotify" => Inotify,
+    "iwd" => Iwd,
+    "journal" => Journal,
+    "kdeconnect" => KdeConnect,
+    "kubernetes" => Kubernetes,
+    "maildir" => Maildir,
+    "matrix" => Matrix,
+    "memory" => Memory,
+    "mpd" => Mpd,
+    "mqtt" => Mqtt,
+    "network" => Network,
+    "nut" => Nut,
+    "ping" => Ping,
+    "pomodoro" => Pomodoro,
+    "power_draw" => PowerDraw,
+    "power_profile" => PowerProfile,
+    "privacy" => Privacy,
+    "pulseaudio" => Pulseaudio,
+    "redshift" => Redshift,
+    "screen_capture" => ScreenCapture,
+    "separator" => Separator,
+    "spacer" => Spacer,
+    "stocks" => Stocks,
+    "storage_pool" => StoragePool,
+    "syncthing" => Syncthing,
+    "temp" => Temp,
+    "timesync" => Timesync,
+    "todotxt" => TodoTxt,
+    "top" => Top,
+    "transmission" => Transmission,
+    "updates" => Updates,
+    "weechat" => Weechat,
+    "xmonad" => XMonad,
+    "xwindow" => XWindow,
+    "xworkspaces" => XWorkspaces,
+}
+
+/// Parses TOML assignments into a panel table.
+fn table(toml: &str) -> HashMap<String, Value> {
+    Config::builder()
+        .add_source(File::from_str(toml, FileFormat::Toml))
+        .build()
+        .and_then(|config| config.collect())
+        .unwrap_or_else(|e| panic!("invalid documented defaults: {e}\n{toml}"))
+}
+
+/// Parses each panel with and without `documented`, which maps panel types to
+/// TOML assignments of their documented defaults, and panics with the panels
+/// whose results differ.
+fn compare(documented: Vec<(String, String)>) {
+    let mut drifted = Vec::new();
+    for (kind, toml) in documented {
+        if SKIPPED.contains(&kind.as_str()) {
+            continue;
+        }
+        let bare =
+            describe(kind.as_str(), HashMap::new()).unwrap_or_else(|| {
+                panic!("panel type `{kind}` is missing from tests/defaults.rs")
+            });
+        let explicit = describe(kind.as_str(), table(toml.as_str()))
+            .expect("the panel type was just described");
+        if bare != explicit {
+            drifted.push(format!(
+                "{kind}:\n{toml}  without options: {bare}\n  with the \
+                 documented defaults: {explicit}"
+            ));
+        }
+    }
+    assert!(
+        drifted.is_empty(),
+        "documented defaults differ from the parsers:\n\n{}",
+        drifted.join("\n\n")
+    );
+}
+
+/// Returns the option assignments in each panel section of the default
+/// config, uncommented.
+fn default_config_options() -> Vec<(String, String)> {
+    let mut panels = Vec::<(String, String)>::new();
+    let mut in_panel = false;
+    for line in parser::default_config().lines() {
+        let line = line.strip_prefix("# ").unwrap_or(line);
+        if line.starts_with('[') {
+            in_panel = line.starts_with("[panels.");
+            if let Some(kind) = line
+                .strip_prefix("[panels.")
+                .and_then(|kind| kind.strip_suffix(']'))
+            {
+                panels.push((kind.to_owned(), String::new()));
+            }
+            continue;
+        }
+        let Some((key, _)) = line.split_once(" = ") else {
+            continue;
+        };
+        if !in_panel
+            || key == "type"
+            || !key.chars().all(|c| {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'
+            })
+        {
+            continue;
+        }
+        if let Some((_, toml)) = panels.last_mut() {
+            // options documented together are assigned once for each key
+            if !toml.lines().any(|l| l.starts_with(&format!("{key} = "))) {
+                toml.push_str(line);
+                toml.push('\n');
+            }
+        }
+    }
+    panels
+}
+
+/// Returns the defaults in each panel definition of the schema as TOML
+/// assignments, including those of the options shared by most panels.
+fn schema_options() -> Vec<(String, String)> {
+    let schema = serde_json::from_str::<serde_json::Value>(parser::schema())
+        .expect("the schema is valid JSON");
+    let defs = schema["$defs"]
+        .as_object()
+        .expect("the schema has definitions");
+    defs.iter()
+        .filter_map(|(name, def)| {
+            let kind = name.strip_prefix("panel_")?;
+            let toml = def["properties"]
+                .as_object()?
+                .iter()
+                .filter_map(|(key, property)| {
+                    // JSON scalars and arrays are written the same way in TOML
+                    Some(format!("{key} = {}\n", property.get("default")?))
+                })
+                .collect();
+            Some((kind.to_owned(), toml))
+        })
+        .collect()
+}
+
+#[test]
+fn default_config() {
+    let panels = default_config_options();
+    assert!(!panels.is_empty(), "the default config has no panels");
+    compare(panels);
+}
+
+#[test]
+fn schema() {
+    let panels = schema_options();
+    assert!(!panels.is_empty(), "the schema has no panels");
+    compare(panels);
+}
@@ -0,0 +1,155 @@
+//! Compares bars drawn by [`Headless`] with the reference images in
+//! `tests/golden`. A missing reference image fails its test. Set
+//! `LAZYBAR_BLESS` to create the reference images, or to replace them after
+//! an intended change to how bars are drawn.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::Result;
+use config::{Config, Value};
+use lazybar::{
+    headless::{compare_golden, FakePanel, Headless},
+    panels::Expand,
+    Alignment, Attrs, Color, Margins, PanelConfig,
+};
+
+const WIDTH: u16 = 480;
+const HEIGHT: u16 = 24;
+/// How far each channel of a pixel may be off, to make up for differences in
+/// font rendering between machines
+const TOLERANCE: u8 = 48;
+
+/// Creates a bar with a fixed font, so that text is drawn the same way
+/// wherever the tests run.
+fn bar() -> Headless {
+    let mut table = HashMap::new();
+    table.insert(String::from("fg"), Value::from("#e0e0e0"));
+    table.insert(String::from("bg"), Value::from("#202020"));
+    table.insert(String::from("font"), Value::from("monospace 10"));
+    Headless::new(
+        WIDTH,
+        HEIGHT,
+        1.0,
+        Color::new(0.125, 0.125, 0.125, 1.0),
+        Margins::new(4.0, 8.0, 4.0),
+        Attrs::parse_global(&mut table, ""),
+    )
+}
+
+/// Creates a panel that shows `format` and is hidden unless `depends_on` is
+/// true.
+fn dependent(format: &str, depends_on: &str) -> Result<Box<dyn PanelConfig>> {
+    let mut table = HashMap::new();
+    table.insert(String::from("format"), Value::from(format));
+    table.insert(String::from("depends_on"), Value::from(depends_on));
+    Ok(Box::new(FakePanel::parse(&mut table, &Config::default())?))
+}
+
+fn expand() -> Result<Box<dyn PanelConfig>> {
+    Ok(Box::new(Expand::parse(
+        &mut HashMap::new(),
+        &Config::default(),
+    )?))
+}
+
+fn check(bar: Headless, name: &str) -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    fs::create_dir_all(&dir)?;
+    compare_golden(&bar.render()?, dir.join(format!("{name}.png")), TOLERANCE)
+}
+
+#[test]
+fn sections() -> Result<()> {
+    let mut bar = bar();
+    bar.add_panel(
+        String::from("workspaces"),
+        Box::new(FakePanel::new(
+            "{current} | {count}",
+            &[("current", "3"), ("count", "5")],
+        )?),
+        Alignment::Left,
+    );
+    bar.add_panel(
+        String::from("clock"),
+        Box::new(FakePanel::new("{time}", &[("time", "12:34")])?),
+        Alignment::Center,
+    );
+    bar.add_panel(
+        String::from("battery"),
+        Box::new(FakePanel::new("BAT {percent}%", &[("percent", "87")])?),
+        Alignment::Right,
+    );
+    check(bar, "sections")
+}
+
+#[test]
+fn center_pushed_right() -> Result<()> {
+    let mut bar = bar();
+    bar.add_panel(
+        String::from("title"),
+        Box::new(FakePanel::new(
+            "a window title that is long enough to reach the middle",
+            &[],
+        )?),
+        Alignment::Left,
+    );
+    bar.add_panel(
+        String::from("clock"),
+        Box::new(FakePanel::new("12:34", &[])?),
+        Alignment::Center,
+    );
+    check(bar, "center_pushed_right")
+}
+
+#[test]
+fn expanding() -> Result<()> {
+    let mut bar = bar();
+    bar.add_panel(
+        String::from("first"),
+        Box::new(FakePanel::new("first", &[])?),
+        Alignment::Left,
+    );
+    bar.add_panel(String::from("expand"), expand()?, Alignment::Left);
+    bar.add_panel(
+        String::from("second"),
+        Box::new(FakePanel::new("second", &[])?),
+        Alignment::Left,
+    );
+    bar.add_panel(
+        String::from("clock"),
+        Box::new(FakePanel::new("12:34", &[])?),
+        Alignment::Center,
+    );
+    bar.add_panel(
+        String::from("battery"),
+        Box::new(FakePanel::new("87%", &[])?),
+        Alignment::Right,
+    );
+    check(bar, "expanding")
+}
+
+#[test]
+fn depends_on() -> Result<()> {
+    let mut bar = bar();
+    bar.add_panel(
+        String::from("battery"),
+        Box::new(FakePanel::new("87%", &[])?),
+        Alignment::Left,
+    );
+    bar.add_panel(
+        String::from("shown"),
+        dependent("charging", "battery")?,
+        Alignment::Left,
+    );
+    bar.add_panel(
+        String::from("hidden"),
+        dependent("muted", "volume")?,
+        Alignment::Right,
+    );
+    bar.add_panel(
+        String::from("clock"),
+        Box::new(FakePanel::new("12:34", &[])?),
+        Alignment::Right,
+    );
+    check(bar, "depends_on")
+}